@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use wgpu::{Instance, Surface};
 use winit::{
     dpi::PhysicalSize,
@@ -510,6 +512,66 @@ pub fn parse_url_query_string<'a>(query: &'a str, search_key: &str) -> Option<&'
     None
 }
 
+/// Camera of the ray tracing examples, which generate their primary rays in the shader from
+/// the inverse view and projection matrices in [`RayCameraUniforms`].
+#[derive(Clone, Copy, Debug)]
+pub struct RayCamera {
+    pub position: Vec3,
+    pub target: Vec3,
+    /// Vertical field of view, in radians.
+    pub fov_y: f32,
+    /// Width divided by height of the viewport.
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl RayCamera {
+    /// A camera at `position` looking at `target`, with the near and far planes most examples use.
+    pub fn new(position: Vec3, target: Vec3, fov_y_degrees: f32, aspect: f32) -> Self {
+        Self {
+            position,
+            target,
+            fov_y: fov_y_degrees.to_radians(),
+            aspect,
+            near: 0.001,
+            far: 1000.0,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.target, Vec3::Y)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fov_y, self.aspect, self.near, self.far)
+    }
+
+    pub fn to_uniforms(&self) -> RayCameraUniforms {
+        RayCameraUniforms {
+            view_inverse: self.view().inverse().to_cols_array_2d(),
+            proj_inverse: self.projection().inverse().to_cols_array_2d(),
+        }
+    }
+
+    /// Direction of the primary ray through `ndc` (in `-1..1`, y up), computed like the shaders do.
+    pub fn ray_direction(&self, ndc: Vec2) -> Vec3 {
+        let target = self.projection().inverse() * Vec4::new(ndc.x, ndc.y, 1.0, 1.0);
+        self.view()
+            .inverse()
+            .transform_vector3(target.truncate().normalize())
+    }
+}
+
+/// Uniforms for primary ray generation, laid out like the `Uniforms` struct of the ray tracing
+/// example shaders.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct RayCameraUniforms {
+    pub view_inverse: [[f32; 4]; 4],
+    pub proj_inverse: [[f32; 4]; 4],
+}
+
 #[cfg(test)]
 pub use wgpu_test::image::ComparisonType;
 
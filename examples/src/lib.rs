@@ -19,7 +19,9 @@ pub mod ray_aabb_compute;
 pub mod ray_cube_compute;
 pub mod ray_cube_fragment;
 pub mod ray_cube_normals;
+pub mod ray_picking;
 pub mod ray_scene;
+pub mod ray_transparency;
 pub mod render_to_texture;
 pub mod repeated_compute;
 pub mod shadow;
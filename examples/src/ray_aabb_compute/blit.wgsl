@@ -46,7 +46,18 @@ var r_color: texture_2d<f32>;
 @binding(1)
 var r_sampler: sampler;
 
+// The ray tracing target is HDR: colors up to the knee are shown as they are,
+// brighter ones are compressed smoothly towards 1 instead of being clipped.
+const TONEMAP_KNEE: f32 = 0.9;
+
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    let shoulder = 1.0 - TONEMAP_KNEE;
+    let compressed = TONEMAP_KNEE + shoulder * (1.0 - exp((TONEMAP_KNEE - color) / shoulder));
+    return select(color, compressed, color > vec3<f32>(TONEMAP_KNEE));
+}
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(r_color, r_sampler, vertex.tex_coords);
+    let color = textureSample(r_color, r_sampler, vertex.tex_coords);
+    return vec4<f32>(tonemap(color.rgb), color.a);
 }
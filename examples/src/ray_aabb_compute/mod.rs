@@ -1,9 +1,11 @@
-use std::{borrow::Cow, future::Future, iter, mem, pin::Pin, task, time::Instant};
+use std::{borrow::Cow, f32::consts, future::Future, iter, mem, pin::Pin, task, time::Instant};
 
 use bytemuck::{Pod, Zeroable};
 use glam::{Affine3A, Mat4, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
+use crate::framework::{RayCamera, RayCameraUniforms};
+
 use rt::traits::*;
 use wgpu::{ray_tracing as rt, StoreOp};
 
@@ -31,155 +33,69 @@ fn create_aabbs() -> Vec<Aabb> {
     aabb_data.to_vec()
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct Uniforms {
-    view_inverse: [[f32; 4]; 4],
-    proj_inverse: [[f32; 4]; 4],
-}
+/// Side length of every face of the environment cube map.
+const ENVIRONMENT_SIZE: u32 = 64;
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct AccelerationStructureInstance {
-    transform: [f32; 12],
-    custom_index_and_mask: u32,
-    shader_binding_table_record_offset_and_flags: u32,
-    acceleration_structure_reference: u64,
-}
+const ZENITH: Vec3 = Vec3::new(0.15, 0.35, 0.8);
+const HORIZON: Vec3 = Vec3::new(0.75, 0.85, 0.95);
+const GROUND: Vec3 = Vec3::new(0.3, 0.25, 0.2);
 
-impl std::fmt::Debug for AccelerationStructureInstance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Instance")
-            .field("transform", &self.transform)
-            .field("custom_index()", &self.custom_index())
-            .field("mask()", &self.mask())
-            .field(
-                "shader_binding_table_record_offset()",
-                &self.shader_binding_table_record_offset(),
-            )
-            .field("flags()", &self.flags())
-            .field(
-                "acceleration_structure_reference",
-                &self.acceleration_structure_reference,
-            )
-            .finish()
+/// Color of the sky seen in direction `dir`, which rays that miss every box show.
+fn sky_color(dir: Vec3) -> Vec3 {
+    let dir = dir.normalize();
+    if dir.y >= 0.0 {
+        HORIZON.lerp(ZENITH, dir.y)
+    } else {
+        HORIZON.lerp(GROUND, (-dir.y * 4.0).min(1.0))
     }
 }
 
-#[allow(dead_code)]
-impl AccelerationStructureInstance {
-    const LOW_24_MASK: u32 = 0x00ff_ffff;
-    const MAX_U24: u32 = (1u32 << 24u32) - 1u32;
-
-    #[inline]
-    fn affine_to_rows(mat: &Affine3A) -> [f32; 12] {
-        let row_0 = mat.matrix3.row(0);
-        let row_1 = mat.matrix3.row(1);
-        let row_2 = mat.matrix3.row(2);
-        let translation = mat.translation;
-        [
-            row_0.x,
-            row_0.y,
-            row_0.z,
-            translation.x,
-            row_1.x,
-            row_1.y,
-            row_1.z,
-            translation.y,
-            row_2.x,
-            row_2.y,
-            row_2.z,
-            translation.z,
-        ]
-    }
-
-    #[inline]
-    fn rows_to_affine(rows: &[f32; 12]) -> Affine3A {
-        Affine3A::from_cols_array(&[
-            rows[0], rows[3], rows[6], rows[9], rows[1], rows[4], rows[7], rows[10], rows[2],
-            rows[5], rows[8], rows[11],
-        ])
-    }
-
-    pub fn transform_as_affine(&self) -> Affine3A {
-        Self::rows_to_affine(&self.transform)
-    }
-    pub fn set_transform(&mut self, transform: &Affine3A) {
-        self.transform = Self::affine_to_rows(transform);
-    }
-
-    pub fn custom_index(&self) -> u32 {
-        self.custom_index_and_mask & Self::LOW_24_MASK
-    }
-
-    pub fn mask(&self) -> u8 {
-        (self.custom_index_and_mask >> 24) as u8
-    }
-
-    pub fn shader_binding_table_record_offset(&self) -> u32 {
-        self.shader_binding_table_record_offset_and_flags & Self::LOW_24_MASK
-    }
-
-    pub fn flags(&self) -> u8 {
-        (self.shader_binding_table_record_offset_and_flags >> 24) as u8
-    }
-
-    pub fn set_custom_index(&mut self, custom_index: u32) {
-        debug_assert!(
-            custom_index <= Self::MAX_U24,
-            "custom_index uses more than 24 bits! {custom_index} > {}",
-            Self::MAX_U24
-        );
-        self.custom_index_and_mask =
-            (custom_index & Self::LOW_24_MASK) | (self.custom_index_and_mask & !Self::LOW_24_MASK)
-    }
-
-    pub fn set_mask(&mut self, mask: u8) {
-        self.custom_index_and_mask =
-            (self.custom_index_and_mask & Self::LOW_24_MASK) | (u32::from(mask) << 24)
-    }
-
-    pub fn set_shader_binding_table_record_offset(
-        &mut self,
-        shader_binding_table_record_offset: u32,
-    ) {
-        debug_assert!(shader_binding_table_record_offset <= Self::MAX_U24, "shader_binding_table_record_offset uses more than 24 bits! {shader_binding_table_record_offset} > {}", Self::MAX_U24);
-        self.shader_binding_table_record_offset_and_flags = (shader_binding_table_record_offset
-            & Self::LOW_24_MASK)
-            | (self.shader_binding_table_record_offset_and_flags & !Self::LOW_24_MASK)
+/// Texels of the environment cube map, face by face in the order of the cube map layers.
+fn create_environment_data() -> Vec<[u8; 4]> {
+    let size = ENVIRONMENT_SIZE as usize;
+    let mut texels = Vec::with_capacity(6 * size * size);
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                // Texel center in [-1, 1], with v pointing down.
+                let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let dir = match face {
+                    0 => Vec3::new(1.0, -v, -u),
+                    1 => Vec3::new(-1.0, -v, u),
+                    2 => Vec3::new(u, 1.0, v),
+                    3 => Vec3::new(u, -1.0, -v),
+                    4 => Vec3::new(u, -v, 1.0),
+                    _ => Vec3::new(-u, -v, -1.0),
+                };
+                let color = sky_color(dir) * 255.0;
+                texels.push([color.x as u8, color.y as u8, color.z as u8, 255]);
+            }
+        }
     }
+    texels
+}
 
-    pub fn set_flags(&mut self, flags: u8) {
-        self.shader_binding_table_record_offset_and_flags =
-            (self.shader_binding_table_record_offset_and_flags & Self::LOW_24_MASK)
-                | (u32::from(flags) << 24)
-    }
+/// Camera that stays in place and looks around following the cursor.
+struct Camera {
+    screen_size: (u32, u32),
+    yaw: f32,
+    pitch: f32,
+}
 
-    pub fn new(
-        transform: &Affine3A,
-        custom_index: u32,
-        mask: u8,
-        shader_binding_table_record_offset: u32,
-        flags: u8,
-        acceleration_structure_reference: u64,
-    ) -> Self {
-        debug_assert!(
-            custom_index <= Self::MAX_U24,
-            "custom_index uses more than 24 bits! {custom_index} > {}",
-            Self::MAX_U24
-        );
-        debug_assert!(
-            shader_binding_table_record_offset <= Self::MAX_U24,
-            "shader_binding_table_record_offset uses more than 24 bits! {shader_binding_table_record_offset} > {}", Self::MAX_U24
-        );
-        AccelerationStructureInstance {
-            transform: Self::affine_to_rows(transform),
-            custom_index_and_mask: (custom_index & Self::MAX_U24) | (u32::from(mask) << 24),
-            shader_binding_table_record_offset_and_flags: (shader_binding_table_record_offset
-                & Self::MAX_U24)
-                | (u32::from(flags) << 24),
-            acceleration_structure_reference,
-        }
+const CAMERA_POSITION: Vec3 = Vec3::new(0.0, 0.0, 2.5);
+
+impl Camera {
+    fn to_uniforms(&self) -> RayCameraUniforms {
+        let direction =
+            Quat::from_euler(glam::EulerRot::YXZ, self.yaw, self.pitch, 0.0) * Vec3::NEG_Z;
+        RayCamera::new(
+            CAMERA_POSITION,
+            CAMERA_POSITION + direction,
+            59.0,
+            self.screen_size.0 as f32 / self.screen_size.1 as f32,
+        )
+        .to_uniforms()
     }
 }
 
@@ -205,15 +121,40 @@ impl<F: Future<Output = Option<wgpu::Error>>> Future for ErrorFuture<F> {
     }
 }
 
+/// How rays are traced against the AABBs.
+#[allow(dead_code)]
+enum Tracer {
+    /// Ray queries against a TLAS built from `aabb_buf`.
+    Hardware {
+        blas: rt::Blas,
+        tlas_package: rt::TlasPackage,
+    },
+    /// Brute-force intersection in a compute shader, for adapters without ray tracing.
+    Software { instance_buf: wgpu::Buffer },
+}
+
+const RAY_TRACING_FEATURES: wgpu::Features =
+    wgpu::Features::RAY_QUERY.union(wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE);
+
+/// World to object transforms, in the layout `software.wgsl` reads them.
+fn instance_data(instances: &[Affine3A]) -> Vec<[[f32; 4]; 4]> {
+    instances
+        .iter()
+        .map(|instance| Mat4::from(instance.inverse()).to_cols_array_2d())
+        .collect()
+}
+
 #[allow(dead_code)]
 struct Example {
     rt_target: wgpu::Texture,
     rt_view: wgpu::TextureView,
     sampler: wgpu::Sampler,
+    environment_view: wgpu::TextureView,
+    camera: Camera,
     uniform_buf: wgpu::Buffer,
     aabb_buf: wgpu::Buffer,
-    blas: rt::Blas,
-    tlas_package: rt::TlasPackage,
+    instances: Vec<Affine3A>,
+    tracer: Tracer,
     compute_pipeline: wgpu::ComputePipeline,
     compute_bind_group: wgpu::BindGroup,
     blit_pipeline: wgpu::RenderPipeline,
@@ -222,16 +163,16 @@ struct Example {
 }
 
 impl crate::framework::Example for Example {
-    fn required_features() -> wgpu::Features {
-        wgpu::Features::TEXTURE_BINDING_ARRAY
-            | wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY
-            | wgpu::Features::VERTEX_WRITABLE_STORAGE
-            | wgpu::Features::RAY_QUERY
-            | wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE
+    // Without ray tracing the example falls back to `software.wgsl`.
+    fn optional_features() -> wgpu::Features {
+        RAY_TRACING_FEATURES
     }
 
     fn required_downlevel_capabilities() -> wgpu::DownlevelCapabilities {
-        wgpu::DownlevelCapabilities::default()
+        wgpu::DownlevelCapabilities {
+            flags: wgpu::DownlevelFlags::COMPUTE_SHADERS,
+            ..Default::default()
+        }
     }
     fn required_limits() -> wgpu::Limits {
         wgpu::Limits::default()
@@ -239,7 +180,7 @@ impl crate::framework::Example for Example {
 
     fn init(
         config: &wgpu::SurfaceConfiguration,
-        _adapter: &wgpu::Adapter,
+        adapter: &wgpu::Adapter,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> Self {
@@ -255,14 +196,14 @@ impl crate::framework::Example for Example {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
+            format: wgpu::TextureFormat::Rgba16Float,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
-            view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
+            view_formats: &[wgpu::TextureFormat::Rgba16Float],
         });
 
         let rt_view = rt_target.create_view(&wgpu::TextureViewDescriptor {
             label: None,
-            format: Some(wgpu::TextureFormat::Rgba8Unorm),
+            format: Some(wgpu::TextureFormat::Rgba16Float),
             dimension: Some(wgpu::TextureViewDimension::D2),
             aspect: wgpu::TextureAspect::All,
             base_mip_level: 0,
@@ -282,61 +223,81 @@ impl crate::framework::Example for Example {
             ..Default::default()
         });
 
-        let uniforms = {
-            let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.5), Vec3::ZERO, Vec3::Y);
-            let proj = Mat4::perspective_rh(
-                59.0_f32.to_radians(),
-                config.width as f32 / config.height as f32,
-                0.001,
-                1000.0,
-            );
+        let environment = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("environment"),
+                size: wgpu::Extent3d {
+                    width: ENVIRONMENT_SIZE,
+                    height: ENVIRONMENT_SIZE,
+                    depth_or_array_layers: 6,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&create_environment_data()),
+        );
 
-            Uniforms {
-                view_inverse: view.inverse().to_cols_array_2d(),
-                proj_inverse: proj.inverse().to_cols_array_2d(),
-            }
+        let environment_view = environment.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("environment"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let camera = Camera {
+            screen_size: (config.width, config.height),
+            yaw: 0.0,
+            pitch: 0.0,
         };
 
         let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[uniforms]),
-            usage: wgpu::BufferUsages::UNIFORM,
+            contents: bytemuck::cast_slice(&[camera.to_uniforms()]),
+            // COPY_SRC lets the tests read the camera back.
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
         });
 
         let aabb_data = create_aabbs();
 
+        // The device may lack the ray tracing features even if the adapter has them.
+        let hardware =
+            adapter.supports_ray_tracing() && device.features().contains(RAY_TRACING_FEATURES);
+
         let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("aabb Buffer"),
             contents: bytemuck::cast_slice(&aabb_data),
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::BLAS_INPUT,
         });
 
-        let blas_geo_size_desc = rt::BlasProceduralGeometrySizeDescriptor {
-            primitive_count: aabb_data.len() as u32,
-            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
-        };
-
-        let blas = device.create_blas(
-            &rt::CreateBlasDescriptor {
-                label: None,
-                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
-                update_mode: rt::AccelerationStructureUpdateMode::Build,
-            },
-            rt::BlasGeometrySizeDescriptors::AABBs {
-                desc: vec![blas_geo_size_desc.clone()],
-            },
-        );
+        let dist = 3.0;
 
-        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
-            label: None,
-            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
-            update_mode: rt::AccelerationStructureUpdateMode::Build,
-            max_instances: side_count * side_count,
-        });
+        let instances = (0..side_count * side_count)
+            .map(|i| {
+                Affine3A::from_rotation_translation(
+                    Quat::from_rotation_y(45.9_f32.to_radians()),
+                    Vec3 {
+                        x: (i % side_count) as f32 * dist,
+                        y: (i / side_count) as f32 * dist,
+                        z: -30.0,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("rt_computer"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(if hardware {
+                include_str!("shader.wgsl")
+            } else {
+                include_str!("software.wgsl")
+            })),
         });
 
         let blit_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -355,25 +316,6 @@ impl crate::framework::Example for Example {
 
         let compute_bind_group_layout = compute_pipeline.get_bind_group_layout(0);
 
-        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &compute_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&rt_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: uniform_buf.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::AccelerationStructure(&tlas),
-                },
-            ],
-        });
-
         let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("blit"),
             layout: None,
@@ -416,52 +358,135 @@ impl crate::framework::Example for Example {
             ],
         });
 
-        let mut tlas_package = rt::TlasPackage::new(tlas, side_count * side_count);
+        let (tracer, compute_bind_group) = if hardware {
+            let blas_geo_size_desc = rt::BlasProceduralGeometrySizeDescriptor {
+                primitive_count: aabb_data.len() as u32,
+                flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+            };
+
+            let blas = device.create_blas(
+                &rt::CreateBlasDescriptor {
+                    label: None,
+                    flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                    update_mode: rt::AccelerationStructureUpdateMode::Build,
+                },
+                rt::BlasGeometrySizeDescriptors::AABBs {
+                    desc: vec![blas_geo_size_desc.clone()],
+                },
+            );
 
-        let dist = 3.0;
+            let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+                max_instances: side_count * side_count,
+            });
 
-        for x in 0..side_count {
-            for y in 0..side_count {
-                *tlas_package
-                    .get_mut_single((x + y * side_count) as usize)
-                    .unwrap() = Some(rt::TlasInstance::new(
-                    &blas,
-                    AccelerationStructureInstance::affine_to_rows(
-                        &Affine3A::from_rotation_translation(
-                            Quat::from_rotation_y(45.9_f32.to_radians()),
-                            Vec3 {
-                                x: x as f32 * dist,
-                                y: y as f32 * dist,
-                                z: -30.0,
-                            },
-                        ),
+            let tlas_package = rt::TlasPackage::new_with_instances(
+                tlas,
+                instances
+                    .iter()
+                    .map(|instance| {
+                        Some(rt::TlasInstance::new(
+                            &blas,
+                            rt::transform_rows_from_columns(instance.to_cols_array_2d()),
+                            0,
+                            0xff,
+                        ))
+                    })
+                    .collect(),
+            );
+
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &blas,
+                    geometry: rt::BlasGeometries::ProceduralGeometries(
+                        vec![rt::BlasProceduralGeometry {
+                            size: &blas_geo_size_desc,
+                            bounding_box_buffer: &aabb_buf,
+                            bounding_box_buffer_offset: 0,
+                            bounding_box_stride: mem::size_of::<Aabb>() as u64,
+                        }]
+                        .into(),
                     ),
-                    0,
-                    0xff,
-                ));
-            }
-        }
+                    mode: None,
+                }),
+                iter::once(&tlas_package),
+            );
 
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            queue.submit(Some(encoder.finish()));
 
-        encoder.build_acceleration_structures(
-            iter::once(&rt::BlasBuildEntry {
-                blas: &blas,
-                geometry: rt::BlasGeometries::ProceduralGeometries(vec![
-                    rt::BlasProceduralGeometry {
-                        size: &blas_geo_size_desc,
-                        bounding_box_buffer: &aabb_buf,
-                        bounding_box_buffer_offset: 0,
-                        bounding_box_stride: mem::size_of::<Aabb>() as u64,
+            let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &compute_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&rt_view),
                     },
-                ]),
-            }),
-            // iter::empty(),
-            iter::once(&tlas_package),
-        );
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: uniform_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: tlas_package.as_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&environment_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
 
-        queue.submit(Some(encoder.finish()));
+            (Tracer::Hardware { blas, tlas_package }, compute_bind_group)
+        } else {
+            let instance_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("instance Buffer"),
+                contents: bytemuck::cast_slice(&instance_data(&instances)),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+
+            let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &compute_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&rt_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: uniform_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: aabb_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: instance_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&environment_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+            (Tracer::Software { instance_buf }, compute_bind_group)
+        };
 
         let start_inst = Instant::now();
 
@@ -469,10 +494,12 @@ impl crate::framework::Example for Example {
             rt_target,
             rt_view,
             sampler,
+            environment_view,
+            camera,
             uniform_buf,
             aabb_buf,
-            blas,
-            tlas_package,
+            instances,
+            tracer,
             compute_pipeline,
             compute_bind_group,
             blit_pipeline,
@@ -481,16 +508,26 @@ impl crate::framework::Example for Example {
         }
     }
 
-    fn update(&mut self, _event: winit::event::WindowEvent) {
-        //empty
+    #[allow(clippy::single_match)]
+    fn update(&mut self, event: winit::event::WindowEvent) {
+        match event {
+            winit::event::WindowEvent::CursorMoved { position, .. } => {
+                let norm_x = position.x as f32 / self.camera.screen_size.0 as f32 - 0.5;
+                let norm_y = position.y as f32 / self.camera.screen_size.1 as f32 - 0.5;
+                self.camera.yaw = -norm_x * consts::FRAC_PI_2;
+                self.camera.pitch = -norm_y * consts::FRAC_PI_2;
+            }
+            _ => {}
+        }
     }
 
     fn resize(
         &mut self,
-        _config: &wgpu::SurfaceConfiguration,
+        config: &wgpu::SurfaceConfiguration,
         _device: &wgpu::Device,
         _queue: &wgpu::Queue,
     ) {
+        self.camera.screen_size = (config.width, config.height);
     }
 
     fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -498,30 +535,51 @@ impl crate::framework::Example for Example {
 
         let anim_time = self.start_inst.elapsed().as_secs_f64() as f32;
 
-        self.tlas_package
-            .get_mut_single(0)
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .transform =
-            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_rotation_translation(
-                Quat::from_euler(
-                    glam::EulerRot::XYZ,
-                    anim_time * 0.342,
-                    anim_time * 0.254,
-                    anim_time * 0.832,
-                ),
-                Vec3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: -6.0,
-                },
-            ));
+        self.instances[0] = Affine3A::from_rotation_translation(
+            Quat::from_euler(
+                glam::EulerRot::XYZ,
+                anim_time * 0.342,
+                anim_time * 0.254,
+                anim_time * 0.832,
+            ),
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: -6.0,
+            },
+        );
+
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            bytemuck::cast_slice(&[self.camera.to_uniforms()]),
+        );
 
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        encoder.build_acceleration_structures(iter::empty(), iter::once(&self.tlas_package));
+        match self.tracer {
+            Tracer::Hardware {
+                ref mut tlas_package,
+                ..
+            } => {
+                tlas_package
+                    .get_mut_single(0)
+                    .unwrap()
+                    .as_mut()
+                    .unwrap()
+                    .transform =
+                    rt::transform_rows_from_columns(self.instances[0].to_cols_array_2d());
+                encoder.build_acceleration_structures(iter::empty(), iter::once(&*tlas_package));
+            }
+            Tracer::Software { ref instance_buf } => {
+                queue.write_buffer(
+                    instance_buf,
+                    0,
+                    bytemuck::cast_slice(&instance_data(&self.instances[..1])),
+                );
+            }
+        }
 
         {
             let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
@@ -569,6 +627,28 @@ static TEST: crate::framework::ExampleTestParams = crate::framework::ExampleTest
     image_path: "/examples/src/ray_aabb_compute/screenshot.png",
     width: 1024,
     height: 768,
+    optional_features: RAY_TRACING_FEATURES,
+    base_test_parameters: wgpu_test::TestParameters {
+        required_features: <Example as crate::framework::Example>::required_features(),
+        required_limits: <Example as crate::framework::Example>::required_limits(),
+        force_fxc: false,
+        skips: vec![],
+        failures: Vec::new(),
+        required_downlevel_caps:
+            <Example as crate::framework::Example>::required_downlevel_capabilities(),
+    },
+    comparisons: &[wgpu_test::ComparisonType::Mean(0.02)],
+    _phantom: std::marker::PhantomData::<Example>,
+};
+
+// Without the ray tracing features the brute-force path has to produce the same image.
+#[cfg(test)]
+#[wgpu_test::gpu_test]
+static TEST_SOFTWARE: crate::framework::ExampleTestParams = crate::framework::ExampleTestParams {
+    name: "ray_aabb_compute_software",
+    image_path: "/examples/src/ray_aabb_compute/screenshot.png",
+    width: 1024,
+    height: 768,
     optional_features: wgpu::Features::default(),
     base_test_parameters: wgpu_test::TestParameters {
         required_features: <Example as crate::framework::Example>::required_features(),
@@ -582,3 +662,90 @@ static TEST: crate::framework::ExampleTestParams = crate::framework::ExampleTest
     comparisons: &[wgpu_test::ComparisonType::Mean(0.02)],
     _phantom: std::marker::PhantomData::<Example>,
 };
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use wgpu_test::{gpu_test, GpuTestConfiguration};
+
+    use super::Example;
+    use crate::framework::{Example as _, RayCameraUniforms};
+
+    #[gpu_test]
+    static RAY_AABB_COMPUTE_CAMERA: GpuTestConfiguration = GpuTestConfiguration::new()
+        .parameters(
+            wgpu_test::TestParameters::default()
+                .features(Example::required_features())
+                .limits(Example::required_limits()),
+        )
+        .run_sync(|ctx| {
+            let (width, height) = (256, 256);
+            let format = wgpu::TextureFormat::Rgba8Unorm;
+            let mut example = Example::init(
+                &wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format,
+                    width,
+                    height,
+                    desired_maximum_frame_latency: 2,
+                    present_mode: wgpu::PresentMode::Fifo,
+                    alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                    view_formats: vec![format],
+                },
+                &ctx.adapter,
+                &ctx.device,
+                &ctx.queue,
+            );
+            let initial = example.camera.to_uniforms();
+
+            // Moving the cursor to the left edge turns the camera to the left.
+            example.update(winit::event::WindowEvent::CursorMoved {
+                device_id: unsafe { winit::event::DeviceId::dummy() },
+                position: winit::dpi::PhysicalPosition::new(0.0, height as f64 / 2.0),
+            });
+            let expected = example.camera.to_uniforms();
+            assert_ne!(expected.view_inverse, initial.view_inverse);
+            assert_eq!(expected.proj_inverse, initial.proj_inverse);
+
+            let target = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            example.render(
+                &target.create_view(&wgpu::TextureViewDescriptor::default()),
+                &ctx.device,
+                &ctx.queue,
+            );
+
+            let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: mem::size_of::<RayCameraUniforms>() as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            encoder.copy_buffer_to_buffer(&example.uniform_buf, 0, &readback, 0, readback.size());
+            ctx.queue.submit(Some(encoder.finish()));
+
+            readback.slice(..).map_async(wgpu::MapMode::Read, |_| ());
+            ctx.device.poll(wgpu::Maintain::Wait);
+
+            let uploaded: RayCameraUniforms =
+                bytemuck::pod_read_unaligned(&readback.slice(..).get_mapped_range());
+            assert_eq!(uploaded.view_inverse, expected.view_inverse);
+            assert_eq!(uploaded.proj_inverse, expected.proj_inverse);
+        });
+}
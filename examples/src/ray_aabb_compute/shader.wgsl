@@ -37,6 +37,8 @@ struct RayIntersection {
     front_face: bool,
     object_to_world: mat4x3<f32>,
     world_to_object: mat4x3<f32>,
+    object_ray_origin: vec3<f32>,
+    object_ray_direction: vec3<f32>,
 }
 */
 
@@ -46,7 +48,7 @@ struct Uniforms {
 };
 
 @group(0) @binding(0)
-var output: texture_storage_2d<rgba8unorm, write>;
+var output: texture_storage_2d<rgba16float, write>;
 
 @group(0) @binding(1)
 var<uniform> uniforms: Uniforms;
@@ -54,29 +56,32 @@ var<uniform> uniforms: Uniforms;
 @group(0) @binding(2)
 var acc_struct: acceleration_structure;
 
-fn query_loop(pos: vec3<f32>, dir: vec3<f32>, acs: acceleration_structure) -> RayIntersection {
-    var rq: ray_query;
-    rayQueryInitialize(&rq, acs, RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.1, 100.0, pos, dir));
+// Sky shown by rays that miss every box.
+@group(0) @binding(4)
+var environment: texture_cube<f32>;
 
-    // while(rayQueryProceed(&rq)) {
-    //     let intersection = rayQueryGetIntersection(&rq);
-    //     if (intersection.kind == RAY_QUERY_INTERSECTION_AABB) {
-    //         rayQueryGenerateIntersection(&rq, 0.0);
-    //     }
-    // }
+@group(0) @binding(5)
+var environment_sampler: sampler;
 
-    // return rayQueryGetCommittedIntersection(&rq);
+// AABB candidates are never committed on their own, so report whether the ray
+// entered any bounding box. This matches the brute-force test in `software.wgsl`.
+fn query_loop(pos: vec3<f32>, dir: vec3<f32>, acs: acceleration_structure) -> bool {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acs, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.1, 100.0, pos, dir));
 
-    while (rayQueryProceed(&rq)) {}
+    var hit = false;
+    while (rayQueryProceed(&rq)) {
+        if (rayQueryGetCandidateIntersectionType(&rq) == RAY_QUERY_INTERSECTION_AABB) {
+            hit = true;
+        }
+    }
 
-    return rayQueryGetCommittedIntersection(&rq);
+    return hit;
 }
 
 @compute @workgroup_size(8, 8)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let target_size = textureDimensions(output);
-    var color =  vec4<f32>(vec2<f32>(global_id.xy) / vec2<f32>(target_size), 0.0, 1.0);
-
 
 	let pixel_center = vec2<f32>(global_id.xy) + vec2<f32>(0.5);
 	let in_uv = pixel_center/vec2<f32>(target_size.xy);
@@ -86,9 +91,8 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 	let temp = uniforms.proj_inv * vec4<f32>(d.x, d.y, 1.0, 1.0);
 	let direction = (uniforms.view_inv * vec4<f32>(normalize(temp.xyz), 0.0)).xyz;
 
-    let intersection = query_loop(origin, direction, acc_struct);
-
-    if (intersection.kind != RAY_QUERY_INTERSECTION_NONE) {
+    var color = textureSampleLevel(environment, environment_sampler, direction, 0.0);
+    if (query_loop(origin, direction, acc_struct)) {
         color = vec4<f32>(1.0, 1.0, 1.0, 1.0);
     }
 
@@ -0,0 +1,79 @@
+// Brute-force fallback for adapters without ray queries: every ray is tested
+// against every AABB of every instance, reading the same `aabb_buf` the BLAS is
+// built from.
+
+struct Uniforms {
+    view_inv: mat4x4<f32>,
+    proj_inv: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var output: texture_storage_2d<rgba16float, write>;
+
+@group(0) @binding(1)
+var<uniform> uniforms: Uniforms;
+
+// Tightly packed `[min, max]` pairs of `vec3<f32>`, like the BLAS input.
+@group(0) @binding(2)
+var<storage, read> aabbs: array<f32>;
+
+// World to object transform of every instance.
+@group(0) @binding(3)
+var<storage, read> instances: array<mat4x4<f32>>;
+
+// Sky shown by rays that miss every box.
+@group(0) @binding(4)
+var environment: texture_cube<f32>;
+
+@group(0) @binding(5)
+var environment_sampler: sampler;
+
+// Returns whether the ray enters the box between `t_min` and `t_max`.
+fn ray_aabb(origin: vec3<f32>, dir: vec3<f32>, box_min: vec3<f32>, box_max: vec3<f32>, t_min: f32, t_max: f32) -> bool {
+    let inv_dir = 1.0 / dir;
+    let t0 = (box_min - origin) * inv_dir;
+    let t1 = (box_max - origin) * inv_dir;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let t_near = max(max(near.x, near.y), max(near.z, t_min));
+    let t_far = min(min(far.x, far.y), min(far.z, t_max));
+    return t_near <= t_far;
+}
+
+// Software counterpart of a ray query that reports whether any AABB candidate was found.
+fn query_loop(pos: vec3<f32>, dir: vec3<f32>) -> bool {
+    let aabb_count = arrayLength(&aabbs) / 6u;
+    for (var i = 0u; i < arrayLength(&instances); i++) {
+        // Transforming the ray into object space keeps `t` unchanged.
+        let object_pos = (instances[i] * vec4<f32>(pos, 1.0)).xyz;
+        let object_dir = (instances[i] * vec4<f32>(dir, 0.0)).xyz;
+        for (var j = 0u; j < aabb_count; j++) {
+            let box_min = vec3<f32>(aabbs[j * 6u], aabbs[j * 6u + 1u], aabbs[j * 6u + 2u]);
+            let box_max = vec3<f32>(aabbs[j * 6u + 3u], aabbs[j * 6u + 4u], aabbs[j * 6u + 5u]);
+            if (ray_aabb(object_pos, object_dir, box_min, box_max, 0.1, 100.0)) {
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let target_size = textureDimensions(output);
+
+    let pixel_center = vec2<f32>(global_id.xy) + vec2<f32>(0.5);
+    let in_uv = pixel_center/vec2<f32>(target_size.xy);
+    let d = in_uv * 2.0 - 1.0;
+
+    let origin = (uniforms.view_inv * vec4<f32>(0.0,0.0,0.0,1.0)).xyz;
+    let temp = uniforms.proj_inv * vec4<f32>(d.x, d.y, 1.0, 1.0);
+    let direction = (uniforms.view_inv * vec4<f32>(normalize(temp.xyz), 0.0)).xyz;
+
+    var color = textureSampleLevel(environment, environment_sampler, direction, 0.0);
+    if (query_loop(origin, direction)) {
+        color = vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+
+    textureStore(output, global_id.xy, color);
+}
@@ -1,9 +1,11 @@
 use std::{borrow::Cow, future::Future, iter, mem, pin::Pin, task, time::Instant};
 
 use bytemuck::{Pod, Zeroable};
-use glam::{Affine3A, Mat4, Quat, Vec3};
+use glam::{Affine3A, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
+use crate::framework::RayCamera;
+
 use rt::traits::*;
 use wgpu::{ray_tracing as rt, StoreOp};
 
@@ -68,158 +70,6 @@ fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
     (vertex_data.to_vec(), index_data.to_vec())
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct Uniforms {
-    view_inverse: [[f32; 4]; 4],
-    proj_inverse: [[f32; 4]; 4],
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct AccelerationStructureInstance {
-    transform: [f32; 12],
-    custom_index_and_mask: u32,
-    shader_binding_table_record_offset_and_flags: u32,
-    acceleration_structure_reference: u64,
-}
-
-impl std::fmt::Debug for AccelerationStructureInstance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Instance")
-            .field("transform", &self.transform)
-            .field("custom_index()", &self.custom_index())
-            .field("mask()", &self.mask())
-            .field(
-                "shader_binding_table_record_offset()",
-                &self.shader_binding_table_record_offset(),
-            )
-            .field("flags()", &self.flags())
-            .field(
-                "acceleration_structure_reference",
-                &self.acceleration_structure_reference,
-            )
-            .finish()
-    }
-}
-
-#[allow(dead_code)]
-impl AccelerationStructureInstance {
-    const LOW_24_MASK: u32 = 0x00ff_ffff;
-    const MAX_U24: u32 = (1u32 << 24u32) - 1u32;
-
-    #[inline]
-    fn affine_to_rows(mat: &Affine3A) -> [f32; 12] {
-        let row_0 = mat.matrix3.row(0);
-        let row_1 = mat.matrix3.row(1);
-        let row_2 = mat.matrix3.row(2);
-        let translation = mat.translation;
-        [
-            row_0.x,
-            row_0.y,
-            row_0.z,
-            translation.x,
-            row_1.x,
-            row_1.y,
-            row_1.z,
-            translation.y,
-            row_2.x,
-            row_2.y,
-            row_2.z,
-            translation.z,
-        ]
-    }
-
-    #[inline]
-    fn rows_to_affine(rows: &[f32; 12]) -> Affine3A {
-        Affine3A::from_cols_array(&[
-            rows[0], rows[3], rows[6], rows[9], rows[1], rows[4], rows[7], rows[10], rows[2],
-            rows[5], rows[8], rows[11],
-        ])
-    }
-
-    pub fn transform_as_affine(&self) -> Affine3A {
-        Self::rows_to_affine(&self.transform)
-    }
-    pub fn set_transform(&mut self, transform: &Affine3A) {
-        self.transform = Self::affine_to_rows(transform);
-    }
-
-    pub fn custom_index(&self) -> u32 {
-        self.custom_index_and_mask & Self::LOW_24_MASK
-    }
-
-    pub fn mask(&self) -> u8 {
-        (self.custom_index_and_mask >> 24) as u8
-    }
-
-    pub fn shader_binding_table_record_offset(&self) -> u32 {
-        self.shader_binding_table_record_offset_and_flags & Self::LOW_24_MASK
-    }
-
-    pub fn flags(&self) -> u8 {
-        (self.shader_binding_table_record_offset_and_flags >> 24) as u8
-    }
-
-    pub fn set_custom_index(&mut self, custom_index: u32) {
-        debug_assert!(
-            custom_index <= Self::MAX_U24,
-            "custom_index uses more than 24 bits! {custom_index} > {}",
-            Self::MAX_U24
-        );
-        self.custom_index_and_mask =
-            (custom_index & Self::LOW_24_MASK) | (self.custom_index_and_mask & !Self::LOW_24_MASK)
-    }
-
-    pub fn set_mask(&mut self, mask: u8) {
-        self.custom_index_and_mask =
-            (self.custom_index_and_mask & Self::LOW_24_MASK) | (u32::from(mask) << 24)
-    }
-
-    pub fn set_shader_binding_table_record_offset(
-        &mut self,
-        shader_binding_table_record_offset: u32,
-    ) {
-        debug_assert!(shader_binding_table_record_offset <= Self::MAX_U24, "shader_binding_table_record_offset uses more than 24 bits! {shader_binding_table_record_offset} > {}", Self::MAX_U24);
-        self.shader_binding_table_record_offset_and_flags = (shader_binding_table_record_offset
-            & Self::LOW_24_MASK)
-            | (self.shader_binding_table_record_offset_and_flags & !Self::LOW_24_MASK)
-    }
-
-    pub fn set_flags(&mut self, flags: u8) {
-        self.shader_binding_table_record_offset_and_flags =
-            (self.shader_binding_table_record_offset_and_flags & Self::LOW_24_MASK)
-                | (u32::from(flags) << 24)
-    }
-
-    pub fn new(
-        transform: &Affine3A,
-        custom_index: u32,
-        mask: u8,
-        shader_binding_table_record_offset: u32,
-        flags: u8,
-        acceleration_structure_reference: u64,
-    ) -> Self {
-        debug_assert!(
-            custom_index <= Self::MAX_U24,
-            "custom_index uses more than 24 bits! {custom_index} > {}",
-            Self::MAX_U24
-        );
-        debug_assert!(
-            shader_binding_table_record_offset <= Self::MAX_U24,
-            "shader_binding_table_record_offset uses more than 24 bits! {shader_binding_table_record_offset} > {}", Self::MAX_U24
-        );
-        AccelerationStructureInstance {
-            transform: Self::affine_to_rows(transform),
-            custom_index_and_mask: (custom_index & Self::MAX_U24) | (u32::from(mask) << 24),
-            shader_binding_table_record_offset_and_flags: (shader_binding_table_record_offset
-                & Self::MAX_U24)
-                | (u32::from(flags) << 24),
-            acceleration_structure_reference,
-        }
-    }
-}
-
 /// A wrapper for `pop_error_scope` futures that panics if an error occurs.
 ///
 /// Given a future `inner` of an `Option<E>` for some error type `E`,
@@ -320,20 +170,13 @@ impl crate::framework::Example for Example {
             ..Default::default()
         });
 
-        let uniforms = {
-            let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.5), Vec3::ZERO, Vec3::Y);
-            let proj = Mat4::perspective_rh(
-                59.0_f32.to_radians(),
-                config.width as f32 / config.height as f32,
-                0.001,
-                1000.0,
-            );
-
-            Uniforms {
-                view_inverse: view.inverse().to_cols_array_2d(),
-                proj_inverse: proj.inverse().to_cols_array_2d(),
-            }
-        };
+        let camera = RayCamera::new(
+            Vec3::new(0.0, 0.0, 2.5),
+            Vec3::ZERO,
+            59.0,
+            config.width as f32 / config.height as f32,
+        );
+        let uniforms = camera.to_uniforms();
 
         let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
@@ -467,26 +310,24 @@ impl crate::framework::Example for Example {
 
         let dist = 3.0;
 
-        for x in 0..side_count {
-            for y in 0..side_count {
-                *tlas_package
-                    .get_mut_single((x + y * side_count) as usize)
-                    .unwrap() = Some(rt::TlasInstance::new(
-                    &blas,
-                    AccelerationStructureInstance::affine_to_rows(
-                        &Affine3A::from_rotation_translation(
-                            Quat::from_rotation_y(45.9_f32.to_radians()),
-                            Vec3 {
-                                x: x as f32 * dist,
-                                y: y as f32 * dist,
-                                z: -30.0,
-                            },
-                        ),
-                    ),
-                    0,
-                    0xff,
-                ));
-            }
+        for (i, instance) in tlas_package.iter_mut() {
+            let (x, y) = (i as u32 % side_count, i as u32 / side_count);
+            *instance = Some(rt::TlasInstance::new(
+                &blas,
+                rt::transform_rows_from_columns(
+                    Affine3A::from_rotation_translation(
+                        Quat::from_rotation_y(45.9_f32.to_radians()),
+                        Vec3 {
+                            x: x as f32 * dist,
+                            y: y as f32 * dist,
+                            z: -30.0,
+                        },
+                    )
+                    .to_cols_array_2d(),
+                ),
+                0,
+                0xff,
+            ));
         }
 
         let mut encoder =
@@ -495,16 +336,22 @@ impl crate::framework::Example for Example {
         encoder.build_acceleration_structures(
             iter::once(&rt::BlasBuildEntry {
                 blas: &blas,
-                geometry: rt::BlasGeometries::TriangleGeometries(vec![rt::BlasTriangleGeometry {
-                    size: &blas_geo_size_desc,
-                    vertex_buffer: &vertex_buf,
-                    first_vertex: 0,
-                    vertex_stride: mem::size_of::<Vertex>() as u64,
-                    index_buffer: Some(&index_buf),
-                    index_buffer_offset: Some(0),
-                    transform_buffer: None,
-                    transform_buffer_offset: None,
-                }]),
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &blas_geo_size_desc,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: Some(mem::size_of::<Vertex>() as u64),
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: Some(&index_buf),
+                        index_buffer_offset: Some(0),
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
             }),
             // iter::empty(),
             iter::once(&tlas_package),
@@ -553,8 +400,8 @@ impl crate::framework::Example for Example {
             .unwrap()
             .as_mut()
             .unwrap()
-            .transform =
-            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_rotation_translation(
+            .transform = rt::transform_rows_from_columns(
+            Affine3A::from_rotation_translation(
                 Quat::from_euler(
                     glam::EulerRot::XYZ,
                     anim_time * 0.342,
@@ -566,7 +413,9 @@ impl crate::framework::Example for Example {
                     y: 0.0,
                     z: -6.0,
                 },
-            ));
+            )
+            .to_cols_array_2d(),
+        );
 
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
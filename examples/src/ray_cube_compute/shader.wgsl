@@ -37,6 +37,8 @@ struct RayIntersection {
     front_face: bool,
     object_to_world: mat4x3<f32>,
     world_to_object: mat4x3<f32>,
+    object_ray_origin: vec3<f32>,
+    object_ray_direction: vec3<f32>,
 }
 */
 
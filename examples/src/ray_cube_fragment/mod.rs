@@ -4,6 +4,8 @@ use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
+use crate::framework::RayCamera;
+
 use rt::traits::*;
 use wgpu::ray_tracing as rt;
 
@@ -68,13 +70,6 @@ fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
     (vertex_data.to_vec(), index_data.to_vec())
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct Uniforms {
-    view_inverse: [[f32; 4]; 4],
-    proj_inverse: [[f32; 4]; 4],
-}
-
 /// A wrapper for `pop_error_scope` futures that panics if an error occurs.
 ///
 /// Given a future `inner` of an `Option<E>` for some error type `E`,
@@ -99,7 +94,7 @@ impl<F: Future<Output = Option<wgpu::Error>>> Future for ErrorFuture<F> {
 
 #[allow(dead_code)]
 struct Example {
-    uniforms: Uniforms,
+    camera: RayCamera,
     uniform_buf: wgpu::Buffer,
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
@@ -130,20 +125,13 @@ impl crate::framework::Example for Example {
     ) -> Self {
         let side_count = 8;
 
-        let uniforms = {
-            let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.5), Vec3::ZERO, Vec3::Y);
-            let proj = Mat4::perspective_rh(
-                59.0_f32.to_radians(),
-                config.width as f32 / config.height as f32,
-                0.001,
-                1000.0,
-            );
-
-            Uniforms {
-                view_inverse: view.inverse().to_cols_array_2d(),
-                proj_inverse: proj.inverse().to_cols_array_2d(),
-            }
-        };
+        let camera = RayCamera::new(
+            Vec3::new(0.0, 0.0, 2.5),
+            Vec3::ZERO,
+            59.0,
+            config.width as f32 / config.height as f32,
+        );
+        let uniforms = camera.to_uniforms();
 
         let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
@@ -246,16 +234,22 @@ impl crate::framework::Example for Example {
         encoder.build_acceleration_structures(
             iter::once(&rt::BlasBuildEntry {
                 blas: &blas,
-                geometry: rt::BlasGeometries::TriangleGeometries(vec![rt::BlasTriangleGeometry {
-                    size: &blas_geo_size_desc,
-                    vertex_buffer: &vertex_buf,
-                    first_vertex: 0,
-                    vertex_stride: mem::size_of::<Vertex>() as u64,
-                    index_buffer: Some(&index_buf),
-                    index_buffer_offset: Some(0),
-                    transform_buffer: None,
-                    transform_buffer_offset: None,
-                }]),
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &blas_geo_size_desc,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: Some(mem::size_of::<Vertex>() as u64),
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: Some(&index_buf),
+                        index_buffer_offset: Some(0),
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
             }),
             // iter::empty(),
             iter::once(&tlas_package),
@@ -266,7 +260,7 @@ impl crate::framework::Example for Example {
         let start_inst = Instant::now();
 
         Example {
-            uniforms,
+            camera,
             uniform_buf,
             vertex_buf,
             index_buf,
@@ -286,16 +280,13 @@ impl crate::framework::Example for Example {
         _device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        let proj = Mat4::perspective_rh(
-            59.0_f32.to_radians(),
-            config.width as f32 / config.height as f32,
-            0.001,
-            1000.0,
-        );
-
-        self.uniforms.proj_inverse = proj.inverse().to_cols_array_2d();
+        self.camera.aspect = config.width as f32 / config.height as f32;
 
-        queue.write_buffer(&self.uniform_buf, 0, bytemuck::cast_slice(&[self.uniforms]));
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            bytemuck::cast_slice(&[self.camera.to_uniforms()]),
+        );
     }
 
     fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -309,13 +300,9 @@ impl crate::framework::Example for Example {
 
             let anim_time = self.start_inst.elapsed().as_secs_f64() as f32;
 
-            for x in 0..side_count {
-                for y in 0..side_count {
-                    let instance = self
-                        .tlas_package
-                        .get_mut_single((x + y * side_count) as usize)
-                        .unwrap();
-
+            let mut instances = Vec::with_capacity(side_count * side_count);
+            for y in 0..side_count {
+                for x in 0..side_count {
                     let x = x as f32 / (side_count - 1) as f32;
                     let y = y as f32 / (side_count - 1) as f32;
                     let x = x * 2.0 - 1.0;
@@ -338,9 +325,10 @@ impl crate::framework::Example for Example {
                         .try_into()
                         .unwrap();
 
-                    *instance = Some(rt::TlasInstance::new(&self.blas, transform, 0, 0xff));
+                    instances.push(Some(rt::TlasInstance::new(&self.blas, transform, 0, 0xff)));
                 }
             }
+            self.tlas_package.set_instances(0, &instances);
         }
 
         let mut encoder =
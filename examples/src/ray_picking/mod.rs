@@ -0,0 +1,413 @@
+use std::{borrow::Cow, iter, mem, str::FromStr};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Affine3A, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::framework::{RayCamera, RayCameraUniforms};
+
+use rt::traits::*;
+use wgpu::ray_tracing as rt;
+
+/// Size in pixels of the viewport that cursor positions are given in.
+const VIEWPORT_SIZE: [f32; 2] = [512.0, 512.0];
+
+/// Custom index the shader reports when there is nothing under the cursor.
+const NO_HIT: u32 = u32::MAX;
+
+/// Number of cubes along each side of the grid.
+const GRID_SIZE: usize = 3;
+
+/// Distance between the centers of neighbouring cubes, which are 2 units wide.
+const GRID_SPACING: f32 = 3.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Uniforms {
+    camera: RayCameraUniforms,
+    cursor: [f32; 2],
+    viewport: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct RawPick {
+    custom_index: u32,
+    t: f32,
+}
+
+/// The object under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pick {
+    /// Custom index of the instance that was hit.
+    custom_index: u32,
+    /// Distance from the camera to the hit.
+    distance: f32,
+}
+
+fn required_features() -> wgpu::Features {
+    wgpu::Features::RAY_QUERY | wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE
+}
+
+fn affine_to_rows(mat: &Affine3A) -> [f32; 12] {
+    let row_0 = mat.matrix3.row(0);
+    let row_1 = mat.matrix3.row(1);
+    let row_2 = mat.matrix3.row(2);
+    let translation = mat.translation;
+    [
+        row_0.x,
+        row_0.y,
+        row_0.z,
+        translation.x,
+        row_1.x,
+        row_1.y,
+        row_1.z,
+        translation.y,
+        row_2.x,
+        row_2.y,
+        row_2.z,
+        translation.z,
+    ]
+}
+
+fn create_cube() -> (Vec<[f32; 3]>, Vec<u16>) {
+    let vertex_data = (0..8)
+        .map(|i| {
+            let coord = |bit| if i & bit != 0 { 1.0 } else { -1.0 };
+            [coord(1), coord(2), coord(4)]
+        })
+        .collect();
+
+    // Two triangles per face, indexing the corners by their x, y and z bits.
+    let index_data = [
+        [0, 2, 4, 6], // -x
+        [1, 3, 5, 7], // +x
+        [0, 1, 4, 5], // -y
+        [2, 3, 6, 7], // +y
+        [0, 1, 2, 3], // -z
+        [4, 5, 6, 7], // +z
+    ]
+    .iter()
+    .flat_map(|&[a, b, c, d]| [a, b, c, b, d, c])
+    .collect();
+
+    (vertex_data, index_data)
+}
+
+/// A grid of cubes, and the pipeline casting picking rays against it.
+///
+/// The cubes are numbered row by row starting at the top left, and each instance uses its
+/// number as its custom index.
+struct Picker {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    uniform_buf: wgpu::Buffer,
+    pick_buf: wgpu::Buffer,
+    readback_buf: wgpu::Buffer,
+    camera: RayCamera,
+}
+
+impl Picker {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let (vertex_data, index_data) = create_cube();
+
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertex_data),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&index_data),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+
+        let blas_geo_size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+            vertex_format: wgpu::VertexFormat::Float32x3,
+            vertex_count: vertex_data.len() as u32,
+            index_format: Some(wgpu::IndexFormat::Uint16),
+            index_count: Some(index_data.len() as u32),
+            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+        };
+
+        let blas = device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: Some("Cube"),
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            rt::BlasGeometrySizeDescriptors::Triangles {
+                desc: vec![blas_geo_size_desc.clone()],
+            },
+        );
+
+        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+            label: Some("Grid"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: (GRID_SIZE * GRID_SIZE) as u32,
+        });
+
+        let half = (GRID_SIZE - 1) as f32 / 2.0;
+        let instances = (0..GRID_SIZE * GRID_SIZE)
+            .map(|index| {
+                let (row, column) = (index / GRID_SIZE, index % GRID_SIZE);
+                let position = Vec3::new(
+                    (column as f32 - half) * GRID_SPACING,
+                    (half - row as f32) * GRID_SPACING,
+                    0.0,
+                );
+                let transform = affine_to_rows(&Affine3A::from_translation(position));
+                Some(rt::TlasInstance::new(&blas, transform, index as u32, 0xff))
+            })
+            .collect();
+        let tlas_package = rt::TlasPackage::new_with_instances(tlas, instances);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(
+            iter::once(&rt::BlasBuildEntry {
+                blas: &blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &blas_geo_size_desc,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: Some(&index_buf),
+                        index_buffer_offset: Some(0),
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            }),
+            iter::once(&tlas_package),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let camera = RayCamera {
+            near: 0.1,
+            far: 100.0,
+            ..RayCamera::new(
+                Vec3::new(0.0, 0.0, 10.0),
+                Vec3::ZERO,
+                45.0,
+                VIEWPORT_SIZE[0] / VIEWPORT_SIZE[1],
+            )
+        };
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Uniform Buffer"),
+            size: mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pick_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pick Buffer"),
+            size: mem::size_of::<RawPick>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: mem::size_of::<RawPick>() as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Picking"),
+            layout: None,
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: tlas_package.as_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: pick_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            uniform_buf,
+            pick_buf,
+            readback_buf,
+            camera,
+        }
+    }
+
+    /// Cast a ray through the pixel at `cursor` and return the closest object it hits.
+    async fn pick(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cursor: [f32; 2],
+    ) -> Option<Pick> {
+        let uniforms = Uniforms {
+            camera: self.camera.to_uniforms(),
+            cursor,
+            viewport: VIEWPORT_SIZE,
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniforms));
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_group, &[]);
+            // A single ray is all it takes.
+            cpass.dispatch_workgroups(1, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(
+            &self.pick_buf,
+            0,
+            &self.readback_buf,
+            0,
+            mem::size_of::<RawPick>() as u64,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = self.readback_buf.slice(..);
+        let (sender, receiver) = flume::bounded(1);
+        buffer_slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
+        device.poll(wgpu::Maintain::wait()).panic_on_timeout();
+        receiver.recv_async().await.unwrap().unwrap();
+
+        let pick: RawPick = *bytemuck::from_bytes(&buffer_slice.get_mapped_range());
+        self.readback_buf.unmap();
+
+        (pick.custom_index != NO_HIT).then_some(Pick {
+            custom_index: pick.custom_index,
+            distance: pick.t,
+        })
+    }
+}
+
+#[cfg_attr(test, allow(dead_code))]
+async fn run() {
+    let cursor = match std::env::args()
+        .skip(2)
+        .map(|s| f32::from_str(&s).expect("You must pass the cursor position in pixels!"))
+        .collect::<Vec<_>>()[..]
+    {
+        [x, y] => [x, y],
+        _ => {
+            let default = [VIEWPORT_SIZE[0] / 2.0, VIEWPORT_SIZE[1] / 2.0];
+            println!("No cursor position was provided, defaulting to {default:?}");
+            default
+        }
+    };
+
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .expect("No suitable adapter found");
+    if !adapter.features().contains(required_features()) {
+        println!("The adapter does not support ray queries");
+        return;
+    }
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: required_features(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: wgpu::MemoryHints::MemoryUsage,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let picker = Picker::new(&device, &queue);
+    match picker.pick(&device, &queue, cursor).await {
+        Some(pick) => println!(
+            "Picked object {} at distance {:.2}",
+            pick.custom_index, pick.distance
+        ),
+        None => println!("Nothing under the cursor"),
+    }
+}
+
+pub fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        env_logger::init();
+        pollster::block_on(run());
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init().expect("could not initialize logger");
+        wasm_bindgen_futures::spawn_local(run());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters};
+
+    #[gpu_test]
+    static RAY_PICKING: GpuTestConfiguration = GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_async(|ctx| async move {
+            let picker = Picker::new(&ctx.device, &ctx.queue);
+            let pick = |cursor| picker.pick(&ctx.device, &ctx.queue, cursor);
+
+            // The center cube, straight ahead of the camera.
+            let center = pick([256.0, 256.0]).await.unwrap();
+            assert_eq!(center.custom_index, 4);
+            assert!((center.distance - 9.0).abs() < 0.01, "{center:?}");
+
+            // The top right cube, with the front face center at (3, 3, 1).
+            let top_right = pick([462.0, 50.0]).await.unwrap();
+            assert_eq!(top_right.custom_index, 2);
+            assert!(
+                (top_right.distance - 99.0_f32.sqrt()).abs() < 0.05,
+                "{top_right:?}"
+            );
+
+            // The gap between the columns.
+            assert_eq!(pick([359.0, 256.0]).await, None);
+        });
+}
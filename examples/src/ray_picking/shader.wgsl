@@ -0,0 +1,45 @@
+struct Uniforms {
+    view_inv: mat4x4<f32>,
+    proj_inv: mat4x4<f32>,
+    cursor: vec2<f32>,
+    viewport: vec2<f32>,
+};
+
+struct Pick {
+    custom_index: u32,
+    t: f32,
+};
+
+// Written to `custom_index` when there is nothing under the cursor.
+const NO_HIT: u32 = 0xFFFFFFFFu;
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(0) @binding(1)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(2)
+var<storage, read_write> pick: Pick;
+
+@compute @workgroup_size(1)
+fn main() {
+    // Aim through the center of the pixel under the cursor, with y pointing down in the viewport.
+    let uv = (floor(uniforms.cursor) + vec2<f32>(0.5)) / uniforms.viewport;
+    let d = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+
+    let origin = (uniforms.view_inv * vec4<f32>(0.0, 0.0, 0.0, 1.0)).xyz;
+    let temp = uniforms.proj_inv * vec4<f32>(d.x, d.y, 1.0, 1.0);
+    let direction = (uniforms.view_inv * vec4<f32>(normalize(temp.xyz), 0.0)).xyz;
+
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 200.0, origin, direction));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind == RAY_QUERY_INTERSECTION_NONE) {
+        pick = Pick(NO_HIT, 0.0);
+    } else {
+        pick = Pick(intersection.instance_custom_index, intersection.t);
+    }
+}
@@ -4,6 +4,8 @@ use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
+use crate::framework::RayCamera;
+
 use rt::traits::*;
 use wgpu::ray_tracing as rt;
 
@@ -19,13 +21,6 @@ struct Vertex {
     _p2: [u32; 2],
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct Uniforms {
-    view_inverse: [[f32; 4]; 4],
-    proj_inverse: [[f32; 4]; 4],
-}
-
 /// A wrapper for `pop_error_scope` futures that panics if an error occurs.
 ///
 /// Given a future `inner` of an `Option<E>` for some error type `E`,
@@ -265,7 +260,9 @@ fn upload_scene_components(
                     size,
                     vertex_buffer: &vertices,
                     first_vertex: vertex_range.start as u32,
-                    vertex_stride: mem::size_of::<Vertex>() as u64,
+                    vertex_stride: Some(mem::size_of::<Vertex>() as u64),
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
                     index_buffer: Some(&indices),
                     index_buffer_offset: Some(scene.geometries[i].0.start as u64 * 4),
                     transform_buffer: None,
@@ -275,7 +272,8 @@ fn upload_scene_components(
 
             rt::BlasBuildEntry {
                 blas,
-                geometry: rt::BlasGeometries::TriangleGeometries(triangle_geometries),
+                geometry: rt::BlasGeometries::TriangleGeometries(triangle_geometries.into()),
+                mode: None,
             }
         })
         .collect();
@@ -307,7 +305,7 @@ fn load_scene(device: &wgpu::Device, queue: &wgpu::Queue) -> SceneComponents {
 
 #[allow(dead_code)]
 struct Example {
-    uniforms: Uniforms,
+    camera: RayCamera,
     uniform_buf: wgpu::Buffer,
     tlas_package: rt::TlasPackage,
     pipeline: wgpu::RenderPipeline,
@@ -338,20 +336,13 @@ impl crate::framework::Example for Example {
 
         let scene_components = load_scene(device, queue);
 
-        let uniforms = {
-            let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.5), Vec3::ZERO, Vec3::Y);
-            let proj = Mat4::perspective_rh(
-                59.0_f32.to_radians(),
-                config.width as f32 / config.height as f32,
-                0.001,
-                1000.0,
-            );
-
-            Uniforms {
-                view_inverse: view.inverse().to_cols_array_2d(),
-                proj_inverse: proj.inverse().to_cols_array_2d(),
-            }
-        };
+        let camera = RayCamera::new(
+            Vec3::new(0.0, 0.0, 2.5),
+            Vec3::ZERO,
+            59.0,
+            config.width as f32 / config.height as f32,
+        );
+        let uniforms = camera.to_uniforms();
 
         let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
@@ -434,7 +425,7 @@ impl crate::framework::Example for Example {
         let start_inst = Instant::now();
 
         Example {
-            uniforms,
+            camera,
             uniform_buf,
             tlas_package,
             pipeline,
@@ -452,16 +443,13 @@ impl crate::framework::Example for Example {
         _device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        let proj = Mat4::perspective_rh(
-            59.0_f32.to_radians(),
-            config.width as f32 / config.height as f32,
-            0.001,
-            1000.0,
-        );
-
-        self.uniforms.proj_inverse = proj.inverse().to_cols_array_2d();
+        self.camera.aspect = config.width as f32 / config.height as f32;
 
-        queue.write_buffer(&self.uniform_buf, 0, bytemuck::cast_slice(&[self.uniforms]));
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            bytemuck::cast_slice(&[self.camera.to_uniforms()]),
+        );
     }
 
     fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
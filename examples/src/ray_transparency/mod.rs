@@ -0,0 +1,495 @@
+use std::{borrow::Cow, iter, mem};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Affine3A, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::framework::RayCamera;
+
+use rt::traits::*;
+use wgpu::ray_tracing as rt;
+
+// Instance custom indices, the shader tells the surfaces apart by them.
+const WALL: u32 = 0;
+const RED_GLASS: u32 = 1;
+const BLUE_GLASS: u32 = 2;
+const VOLUMES: u32 = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Aabb {
+    _min: [f32; 3],
+    _max: [f32; 3],
+}
+
+/// Bounding box of a sphere, the shader intersects the sphere itself.
+fn sphere_aabb(center: Vec3, radius: f32) -> Aabb {
+    Aabb {
+        _min: (center - radius).to_array(),
+        _max: (center + radius).to_array(),
+    }
+}
+
+/// The smoke and the solid ball, primitives 0 and 1 of the volumes BLAS. Their shapes have to
+/// match the `SMOKE_*` and `BALL_*` constants of the shader.
+fn create_volumes() -> [Aabb; 2] {
+    [
+        sphere_aabb(Vec3::ZERO, 1.2),
+        sphere_aabb(Vec3::new(1.9, -1.0, 0.5), 0.6),
+    ]
+}
+
+/// A square in the xy plane, spanning `-half_size..half_size`.
+fn create_quad(half_size: f32) -> [[f32; 3]; 4] {
+    [
+        [-half_size, -half_size, 0.0],
+        [half_size, -half_size, 0.0],
+        [-half_size, half_size, 0.0],
+        [half_size, half_size, 0.0],
+    ]
+}
+
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 1, 3];
+
+fn affine_to_rows(mat: &Affine3A) -> [f32; 12] {
+    let row_0 = mat.matrix3.row(0);
+    let row_1 = mat.matrix3.row(1);
+    let row_2 = mat.matrix3.row(2);
+    let translation = mat.translation;
+    [
+        row_0.x,
+        row_0.y,
+        row_0.z,
+        translation.x,
+        row_1.x,
+        row_1.y,
+        row_1.z,
+        translation.y,
+        row_2.x,
+        row_2.y,
+        row_2.z,
+        translation.z,
+    ]
+}
+
+#[allow(dead_code)]
+struct Example {
+    camera: RayCamera,
+    uniform_buf: wgpu::Buffer,
+    blas_s: [rt::Blas; 3],
+    tlas_package: rt::TlasPackage,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl crate::framework::Example for Example {
+    fn required_features() -> wgpu::Features {
+        wgpu::Features::RAY_QUERY | wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE
+    }
+
+    fn required_downlevel_capabilities() -> wgpu::DownlevelCapabilities {
+        wgpu::DownlevelCapabilities::default()
+    }
+    fn required_limits() -> wgpu::Limits {
+        wgpu::Limits::default()
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let wall_vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wall Vertex Buffer"),
+            contents: bytemuck::cast_slice(&create_quad(10.0)),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+        let glass_vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Glass Vertex Buffer"),
+            contents: bytemuck::cast_slice(&create_quad(1.0)),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&QUAD_INDICES),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+        let volumes = create_volumes();
+        let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Volume Buffer"),
+            contents: bytemuck::cast_slice(&volumes),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+
+        let quad_size = |flags| rt::BlasTriangleGeometrySizeDescriptor {
+            vertex_format: wgpu::VertexFormat::Float32x3,
+            vertex_count: 4,
+            index_format: Some(wgpu::IndexFormat::Uint16),
+            index_count: Some(QUAD_INDICES.len() as u32),
+            flags,
+        };
+        // The wall is committed as soon as it is hit. Everything else is reported to the shader
+        // as a candidate, exactly once per ray so that no layer is accumulated twice.
+        let wall_size = quad_size(rt::AccelerationStructureGeometryFlags::OPAQUE);
+        let glass_size =
+            quad_size(rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION);
+        let volumes_size = rt::BlasProceduralGeometrySizeDescriptor {
+            primitive_count: volumes.len() as u32,
+            flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+        };
+
+        let blas_desc = |label| rt::CreateBlasDescriptor {
+            label: Some(label),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        };
+        let triangles = |size: &rt::BlasTriangleGeometrySizeDescriptor| {
+            rt::BlasGeometrySizeDescriptors::Triangles {
+                desc: vec![size.clone()],
+            }
+        };
+        let blas_s = [
+            device.create_blas(&blas_desc("Wall"), triangles(&wall_size)),
+            device.create_blas(&blas_desc("Glass"), triangles(&glass_size)),
+            device.create_blas(
+                &blas_desc("Volumes"),
+                rt::BlasGeometrySizeDescriptors::AABBs {
+                    desc: vec![volumes_size.clone()],
+                },
+            ),
+        ];
+        let [wall_blas, glass_blas, volumes_blas] = &blas_s;
+
+        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+            label: Some("Scene"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 4,
+        });
+        // Two overlapping glass panes in front of a cloud of smoke, all in front of the wall.
+        let instances = [
+            (wall_blas, WALL, Vec3::new(0.0, 0.0, -4.0)),
+            (glass_blas, RED_GLASS, Vec3::new(-0.5, 0.2, 1.0)),
+            (glass_blas, BLUE_GLASS, Vec3::new(0.5, -0.2, 0.0)),
+            (volumes_blas, VOLUMES, Vec3::new(0.0, 0.0, -2.0)),
+        ]
+        .iter()
+        .map(|&(blas, custom_index, position)| {
+            Some(rt::TlasInstance::new(
+                blas,
+                affine_to_rows(&Affine3A::from_translation(position)),
+                custom_index,
+                0xff,
+            ))
+        })
+        .collect();
+        let tlas_package = rt::TlasPackage::new_with_instances(tlas, instances);
+
+        let quad_geometry = |size, vertex_buffer| {
+            rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size,
+                    vertex_buffer,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: Some(&index_buf),
+                    index_buffer_offset: Some(0),
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            )
+        };
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(
+            [
+                rt::BlasBuildEntry {
+                    blas: wall_blas,
+                    geometry: quad_geometry(&wall_size, &wall_vertex_buf),
+                    mode: None,
+                },
+                rt::BlasBuildEntry {
+                    blas: glass_blas,
+                    geometry: quad_geometry(&glass_size, &glass_vertex_buf),
+                    mode: None,
+                },
+                rt::BlasBuildEntry {
+                    blas: volumes_blas,
+                    geometry: rt::BlasGeometries::ProceduralGeometries(
+                        vec![rt::BlasProceduralGeometry {
+                            size: &volumes_size,
+                            bounding_box_buffer: &aabb_buf,
+                            bounding_box_buffer_offset: 0,
+                            bounding_box_stride: mem::size_of::<Aabb>() as u64,
+                        }]
+                        .into(),
+                    ),
+                    mode: None,
+                },
+            ]
+            .iter(),
+            iter::once(&tlas_package),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let camera = RayCamera::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::ZERO,
+            45.0,
+            config.width as f32 / config.height as f32,
+        );
+
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[camera.to_uniforms()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Transparency"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: tlas_package.as_binding(),
+                },
+            ],
+        });
+
+        Example {
+            camera,
+            uniform_buf,
+            blas_s,
+            tlas_package,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    fn update(&mut self, _event: winit::event::WindowEvent) {}
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.camera.aspect = config.width as f32 / config.height as f32;
+
+        queue.write_buffer(
+            &self.uniform_buf,
+            0,
+            bytemuck::cast_slice(&[self.camera.to_uniforms()]),
+        );
+    }
+
+    fn render(&mut self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+pub fn main() {
+    crate::framework::run::<Example>("ray-transparency");
+}
+
+#[cfg(test)]
+#[wgpu_test::gpu_test]
+static TEST: crate::framework::ExampleTestParams = crate::framework::ExampleTestParams {
+    name: "ray_transparency",
+    image_path: "/examples/src/ray_transparency/screenshot.png",
+    width: 1024,
+    height: 768,
+    optional_features: wgpu::Features::default(),
+    base_test_parameters: wgpu_test::TestParameters {
+        required_features: <Example as crate::framework::Example>::required_features(),
+        required_limits: <Example as crate::framework::Example>::required_limits(),
+        force_fxc: false,
+        skips: vec![],
+        failures: Vec::new(),
+        required_downlevel_caps:
+            <Example as crate::framework::Example>::required_downlevel_capabilities(),
+    },
+    comparisons: &[wgpu_test::ComparisonType::Mean(0.02)],
+    _phantom: std::marker::PhantomData::<Example>,
+};
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, mem};
+
+    use glam::Vec3;
+    use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters};
+
+    use super::Example;
+    use crate::framework::Example as _;
+
+    #[gpu_test]
+    static RAY_TRANSPARENCY_CENTER: GpuTestConfiguration = GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(Example::required_features()),
+        )
+        .run_sync(|ctx| {
+            let (width, height) = (1024, 768);
+            let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+            let example = Example::init(
+                &wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format,
+                    width,
+                    height,
+                    desired_maximum_frame_latency: 2,
+                    present_mode: wgpu::PresentMode::Fifo,
+                    alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                    view_formats: vec![format],
+                },
+                &ctx.adapter,
+                &ctx.device,
+                &ctx.queue,
+            );
+
+            // Traces the ray through the center of the screen with the same code as every pixel.
+            let shader = ctx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+                });
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("Probe"),
+                    layout: None,
+                    module: &shader,
+                    entry_point: Some("probe"),
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+            let probe_buf = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Probe Buffer"),
+                size: mem::size_of::<[f32; 4]>() as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: probe_buf.size(),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: example.uniform_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: example.tlas_package.as_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: probe_buf.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes: None,
+                });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bind_group, &[]);
+                cpass.dispatch_workgroups(1, 1, 1);
+            }
+            encoder.copy_buffer_to_buffer(&probe_buf, 0, &readback, 0, readback.size());
+            ctx.queue.submit(Some(encoder.finish()));
+
+            readback.slice(..).map_async(wgpu::MapMode::Read, |_| ());
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let [r, g, b, t]: [f32; 4] =
+                bytemuck::pod_read_unaligned(&readback.slice(..).get_mapped_range());
+
+            // The center ray passes through both panes and 2.4 units of smoke, in the opposite
+            // order they are declared in, before it ends on the wall 9 units away.
+            let red_glass = Vec3::new(0.95, 0.35, 0.3);
+            let blue_glass = Vec3::new(0.3, 0.55, 0.95);
+            let smoke = 0.55_f32.powf(2.4);
+            let expected = red_glass * blue_glass * smoke;
+            let transmittance = Vec3::new(r, g, b);
+            assert!(
+                transmittance.abs_diff_eq(expected, 1e-3),
+                "transmittance {transmittance} instead of {expected}"
+            );
+            assert!((t - 9.0).abs() < 1e-3, "wall hit at {t} instead of 9");
+        });
+}
@@ -0,0 +1,210 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Draws one triangle covering the clip space, see `ray_cube_fragment`.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var result: VertexOutput;
+    let x = i32(vertex_index) / 2;
+    let y = i32(vertex_index) & 1;
+    let tc = vec2<f32>(
+        f32(x) * 2.0,
+        f32(y) * 2.0
+    );
+    result.position = vec4<f32>(
+        tc.x * 2.0 - 1.0,
+        1.0 - tc.y * 2.0,
+        0.0, 1.0
+    );
+    result.tex_coords = tc;
+    return result;
+}
+
+struct Uniforms {
+    view_inv: mat4x4<f32>,
+    proj_inv: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(0) @binding(1)
+var acc_struct: acceleration_structure;
+
+// Instance custom indices.
+const WALL = 0u;
+const RED_GLASS = 1u;
+const BLUE_GLASS = 2u;
+const VOLUMES = 3u;
+
+// Primitives of the volumes BLAS.
+const SMOKE = 0u;
+const BALL = 1u;
+
+const T_MIN = 0.1;
+const T_MAX = 100.0;
+
+// Glass panes span -1..1 in object space, and are opaque beyond this.
+const FRAME_START = 0.9;
+
+const SMOKE_CENTER = vec3<f32>(0.0, 0.0, 0.0);
+const SMOKE_RADIUS = 1.2;
+// Fraction of light that makes it through a unit length of smoke.
+const SMOKE_TRANSMITTANCE = vec3<f32>(0.55, 0.55, 0.55);
+
+const BALL_CENTER = vec3<f32>(1.9, -1.0, 0.5);
+const BALL_RADIUS = 0.6;
+
+// A transparent surface or volume along the ray, between `t_enter` and `t_exit`.
+struct Layer {
+    t_enter: f32,
+    t_exit: f32,
+    // For volumes, per unit length.
+    transmittance: vec3<f32>,
+}
+
+// Transparent layers reported after the first `MAX_LAYERS` are dropped.
+const MAX_LAYERS = 8u;
+
+struct Trace {
+    // Color of the opaque surface the ray ends on.
+    surface: vec3<f32>,
+    // Fraction of `surface` that makes it through the transparent layers in front of it.
+    transmittance: vec3<f32>,
+    t: f32,
+}
+
+// Distances along the ray to where it enters and leaves a sphere, if it hits it.
+fn intersect_sphere(origin: vec3<f32>, dir: vec3<f32>, center: vec3<f32>, radius: f32) -> vec2<f32> {
+    let oc = origin - center;
+    let b = dot(oc, dir);
+    let c = dot(oc, oc) - radius * radius;
+    let h = b * b - c;
+    if (h < 0.0) {
+        return vec2<f32>(-1.0);
+    }
+    let s = sqrt(h);
+    return vec2<f32>(-b - s, -b + s);
+}
+
+fn glass_tint(custom_index: u32) -> vec3<f32> {
+    if (custom_index == RED_GLASS) {
+        return vec3<f32>(0.95, 0.35, 0.3);
+    }
+    return vec3<f32>(0.3, 0.55, 0.95);
+}
+
+fn trace(origin: vec3<f32>, dir: vec3<f32>) -> Trace {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, T_MIN, T_MAX, origin, dir));
+
+    var layers: array<Layer, MAX_LAYERS>;
+    var layer_count = 0u;
+    while (rayQueryProceed(&rq)) {
+        let candidate = rayQueryGetCandidateIntersection(&rq);
+        let object_origin = candidate.object_ray_origin;
+        let object_dir = candidate.object_ray_direction;
+
+        var layer = Layer(0.0, 0.0, vec3<f32>(1.0));
+        var transparent = false;
+        let kind = rayQueryGetCandidateIntersectionType(&rq);
+        if (kind == RAY_QUERY_INTERSECTION_TRIANGLE) {
+            // Only glass is non-opaque, its frame is committed like an opaque surface.
+            let p = object_origin + candidate.t * object_dir;
+            if (max(abs(p.x), abs(p.y)) > FRAME_START) {
+                rayQueryConfirmIntersection(&rq);
+            } else {
+                layer = Layer(candidate.t, candidate.t, glass_tint(candidate.instance_custom_index));
+                transparent = true;
+            }
+        } else if (kind == RAY_QUERY_INTERSECTION_AABB) {
+            let committed = rayQueryGetCommittedIntersection(&rq);
+            var t_max = T_MAX;
+            if (committed.kind != RAY_QUERY_INTERSECTION_NONE) {
+                t_max = committed.t;
+            }
+            if (candidate.primitive_index == SMOKE) {
+                let hit = intersect_sphere(object_origin, object_dir, SMOKE_CENTER, SMOKE_RADIUS);
+                if (hit.y > T_MIN) {
+                    layer = Layer(max(hit.x, T_MIN), hit.y, SMOKE_TRANSMITTANCE);
+                    transparent = true;
+                }
+            } else if (candidate.primitive_index == BALL) {
+                // The ball is solid, so report where the ray enters it.
+                let hit = intersect_sphere(object_origin, object_dir, BALL_CENTER, BALL_RADIUS);
+                if (hit.x >= T_MIN && hit.x <= t_max) {
+                    rayQueryGenerateIntersection(&rq, hit.x);
+                }
+            }
+        }
+
+        if (transparent && layer_count < MAX_LAYERS) {
+            layers[layer_count] = layer;
+            layer_count += 1u;
+        }
+    }
+
+    let committed = rayQueryGetCommittedIntersection(&rq);
+    var result = Trace(vec3<f32>(0.5, 0.7, 1.0), vec3<f32>(1.0), T_MAX);
+    if (committed.kind != RAY_QUERY_INTERSECTION_NONE) {
+        result.t = committed.t;
+    }
+    if (committed.kind == RAY_QUERY_INTERSECTION_GENERATED) {
+        let p = committed.object_ray_origin + committed.t * committed.object_ray_direction;
+        let n = normalize(p - BALL_CENTER);
+        let light = max(dot(n, normalize(vec3<f32>(0.4, 0.8, 0.6))), 0.0);
+        result.surface = vec3<f32>(0.9, 0.55, 0.15) * (0.2 + 0.8 * light);
+    } else if (committed.kind == RAY_QUERY_INTERSECTION_TRIANGLE) {
+        if (committed.instance_custom_index == WALL) {
+            let p = origin + committed.t * dir;
+            let cell = vec2<i32>(floor(p.xy + 0.5));
+            result.surface = select(vec3<f32>(0.25), vec3<f32>(0.75), ((cell.x + cell.y) & 1) == 0);
+        } else {
+            result.surface = vec3<f32>(0.08, 0.08, 0.1);
+        }
+    }
+
+    // Candidates aren't reported in order, and some may lie beyond the surface that was
+    // committed last, so only now that it is known can the layers be combined.
+    for (var i = 0u; i < layer_count; i += 1u) {
+        let layer = layers[i];
+        if (layer.t_enter >= result.t) {
+            continue;
+        }
+        if (layer.t_exit > layer.t_enter) {
+            let thickness = min(layer.t_exit, result.t) - layer.t_enter;
+            result.transmittance *= pow(layer.transmittance, vec3<f32>(thickness));
+        } else {
+            result.transmittance *= layer.transmittance;
+        }
+    }
+    return result;
+}
+
+fn primary_ray(d: vec2<f32>) -> array<vec3<f32>, 2> {
+    let origin = (uniforms.view_inv * vec4<f32>(0.0, 0.0, 0.0, 1.0)).xyz;
+    let temp = uniforms.proj_inv * vec4<f32>(d.x, d.y, 1.0, 1.0);
+    let direction = (uniforms.view_inv * vec4<f32>(normalize(temp.xyz), 0.0)).xyz;
+    return array<vec3<f32>, 2>(origin, direction);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let d = vec2<f32>(vertex.tex_coords.x * 2.0 - 1.0, 1.0 - vertex.tex_coords.y * 2.0);
+    let ray = primary_ray(d);
+    let result = trace(ray[0], ray[1]);
+    return vec4<f32>(result.surface * result.transmittance, 1.0);
+}
+
+// Transmittance in front of, and distance to, the surface at the center of the screen.
+@group(0) @binding(2)
+var<storage, read_write> probe_result: vec4<f32>;
+
+@compute @workgroup_size(1)
+fn probe() {
+    let ray = primary_ray(vec2<f32>(0.0));
+    let result = trace(ray[0], ray[1]);
+    probe_result = vec4<f32>(result.transmittance, result.t);
+}
@@ -0,0 +1,26 @@
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use wgpu_examples::framework::RayCamera;
+
+#[test]
+fn ray_camera() {
+    let camera = RayCamera::new(Vec3::new(0.0, 0.0, 2.5), Vec3::ZERO, 90.0, 2.0);
+    let uniforms = camera.to_uniforms();
+
+    // The inverse view moves from the camera at z = 2.5 back to the world, without rotating.
+    assert_eq!(
+        Mat4::from_cols_array_2d(&uniforms.view_inverse),
+        Mat4::from_translation(Vec3::new(0.0, 0.0, 2.5))
+    );
+    // With a 90 degree vertical field of view, the corners of the far plane are as far
+    // off-center as they are deep, twice as much horizontally for an aspect of 2.
+    let proj_inverse = Mat4::from_cols_array_2d(&uniforms.proj_inverse);
+    let corner = proj_inverse * Vec4::new(1.0, 1.0, 1.0, 1.0);
+    let corner = corner.truncate() / corner.w;
+    assert!((corner.x / -corner.z - 2.0).abs() < 1e-4, "{corner:?}");
+    assert!((corner.y / -corner.z - 1.0).abs() < 1e-4, "{corner:?}");
+
+    // The ray through the center pixel points forward, toward the target.
+    let direction = camera.ray_direction(Vec2::ZERO);
+    assert!(direction.abs_diff_eq(Vec3::NEG_Z, 1e-5), "{direction:?}");
+}
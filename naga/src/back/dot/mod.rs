@@ -278,6 +278,13 @@ impl StatementGraph {
                             self.emits.push((id, result));
                             "RayQueryProceed"
                         }
+                        crate::RayQueryFunction::ConfirmIntersection => {
+                            "RayQueryConfirmIntersection"
+                        }
+                        crate::RayQueryFunction::GenerateIntersection { hit_t } => {
+                            self.dependencies.push((id, hit_t, "hit_t"));
+                            "RayQueryGenerateIntersection"
+                        }
                         crate::RayQueryFunction::Terminate => "RayQueryTerminate",
                     }
                 }
@@ -700,6 +707,16 @@ fn write_function_expressions(
                 let ty = if committed { "Committed" } else { "Candidate" };
                 (format!("rayQueryGet{}Intersection", ty).into(), 4)
             }
+            E::RayQueryGetIntersectionType { query, committed } => {
+                edges.insert("", query);
+                let ty = if committed { "Committed" } else { "Candidate" };
+                (format!("rayQueryGet{}IntersectionType", ty).into(), 4)
+            }
+            E::RayQueryGetIntersectionInstanceId { query, committed } => {
+                edges.insert("", query);
+                let ty = if committed { "Committed" } else { "Candidate" };
+                (format!("rayQueryGet{}IntersectionInstanceId", ty).into(), 4)
+            }
             E::SubgroupBallotResult => ("SubgroupBallotResult".into(), 4),
             E::SubgroupOperationResult { .. } => ("SubgroupOperationResult".into(), 4),
             E::RayQueryVertexPositions { query, committed } => {
@@ -707,6 +724,11 @@ fn write_function_expressions(
                 let ty = if committed { "Committed" } else { "Candidate" };
                 (format!("get{}HitVertexPositions", ty).into(), 4)
             }
+            E::RayQueryLssPositions { query, committed } => {
+                edges.insert("", query);
+                let ty = if committed { "Committed" } else { "Candidate" };
+                (format!("get{}HitLssPositions", ty).into(), 4)
+            }
         };
 
         // give uniform expressions an outline
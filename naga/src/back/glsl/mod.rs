@@ -3916,7 +3916,10 @@ impl<'a, W: Write> Writer<'a, W> {
             }
             // not supported yet
             Expression::RayQueryGetIntersection { .. }
-            | Expression::RayQueryVertexPositions { .. } => unreachable!(),
+            | Expression::RayQueryGetIntersectionType { .. }
+            | Expression::RayQueryGetIntersectionInstanceId { .. }
+            | Expression::RayQueryVertexPositions { .. }
+            | Expression::RayQueryLssPositions { .. } => unreachable!(),
         }
 
         Ok(())
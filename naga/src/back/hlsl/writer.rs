@@ -3530,7 +3530,10 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             }
             // Not supported yet
             Expression::RayQueryGetIntersection { .. }
-            | Expression::RayQueryVertexPositions { .. } => unreachable!(),
+            | Expression::RayQueryGetIntersectionType { .. }
+            | Expression::RayQueryGetIntersectionInstanceId { .. }
+            | Expression::RayQueryVertexPositions { .. }
+            | Expression::RayQueryLssPositions { .. } => unreachable!(),
             // Nothing to do here, since call expression already cached
             Expression::CallResult(_)
             | Expression::AtomicResult { .. }
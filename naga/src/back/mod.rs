@@ -18,6 +18,8 @@ pub mod wgsl;
 
 #[cfg(any(hlsl_out, msl_out, spv_out, glsl_out))]
 pub mod pipeline_constants;
+#[cfg(any(hlsl_out, msl_out, spv_out, glsl_out))]
+pub mod ray_query_counters;
 
 #[cfg(any(hlsl_out, glsl_out))]
 mod continue_forward;
@@ -305,9 +307,18 @@ bitflags::bitflags! {
     }
 }
 
-/// The intersection test to use for ray queries.
+/// The kind of a ray query intersection, as exposed to shaders.
+///
+/// The committed kinds match the values of `RayQueryCommittedIntersectionTypeKHR`
+/// in SPIR-V.
 #[repr(u32)]
 pub enum RayIntersectionType {
+    /// There is no committed intersection, the ray missed.
+    None = 0,
     Triangle = 1,
-    BoundingBox = 4,
+    /// An intersection with a procedural (AABB) geometry.
+    Generated = 2,
+    /// A candidate intersection with a procedural (AABB) geometry, which has
+    /// not been generated yet.
+    Aabb = 3,
 }
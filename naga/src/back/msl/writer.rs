@@ -2141,6 +2141,38 @@ impl<W: Write> Writer<W> {
             crate::Expression::RayQueryVertexPositions { .. } => {
                 unimplemented!()
             }
+            crate::Expression::RayQueryLssPositions { .. } => {
+                return Err(Error::CapabilityNotSupported(
+                    valid::Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES,
+                ));
+            }
+            crate::Expression::RayQueryGetIntersectionType { query, committed } => {
+                if context.lang_version < (2, 4) {
+                    return Err(Error::UnsupportedRayTracing);
+                }
+
+                if !committed {
+                    return Err(Error::FeatureNotImplemented(
+                        "candidate intersection".to_string(),
+                    ));
+                }
+                write!(self.out, "{RAY_QUERY_FUN_MAP_INTERSECTION}(")?;
+                self.put_expression(query, context, true)?;
+                write!(self.out, ".{RAY_QUERY_FIELD_INTERSECTION}.type)")?;
+            }
+            crate::Expression::RayQueryGetIntersectionInstanceId { query, committed } => {
+                if context.lang_version < (2, 4) {
+                    return Err(Error::UnsupportedRayTracing);
+                }
+
+                if !committed {
+                    return Err(Error::FeatureNotImplemented(
+                        "candidate intersection".to_string(),
+                    ));
+                }
+                self.put_expression(query, context, true)?;
+                write!(self.out, ".{RAY_QUERY_FIELD_INTERSECTION}.instance_id")?;
+            }
             crate::Expression::RayQueryGetIntersection { query, committed } => {
                 if context.lang_version < (2, 4) {
                     return Err(Error::UnsupportedRayTracing);
@@ -2166,11 +2198,46 @@ impl<W: Write> Writer<W> {
                     "",                          // padding
                     "object_to_world_transform", // req Metal 2.4
                     "world_to_object_transform", // req Metal 2.4
+                    // Metal has no getters for the object space ray, so it is always zero.
+                    "", // object space ray origin
+                    "", // object space ray direction
                 ];
                 for field in fields {
                     write!(self.out, ", ")?;
                     if field.is_empty() {
                         write!(self.out, "{{}}")?;
+                    } else if field.ends_with("_transform") {
+                        // The transforms are undefined without a hit, use the identity instead.
+                        write!(self.out, "(")?;
+                        self.put_expression(query, context, true)?;
+                        write!(
+                            self.out,
+                            ".{RAY_QUERY_FIELD_INTERSECTION}.type == {RT_NAMESPACE}::intersection_type::none ? "
+                        )?;
+                        write!(
+                            self.out,
+                            "{NAMESPACE}::float4x3({NAMESPACE}::float3(1.0, 0.0, 0.0), {NAMESPACE}::float3(0.0, 1.0, 0.0), {NAMESPACE}::float3(0.0, 0.0, 1.0), {NAMESPACE}::float3(0.0)) : "
+                        )?;
+                        self.put_expression(query, context, true)?;
+                        write!(self.out, ".{RAY_QUERY_FIELD_INTERSECTION}.{field})")?;
+                    } else if field.starts_with("triangle_") {
+                        // Only triangle hits have barycentrics and a facing, default them otherwise.
+                        write!(self.out, "(")?;
+                        self.put_expression(query, context, true)?;
+                        write!(
+                            self.out,
+                            ".{RAY_QUERY_FIELD_INTERSECTION}.type == {RT_NAMESPACE}::intersection_type::triangle ? "
+                        )?;
+                        self.put_expression(query, context, true)?;
+                        let default = if field == "triangle_front_facing" {
+                            "false".to_string()
+                        } else {
+                            format!("{NAMESPACE}::float2(0.0)")
+                        };
+                        write!(
+                            self.out,
+                            ".{RAY_QUERY_FIELD_INTERSECTION}.{field} : {default})"
+                        )?;
                     } else {
                         self.put_expression(query, context, true)?;
                         write!(self.out, ".{RAY_QUERY_FIELD_INTERSECTION}.{field}")?;
@@ -3096,10 +3163,30 @@ impl<W: Write> Writer<W> {
                             acceleration_structure,
                             descriptor,
                         } => {
-                            //TODO: how to deal with winding?
                             write!(self.out, "{level}")?;
                             self.put_expression(query, &context.expression, true)?;
                             writeln!(self.out, ".{RAY_QUERY_FIELD_INTERSECTOR}.assume_geometry_type({RT_NAMESPACE}::geometry_type::triangle);")?;
+                            // Like in Vulkan and D3D12, triangles that are clockwise seen from
+                            // the ray origin are front facing, which `triangle_front_facing`
+                            // reports and the cull flags refer to.
+                            write!(self.out, "{level}")?;
+                            self.put_expression(query, &context.expression, true)?;
+                            writeln!(self.out, ".{RAY_QUERY_FIELD_INTERSECTOR}.set_triangle_front_facing_winding({NAMESPACE}::winding::clockwise);")?;
+                            {
+                                let f_back = back::RayFlag::CULL_BACK_FACING.bits();
+                                let f_front = back::RayFlag::CULL_FRONT_FACING.bits();
+                                write!(self.out, "{level}")?;
+                                self.put_expression(query, &context.expression, true)?;
+                                write!(
+                                    self.out,
+                                    ".{RAY_QUERY_FIELD_INTERSECTOR}.set_triangle_cull_mode(("
+                                )?;
+                                self.put_expression(descriptor, &context.expression, true)?;
+                                write!(self.out, ".flags & {f_back}) != 0 ? {RT_NAMESPACE}::triangle_cull_mode::back : (")?;
+                                self.put_expression(descriptor, &context.expression, true)?;
+                                write!(self.out, ".flags & {f_front}) != 0 ? {RT_NAMESPACE}::triangle_cull_mode::front : ")?;
+                                writeln!(self.out, "{RT_NAMESPACE}::triangle_cull_mode::none);")?;
+                            }
                             {
                                 let f_opaque = back::RayFlag::CULL_OPAQUE.bits();
                                 let f_no_opaque = back::RayFlag::CULL_NO_OPAQUE.bits();
@@ -3158,7 +3245,7 @@ impl<W: Write> Writer<W> {
                             self.put_expression(acceleration_structure, &context.expression, true)?;
                             write!(self.out, ", ")?;
                             self.put_expression(descriptor, &context.expression, true)?;
-                            write!(self.out, ".cull_mask);")?;
+                            writeln!(self.out, ".cull_mask);")?;
 
                             write!(self.out, "{level}")?;
                             self.put_expression(query, &context.expression, true)?;
@@ -3177,6 +3264,16 @@ impl<W: Write> Writer<W> {
                             self.put_expression(query, &context.expression, true)?;
                             writeln!(self.out, ".{RAY_QUERY_FIELD_READY} = false;")?;
                         }
+                        crate::RayQueryFunction::ConfirmIntersection => {
+                            return Err(Error::FeatureNotImplemented(
+                                "candidate intersection".to_string(),
+                            ));
+                        }
+                        crate::RayQueryFunction::GenerateIntersection { .. } => {
+                            return Err(Error::FeatureNotImplemented(
+                                "candidate intersection".to_string(),
+                            ));
+                        }
                         crate::RayQueryFunction::Terminate => {
                             write!(self.out, "{level}")?;
                             self.put_expression(query, &context.expression, true)?;
@@ -3515,15 +3612,16 @@ impl<W: Write> Writer<W> {
         writeln!(self.out, "{tab}bool {RAY_QUERY_FIELD_READY} = false;")?;
         writeln!(self.out, "}};")?;
         writeln!(self.out, "constexpr {NAMESPACE}::uint {RAY_QUERY_FUN_MAP_INTERSECTION}(const {RT_NAMESPACE}::intersection_type ty) {{")?;
+        let v_none = back::RayIntersectionType::None as u32;
         let v_triangle = back::RayIntersectionType::Triangle as u32;
-        let v_bbox = back::RayIntersectionType::BoundingBox as u32;
+        let v_generated = back::RayIntersectionType::Generated as u32;
         writeln!(
             self.out,
             "{tab}return ty=={RT_NAMESPACE}::intersection_type::triangle ? {v_triangle} : "
         )?;
         writeln!(
             self.out,
-            "{tab}{tab}ty=={RT_NAMESPACE}::intersection_type::bounding_box ? {v_bbox} : 0;"
+            "{tab}{tab}ty=={RT_NAMESPACE}::intersection_type::bounding_box ? {v_generated} : {v_none};"
         )?;
         writeln!(self.out, "}}")?;
         Ok(())
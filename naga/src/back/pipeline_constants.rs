@@ -506,6 +506,14 @@ fn adjust_expr(new_pos: &HandleVec<Expression, Handle<Expression>>, expr: &mut E
         Expression::RayQueryGetIntersection {
             ref mut query,
             committed: _,
+        }
+        | Expression::RayQueryGetIntersectionType {
+            ref mut query,
+            committed: _,
+        }
+        | Expression::RayQueryGetIntersectionInstanceId {
+            ref mut query,
+            committed: _,
         } => {
             adjust(query);
         }
@@ -528,6 +536,10 @@ fn adjust_expr(new_pos: &HandleVec<Expression, Handle<Expression>>, expr: &mut E
         Expression::RayQueryVertexPositions {
             ref mut query,
             committed: _,
+        }
+        | Expression::RayQueryLssPositions {
+            ref mut query,
+            committed: _,
         } => {
             adjust(query);
         }
@@ -710,7 +722,11 @@ fn adjust_stmt(new_pos: &HandleVec<Expression, Handle<Expression>>, stmt: &mut S
                 crate::RayQueryFunction::Proceed { ref mut result } => {
                     adjust(result);
                 }
-                crate::RayQueryFunction::Terminate => {}
+                crate::RayQueryFunction::GenerateIntersection { ref mut hit_t } => {
+                    adjust(hit_t);
+                }
+                crate::RayQueryFunction::ConfirmIntersection
+                | crate::RayQueryFunction::Terminate => {}
             }
         }
         Statement::Break | Statement::Continue | Statement::Kill | Statement::Barrier(_) => {}
@@ -0,0 +1,413 @@
+/*!
+Instrumentation of ray queries with traversal counters.
+
+[`instrument`] rewrites a module so that every [`Proceed`] of a ray query
+bumps a set of atomic counters in a storage buffer the caller binds. This
+gives a cheap, backend independent way of seeing how much work traversal
+does for a given scene, without vendor profiling tools.
+
+The counters live in a global `array<atomic<u32>, COUNTER_COUNT>` in the
+[`Storage`] address space, bound read-write at the [`ResourceBinding`]
+passed to [`instrument`]. The buffer bound there must be at least
+`COUNTER_COUNT * 4` bytes long. The counters are only ever incremented, so
+the caller is responsible for clearing them between dispatches if it wants
+per-dispatch numbers. Each slot counts:
+
+- [`PROCEED_INDEX`]: calls to [`Proceed`], i.e. iterations of the traversal
+  loop, including the final one that returns `false`.
+
+- [`CANDIDATE_TRIANGLE_INDEX`]: [`Proceed`] calls that stopped at a
+  candidate triangle intersection.
+
+- [`CANDIDATE_AABB_INDEX`]: [`Proceed`] calls that stopped at a candidate
+  AABB, which the shader then has to test itself.
+
+[`Proceed`]: crate::RayQueryFunction::Proceed
+[`Storage`]: crate::AddressSpace::Storage
+*/
+
+use crate::{
+    valid::{Capabilities, ModuleInfo, ValidationError, ValidationFlags, Validator},
+    AddressSpace, ArraySize, AtomicFunction, BinaryOperator, Block, Expression, GlobalVariable,
+    Handle, Literal, Module, Range, RayQueryFunction, ResourceBinding, Scalar, Span, Statement,
+    StorageAccess, Type, TypeInner, WithSpan,
+};
+use std::{borrow::Cow, mem, num::NonZeroU32};
+use thiserror::Error;
+
+/// Index of the counter for [`Proceed`](crate::RayQueryFunction::Proceed) calls.
+pub const PROCEED_INDEX: u32 = 0;
+/// Index of the counter for candidate triangle intersections.
+pub const CANDIDATE_TRIANGLE_INDEX: u32 = 1;
+/// Index of the counter for candidate AABB intersections.
+pub const CANDIDATE_AABB_INDEX: u32 = 2;
+/// Number of `u32` counters in the counters buffer.
+pub const COUNTER_COUNT: u32 = 3;
+
+/// The value of [`RayQueryGetIntersectionType`] for candidate triangles.
+///
+/// [`RayQueryGetIntersectionType`]: Expression::RayQueryGetIntersectionType
+const CANDIDATE_TYPE_TRIANGLE: u32 = crate::back::RayIntersectionType::Triangle as u32;
+
+#[derive(Error, Debug, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum RayQueryCountersError {
+    #[error("Ray query counters binding {0:?} is already used by the module")]
+    BindingInUse(ResourceBinding),
+    #[error(transparent)]
+    ValidationError(#[from] WithSpan<ValidationError>),
+}
+
+/// Add ray query traversal counters to `module`, bound at `binding`.
+///
+/// If `module` never calls [`Proceed`], this returns `module` and
+/// `module_info` unchanged. Otherwise it adds the counters global described
+/// in the [module documentation](self), instruments every [`Proceed`] in
+/// every function and entry point, and returns the module revalidated with
+/// `capabilities`, which should be the ones `module` was validated with.
+///
+/// This is meant to be applied to the output of
+/// [`process_overrides`](super::pipeline_constants::process_overrides).
+///
+/// [`Proceed`]: crate::RayQueryFunction::Proceed
+pub fn instrument<'a>(
+    module: Cow<'a, Module>,
+    module_info: Cow<'a, ModuleInfo>,
+    capabilities: Capabilities,
+    binding: &ResourceBinding,
+) -> Result<(Cow<'a, Module>, Cow<'a, ModuleInfo>), RayQueryCountersError> {
+    let uses_proceed = module
+        .functions
+        .iter()
+        .map(|(_, function)| &function.body)
+        .chain(module.entry_points.iter().map(|ep| &ep.function.body))
+        .any(block_uses_proceed);
+    if !uses_proceed {
+        return Ok((module, module_info));
+    }
+
+    if module
+        .global_variables
+        .iter()
+        .any(|(_, var)| var.binding.as_ref() == Some(binding))
+    {
+        return Err(RayQueryCountersError::BindingInUse(binding.clone()));
+    }
+
+    let mut module = module.into_owned();
+
+    let atomic_ty = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Atomic(Scalar::U32),
+        },
+        Span::UNDEFINED,
+    );
+    let counters_ty = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Array {
+                base: atomic_ty,
+                size: ArraySize::Constant(NonZeroU32::new(COUNTER_COUNT).unwrap()),
+                stride: 4,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let counters = module.global_variables.append(
+        GlobalVariable {
+            name: Some("naga_ray_query_counters".to_string()),
+            space: AddressSpace::Storage {
+                access: StorageAccess::LOAD | StorageAccess::STORE,
+            },
+            binding: Some(binding.clone()),
+            ty: counters_ty,
+            init: None,
+        },
+        Span::UNDEFINED,
+    );
+
+    for (_, function) in module.functions.iter_mut() {
+        instrument_block(&mut function.body, &mut function.expressions, counters);
+    }
+    for ep in module.entry_points.iter_mut() {
+        instrument_block(
+            &mut ep.function.body,
+            &mut ep.function.expressions,
+            counters,
+        );
+    }
+
+    let mut validator = Validator::new(ValidationFlags::all(), capabilities);
+    let module_info = validator.validate(&module)?;
+
+    Ok((Cow::Owned(module), Cow::Owned(module_info)))
+}
+
+fn block_uses_proceed(block: &Block) -> bool {
+    block.iter().any(|stmt| match *stmt {
+        Statement::RayQuery {
+            fun: RayQueryFunction::Proceed { .. },
+            ..
+        } => true,
+        Statement::Block(ref block) => block_uses_proceed(block),
+        Statement::If {
+            ref accept,
+            ref reject,
+            ..
+        } => block_uses_proceed(accept) || block_uses_proceed(reject),
+        Statement::Switch { ref cases, .. } => {
+            cases.iter().any(|case| block_uses_proceed(&case.body))
+        }
+        Statement::Loop {
+            ref body,
+            ref continuing,
+            ..
+        } => block_uses_proceed(body) || block_uses_proceed(continuing),
+        _ => false,
+    })
+}
+
+/// Insert counter updates after every `Proceed` in `block`.
+fn instrument_block(
+    block: &mut Block,
+    expressions: &mut crate::Arena<Expression>,
+    counters: Handle<GlobalVariable>,
+) {
+    let original = mem::replace(block, Block::with_capacity(block.len()));
+    for (mut stmt, span) in original.span_into_iter() {
+        match stmt {
+            Statement::Block(ref mut child) => instrument_block(child, expressions, counters),
+            Statement::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                instrument_block(accept, expressions, counters);
+                instrument_block(reject, expressions, counters);
+            }
+            Statement::Switch { ref mut cases, .. } => {
+                for case in cases.iter_mut() {
+                    instrument_block(&mut case.body, expressions, counters);
+                }
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+                ..
+            } => {
+                instrument_block(body, expressions, counters);
+                instrument_block(continuing, expressions, counters);
+            }
+            _ => {}
+        }
+
+        let proceed = match stmt {
+            Statement::RayQuery {
+                query,
+                fun: RayQueryFunction::Proceed { result },
+            } => Some((query, result)),
+            _ => None,
+        };
+        block.push(stmt, span);
+
+        if let Some((query, result)) = proceed {
+            increment(block, expressions, counters, PROCEED_INDEX, span);
+
+            // `Proceed` returning `true` means traversal stopped at a
+            // candidate, so it is safe to ask for the candidate's type.
+            let mut accept = Block::new();
+            let candidate_type = append_emitted(
+                &mut accept,
+                expressions,
+                Expression::RayQueryGetIntersectionType {
+                    query,
+                    committed: false,
+                },
+                span,
+            );
+            let triangle = expressions.append(
+                Expression::Literal(Literal::U32(CANDIDATE_TYPE_TRIANGLE)),
+                span,
+            );
+            let is_triangle = append_emitted(
+                &mut accept,
+                expressions,
+                Expression::Binary {
+                    op: BinaryOperator::Equal,
+                    left: candidate_type,
+                    right: triangle,
+                },
+                span,
+            );
+            let mut triangle_block = Block::new();
+            increment(
+                &mut triangle_block,
+                expressions,
+                counters,
+                CANDIDATE_TRIANGLE_INDEX,
+                span,
+            );
+            let mut aabb_block = Block::new();
+            increment(
+                &mut aabb_block,
+                expressions,
+                counters,
+                CANDIDATE_AABB_INDEX,
+                span,
+            );
+            accept.push(
+                Statement::If {
+                    condition: is_triangle,
+                    accept: triangle_block,
+                    reject: aabb_block,
+                },
+                span,
+            );
+
+            block.push(
+                Statement::If {
+                    condition: result,
+                    accept,
+                    reject: Block::new(),
+                },
+                span,
+            );
+        }
+    }
+}
+
+/// Append `expression` to `expressions`, and emit it in `block`.
+fn append_emitted(
+    block: &mut Block,
+    expressions: &mut crate::Arena<Expression>,
+    expression: Expression,
+    span: Span,
+) -> Handle<Expression> {
+    let handle = expressions.append(expression, span);
+    block.push(
+        Statement::Emit(Range::new_from_bounds(handle, handle)),
+        span,
+    );
+    handle
+}
+
+/// Push an atomic increment of counter `index` onto `block`.
+fn increment(
+    block: &mut Block,
+    expressions: &mut crate::Arena<Expression>,
+    counters: Handle<GlobalVariable>,
+    index: u32,
+    span: Span,
+) {
+    let global = expressions.append(Expression::GlobalVariable(counters), span);
+    let pointer = append_emitted(
+        block,
+        expressions,
+        Expression::AccessIndex {
+            base: global,
+            index,
+        },
+        span,
+    );
+    let one = expressions.append(Expression::Literal(Literal::U32(1)), span);
+    block.push(
+        Statement::Atomic {
+            pointer,
+            fun: AtomicFunction::Add,
+            value: one,
+            result: None,
+        },
+        span,
+    );
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::*;
+
+    /// Capabilities the test modules are validated with, before and after instrumenting them.
+    const CAPABILITIES: Capabilities = Capabilities::RAY_QUERY;
+
+    fn parse(source: &str) -> (Module, ModuleInfo) {
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        let info = Validator::new(ValidationFlags::all(), CAPABILITIES)
+            .validate(&module)
+            .unwrap();
+        (module, info)
+    }
+
+    #[test]
+    fn instrument_proceed() {
+        let (module, info) = parse(
+            "
+            @group(0) @binding(0) var acc_struct: acceleration_structure;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var rq: ray_query;
+                rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+                while (rayQueryProceed(&rq)) {}
+            }
+            ",
+        );
+        let binding = ResourceBinding {
+            group: 1,
+            binding: 0,
+        };
+        let (instrumented, instrumented_info) = instrument(
+            Cow::Borrowed(&module),
+            Cow::Borrowed(&info),
+            CAPABILITIES,
+            &binding,
+        )
+        .unwrap();
+        assert!(matches!(instrumented, Cow::Owned(_)));
+        assert!(instrumented
+            .global_variables
+            .iter()
+            .any(|(_, var)| var.binding.as_ref() == Some(&binding)));
+        #[cfg(spv_out)]
+        crate::back::spv::write_vec(
+            &instrumented,
+            &instrumented_info,
+            &crate::back::spv::Options::default(),
+            None,
+        )
+        .unwrap();
+        #[cfg(not(spv_out))]
+        let _ = instrumented_info;
+
+        let in_use = ResourceBinding {
+            group: 0,
+            binding: 0,
+        };
+        assert_eq!(
+            instrument(
+                Cow::Borrowed(&module),
+                Cow::Borrowed(&info),
+                CAPABILITIES,
+                &in_use,
+            )
+            .unwrap_err(),
+            RayQueryCountersError::BindingInUse(in_use)
+        );
+    }
+
+    #[test]
+    fn skip_without_proceed() {
+        let (module, info) = parse("@compute @workgroup_size(1) fn main() {}");
+        let binding = ResourceBinding {
+            group: 0,
+            binding: 0,
+        };
+        let (module, _) = instrument(
+            Cow::Borrowed(&module),
+            Cow::Borrowed(&info),
+            CAPABILITIES,
+            &binding,
+        )
+        .unwrap();
+        assert!(matches!(module, Cow::Borrowed(_)));
+    }
+}
@@ -357,6 +357,11 @@ impl<'w> BlockContext<'w> {
                     }
                 }
             }
+            crate::Expression::AccessIndex { base, index }
+                if self.ray_intersection_field(base, index).is_some() =>
+            {
+                self.ray_intersection_field(base, index).unwrap()
+            }
             crate::Expression::AccessIndex { base, index: _ } if self.is_intermediate(base) => {
                 // See `is_intermediate`; we'll handle this later in
                 // `write_expression_pointer`.
@@ -1721,16 +1726,41 @@ impl<'w> BlockContext<'w> {
             }
             crate::Expression::ArrayLength(expr) => self.write_runtime_array_length(expr, block)?,
             crate::Expression::RayQueryGetIntersection { query, committed } => {
-                if !committed {
-                    return Err(Error::FeatureNotImplemented("candidate intersection"));
+                let fields = if self
+                    .writer
+                    .flags
+                    .contains(WriterFlags::SEPARATE_RAY_QUERY_GETTERS)
+                {
+                    self.ray_intersection_accessed_fields(expr_handle)
+                } else {
+                    None
+                };
+                match fields {
+                    Some(fields) => {
+                        // The accesses look up the fields, there is no composite.
+                        self.write_ray_query_get_intersection_fields(
+                            expr_handle,
+                            query,
+                            committed,
+                            &fields,
+                            block,
+                        );
+                        0
+                    }
+                    None => self.write_ray_query_get_intersection(query, committed, block),
                 }
-                self.write_ray_query_get_intersection(query, block)
+            }
+            crate::Expression::RayQueryGetIntersectionType { query, committed } => {
+                self.write_ray_query_intersection_kind(query, committed, block)
+            }
+            crate::Expression::RayQueryGetIntersectionInstanceId { query, committed } => {
+                self.write_ray_query_get_intersection_instance_id(query, committed, block)
             }
             crate::Expression::RayQueryVertexPositions { query, committed } => {
-                if !committed {
-                    return Err(Error::FeatureNotImplemented("candidate intersection"));
-                }
-                self.write_ray_query_return_vertex_position(query, block)
+                self.write_ray_query_return_vertex_position(query, committed, block)?
+            }
+            crate::Expression::RayQueryLssPositions { query, committed } => {
+                self.write_ray_query_lss_positions(query, committed, block)
             }
         };
 
@@ -2049,6 +2079,22 @@ impl<'w> BlockContext<'w> {
         exit: BlockExit,
         loop_context: LoopContext,
         debug_info: Option<&DebugInfoInner>,
+    ) -> Result<(), Error> {
+        // Values fetched inside the block don't dominate the code following it.
+        let ray_query_scope = self.ray_query_tracker.enter_scope();
+        let result =
+            self.write_block_statements(label_id, naga_block, exit, loop_context, debug_info);
+        self.ray_query_tracker.leave_scope(ray_query_scope);
+        result
+    }
+
+    fn write_block_statements(
+        &mut self,
+        label_id: Word,
+        naga_block: &crate::Block,
+        exit: BlockExit,
+        loop_context: LoopContext,
+        debug_info: Option<&DebugInfoInner>,
     ) -> Result<(), Error> {
         let mut block = Block::new(label_id);
         for (statement, span) in naga_block.span_iter() {
@@ -2262,6 +2308,11 @@ impl<'w> BlockContext<'w> {
                     ));
                     self.function.consume(block, Instruction::branch(body_id));
 
+                    // Values fetched before the loop are stale in later iterations
+                    // if the loop advances the query.
+                    let ray_query_scope = self.ray_query_tracker.enter_scope();
+                    self.ray_query_tracker.clear();
+
                     self.write_block(
                         body_id,
                         body,
@@ -2296,6 +2347,7 @@ impl<'w> BlockContext<'w> {
                         debug_info,
                     )?;
 
+                    self.ray_query_tracker.leave_scope(ray_query_scope);
                     block = Block::new(merge_id);
                 }
                 Statement::Break => {
@@ -2400,6 +2452,8 @@ impl<'w> BlockContext<'w> {
                     ref arguments,
                     result,
                 } => {
+                    // The callee may advance ray queries passed to it.
+                    self.ray_query_tracker.invalidate();
                     let id = self.gen_id();
                     self.temp_list.clear();
                     for &argument in arguments {
@@ -222,8 +222,13 @@ impl super::Instruction {
     }
 
     pub(super) fn capability(capability: spirv::Capability) -> Self {
+        Self::capability_raw(capability as u32)
+    }
+
+    /// Declare a capability the `spirv` crate doesn't know about.
+    pub(super) fn capability_raw(capability: Word) -> Self {
         let mut instruction = Self::new(Op::Capability);
-        instruction.add_operand(capability as u32);
+        instruction.add_operand(capability);
         instruction
     }
 
@@ -744,17 +749,50 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn ray_query_confirm_intersection(query: Word) -> Self {
+        let mut instruction = Self::new(Op::RayQueryConfirmIntersectionKHR);
+        instruction.add_operand(query);
+        instruction
+    }
+
+    pub(super) fn ray_query_generate_intersection(query: Word, hit_t: Word) -> Self {
+        let mut instruction = Self::new(Op::RayQueryGenerateIntersectionKHR);
+        instruction.add_operand(query);
+        instruction.add_operand(hit_t);
+        instruction
+    }
+
+    pub(super) fn ray_query_terminate(query: Word) -> Self {
+        let mut instruction = Self::new(Op::RayQueryTerminateKHR);
+        instruction.add_operand(query);
+        instruction
+    }
+
     pub(super) fn ray_query_return_vertex_position(
         result_type_id: Word,
         id: Word,
         query: Word,
-        bool_id: Word,
+        intersection: Word,
     ) -> Self {
         let mut instruction = Self::new(Op::RayQueryGetIntersectionTriangleVertexPositionsKHR);
         instruction.set_type(result_type_id);
         instruction.set_result(id);
         instruction.add_operand(query);
-        instruction.add_operand(bool_id);
+        instruction.add_operand(intersection);
+        instruction
+    }
+
+    pub(super) fn ray_query_get_intersection_lss_positions(
+        result_type_id: Word,
+        id: Word,
+        query: Word,
+        intersection: Word,
+    ) -> Self {
+        let mut instruction = Self::new_raw(super::OP_RAY_QUERY_GET_INTERSECTION_LSS_POSITIONS_NV);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(query);
+        instruction.add_operand(intersection);
         instruction
     }
 
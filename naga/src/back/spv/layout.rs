@@ -73,6 +73,11 @@ impl super::recyclable::Recyclable for LogicalLayout {
 
 impl Instruction {
     pub(super) const fn new(op: Op) -> Self {
+        Self::new_raw(op as Word)
+    }
+
+    /// Create an instruction with an opcode the `spirv` crate doesn't know about.
+    pub(super) const fn new_raw(op: Word) -> Self {
         Instruction {
             op,
             wc: 1, // Always start at 1 for the first word (OP + WC),
@@ -108,7 +113,7 @@ impl Instruction {
     }
 
     pub(super) fn to_words(&self, sink: &mut impl Extend<Word>) {
-        sink.extend(Some(self.wc << 16 | self.op as u32));
+        sink.extend(Some(self.wc << 16 | self.op));
         sink.extend(self.type_id);
         sink.extend(self.result_id);
         sink.extend(self.operands.iter().cloned());
@@ -123,7 +128,7 @@ impl Instruction {
         inst_index += 1;
 
         assert_eq!(wc, words.len() as u16);
-        assert_eq!(op, self.op as u16);
+        assert_eq!(u32::from(op), self.op);
 
         if self.type_id.is_some() {
             assert_eq!(words[inst_index], self.type_id.unwrap());
@@ -50,7 +50,9 @@ struct LogicalLayout {
 }
 
 struct Instruction {
-    op: spirv::Op,
+    /// The opcode, kept raw so that instructions unknown to the `spirv` crate
+    /// can be written too.
+    op: Word,
     wc: u32,
     type_id: Option<Word>,
     result_id: Option<Word>,
@@ -59,6 +61,13 @@ struct Instruction {
 
 const BITS_PER_BYTE: crate::Bytes = 8;
 
+/// `OpRayQueryGetIntersectionLSSPositionsNV` from `SPV_NV_linear_swept_spheres`.
+///
+/// The `spirv` crate predates the extension, so its values are defined here.
+const OP_RAY_QUERY_GET_INTERSECTION_LSS_POSITIONS_NV: Word = 5429;
+/// The `RayTracingLinearSweptSpheresGeometryNV` capability.
+const CAPABILITY_RAY_TRACING_LINEAR_SWEPT_SPHERES_GEOMETRY_NV: Word = 5419;
+
 #[derive(Clone, Debug, Error)]
 pub enum Error {
     #[error("The requested entry point couldn't be found")]
@@ -598,6 +607,17 @@ struct BlockContext<'w> {
 
     /// Tracks the constness of `Expression`s residing in `self.ir_function.expressions`
     expression_constness: ExpressionConstnessTracker,
+
+    /// Intersection types fetched from ray queries that can still be reused.
+    ray_query_tracker: ray::RayQueryTracker,
+
+    /// The ids of the fields of `RayIntersection` expressions written without
+    /// a composite, see [`WriterFlags::SEPARATE_RAY_QUERY_GETTERS`].
+    ray_intersection_fields: crate::FastHashMap<Handle<crate::Expression>, Vec<Option<Word>>>,
+
+    /// The fields accessed from each `RayIntersection` expression, collected
+    /// once per function.
+    ray_intersection_accesses: ray::RayIntersectionAccesses,
 }
 
 impl BlockContext<'_> {
@@ -712,6 +732,15 @@ bitflags::bitflags! {
         ///
         /// [`BuiltIn::FragDepth`]: crate::BuiltIn::FragDepth
         const CLAMP_FRAG_DEPTH = 0x10;
+
+        /// Read each field of a committed or candidate `RayIntersection` that is
+        /// accessed with its own `OpRayQueryGetIntersection*KHR`, instead of
+        /// building the whole intersection with `OpCompositeConstruct`.
+        ///
+        /// This only applies to intersections whose fields are all accessed
+        /// directly. Some drivers handle the individual getters better, as the
+        /// unused fields are never read.
+        const SEPARATE_RAY_QUERY_GETTERS = 0x20;
     }
 }
 
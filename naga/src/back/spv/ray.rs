@@ -2,9 +2,113 @@
 Generating SPIR-V for ray query operations.
 */
 
-use super::{Block, BlockContext, Instruction, LocalType, LookupType};
+use super::{selection::Selection, Block, BlockContext, Error, Instruction, LocalType, LookupType};
 use crate::arena::Handle;
 
+/// Values already fetched from ray queries, so later getters can reuse them
+/// instead of emitting the `OpRayQueryGetIntersection*KHR` instructions again.
+///
+/// Entries are keyed by the id of the query pointer and whether the committed
+/// intersection was read. An entry is only valid while it dominates the code
+/// being written and the query hasn't been advanced since it was fetched:
+///
+/// - Any ray query statement or function call invalidates all entries, since it
+///   may advance the query (possibly through another pointer to it).
+///
+/// - Entries fetched inside a structured construct are dropped when leaving it,
+///   see [`BlockContext::write_block`].
+///
+/// - Loop bodies start without entries, as the loop may advance the query
+///   before the next iteration reaches a getter.
+#[derive(Default)]
+pub(super) struct RayQueryTracker {
+    /// Incremented on every invalidation, to detect ones made in nested constructs.
+    generation: u32,
+    fetched: FetchedRayQueryValues,
+}
+
+#[derive(Clone, Default)]
+struct FetchedRayQueryValues {
+    /// Intersection kinds built by [`BlockContext::write_ray_query_intersection_kind`].
+    intersection_kinds: crate::FastHashMap<(spirv::Word, bool), spirv::Word>,
+    /// `RayIntersection` composites built by [`BlockContext::write_ray_query_get_intersection`].
+    intersections: crate::FastHashMap<(spirv::Word, bool), spirv::Word>,
+    /// Fields of `RayIntersection` read by
+    /// [`BlockContext::write_ray_query_get_intersection_fields`], by field index.
+    intersection_fields: crate::FastHashMap<(spirv::Word, bool, u32), spirv::Word>,
+}
+
+impl FetchedRayQueryValues {
+    fn clear(&mut self) {
+        self.intersection_kinds.clear();
+        self.intersections.clear();
+        self.intersection_fields.clear();
+    }
+}
+
+/// The state of a [`RayQueryTracker`] when entering a structured construct.
+pub(super) struct RayQueryScope {
+    generation: u32,
+    fetched: FetchedRayQueryValues,
+}
+
+impl RayQueryTracker {
+    pub(super) fn enter_scope(&self) -> RayQueryScope {
+        RayQueryScope {
+            generation: self.generation,
+            fetched: self.fetched.clone(),
+        }
+    }
+
+    /// Restore the entries from before the construct, unless it advanced a query.
+    pub(super) fn leave_scope(&mut self, scope: RayQueryScope) {
+        if scope.generation == self.generation {
+            self.fetched = scope.fetched;
+        } else {
+            self.fetched.clear();
+        }
+    }
+
+    /// Forget the entries fetched so far, while keeping them to be restored by
+    /// [`Self::leave_scope`] if nothing advances a query in the meantime.
+    pub(super) fn clear(&mut self) {
+        self.fetched.clear();
+    }
+
+    pub(super) fn invalidate(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.fetched.clear();
+    }
+}
+
+/// The `RayIntersection` fields read by [`Expression::AccessIndex`] from each
+/// [`Expression::RayQueryGetIntersection`] of a function, with the number of
+/// accesses, see [`BlockContext::ray_intersection_accessed_fields`].
+///
+/// [`Expression::AccessIndex`]: crate::Expression::AccessIndex
+/// [`Expression::RayQueryGetIntersection`]: crate::Expression::RayQueryGetIntersection
+pub(super) type RayIntersectionAccesses =
+    crate::FastHashMap<Handle<crate::Expression>, (usize, Vec<u32>)>;
+
+/// Collect the [`RayIntersectionAccesses`] of `expressions` in a single pass.
+pub(super) fn ray_intersection_accesses(
+    expressions: &crate::Arena<crate::Expression>,
+) -> RayIntersectionAccesses {
+    let mut accesses = RayIntersectionAccesses::default();
+    for (_, expr) in expressions.iter() {
+        if let crate::Expression::AccessIndex { base, index } = *expr {
+            if let crate::Expression::RayQueryGetIntersection { .. } = expressions[base] {
+                let &mut (ref mut uses, ref mut fields) = accesses.entry(base).or_default();
+                *uses += 1;
+                if !fields.contains(&index) {
+                    fields.push(index);
+                }
+            }
+        }
+    }
+    accesses
+}
+
 impl<'w> BlockContext<'w> {
     pub(super) fn write_ray_query_function(
         &mut self,
@@ -13,6 +117,7 @@ impl<'w> BlockContext<'w> {
         block: &mut Block,
     ) {
         let query_id = self.cached[query];
+        self.ray_query_tracker.invalidate();
         match *function {
             crate::RayQueryFunction::Initialize {
                 acceleration_structure,
@@ -102,168 +207,694 @@ impl<'w> BlockContext<'w> {
                     .body
                     .push(Instruction::ray_query_proceed(result_type_id, id, query_id));
             }
-            crate::RayQueryFunction::Terminate => {}
+            crate::RayQueryFunction::ConfirmIntersection => {
+                block
+                    .body
+                    .push(Instruction::ray_query_confirm_intersection(query_id));
+            }
+            crate::RayQueryFunction::GenerateIntersection { hit_t } => {
+                let hit_t_id = self.cached[hit_t];
+                block
+                    .body
+                    .push(Instruction::ray_query_generate_intersection(
+                        query_id, hit_t_id,
+                    ));
+            }
+            crate::RayQueryFunction::Terminate => {
+                block.body.push(Instruction::ray_query_terminate(query_id));
+            }
         }
     }
 
-    pub(super) fn write_ray_query_get_intersection(
+    /// Get the raw SPIR-V intersection type of `query`.
+    fn write_ray_query_get_intersection_type(
         &mut self,
-        query: Handle<crate::Expression>,
+        query_id: spirv::Word,
+        committed: bool,
         block: &mut Block,
     ) -> spirv::Word {
-        let query_id = self.cached[query];
-        let intersection_id = self.writer.get_constant_scalar(crate::Literal::U32(
-            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR as _,
-        ));
-
+        let intersection = if committed {
+            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR
+        } else {
+            spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR
+        };
+        let intersection_id = self
+            .writer
+            .get_constant_scalar(crate::Literal::U32(intersection as _));
         let flag_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: None,
             scalar: crate::Scalar::U32,
             pointer_space: None,
         }));
-        let kind_id = self.gen_id();
+        let id = self.gen_id();
         block.body.push(Instruction::ray_query_get_intersection(
             spirv::Op::RayQueryGetIntersectionTypeKHR,
             flag_type_id,
-            kind_id,
-            query_id,
-            intersection_id,
-        ));
-        let instance_custom_index_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionInstanceCustomIndexKHR,
-            flag_type_id,
-            instance_custom_index_id,
+            id,
             query_id,
             intersection_id,
         ));
-        let instance_id = self.gen_id();
+        id
+    }
+
+    pub(super) fn write_ray_query_get_intersection_instance_id(
+        &mut self,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        block: &mut Block,
+    ) -> spirv::Word {
+        let query_id = self.cached[query];
+        let intersection = if committed {
+            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR
+        } else {
+            spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR
+        };
+        let intersection_id = self
+            .writer
+            .get_constant_scalar(crate::Literal::U32(intersection as _));
+        let flag_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            scalar: crate::Scalar::U32,
+            pointer_space: None,
+        }));
+        let id = self.gen_id();
         block.body.push(Instruction::ray_query_get_intersection(
             spirv::Op::RayQueryGetIntersectionInstanceIdKHR,
             flag_type_id,
-            instance_id,
-            query_id,
-            intersection_id,
-        ));
-        let sbt_record_offset_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionInstanceShaderBindingTableRecordOffsetKHR,
-            flag_type_id,
-            sbt_record_offset_id,
-            query_id,
-            intersection_id,
-        ));
-        let geometry_index_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionGeometryIndexKHR,
-            flag_type_id,
-            geometry_index_id,
-            query_id,
-            intersection_id,
-        ));
-        let primitive_index_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionPrimitiveIndexKHR,
-            flag_type_id,
-            primitive_index_id,
+            id,
             query_id,
             intersection_id,
         ));
+        id
+    }
+
+    /// Write the committed or candidate intersection of `query` as a `RayIntersection`.
+    ///
+    /// Only the fields that are defined for the intersection are read: nothing
+    /// for a committed miss, and no barycentrics or facing for procedural hits
+    /// (nor `t` for procedural candidates, which have no hit distance yet).
+    /// Those fields get defined defaults instead, see
+    /// [`Expression::RayQueryGetIntersection`], so a miss also returns zero for
+    /// `t` and all indices.
+    ///
+    /// [`Expression::RayQueryGetIntersection`]: crate::Expression::RayQueryGetIntersection
+    pub(super) fn write_ray_query_get_intersection(
+        &mut self,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        block: &mut Block,
+    ) -> spirv::Word {
+        let query_id = self.cached[query];
+        if let Some(&id) = self
+            .ray_query_tracker
+            .fetched
+            .intersections
+            .get(&(query_id, committed))
+        {
+            return id;
+        }
+        let intersection_id = self.get_ray_query_intersection_id(committed);
 
+        let flag_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            scalar: crate::Scalar::U32,
+            pointer_space: None,
+        }));
         let scalar_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: None,
             scalar: crate::Scalar::F32,
             pointer_space: None,
         }));
-        let t_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionTKHR,
-            scalar_type_id,
-            t_id,
-            query_id,
-            intersection_id,
-        ));
-
         let barycentrics_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: Some(crate::VectorSize::Bi),
             scalar: crate::Scalar::F32,
             pointer_space: None,
         }));
-        let barycentrics_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionBarycentricsKHR,
-            barycentrics_type_id,
-            barycentrics_id,
-            query_id,
-            intersection_id,
-        ));
-
         let bool_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: None,
             scalar: crate::Scalar::BOOL,
             pointer_space: None,
         }));
-        let front_face_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
-            spirv::Op::RayQueryGetIntersectionFrontFaceKHR,
-            bool_type_id,
-            front_face_id,
-            query_id,
-            intersection_id,
-        ));
-
-        let transform_type_id = self.get_type_id(LookupType::Local(LocalType::Matrix {
+        let transform_type = LookupType::Local(LocalType::Matrix {
             columns: crate::VectorSize::Quad,
             rows: crate::VectorSize::Tri,
             width: 4,
+        });
+        let transform_type_id = self.get_type_id(transform_type);
+        let ray_vector_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: Some(crate::VectorSize::Tri),
+            scalar: crate::Scalar::F32,
+            pointer_space: None,
         }));
-        let object_to_world_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
+        let intersection_type =
+            LookupType::Handle(self.ir_module.special_types.ray_intersection.unwrap());
+        let intersection_type_id = self.get_type_id(intersection_type);
+
+        let u32_zero_id = self.writer.get_constant_scalar(crate::Literal::U32(0));
+        let f32_zero_id = self.writer.get_constant_scalar(crate::Literal::F32(0.0));
+        let barycentrics_zero_id = self.writer.get_constant_null(barycentrics_type_id);
+        let ray_vector_zero_id = self.writer.get_constant_null(ray_vector_type_id);
+        let false_id = self.writer.get_constant_scalar(crate::Literal::Bool(false));
+        let identity_id = self.get_identity_transform(transform_type);
+
+        //Note: the arguments must match `generate_ray_intersection_type` layout
+        let miss_id = self.writer.get_constant_composite(
+            intersection_type,
+            &[
+                u32_zero_id,
+                f32_zero_id,
+                u32_zero_id,
+                u32_zero_id,
+                u32_zero_id,
+                u32_zero_id,
+                u32_zero_id,
+                barycentrics_zero_id,
+                false_id,
+                identity_id,
+                identity_id,
+                ray_vector_zero_id,
+                ray_vector_zero_id,
+            ],
+        );
+
+        let kind_id = self.write_ray_query_intersection_kind(query, committed, block);
+
+        let mut selection = Selection::start(block, intersection_type_id);
+        // There is always a candidate, only the committed intersection can be a miss.
+        if committed {
+            let has_hit_id = self.gen_id();
+            selection.block().body.push(Instruction::binary(
+                spirv::Op::INotEqual,
+                bool_type_id,
+                has_hit_id,
+                kind_id,
+                u32_zero_id,
+            ));
+            selection.if_true(self, has_hit_id, miss_id);
+        }
+
+        // Fields defined for any hit, and `t` for any committed hit.
+        let get = |ctx: &mut Self, block: &mut Block, op, type_id| {
+            let id = ctx.gen_id();
+            block.body.push(Instruction::ray_query_get_intersection(
+                op,
+                type_id,
+                id,
+                query_id,
+                intersection_id,
+            ));
+            id
+        };
+        let hit_block = selection.block();
+        let hit_t_id = if committed {
+            get(
+                self,
+                hit_block,
+                spirv::Op::RayQueryGetIntersectionTKHR,
+                scalar_type_id,
+            )
+        } else {
+            f32_zero_id
+        };
+        let instance_custom_index_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionInstanceCustomIndexKHR,
+            flag_type_id,
+        );
+        let instance_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionInstanceIdKHR,
+            flag_type_id,
+        );
+        let sbt_record_offset_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionInstanceShaderBindingTableRecordOffsetKHR,
+            flag_type_id,
+        );
+        let geometry_index_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionGeometryIndexKHR,
+            flag_type_id,
+        );
+        let primitive_index_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionPrimitiveIndexKHR,
+            flag_type_id,
+        );
+        let object_to_world_id = get(
+            self,
+            hit_block,
             spirv::Op::RayQueryGetIntersectionObjectToWorldKHR,
             transform_type_id,
-            object_to_world_id,
-            query_id,
-            intersection_id,
-        ));
-        let world_to_object_id = self.gen_id();
-        block.body.push(Instruction::ray_query_get_intersection(
+        );
+        let world_to_object_id = get(
+            self,
+            hit_block,
             spirv::Op::RayQueryGetIntersectionWorldToObjectKHR,
             transform_type_id,
-            world_to_object_id,
-            query_id,
-            intersection_id,
-        ));
+        );
+        let object_ray_origin_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionObjectRayOriginKHR,
+            ray_vector_type_id,
+        );
+        let object_ray_direction_id = get(
+            self,
+            hit_block,
+            spirv::Op::RayQueryGetIntersectionObjectRayDirectionKHR,
+            ray_vector_type_id,
+        );
 
-        let id = self.gen_id();
-        let intersection_type_id = self.get_type_id(LookupType::Handle(
-            self.ir_module.special_types.ray_intersection.unwrap(),
+        let composite =
+            |ctx: &mut Self, block: &mut Block, t_id, barycentrics_id, front_face_id| {
+                let id = ctx.gen_id();
+                block.body.push(Instruction::composite_construct(
+                    intersection_type_id,
+                    id,
+                    &[
+                        kind_id,
+                        t_id,
+                        instance_custom_index_id,
+                        instance_id,
+                        sbt_record_offset_id,
+                        geometry_index_id,
+                        primitive_index_id,
+                        barycentrics_id,
+                        front_face_id,
+                        object_to_world_id,
+                        world_to_object_id,
+                        object_ray_origin_id,
+                        object_ray_direction_id,
+                    ],
+                ));
+                id
+            };
+        let procedural_id = composite(self, hit_block, hit_t_id, barycentrics_zero_id, false_id);
+
+        let triangle_kind_id = self.writer.get_constant_scalar(crate::Literal::U32(
+            spirv::RayQueryCommittedIntersectionType::RayQueryCommittedIntersectionTriangleKHR as _,
         ));
-        //Note: the arguments must match `generate_ray_intersection_type` layout
-        block.body.push(Instruction::composite_construct(
-            intersection_type_id,
-            id,
-            &[
-                kind_id,
-                t_id,
-                instance_custom_index_id,
-                instance_id,
-                sbt_record_offset_id,
-                geometry_index_id,
-                primitive_index_id,
-                barycentrics_id,
-                front_face_id,
-                object_to_world_id,
-                world_to_object_id,
-            ],
+        let is_triangle_id = self.gen_id();
+        hit_block.body.push(Instruction::binary(
+            spirv::Op::IEqual,
+            bool_type_id,
+            is_triangle_id,
+            kind_id,
+            triangle_kind_id,
         ));
+        selection.if_true(self, is_triangle_id, procedural_id);
+
+        // Fields only defined for triangles, and `t` for triangle candidates.
+        let triangle_block = selection.block();
+        let t_id = if committed {
+            hit_t_id
+        } else {
+            get(
+                self,
+                triangle_block,
+                spirv::Op::RayQueryGetIntersectionTKHR,
+                scalar_type_id,
+            )
+        };
+        let barycentrics_id = get(
+            self,
+            triangle_block,
+            spirv::Op::RayQueryGetIntersectionBarycentricsKHR,
+            barycentrics_type_id,
+        );
+        let front_face_id = get(
+            self,
+            triangle_block,
+            spirv::Op::RayQueryGetIntersectionFrontFaceKHR,
+            bool_type_id,
+        );
+        let triangle_id = composite(self, triangle_block, t_id, barycentrics_id, front_face_id);
+
+        let id = selection.finish(self, triangle_id);
+        self.ray_query_tracker
+            .fetched
+            .intersections
+            .insert((query_id, committed), id);
         id
     }
 
-    pub(super) fn write_ray_query_return_vertex_position(
+    /// Return the constant selecting the committed or candidate intersection.
+    fn get_ray_query_intersection_id(&mut self, committed: bool) -> spirv::Word {
+        let intersection = if committed {
+            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR
+        } else {
+            spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR
+        };
+        self.writer
+            .get_constant_scalar(crate::Literal::U32(intersection as _))
+    }
+
+    /// Get the `kind` member of the intersection of `query`.
+    ///
+    /// This is the intersection type for the committed intersection, but the
+    /// candidate types are remapped, so that triangles have the same kind in
+    /// both and the kind of procedural candidates differs from a miss.
+    ///
+    /// The kind is reused from an earlier getter if it's still valid.
+    pub(super) fn write_ray_query_intersection_kind(
         &mut self,
         query: Handle<crate::Expression>,
+        committed: bool,
         block: &mut Block,
     ) -> spirv::Word {
+        let query_id = self.cached[query];
+        if let Some(&id) = self
+            .ray_query_tracker
+            .fetched
+            .intersection_kinds
+            .get(&(query_id, committed))
+        {
+            return id;
+        }
+
+        let raw_kind_id = self.write_ray_query_get_intersection_type(query_id, committed, block);
+        if committed {
+            self.ray_query_tracker
+                .fetched
+                .intersection_kinds
+                .insert((query_id, committed), raw_kind_id);
+            return raw_kind_id;
+        }
+
+        let flag_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            scalar: crate::Scalar::U32,
+            pointer_space: None,
+        }));
+        let bool_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            scalar: crate::Scalar::BOOL,
+            pointer_space: None,
+        }));
+        let candidate_triangle_id = self.writer.get_constant_scalar(crate::Literal::U32(
+            spirv::RayQueryCandidateIntersectionType::RayQueryCandidateIntersectionTriangleKHR as _,
+        ));
+        let triangle_kind_id = self.writer.get_constant_scalar(crate::Literal::U32(
+            spirv::RayQueryCommittedIntersectionType::RayQueryCommittedIntersectionTriangleKHR as _,
+        ));
+        let aabb_kind_id = self.writer.get_constant_scalar(crate::Literal::U32(
+            crate::back::RayIntersectionType::Aabb as _,
+        ));
+
+        let is_triangle_id = self.gen_id();
+        block.body.push(Instruction::binary(
+            spirv::Op::IEqual,
+            bool_type_id,
+            is_triangle_id,
+            raw_kind_id,
+            candidate_triangle_id,
+        ));
+        let kind_id = self.gen_id();
+        block.body.push(Instruction::select(
+            flag_type_id,
+            kind_id,
+            is_triangle_id,
+            triangle_kind_id,
+            aabb_kind_id,
+        ));
+
+        self.ray_query_tracker
+            .fetched
+            .intersection_kinds
+            .insert((query_id, committed), kind_id);
+        kind_id
+    }
+
+    /// Return the indices of the `RayIntersection` fields read from `expr`, if
+    /// every use of it is an [`Expression::AccessIndex`].
+    ///
+    /// [`Expression::AccessIndex`]: crate::Expression::AccessIndex
+    pub(super) fn ray_intersection_accessed_fields(
+        &self,
+        expr: Handle<crate::Expression>,
+    ) -> Option<Vec<u32>> {
+        let &(uses, ref fields) = self.ray_intersection_accesses.get(&expr)?;
+        (uses == self.fun_info[expr].ref_count).then(|| fields.clone())
+    }
+
+    /// Write the given `fields` of the committed or candidate intersection of
+    /// `query`, each with its own getter instead of building a `RayIntersection`
+    /// composite.
+    ///
+    /// The fields read the same values as [`Self::write_ray_query_get_intersection`]
+    /// would put in the composite. Their ids are cached for `expr`, so the
+    /// [`Expression::AccessIndex`] expressions using it can look them up.
+    ///
+    /// [`Expression::AccessIndex`]: crate::Expression::AccessIndex
+    pub(super) fn write_ray_query_get_intersection_fields(
+        &mut self,
+        expr: Handle<crate::Expression>,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        fields: &[u32],
+        block: &mut Block,
+    ) {
+        let query_id = self.cached[query];
+        let intersection_id = self.get_ray_query_intersection_id(committed);
+        let bool_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            scalar: crate::Scalar::BOOL,
+            pointer_space: None,
+        }));
+        let u32_zero_id = self.writer.get_constant_scalar(crate::Literal::U32(0));
+
+        let mut ids = vec![None; 13];
+        // Reuse the fields an earlier getter of the same intersection already read.
+        let fetched = &self.ray_query_tracker.fetched.intersection_fields;
+        let fields: Vec<u32> = fields
+            .iter()
+            .copied()
+            .filter(|&field| match fetched.get(&(query_id, committed, field)) {
+                Some(&id) => {
+                    ids[field as usize] = Some(id);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        if fields.is_empty() {
+            self.ray_intersection_fields.insert(expr, ids);
+            return;
+        }
+
+        let kind_id = self.write_ray_query_intersection_kind(query, committed, block);
+        let mut has_hit_id = None;
+        let mut is_triangle_id = None;
+
+        for field in fields {
+            //Note: the fields must match `generate_ray_intersection_type` layout
+            let (op, scalar, vector_size, triangle_only) = match field {
+                0 => {
+                    ids[0] = Some(kind_id);
+                    continue;
+                }
+                1 => (
+                    spirv::Op::RayQueryGetIntersectionTKHR,
+                    crate::Scalar::F32,
+                    None,
+                    !committed,
+                ),
+                2 => (
+                    spirv::Op::RayQueryGetIntersectionInstanceCustomIndexKHR,
+                    crate::Scalar::U32,
+                    None,
+                    false,
+                ),
+                3 => (
+                    spirv::Op::RayQueryGetIntersectionInstanceIdKHR,
+                    crate::Scalar::U32,
+                    None,
+                    false,
+                ),
+                4 => (
+                    spirv::Op::RayQueryGetIntersectionInstanceShaderBindingTableRecordOffsetKHR,
+                    crate::Scalar::U32,
+                    None,
+                    false,
+                ),
+                5 => (
+                    spirv::Op::RayQueryGetIntersectionGeometryIndexKHR,
+                    crate::Scalar::U32,
+                    None,
+                    false,
+                ),
+                6 => (
+                    spirv::Op::RayQueryGetIntersectionPrimitiveIndexKHR,
+                    crate::Scalar::U32,
+                    None,
+                    false,
+                ),
+                7 => (
+                    spirv::Op::RayQueryGetIntersectionBarycentricsKHR,
+                    crate::Scalar::F32,
+                    Some(crate::VectorSize::Bi),
+                    true,
+                ),
+                8 => (
+                    spirv::Op::RayQueryGetIntersectionFrontFaceKHR,
+                    crate::Scalar::BOOL,
+                    None,
+                    true,
+                ),
+                9 => (
+                    spirv::Op::RayQueryGetIntersectionObjectToWorldKHR,
+                    crate::Scalar::F32,
+                    None,
+                    false,
+                ),
+                10 => (
+                    spirv::Op::RayQueryGetIntersectionWorldToObjectKHR,
+                    crate::Scalar::F32,
+                    None,
+                    false,
+                ),
+                11 => (
+                    spirv::Op::RayQueryGetIntersectionObjectRayOriginKHR,
+                    crate::Scalar::F32,
+                    Some(crate::VectorSize::Tri),
+                    false,
+                ),
+                12 => (
+                    spirv::Op::RayQueryGetIntersectionObjectRayDirectionKHR,
+                    crate::Scalar::F32,
+                    Some(crate::VectorSize::Tri),
+                    false,
+                ),
+                _ => unreachable!(),
+            };
+
+            let (type_id, default_id) = if field == 9 || field == 10 {
+                let transform_type = LookupType::Local(LocalType::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Tri,
+                    width: 4,
+                });
+                (
+                    self.get_type_id(transform_type),
+                    self.get_identity_transform(transform_type),
+                )
+            } else {
+                let type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                    vector_size,
+                    scalar,
+                    pointer_space: None,
+                }));
+                (type_id, self.writer.get_constant_null(type_id))
+            };
+
+            // Only read the field when it's defined for the intersection. Any
+            // candidate is a hit, so only triangle fields need a check for it.
+            let condition_id = if triangle_only {
+                Some(*is_triangle_id.get_or_insert_with(|| {
+                    let triangle_kind_id = self.writer.get_constant_scalar(crate::Literal::U32(
+                        spirv::RayQueryCommittedIntersectionType::RayQueryCommittedIntersectionTriangleKHR
+                            as _,
+                    ));
+                    let id = self.gen_id();
+                    block.body.push(Instruction::binary(
+                        spirv::Op::IEqual,
+                        bool_type_id,
+                        id,
+                        kind_id,
+                        triangle_kind_id,
+                    ));
+                    id
+                }))
+            } else if committed {
+                Some(*has_hit_id.get_or_insert_with(|| {
+                    let id = self.gen_id();
+                    block.body.push(Instruction::binary(
+                        spirv::Op::INotEqual,
+                        bool_type_id,
+                        id,
+                        kind_id,
+                        u32_zero_id,
+                    ));
+                    id
+                }))
+            } else {
+                None
+            };
+
+            let mut selection = Selection::start(block, type_id);
+            if let Some(condition_id) = condition_id {
+                selection.if_true(self, condition_id, default_id);
+            }
+            let id = self.gen_id();
+            selection
+                .block()
+                .body
+                .push(Instruction::ray_query_get_intersection(
+                    op,
+                    type_id,
+                    id,
+                    query_id,
+                    intersection_id,
+                ));
+            let id = selection.finish(self, id);
+            ids[field as usize] = Some(id);
+            self.ray_query_tracker
+                .fetched
+                .intersection_fields
+                .insert((query_id, committed, field), id);
+        }
+
+        self.ray_intersection_fields.insert(expr, ids);
+    }
+
+    /// Return the id of `field` of the intersection `expr` written by
+    /// [`Self::write_ray_query_get_intersection_fields`], if it was.
+    pub(super) fn ray_intersection_field(
+        &self,
+        expr: Handle<crate::Expression>,
+        field: u32,
+    ) -> Option<spirv::Word> {
+        self.ray_intersection_fields
+            .get(&expr)
+            .and_then(|ids| ids[field as usize])
+    }
+
+    /// Return the constant identity 4x3 transform of type `transform_type`.
+    fn get_identity_transform(&mut self, transform_type: LookupType) -> spirv::Word {
+        let column_type = LookupType::Local(LocalType::Value {
+            vector_size: Some(crate::VectorSize::Tri),
+            scalar: crate::Scalar::F32,
+            pointer_space: None,
+        });
+        let zero_id = self.writer.get_constant_scalar(crate::Literal::F32(0.0));
+        let one_id = self.writer.get_constant_scalar(crate::Literal::F32(1.0));
+        let column_ids = [
+            [one_id, zero_id, zero_id],
+            [zero_id, one_id, zero_id],
+            [zero_id, zero_id, one_id],
+            [zero_id, zero_id, zero_id],
+        ]
+        .map(|column| self.writer.get_constant_composite(column_type, &column));
+        self.writer
+            .get_constant_composite(transform_type, &column_ids)
+    }
+
+    pub(super) fn write_ray_query_return_vertex_position(
+        &mut self,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        block: &mut Block,
+    ) -> Result<spirv::Word, Error> {
+        self.writer.require_any(
+            "ray query vertex positions",
+            &[spirv::Capability::RayQueryPositionFetchKHR],
+        )?;
         let query_id = self.cached[query];
         let id = self.gen_id();
         let result = self
@@ -271,7 +902,7 @@ impl<'w> BlockContext<'w> {
             .special_types
             .ray_vertex_return
             .expect("type should have been populated");
-        let bool_id = self.writer.get_constant_scalar(crate::Literal::Bool(true));
+        let intersection_id = self.get_ray_query_intersection_id(committed);
         block
             .body
             .push(Instruction::ray_query_return_vertex_position(
@@ -282,7 +913,32 @@ impl<'w> BlockContext<'w> {
                     .expect("type should have been populated"),
                 id,
                 query_id,
-                bool_id,
+                intersection_id,
+            ));
+        Ok(id)
+    }
+
+    pub(super) fn write_ray_query_lss_positions(
+        &mut self,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        block: &mut Block,
+    ) -> spirv::Word {
+        let query_id = self.cached[query];
+        let id = self.gen_id();
+        let result = self
+            .ir_module
+            .special_types
+            .ray_lss_positions
+            .expect("type should have been populated");
+        let intersection_id = self.get_ray_query_intersection_id(committed);
+        block
+            .body
+            .push(Instruction::ray_query_get_intersection_lss_positions(
+                self.get_type_id(LookupType::Handle(result)),
+                id,
+                query_id,
+                intersection_id,
             ));
         id
     }
@@ -604,6 +604,12 @@ impl Writer {
 
         // Create a `BlockContext` for generating SPIR-V for the function's
         // body.
+        let ray_intersection_accesses =
+            if self.flags.contains(WriterFlags::SEPARATE_RAY_QUERY_GETTERS) {
+                super::ray::ray_intersection_accesses(&ir_function.expressions)
+            } else {
+                Default::default()
+            };
         let mut context = BlockContext {
             ir_module,
             ir_function,
@@ -618,6 +624,9 @@ impl Writer {
             expression_constness: super::ExpressionConstnessTracker::from_arena(
                 &ir_function.expressions,
             ),
+            ray_query_tracker: Default::default(),
+            ray_intersection_fields: Default::default(),
+            ray_intersection_accesses,
         };
 
         // fill up the pre-emitted and const expressions
@@ -1941,6 +1950,7 @@ impl Writer {
         let mut has_ray_query = ir_module.special_types.ray_desc.is_some()
             | ir_module.special_types.ray_intersection.is_some();
         let has_vertex_return = ir_module.special_types.ray_vertex_return.is_some();
+        let has_lss_positions = ir_module.special_types.ray_lss_positions.is_some();
 
         for (_, &crate::Type { ref inner, .. }) in ir_module.types.iter() {
             // spirv does not about whether these have vertex return - that is done by us
@@ -1967,8 +1977,14 @@ impl Writer {
         if has_vertex_return {
             Instruction::extension("SPV_KHR_ray_tracing_position_fetch")
                 .to_words(&mut self.logical_layout.extensions);
-            Instruction::capability(spirv::Capability::RayQueryPositionFetchKHR)
-                .to_words(&mut self.logical_layout.capabilities)
+        }
+        if has_lss_positions {
+            Instruction::extension("SPV_NV_linear_swept_spheres")
+                .to_words(&mut self.logical_layout.extensions);
+            Instruction::capability_raw(
+                super::CAPABILITY_RAY_TRACING_LINEAR_SWEPT_SPHERES_GEOMETRY_NV,
+            )
+            .to_words(&mut self.logical_layout.capabilities)
         }
         Instruction::type_void(self.void_type).to_words(&mut self.logical_layout.declarations);
         Instruction::ext_inst_import(self.gl450_ext_inst_id, "GLSL.std.450")
@@ -1819,7 +1819,10 @@ impl<W: Write> Writer<W> {
             }
             // Not supported yet
             Expression::RayQueryGetIntersection { .. }
-            | Expression::RayQueryVertexPositions { .. } => unreachable!(),
+            | Expression::RayQueryGetIntersectionType { .. }
+            | Expression::RayQueryGetIntersectionInstanceId { .. }
+            | Expression::RayQueryVertexPositions { .. }
+            | Expression::RayQueryLssPositions { .. } => unreachable!(),
             // Nothing to do here, since call expression already cached
             Expression::CallResult(_)
             | Expression::AtomicResult { .. }
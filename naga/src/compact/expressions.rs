@@ -220,12 +220,24 @@ impl<'tracer> ExpressionTracer<'tracer> {
                 Ex::RayQueryGetIntersection {
                     query,
                     committed: _,
+                }
+                | Ex::RayQueryGetIntersectionType {
+                    query,
+                    committed: _,
+                }
+                | Ex::RayQueryGetIntersectionInstanceId {
+                    query,
+                    committed: _,
                 } => {
                     self.expressions_used.insert(query);
                 }
                 Ex::RayQueryVertexPositions {
                     query,
                     committed: _,
+                }
+                | Ex::RayQueryLssPositions {
+                    query,
+                    committed: _,
                 } => {
                     self.expressions_used.insert(query);
                 }
@@ -392,10 +404,22 @@ impl ModuleMap {
             Ex::RayQueryGetIntersection {
                 ref mut query,
                 committed: _,
+            }
+            | Ex::RayQueryGetIntersectionType {
+                ref mut query,
+                committed: _,
+            }
+            | Ex::RayQueryGetIntersectionInstanceId {
+                ref mut query,
+                committed: _,
             } => adjust(query),
             Ex::RayQueryVertexPositions {
                 ref mut query,
                 committed: _,
+            }
+            | Ex::RayQueryLssPositions {
+                ref mut query,
+                committed: _,
             } => adjust(query),
         }
     }
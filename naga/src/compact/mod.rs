@@ -229,6 +229,7 @@ impl<'module> ModuleTracer<'module> {
             ref ray_desc,
             ref ray_intersection,
             ref ray_vertex_return,
+            ref ray_lss_positions,
             ref predeclared_types,
         } = *special_types;
 
@@ -241,6 +242,9 @@ impl<'module> ModuleTracer<'module> {
         if let Some(ray_vertex_return) = *ray_vertex_return {
             self.types_used.insert(ray_vertex_return);
         }
+        if let Some(ray_lss_positions) = *ray_lss_positions {
+            self.types_used.insert(ray_lss_positions);
+        }
         for (_, &handle) in predeclared_types {
             self.types_used.insert(handle);
         }
@@ -301,6 +305,7 @@ impl ModuleMap {
             ref mut ray_desc,
             ref mut ray_intersection,
             ref mut ray_vertex_return,
+            ref mut ray_lss_positions,
             ref mut predeclared_types,
         } = *special;
 
@@ -315,6 +320,10 @@ impl ModuleMap {
             self.types.adjust(ray_vertex_return);
         }
 
+        if let Some(ref mut ray_lss_positions) = *ray_lss_positions {
+            self.types.adjust(ray_lss_positions);
+        }
+
         for handle in predeclared_types.values_mut() {
             self.types.adjust(handle);
         }
@@ -132,6 +132,37 @@ impl crate::Module {
         array
     }
 
+    /// Make sure the type for the endpoint positions of a linear swept sphere
+    /// hit is in the module's type arena, and return its handle.
+    pub fn generate_lss_positions_type(&mut self) -> Handle<crate::Type> {
+        if let Some(handle) = self.special_types.ray_lss_positions {
+            return handle;
+        }
+        let ty_vec3f = self.types.insert(
+            crate::Type {
+                name: None,
+                inner: crate::TypeInner::Vector {
+                    size: crate::VectorSize::Tri,
+                    scalar: crate::Scalar::F32,
+                },
+            },
+            Span::UNDEFINED,
+        );
+        let array = self.types.insert(
+            crate::Type {
+                name: None,
+                inner: crate::TypeInner::Array {
+                    base: ty_vec3f,
+                    size: crate::ArraySize::Constant(std::num::NonZeroU32::new(2).unwrap()),
+                    stride: 16,
+                },
+            },
+            Span::UNDEFINED,
+        );
+        self.special_types.ray_lss_positions = Some(array);
+        array
+    }
+
     /// Populate this module's [`SpecialTypes::ray_intersection`] type.
     ///
     /// [`SpecialTypes::ray_intersection`] is the type of a
@@ -191,6 +222,16 @@ impl crate::Module {
             },
             Span::UNDEFINED,
         );
+        let ty_ray_vector = self.types.insert(
+            crate::Type {
+                name: None,
+                inner: crate::TypeInner::Vector {
+                    size: crate::VectorSize::Tri,
+                    scalar: crate::Scalar::F32,
+                },
+            },
+            Span::UNDEFINED,
+        );
 
         let handle = self.types.insert(
             crate::Type {
@@ -263,8 +304,20 @@ impl crate::Module {
                             binding: None,
                             offset: 112,
                         },
+                        crate::StructMember {
+                            name: Some("object_ray_origin".to_string()),
+                            ty: ty_ray_vector,
+                            binding: None,
+                            offset: 176,
+                        },
+                        crate::StructMember {
+                            name: Some("object_ray_direction".to_string()),
+                            ty: ty_ray_vector,
+                            binding: None,
+                            offset: 192,
+                        },
                     ],
-                    span: 176,
+                    span: 208,
                 },
             },
             Span::UNDEFINED,
@@ -13,6 +13,12 @@ use crate::{Arena, FastHashMap, FastIndexMap, Handle, Span};
 mod construction;
 mod conversion;
 
+/// The `t_min` of a `RayDesc` constructed without one.
+///
+/// This is a distance in world space, so it may be too small to avoid
+/// self-intersection in large scenes; `offsetRayOrigin` works at any scale.
+const RAY_DESC_DEFAULT_T_MIN: f32 = 0.001;
+
 /// Resolves the inner type of a given expression.
 ///
 /// Expects a &mut [`ExpressionContext`] and a [`Handle<Expression>`].
@@ -2381,6 +2387,42 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                                 committed: true,
                             }
                         }
+                        "getCandidateHitVertexPositions" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let _ = ctx.module.generate_vertex_return_type();
+
+                            crate::Expression::RayQueryVertexPositions {
+                                query,
+                                committed: false,
+                            }
+                        }
+                        "getCommittedHitLssPositions" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let _ = ctx.module.generate_lss_positions_type();
+
+                            crate::Expression::RayQueryLssPositions {
+                                query,
+                                committed: true,
+                            }
+                        }
+                        "getCandidateHitLssPositions" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let _ = ctx.module.generate_lss_positions_type();
+
+                            crate::Expression::RayQueryLssPositions {
+                                query,
+                                committed: false,
+                            }
+                        }
                         "rayQueryProceed" => {
                             let mut args = ctx.prepare_args(arguments, 1, span);
                             let query = self.ray_query_pointer(args.next()?, ctx)?;
@@ -2408,6 +2450,91 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                                 committed: true,
                             }
                         }
+                        "rayQueryGetCandidateIntersection" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let _ = ctx.module.generate_ray_intersection_type();
+
+                            crate::Expression::RayQueryGetIntersection {
+                                query,
+                                committed: false,
+                            }
+                        }
+                        "rayQueryConfirmIntersection" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let fun = crate::RayQueryFunction::ConfirmIntersection;
+                            let rctx = ctx.runtime_expression_ctx(span)?;
+                            rctx.block
+                                .extend(rctx.emitter.finish(&rctx.function.expressions));
+                            rctx.emitter.start(&rctx.function.expressions);
+                            rctx.block
+                                .push(crate::Statement::RayQuery { query, fun }, span);
+                            return Ok(None);
+                        }
+                        "rayQueryGenerateIntersection" => {
+                            let mut args = ctx.prepare_args(arguments, 2, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            let hit_t = self.expression(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let fun = crate::RayQueryFunction::GenerateIntersection { hit_t };
+                            let rctx = ctx.runtime_expression_ctx(span)?;
+                            rctx.block
+                                .extend(rctx.emitter.finish(&rctx.function.expressions));
+                            rctx.emitter.start(&rctx.function.expressions);
+                            rctx.block
+                                .push(crate::Statement::RayQuery { query, fun }, span);
+                            return Ok(None);
+                        }
+                        "rayQueryGetCommittedIntersectionType" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            crate::Expression::RayQueryGetIntersectionType {
+                                query,
+                                committed: true,
+                            }
+                        }
+                        "rayQueryGetCandidateIntersectionType" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            crate::Expression::RayQueryGetIntersectionType {
+                                query,
+                                committed: false,
+                            }
+                        }
+                        "rayQueryGetCommittedIntersectionInstanceId" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            crate::Expression::RayQueryGetIntersectionInstanceId {
+                                query,
+                                committed: true,
+                            }
+                        }
+                        "rayQueryGetCandidateIntersectionInstanceId" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            crate::Expression::RayQueryGetIntersectionInstanceId {
+                                query,
+                                committed: false,
+                            }
+                        }
+                        "RayDesc" if arguments.len() == 5 => {
+                            let ty = ctx.module.generate_ray_desc_type();
+                            return self.ray_desc_with_default_t_min(ty, arguments, span, ctx);
+                        }
                         "RayDesc" => {
                             let ty = ctx.module.generate_ray_desc_type();
                             let handle = self.construct(
@@ -2419,6 +2546,48 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                             )?;
                             return Ok(Some(handle));
                         }
+                        "offsetRayOrigin" => {
+                            let mut args = ctx.prepare_args(arguments, 2, span);
+                            let position = self.expression(args.next()?, ctx)?;
+                            let normal = self.expression(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            return self
+                                .offset_ray_origin(position, normal, span, ctx)
+                                .map(Some);
+                        }
+                        "computeWorldNormal" => {
+                            let mut args = ctx.prepare_args(arguments, 4, span);
+                            let vertices = [
+                                self.expression(args.next()?, ctx)?,
+                                self.expression(args.next()?, ctx)?,
+                                self.expression(args.next()?, ctx)?,
+                            ];
+                            let object_to_world = self.expression(args.next()?, ctx)?;
+                            let front_face = if arguments.len() == 5 {
+                                Some(self.expression(args.next()?, ctx)?)
+                            } else {
+                                None
+                            };
+                            args.finish()?;
+
+                            return self
+                                .compute_world_normal(
+                                    vertices,
+                                    object_to_world,
+                                    front_face,
+                                    span,
+                                    ctx,
+                                )
+                                .map(Some);
+                        }
+                        "barycentricWeights" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let barycentrics = self.expression(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            return self.barycentric_weights(barycentrics, span, ctx).map(Some);
+                        }
                         "subgroupBallot" => {
                             let mut args = ctx.prepare_args(arguments, 0, span);
                             let predicate = if arguments.len() == 1 {
@@ -2976,6 +3145,347 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
             }
         }
     }
+
+    /// Construct a `RayDesc` of type `ty` from `(flags, cull_mask, t_max, origin, dir)`,
+    /// using [`RAY_DESC_DEFAULT_T_MIN`] for `t_min`.
+    fn ray_desc_with_default_t_min(
+        &mut self,
+        ty: Handle<crate::Type>,
+        arguments: &[Handle<ast::Expression<'source>>],
+        span: Span,
+        ctx: &mut ExpressionContext<'source, '_, '_>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error<'source>> {
+        let members = match ctx.module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => {
+                members.iter().map(|member| member.ty).collect::<Vec<_>>()
+            }
+            _ => return Err(Error::Internal("RayDesc is not a struct")),
+        };
+
+        let mut components = Vec::with_capacity(members.len());
+        let mut arguments = arguments.iter();
+        for (index, member_ty) in members.into_iter().enumerate() {
+            let component = if index == 2 {
+                ctx.append_expression(
+                    crate::Expression::Literal(crate::Literal::F32(RAY_DESC_DEFAULT_T_MIN)),
+                    span,
+                )?
+            } else {
+                let argument = *arguments.next().unwrap();
+                let argument_span = ctx.ast_expressions.get_span(argument);
+                let component = self.expression(argument, ctx)?;
+                ctx.try_automatic_conversions(
+                    component,
+                    &crate::proc::TypeResolution::Handle(member_ty),
+                    argument_span,
+                )?
+            };
+            components.push(component);
+        }
+
+        ctx.append_expression(crate::Expression::Compose { ty, components }, span)
+            .map(Some)
+    }
+
+    /// Lower `offsetRayOrigin(position, normal)`, moving `position` off the surface with
+    /// geometric `normal` far enough that rays starting there don't hit it again.
+    ///
+    /// This is the integer offset method from "A Fast and Robust Method for Avoiding
+    /// Self-Intersection" (Wächter and Binder, Ray Tracing Gems): the offset is applied to
+    /// the bit pattern of each coordinate, so it scales with the magnitude of the position.
+    /// Coordinates close to zero, where that offset would be too small, are offset by a
+    /// fixed amount instead.
+    fn offset_ray_origin(
+        &mut self,
+        position: Handle<crate::Expression>,
+        normal: Handle<crate::Expression>,
+        span: Span,
+        ctx: &mut ExpressionContext<'source, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'source>> {
+        const ORIGIN: f32 = 1.0 / 32.0;
+        const FLOAT_SCALE: f32 = 1.0 / 65536.0;
+        const INT_SCALE: f32 = 256.0;
+
+        let vec3f = crate::proc::TypeResolution::Value(crate::TypeInner::Vector {
+            size: crate::VectorSize::Tri,
+            scalar: crate::Scalar::F32,
+        });
+        let position = ctx.try_automatic_conversions(position, &vec3f, span)?;
+        let normal = ctx.try_automatic_conversions(normal, &vec3f, span)?;
+
+        let literal = |ctx: &mut ExpressionContext<'source, '_, '_>, value| {
+            ctx.append_expression(crate::Expression::Literal(crate::Literal::F32(value)), span)
+        };
+        let zero = literal(ctx, 0.0)?;
+        let origin = literal(ctx, ORIGIN)?;
+        let float_scale = literal(ctx, FLOAT_SCALE)?;
+        let int_scale = literal(ctx, INT_SCALE)?;
+        let splat = |ctx: &mut ExpressionContext<'source, '_, '_>, value| {
+            ctx.append_expression(
+                crate::Expression::Splat {
+                    size: crate::VectorSize::Tri,
+                    value,
+                },
+                span,
+            )
+        };
+        let zero = splat(ctx, zero)?;
+        let origin = splat(ctx, origin)?;
+
+        let binary = |ctx: &mut ExpressionContext<'source, '_, '_>, op, left, right| {
+            ctx.append_expression(crate::Expression::Binary { op, left, right }, span)
+        };
+
+        // Offset the bits of each coordinate away from the surface.
+        let int_offset = binary(ctx, crate::BinaryOperator::Multiply, normal, int_scale)?;
+        let int_offset = ctx.append_expression(
+            crate::Expression::As {
+                expr: int_offset,
+                kind: crate::ScalarKind::Sint,
+                convert: Some(4),
+            },
+            span,
+        )?;
+        let negated_int_offset = ctx.append_expression(
+            crate::Expression::Unary {
+                op: crate::UnaryOperator::Negate,
+                expr: int_offset,
+            },
+            span,
+        )?;
+        let negative = binary(ctx, crate::BinaryOperator::Less, position, zero)?;
+        let int_offset = ctx.append_expression(
+            crate::Expression::Select {
+                condition: negative,
+                accept: negated_int_offset,
+                reject: int_offset,
+            },
+            span,
+        )?;
+        let bits = ctx.append_expression(
+            crate::Expression::As {
+                expr: position,
+                kind: crate::ScalarKind::Sint,
+                convert: None,
+            },
+            span,
+        )?;
+        let bits = binary(ctx, crate::BinaryOperator::Add, bits, int_offset)?;
+        let int_offset_position = ctx.append_expression(
+            crate::Expression::As {
+                expr: bits,
+                kind: crate::ScalarKind::Float,
+                convert: None,
+            },
+            span,
+        )?;
+
+        // Offset coordinates close to zero by a fixed amount.
+        let float_offset = binary(ctx, crate::BinaryOperator::Multiply, normal, float_scale)?;
+        let float_offset_position =
+            binary(ctx, crate::BinaryOperator::Add, position, float_offset)?;
+        let magnitude = ctx.append_expression(
+            crate::Expression::Math {
+                fun: crate::MathFunction::Abs,
+                arg: position,
+                arg1: None,
+                arg2: None,
+                arg3: None,
+            },
+            span,
+        )?;
+        let near_origin = binary(ctx, crate::BinaryOperator::Less, magnitude, origin)?;
+
+        ctx.append_expression(
+            crate::Expression::Select {
+                condition: near_origin,
+                accept: float_offset_position,
+                reject: int_offset_position,
+            },
+            span,
+        )
+    }
+
+    /// Lower `barycentricWeights(barycentrics)`, the weights `(1 - u - v, u, v)` of the three
+    /// vertices of a triangle hit from the `(u, v)` barycentrics of a `RayIntersection`, which
+    /// only hold the weights of the second and third vertex.
+    fn barycentric_weights(
+        &mut self,
+        barycentrics: Handle<crate::Expression>,
+        span: Span,
+        ctx: &mut ExpressionContext<'source, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'source>> {
+        let vec2f = crate::proc::TypeResolution::Value(crate::TypeInner::Vector {
+            size: crate::VectorSize::Bi,
+            scalar: crate::Scalar::F32,
+        });
+        let barycentrics = ctx.try_automatic_conversions(barycentrics, &vec2f, span)?;
+
+        let u = ctx.append_expression(
+            crate::Expression::AccessIndex {
+                base: barycentrics,
+                index: 0,
+            },
+            span,
+        )?;
+        let v = ctx.append_expression(
+            crate::Expression::AccessIndex {
+                base: barycentrics,
+                index: 1,
+            },
+            span,
+        )?;
+        let one =
+            ctx.append_expression(crate::Expression::Literal(crate::Literal::F32(1.0)), span)?;
+        let w = ctx.append_expression(
+            crate::Expression::Binary {
+                op: crate::BinaryOperator::Subtract,
+                left: one,
+                right: u,
+            },
+            span,
+        )?;
+        let w = ctx.append_expression(
+            crate::Expression::Binary {
+                op: crate::BinaryOperator::Subtract,
+                left: w,
+                right: v,
+            },
+            span,
+        )?;
+
+        let vec3f = ctx.ensure_type_exists(crate::TypeInner::Vector {
+            size: crate::VectorSize::Tri,
+            scalar: crate::Scalar::F32,
+        });
+        ctx.append_expression(
+            crate::Expression::Compose {
+                ty: vec3f,
+                components: vec![w, u, v],
+            },
+            span,
+        )
+    }
+
+    /// Lower `computeWorldNormal(v0, v1, v2, object_to_world[, front_face])`, the normalized
+    /// world space geometric normal of the triangle with object space vertices `v0`, `v1` and
+    /// `v2`, as returned by `getCommittedHitVertexPositions`.
+    ///
+    /// Normals transform by the inverse transpose of the linear part of `object_to_world`. That
+    /// is its cofactor matrix divided by its determinant, so multiplying the cofactor matrix by
+    /// the determinant instead keeps the orientation while avoiding the division. If
+    /// `front_face` is given and false, the normal is flipped to face the side that was hit.
+    fn compute_world_normal(
+        &mut self,
+        vertices: [Handle<crate::Expression>; 3],
+        object_to_world: Handle<crate::Expression>,
+        front_face: Option<Handle<crate::Expression>>,
+        span: Span,
+        ctx: &mut ExpressionContext<'source, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'source>> {
+        let vec3f = crate::proc::TypeResolution::Value(crate::TypeInner::Vector {
+            size: crate::VectorSize::Tri,
+            scalar: crate::Scalar::F32,
+        });
+        let mut converted = [vertices[0]; 3];
+        for (vertex, converted) in vertices.into_iter().zip(converted.iter_mut()) {
+            *converted = ctx.try_automatic_conversions(vertex, &vec3f, span)?;
+        }
+        let [v0, v1, v2] = converted;
+        let object_to_world = ctx.concretize(object_to_world)?;
+
+        let binary = |ctx: &mut ExpressionContext<'source, '_, '_>, op, left, right| {
+            ctx.append_expression(crate::Expression::Binary { op, left, right }, span)
+        };
+        let math = |ctx: &mut ExpressionContext<'source, '_, '_>, fun, arg, arg1| {
+            ctx.append_expression(
+                crate::Expression::Math {
+                    fun,
+                    arg,
+                    arg1,
+                    arg2: None,
+                    arg3: None,
+                },
+                span,
+            )
+        };
+
+        let edge1 = binary(ctx, crate::BinaryOperator::Subtract, v1, v0)?;
+        let edge2 = binary(ctx, crate::BinaryOperator::Subtract, v2, v0)?;
+        let normal = math(ctx, crate::MathFunction::Cross, edge1, Some(edge2))?;
+
+        let mut columns = [object_to_world; 3];
+        for (index, column) in columns.iter_mut().enumerate() {
+            *column = ctx.append_expression(
+                crate::Expression::AccessIndex {
+                    base: object_to_world,
+                    index: index as u32,
+                },
+                span,
+            )?;
+        }
+        let mut cofactors = [object_to_world; 3];
+        for (index, cofactor) in cofactors.iter_mut().enumerate() {
+            *cofactor = math(
+                ctx,
+                crate::MathFunction::Cross,
+                columns[(index + 1) % 3],
+                Some(columns[(index + 2) % 3]),
+            )?;
+        }
+        let determinant = math(
+            ctx,
+            crate::MathFunction::Dot,
+            columns[0],
+            Some(cofactors[0]),
+        )?;
+
+        let mat3x3f = ctx.ensure_type_exists(crate::TypeInner::Matrix {
+            columns: crate::VectorSize::Tri,
+            rows: crate::VectorSize::Tri,
+            scalar: crate::Scalar::F32,
+        });
+        let cofactor_matrix = ctx.append_expression(
+            crate::Expression::Compose {
+                ty: mat3x3f,
+                components: cofactors.to_vec(),
+            },
+            span,
+        )?;
+        let world_normal = binary(
+            ctx,
+            crate::BinaryOperator::Multiply,
+            cofactor_matrix,
+            normal,
+        )?;
+        let world_normal = binary(
+            ctx,
+            crate::BinaryOperator::Multiply,
+            world_normal,
+            determinant,
+        )?;
+        let world_normal = math(ctx, crate::MathFunction::Normalize, world_normal, None)?;
+
+        let front_face = match front_face {
+            Some(front_face) => front_face,
+            None => return Ok(world_normal),
+        };
+        let flipped = ctx.append_expression(
+            crate::Expression::Unary {
+                op: crate::UnaryOperator::Negate,
+                expr: world_normal,
+            },
+            span,
+        )?;
+        ctx.append_expression(
+            crate::Expression::Select {
+                condition: front_face,
+                accept: world_normal,
+                reject: flipped,
+            },
+            span,
+        )
+    }
 }
 
 impl crate::AtomicFunction {
@@ -662,9 +662,37 @@ impl Parser {
                 let _ = lexer.next();
                 ast::Expression::Literal(ast::Literal::Number(Number::U32(4)))
             }
+            (Token::Word("RAY_FLAG_SKIP_TRIANGLES"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(0x100)))
+            }
+            (Token::Word("RAY_FLAG_SKIP_AABBS"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(0x200)))
+            }
             (Token::Word("RAY_QUERY_INTERSECTION_NONE"), _) => {
                 let _ = lexer.next();
-                ast::Expression::Literal(ast::Literal::Number(Number::U32(0)))
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(
+                    crate::back::RayIntersectionType::None as u32,
+                )))
+            }
+            (Token::Word("RAY_QUERY_INTERSECTION_TRIANGLE"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(
+                    crate::back::RayIntersectionType::Triangle as u32,
+                )))
+            }
+            (Token::Word("RAY_QUERY_INTERSECTION_GENERATED"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(
+                    crate::back::RayIntersectionType::Generated as u32,
+                )))
+            }
+            (Token::Word("RAY_QUERY_INTERSECTION_AABB"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(
+                    crate::back::RayIntersectionType::Aabb as u32,
+                )))
             }
             (Token::Word(word), span) => {
                 let start = lexer.start_byte_offset();
@@ -636,3 +636,124 @@ fn parse_missing_workgroup_size() {
         Error::MissingWorkgroupSize(span) if span == Span::new(1, 8)
     ));
 }
+
+#[test]
+fn parse_ray_query_intersection_kinds() {
+    parse_str(
+        "
+        fn foo(kind: u32) -> bool {
+            return kind == RAY_QUERY_INTERSECTION_NONE
+                || kind == RAY_QUERY_INTERSECTION_TRIANGLE
+                || kind == RAY_QUERY_INTERSECTION_GENERATED
+                || kind == RAY_QUERY_INTERSECTION_AABB;
+        }",
+    )
+    .unwrap();
+}
+
+#[test]
+fn parse_ray_offset_origin() {
+    let module = parse_str(
+        "
+        fn shadow_ray(position: vec3<f32>, normal: vec3<f32>) -> RayDesc {
+            return RayDesc(0u, 0xFFu, 100.0, offsetRayOrigin(position, normal), normal);
+        }
+        fn abstract_arguments() -> vec3<f32> {
+            return offsetRayOrigin(vec3(0.0, 1.0, 0.0), vec3(0.0, 1.0, 0.0));
+        }",
+    )
+    .unwrap();
+
+    let ray_desc = module.special_types.ray_desc.unwrap();
+    let (_, function) = module.functions.iter().next().unwrap();
+    let t_min = function
+        .expressions
+        .iter()
+        .find_map(|(_, expr)| match *expr {
+            crate::Expression::Compose { ty, ref components } if ty == ray_desc => {
+                Some(function.expressions[components[2]].clone())
+            }
+            _ => None,
+        });
+    assert!(matches!(
+        t_min,
+        Some(crate::Expression::Literal(crate::Literal::F32(t))) if t > 0.0
+    ));
+
+    crate::valid::Validator::new(Default::default(), crate::valid::Capabilities::all())
+        .validate(&module)
+        .unwrap();
+}
+
+#[test]
+fn parse_ray_world_normal() {
+    let module = parse_str(
+        "
+        @group(0) @binding(0)
+        var acc_struct: acceleration_structure<vertex_return>;
+
+        fn hit_normal(ray: RayDesc) -> vec3<f32> {
+            var rq: ray_query<vertex_return>;
+            rayQueryInitialize(&rq, acc_struct, ray);
+            rayQueryProceed(&rq);
+            let intersection = rayQueryGetCommittedIntersection(&rq);
+            let vertices = getCommittedHitVertexPositions(&rq);
+            return computeWorldNormal(
+                vertices[0], vertices[1], vertices[2],
+                intersection.object_to_world, intersection.front_face,
+            );
+        }
+        fn linear_transform(m: mat3x3<f32>) -> vec3<f32> {
+            return computeWorldNormal(vec3(0.0), vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), m);
+        }",
+    )
+    .unwrap();
+
+    crate::valid::Validator::new(Default::default(), crate::valid::Capabilities::all())
+        .validate(&module)
+        .unwrap();
+
+    assert!(parse_str(
+        "fn f() -> vec3<f32> { return computeWorldNormal(vec3(0.0), vec3(0.0), vec3(0.0)); }"
+    )
+    .is_err());
+}
+
+#[test]
+fn parse_barycentric_weights() {
+    let module = parse_str(
+        "
+        @group(0) @binding(0)
+        var acc_struct: acceleration_structure;
+
+        fn hit_weights(ray: RayDesc) -> vec3<f32> {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, ray);
+            rayQueryProceed(&rq);
+            let intersection = rayQueryGetCommittedIntersection(&rq);
+            return barycentricWeights(intersection.barycentrics);
+        }
+        const WEIGHTS: vec3<f32> = barycentricWeights(vec2(0.25, 0.5));",
+    )
+    .unwrap();
+
+    let (_, weights) = module.constants.iter().next().unwrap();
+    let components = match module.global_expressions[weights.init] {
+        crate::Expression::Compose { ref components, .. } => components
+            .iter()
+            .map(|&component| match module.global_expressions[component] {
+                crate::Expression::Literal(crate::Literal::F32(value)) => value,
+                ref other => panic!("{other:?}"),
+            })
+            .collect::<Vec<_>>(),
+        ref other => panic!("{other:?}"),
+    };
+    assert_eq!(components, [0.25, 0.25, 0.5]);
+
+    crate::valid::Validator::new(Default::default(), crate::valid::Capabilities::all())
+        .validate(&module)
+        .unwrap();
+
+    assert!(parse_str("fn f() -> vec3<f32> { return barycentricWeights(); }").is_err());
+    assert!(parse_str("fn f() -> vec3<f32> { return barycentricWeights(vec3(0.0)); }").is_err());
+}
@@ -1655,7 +1655,13 @@ pub enum Expression {
     /// a pointer to a structure containing a runtime array in its' last field.
     ArrayLength(Handle<Expression>),
 
-    /// Get the Positions of the triangle hit by the [`RayQuery`]
+    /// Get the positions of the three vertices of the triangle hit by the
+    /// [`RayQuery`], as an array of three `vec3<f32>`.
+    ///
+    /// If `committed` is true, this is the committed triangle, otherwise the
+    /// candidate one, which must be a triangle. The query must be a
+    /// `ray_query<vertex_return>` initialized with an
+    /// `acceleration_structure<vertex_return>`.
     ///
     /// [`RayQuery`]: Statement::RayQuery
     RayQueryVertexPositions {
@@ -1663,6 +1669,20 @@ pub enum Expression {
         committed: bool,
     },
 
+    /// Get the positions of the two endpoints of the linear swept sphere hit
+    /// by the [`RayQuery`], as an array of two `vec3<f32>`.
+    ///
+    /// Requires [`Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES`]. Only the
+    /// SPIR-V backend supports this so far; building acceleration structures
+    /// out of curve primitives is not supported yet.
+    ///
+    /// [`RayQuery`]: Statement::RayQuery
+    /// [`Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES`]: crate::valid::Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES
+    RayQueryLssPositions {
+        query: Handle<Expression>,
+        committed: bool,
+    },
+
     /// Result of a [`Proceed`] [`RayQuery`] statement.
     ///
     /// [`Proceed`]: RayQueryFunction::Proceed
@@ -1672,10 +1692,55 @@ pub enum Expression {
     /// Return an intersection found by `query`.
     ///
     /// If `committed` is true, return the committed result available when
+    /// the query is done or between [`Proceed`] calls. Otherwise, return the
+    /// candidate intersection that the last [`Proceed`] stopped at, which is
+    /// only valid while it returned `true`.
+    ///
+    /// The `kind` member tells a miss from a hit: it is
+    /// `RAY_QUERY_INTERSECTION_NONE` for a committed miss,
+    /// `RAY_QUERY_INTERSECTION_TRIANGLE` for triangles,
+    /// `RAY_QUERY_INTERSECTION_GENERATED` for committed procedural hits and
+    /// `RAY_QUERY_INTERSECTION_AABB` for procedural candidates. Fields that
+    /// aren't defined for the `kind`, like the transforms of a miss or the
+    /// barycentrics of a procedural hit, are given defined defaults by backends
+    /// instead: the identity transform, zero and `false`.
+    ///
+    /// [`Proceed`]: RayQueryFunction::Proceed
     RayQueryGetIntersection {
         query: Handle<Expression>,
         committed: bool,
     },
+
+    /// Return the kind of the intersection found by `query`, as a `u32`.
+    ///
+    /// This has the same values as the `kind` member of
+    /// [`RayQueryGetIntersection`], so it is the cheapest way to check for a
+    /// miss. For the candidate intersection, it is `RAY_QUERY_INTERSECTION_TRIANGLE`
+    /// for triangles and `RAY_QUERY_INTERSECTION_AABB` for procedural geometry.
+    ///
+    /// This is cheaper than fetching the whole intersection when only the kind
+    /// is needed, e.g. to dispatch on the candidate type in a proceed loop.
+    /// Backends may reuse the kind fetched by an earlier getter for the same
+    /// query and state, instead of querying it again.
+    ///
+    /// [`RayQueryGetIntersection`]: Expression::RayQueryGetIntersection
+    RayQueryGetIntersectionType {
+        query: Handle<Expression>,
+        committed: bool,
+    },
+
+    /// Return the index of the instance of the intersection found by `query`
+    /// in its top level acceleration structure, as a `u32`.
+    ///
+    /// This is the `instance_id` member of [`RayQueryGetIntersection`], but
+    /// also available for the candidate intersection, e.g. to look up
+    /// per-instance parameters of procedural geometry in a proceed loop.
+    ///
+    /// [`RayQueryGetIntersection`]: Expression::RayQueryGetIntersection
+    RayQueryGetIntersectionInstanceId {
+        query: Handle<Expression>,
+        committed: bool,
+    },
     /// Result of a [`SubgroupBallot`] statement.
     ///
     /// [`SubgroupBallot`]: Statement::SubgroupBallot
@@ -1755,6 +1820,26 @@ pub enum RayQueryFunction {
         result: Handle<Expression>,
     },
 
+    /// Commit the current candidate intersection of the query, which must be
+    /// a triangle, e.g. after an alpha test passed.
+    ///
+    /// The traversal continues with the next [`Proceed`], but only considers
+    /// intersections closer than the committed one.
+    ///
+    /// [`Proceed`]: RayQueryFunction::Proceed
+    ConfirmIntersection,
+
+    /// Report a hit at distance `hit_t` for the current candidate intersection
+    /// of the query, which must be the bounding box of procedural geometry.
+    ///
+    /// This commits the hit like [`ConfirmIntersection`] does for triangles.
+    ///
+    /// [`ConfirmIntersection`]: RayQueryFunction::ConfirmIntersection
+    GenerateIntersection {
+        /// The distance of the hit along the ray, an `f32` scalar.
+        hit_t: Handle<Expression>,
+    },
+
     Terminate,
 }
 
@@ -2224,6 +2309,12 @@ pub struct SpecialTypes {
     /// Call [`Module::generate_vertex_return_type`]
     pub ray_vertex_return: Option<Handle<Type>>,
 
+    /// Type for the endpoint positions of a linear swept sphere hit.
+    ///
+    /// Call [`Module::generate_lss_positions_type`] to populate this if
+    /// needed and return the handle.
+    pub ray_lss_positions: Option<Handle<Type>>,
+
     /// Types for predeclared wgsl types instantiated on demand.
     ///
     /// Call [`Module::generate_predeclared_type`] to populate this if
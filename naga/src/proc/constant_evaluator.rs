@@ -890,7 +890,10 @@ impl<'a> ConstantEvaluator<'a> {
             | Expression::ImageQuery { .. } => Err(ConstantEvaluatorError::ImageExpression),
             Expression::RayQueryProceedResult
             | Expression::RayQueryGetIntersection { .. }
-            | Expression::RayQueryVertexPositions { .. } => {
+            | Expression::RayQueryGetIntersectionType { .. }
+            | Expression::RayQueryGetIntersectionInstanceId { .. }
+            | Expression::RayQueryVertexPositions { .. }
+            | Expression::RayQueryLssPositions { .. } => {
                 Err(ConstantEvaluatorError::RayQueryExpression)
             }
             Expression::SubgroupBallotResult { .. } => {
@@ -897,6 +897,10 @@ impl<'a> ResolveContext<'a> {
                     .ok_or(ResolveError::MissingSpecialType)?;
                 TypeResolution::Handle(result)
             }
+            crate::Expression::RayQueryGetIntersectionType { .. }
+            | crate::Expression::RayQueryGetIntersectionInstanceId { .. } => {
+                TypeResolution::Value(Ti::Scalar(crate::Scalar::U32))
+            }
             crate::Expression::RayQueryVertexPositions { .. } => {
                 let result = self
                     .special_types
@@ -904,6 +908,13 @@ impl<'a> ResolveContext<'a> {
                     .ok_or(ResolveError::MissingSpecialType)?;
                 TypeResolution::Handle(result)
             }
+            crate::Expression::RayQueryLssPositions { .. } => {
+                let result = self
+                    .special_types
+                    .ray_lss_positions
+                    .ok_or(ResolveError::MissingSpecialType)?;
+                TypeResolution::Handle(result)
+            }
             crate::Expression::SubgroupBallotResult => TypeResolution::Value(Ti::Vector {
                 scalar: crate::Scalar::U32,
                 size: crate::VectorSize::Quad,
@@ -787,6 +787,14 @@ impl FunctionInfo {
             E::RayQueryGetIntersection {
                 query,
                 committed: _,
+            }
+            | E::RayQueryGetIntersectionType {
+                query,
+                committed: _,
+            }
+            | E::RayQueryGetIntersectionInstanceId {
+                query,
+                committed: _,
             } => Uniformity {
                 non_uniform_result: self.add_ref(query),
                 requirements: UniformityRequirements::empty(),
@@ -802,6 +810,10 @@ impl FunctionInfo {
             E::RayQueryVertexPositions {
                 query,
                 committed: _,
+            }
+            | E::RayQueryLssPositions {
+                query,
+                committed: _,
             } => Uniformity {
                 non_uniform_result: self.add_ref(query),
                 requirements: UniformityRequirements::empty(),
@@ -1038,13 +1050,20 @@ impl FunctionInfo {
                 }
                 S::RayQuery { query, ref fun } => {
                     let _ = self.add_ref(query);
-                    if let crate::RayQueryFunction::Initialize {
-                        acceleration_structure,
-                        descriptor,
-                    } = *fun
-                    {
-                        let _ = self.add_ref(acceleration_structure);
-                        let _ = self.add_ref(descriptor);
+                    match *fun {
+                        crate::RayQueryFunction::Initialize {
+                            acceleration_structure,
+                            descriptor,
+                        } => {
+                            let _ = self.add_ref(acceleration_structure);
+                            let _ = self.add_ref(descriptor);
+                        }
+                        crate::RayQueryFunction::GenerateIntersection { hit_t } => {
+                            let _ = self.add_ref(hit_t);
+                        }
+                        crate::RayQueryFunction::Proceed { .. }
+                        | crate::RayQueryFunction::ConfirmIntersection
+                        | crate::RayQueryFunction::Terminate => {}
                     }
                     FunctionUniformity::new()
                 }
@@ -1618,6 +1618,14 @@ impl super::Validator {
             E::RayQueryGetIntersection {
                 query,
                 committed: _,
+            }
+            | E::RayQueryGetIntersectionType {
+                query,
+                committed: _,
+            }
+            | E::RayQueryGetIntersectionInstanceId {
+                query,
+                committed: _,
             } => match resolver[query] {
                 Ti::Pointer {
                     base,
@@ -1655,6 +1663,35 @@ impl super::Validator {
                     return Err(ExpressionError::InvalidRayQueryType(query));
                 }
             },
+            E::RayQueryLssPositions {
+                query,
+                committed: _,
+            } => {
+                if !self
+                    .capabilities
+                    .contains(super::Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES)
+                {
+                    return Err(ExpressionError::MissingCapabilities(
+                        super::Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES,
+                    ));
+                }
+                match resolver[query] {
+                    Ti::Pointer {
+                        base,
+                        space: crate::AddressSpace::Function,
+                    } => match resolver.types[base].inner {
+                        Ti::RayQuery { .. } => ShaderStages::all(),
+                        ref other => {
+                            log::error!("Intersection result of a pointer to {:?}", other);
+                            return Err(ExpressionError::InvalidRayQueryType(query));
+                        }
+                    },
+                    ref other => {
+                        log::error!("Intersection result of {:?}", other);
+                        return Err(ExpressionError::InvalidRayQueryType(query));
+                    }
+                }
+            }
             E::SubgroupBallotResult | E::SubgroupOperationResult { .. } => self.subgroup_stages,
         };
         Ok(stages)
@@ -166,6 +166,12 @@ pub enum FunctionError {
     MissingRayQueryVertexReturn(Handle<crate::Expression>),
     #[error("Ray descriptor {0:?} is not a matching expression")]
     InvalidRayDescriptor(Handle<crate::Expression>),
+    #[error("Ray descriptor {0:?} skips both triangles and AABBs, so the query can never intersect anything")]
+    RayDescriptorSkipsAllGeometry(Handle<crate::Expression>),
+    #[error("Ray query {0:?} has no candidate intersection to confirm or generate outside of its proceed loop")]
+    RayQueryCandidateOutsideLoop(Handle<crate::Expression>),
+    #[error("Hit distance {0:?} of a generated intersection is not an f32 scalar")]
+    InvalidRayQueryHitT(Handle<crate::Expression>),
     #[error("Ray Query {0:?} does not have a matching type")]
     InvalidRayQueryType(Handle<crate::Type>),
     #[error("Shader requires capability {0:?}")]
@@ -716,7 +722,10 @@ impl super::Validator {
                             | Ex::As { .. }
                             | Ex::ArrayLength(_)
                             | Ex::RayQueryGetIntersection { .. }
-                            | Ex::RayQueryVertexPositions { .. } => {
+                            | Ex::RayQueryGetIntersectionType { .. }
+                            | Ex::RayQueryGetIntersectionInstanceId { .. }
+                            | Ex::RayQueryVertexPositions { .. }
+                            | Ex::RayQueryLssPositions { .. } => {
                                 self.emit_expression(handle, context)?
                             }
                             Ex::CallResult(_)
@@ -1230,10 +1239,49 @@ impl super::Validator {
                                 return Err(FunctionError::InvalidRayDescriptor(descriptor)
                                     .with_span_static(span, "invalid ray descriptor"));
                             }
+                            // The flags are only known here when the descriptor is built
+                            // from a literal, which is how they are usually written.
+                            if let crate::Expression::Compose { ref components, .. } =
+                                *context.get_expression(descriptor)
+                            {
+                                if let crate::Expression::Literal(crate::Literal::U32(flags)) =
+                                    *context.get_expression(components[0])
+                                {
+                                    let skip_all = crate::back::RayFlag::SKIP_TRIANGLES
+                                        | crate::back::RayFlag::SKIP_AABBS;
+                                    if crate::back::RayFlag::from_bits_retain(flags)
+                                        .contains(skip_all)
+                                    {
+                                        return Err(FunctionError::RayDescriptorSkipsAllGeometry(
+                                            descriptor,
+                                        )
+                                        .with_span_static(span, "ray query skips all geometry"));
+                                    }
+                                }
+                            }
                         }
                         crate::RayQueryFunction::Proceed { result } => {
                             self.emit_expression(result, context)?;
                         }
+                        crate::RayQueryFunction::ConfirmIntersection => {
+                            if !context.abilities.contains(ControlFlowAbility::CONTINUE) {
+                                return Err(FunctionError::RayQueryCandidateOutsideLoop(query)
+                                    .with_span_static(span, "no candidate intersection"));
+                            }
+                        }
+                        crate::RayQueryFunction::GenerateIntersection { hit_t } => {
+                            if !context.abilities.contains(ControlFlowAbility::CONTINUE) {
+                                return Err(FunctionError::RayQueryCandidateOutsideLoop(query)
+                                    .with_span_static(span, "no candidate intersection"));
+                            }
+                            match *context.resolve_type(hit_t, &self.valid_expression_set)? {
+                                Ti::Scalar(crate::Scalar::F32) => {}
+                                _ => {
+                                    return Err(FunctionError::InvalidRayQueryHitT(hit_t)
+                                        .with_span_static(span, "invalid hit distance"))
+                                }
+                            }
+                        }
                         crate::RayQueryFunction::Terminate => {}
                     }
                 }
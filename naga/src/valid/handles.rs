@@ -431,9 +431,21 @@ impl super::Validator {
                 query,
                 committed: _,
             }
+            | crate::Expression::RayQueryGetIntersectionType {
+                query,
+                committed: _,
+            }
+            | crate::Expression::RayQueryGetIntersectionInstanceId {
+                query,
+                committed: _,
+            }
             | crate::Expression::RayQueryVertexPositions {
                 query,
                 committed: _,
+            }
+            | crate::Expression::RayQueryLssPositions {
+                query,
+                committed: _,
             } => {
                 handle.check_dep(query)?;
             }
@@ -570,7 +582,11 @@ impl super::Validator {
                     crate::RayQueryFunction::Proceed { result } => {
                         validate_expr(result)?;
                     }
-                    crate::RayQueryFunction::Terminate => {}
+                    crate::RayQueryFunction::GenerateIntersection { hit_t } => {
+                        validate_expr(hit_t)?;
+                    }
+                    crate::RayQueryFunction::ConfirmIntersection
+                    | crate::RayQueryFunction::Terminate => {}
                 }
                 Ok(())
             }
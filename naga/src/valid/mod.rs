@@ -145,6 +145,8 @@ bitflags::bitflags! {
         const SHADER_INT64_ATOMIC_ALL_OPS = 0x100000;
         /// Support for ray queries returning vertex position
         const RAY_HIT_VERTEX_POSITION = 0x200000;
+        /// Support for ray queries returning the positions of hit linear swept spheres.
+        const RAY_TRACING_LINEAR_SWEPT_SPHERES = 0x400000;
     }
 }
 
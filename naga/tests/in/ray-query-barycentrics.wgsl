@@ -0,0 +1,19 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> colors: array<vec3<f32>, 3>;
+
+@group(0) @binding(2)
+var<storage, read_write> color: vec3<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3(0.0), vec3(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    let weights = barycentricWeights(intersection.barycentrics);
+    color = weights.x * colors[0] + weights.y * colors[1] + weights.z * colors[2];
+}
@@ -0,0 +1,37 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+struct Output {
+    t: f32,
+    kind: u32,
+    candidates: u32,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> output: Output;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+
+    var candidates = 0u;
+    while (rayQueryProceed(&rq)) {
+        let candidate = rayQueryGetCandidateIntersection(&rq);
+        candidates += 1u;
+        if (candidate.kind == 1u) {
+            // Triangle candidates are accepted as they are.
+            if (candidate.front_face) {
+                rayQueryConfirmIntersection(&rq);
+            }
+        } else {
+            // Bounding box candidates get a hit generated halfway along the ray.
+            rayQueryGenerateIntersection(&rq, 50.0);
+        }
+    }
+
+    let committed = rayQueryGetCommittedIntersection(&rq);
+    output.t = committed.t;
+    output.kind = committed.kind;
+    output.candidates = candidates;
+}
@@ -0,0 +1,27 @@
+@group(0) @binding(0)
+var acc_struct_a: acceleration_structure;
+
+@group(0) @binding(1)
+var acc_struct_b: acceleration_structure;
+
+@group(0) @binding(2)
+var<storage, read_write> output: array<u32, 2>;
+
+// A traversal helper that works on whichever acceleration structure it's given.
+fn trace_shadow(acs: acceleration_structure, origin: vec3f) -> u32 {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acs, RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.1, 100.0, origin, vec3f(0.0, 0.0, 1.0)));
+    while (rayQueryProceed(&rq)) {}
+    return rayQueryGetCommittedIntersection(&rq).instance_custom_index;
+}
+
+fn trace_both(a: acceleration_structure, b: acceleration_structure) -> vec2u {
+    return vec2u(trace_shadow(a, vec3f(0.0)), trace_shadow(b, vec3f(0.0)));
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    let hits = trace_both(acc_struct_a, acc_struct_b);
+    output[0] = hits.x;
+    output[1] = hits.y;
+}
@@ -0,0 +1,24 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+struct Output {
+    candidate_instances: u32,
+    committed_instance: u32,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> output: Output;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+
+    var candidate_instances = 0u;
+    while (rayQueryProceed(&rq)) {
+        candidate_instances |= 1u << rayQueryGetCandidateIntersectionInstanceId(&rq);
+    }
+
+    output.candidate_instances = candidate_instances;
+    output.committed_instance = rayQueryGetCommittedIntersectionInstanceId(&rq);
+}
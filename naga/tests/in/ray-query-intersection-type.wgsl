@@ -0,0 +1,28 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+struct Output {
+    candidates: u32,
+    kind: u32,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> output: Output;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+
+    var candidates = 0u;
+    while (rayQueryProceed(&rq)) {
+        // Both getters should share a single fetch of the candidate type.
+        if (rayQueryGetCandidateIntersectionType(&rq) == RAY_QUERY_INTERSECTION_TRIANGLE) {
+            candidates += 1u;
+        }
+        candidates += rayQueryGetCandidateIntersectionType(&rq);
+    }
+
+    output.candidates = candidates;
+    output.kind = rayQueryGetCommittedIntersectionType(&rq);
+}
@@ -0,0 +1,30 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+struct Output {
+    visible: u32,
+    reflection_kind: u32,
+    reflection_t: f32,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> output: Output;
+
+// Two queries live at the same time, one for visibility and one for a reflection.
+@compute @workgroup_size(1)
+fn main() {
+    var shadow: ray_query;
+    var reflection: ray_query;
+
+    rayQueryInitialize(&shadow, acc_struct, RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 1.0, 0.0)));
+    rayQueryInitialize(&reflection, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+
+    while (rayQueryProceed(&shadow)) {}
+    rayQueryProceed(&reflection);
+
+    let shadow_hit = rayQueryGetCommittedIntersection(&shadow);
+    let reflection_hit = rayQueryGetCommittedIntersection(&reflection);
+    output.visible = u32(shadow_hit.kind == RAY_QUERY_INTERSECTION_NONE);
+    output.reflection_kind = reflection_hit.kind;
+    output.reflection_t = reflection_hit.t;
+}
@@ -0,0 +1,19 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> shadowed: u32;
+
+@compute @workgroup_size(1)
+fn main() {
+    let position = vec3<f32>(1.0, 0.0, 2.0);
+    let normal = vec3<f32>(0.0, 1.0, 0.0);
+
+    var rq: ray_query;
+    // Without a `t_min`, the ray gets the default one.
+    let ray = RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 100.0, offsetRayOrigin(position, normal), normal);
+    rayQueryInitialize(&rq, acc_struct, ray);
+    rayQueryProceed(&rq);
+
+    shadowed = u32(rayQueryGetCommittedIntersectionType(&rq) != RAY_QUERY_INTERSECTION_NONE);
+}
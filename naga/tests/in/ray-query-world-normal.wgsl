@@ -0,0 +1,20 @@
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> vertices: array<vec3<f32>, 3>;
+
+@group(0) @binding(2)
+var<storage, read_write> normals: array<vec3<f32>, 2>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3(0.0), vec3(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    // Without `front_face`, the normal follows the winding of the vertices.
+    normals[0] = computeWorldNormal(vertices[0], vertices[1], vertices[2], intersection.object_to_world);
+    normals[1] = computeWorldNormal(vertices[0], vertices[1], vertices[2], intersection.object_to_world, intersection.front_face);
+}
@@ -1,5 +1,6 @@
 mod example_wgsl;
 mod snapshots;
 mod spirv_capabilities;
+mod spirv_ray_query;
 mod validation;
 mod wgsl_errors;
@@ -853,6 +853,17 @@ fn convert_wgsl() {
         ("force_point_size_vertex_shader_webgl", Targets::GLSL),
         ("invariant", Targets::GLSL),
         ("ray-query", Targets::SPIRV | Targets::METAL),
+        ("ray-query-multiple", Targets::SPIRV | Targets::METAL),
+        (
+            "ray-query-function-argument",
+            Targets::SPIRV | Targets::METAL,
+        ),
+        ("ray-query-intersection-type", Targets::SPIRV),
+        ("ray-query-instance-id", Targets::SPIRV),
+        ("ray-query-offset-origin", Targets::SPIRV),
+        ("ray-query-world-normal", Targets::SPIRV),
+        ("ray-query-barycentrics", Targets::SPIRV),
+        ("ray-query-candidate", Targets::SPIRV),
         ("hlsl-keyword", Targets::HLSL),
         (
             "constructors",
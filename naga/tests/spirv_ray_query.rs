@@ -0,0 +1,539 @@
+/*!
+Test SPIR-V backend ray query lowering options.
+*/
+
+#![cfg(all(feature = "wgsl-in", spv_out))]
+
+use naga::back::spv;
+use rspirv::spirv::Op;
+
+const SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: vec4<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {}
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    output = vec4<f32>(f32(intersection.kind), intersection.t, intersection.barycentrics);
+}
+";
+
+fn compile(flags: spv::WriterFlags) -> rspirv::dr::Module {
+    let module = naga::front::wgsl::parse_str(SHADER).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("validation failed");
+
+    let options = spv::Options {
+        lang_version: (1, 4),
+        flags,
+        ..spv::Options::default()
+    };
+    let words = spv::write_vec(&module, &info, &options, None).unwrap();
+    rspirv::dr::load_words(words).unwrap()
+}
+
+/// The `OpRayQueryGetIntersection*KHR` getters in `module`, sorted so that
+/// modules can be compared independently of where they read the fields.
+fn getters(module: &rspirv::dr::Module) -> Vec<Op> {
+    let mut getters: Vec<_> = module
+        .all_inst_iter()
+        .map(|inst| inst.class.opcode)
+        .filter(|op| format!("{op:?}").starts_with("RayQueryGetIntersection"))
+        .collect();
+    getters.sort_by_key(|&op| op as u32);
+    getters
+}
+
+/// Whether `module` builds a `RayIntersection` with `OpCompositeConstruct`.
+fn constructs_intersection(module: &rspirv::dr::Module) -> bool {
+    let intersection_types: Vec<_> = module
+        .types_global_values
+        .iter()
+        .filter(|inst| inst.class.opcode == Op::TypeStruct && inst.operands.len() == 13)
+        .filter_map(|inst| inst.result_id)
+        .collect();
+    module.all_inst_iter().any(|inst| {
+        inst.class.opcode == Op::CompositeConstruct
+            && inst
+                .result_type
+                .is_some_and(|ty| intersection_types.contains(&ty))
+    })
+}
+
+/// The opcodes and operands of the instructions in `words`, for instructions
+/// that `rspirv` can't load.
+fn raw_instructions(words: &[u32]) -> Vec<(u32, &[u32])> {
+    let mut instructions = Vec::new();
+    let mut rest = &words[5..];
+    while let Some(&first) = rest.first() {
+        let (instruction, next) = rest.split_at((first >> 16) as usize);
+        instructions.push((first & 0xffff, &instruction[1..]));
+        rest = next;
+    }
+    instructions
+}
+
+#[test]
+fn separate_ray_query_getters() {
+    let composite = compile(spv::WriterFlags::empty());
+    let separate = compile(spv::WriterFlags::SEPARATE_RAY_QUERY_GETTERS);
+
+    assert!(constructs_intersection(&composite));
+    assert!(!constructs_intersection(&separate));
+
+    // The separate getters only read the fields the shader uses, each of which
+    // the composite reads as well.
+    let mut expected = vec![
+        Op::RayQueryGetIntersectionTypeKHR,
+        Op::RayQueryGetIntersectionTKHR,
+        Op::RayQueryGetIntersectionBarycentricsKHR,
+    ];
+    expected.sort_by_key(|&op| op as u32);
+    let separate_getters = getters(&separate);
+    assert_eq!(separate_getters, expected);
+
+    let composite_getters = getters(&composite);
+    assert!(separate_getters
+        .iter()
+        .all(|op| composite_getters.contains(op)));
+}
+
+#[test]
+fn ray_query_terminate() {
+    let mut module = naga::front::wgsl::parse_str(SHADER).unwrap();
+
+    // WGSL has no builtin for it, so terminate the query right after initializing it.
+    let body = &mut module.entry_points[0].function.body;
+    let (index, query) = body
+        .iter()
+        .enumerate()
+        .find_map(|(index, statement)| match *statement {
+            naga::Statement::RayQuery {
+                query,
+                fun: naga::RayQueryFunction::Initialize { .. },
+            } => Some((index, query)),
+            _ => None,
+        })
+        .unwrap();
+    body.splice(
+        index + 1..index + 1,
+        naga::Block::from_vec(vec![naga::Statement::RayQuery {
+            query,
+            fun: naga::RayQueryFunction::Terminate,
+        }]),
+    );
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("validation failed");
+    let options = spv::Options {
+        lang_version: (1, 4),
+        ..spv::Options::default()
+    };
+    let words = spv::write_vec(&module, &info, &options, None).unwrap();
+    let module = rspirv::dr::load_words(words).unwrap();
+
+    let ops: Vec<Op> = module
+        .all_inst_iter()
+        .map(|inst| inst.class.opcode)
+        .collect();
+    let initialize = ops
+        .iter()
+        .position(|&op| op == Op::RayQueryInitializeKHR)
+        .unwrap();
+    assert_eq!(ops[initialize + 1], Op::RayQueryTerminateKHR);
+    assert!(module.capabilities.iter().any(|inst| {
+        inst.operands[0] == rspirv::dr::Operand::Capability(rspirv::spirv::Capability::RayQueryKHR)
+    }));
+}
+
+const OBJECT_RAY_SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: vec3<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {}
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    output = intersection.object_ray_origin + intersection.t * intersection.object_ray_direction;
+}
+";
+
+#[test]
+fn object_ray_origin_and_direction() {
+    let module = naga::front::wgsl::parse_str(OBJECT_RAY_SHADER).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("validation failed");
+
+    for flags in [
+        spv::WriterFlags::empty(),
+        spv::WriterFlags::SEPARATE_RAY_QUERY_GETTERS,
+    ] {
+        let options = spv::Options {
+            lang_version: (1, 4),
+            flags,
+            ..spv::Options::default()
+        };
+        let words = spv::write_vec(&module, &info, &options, None).unwrap();
+        let module = rspirv::dr::load_words(words).unwrap();
+
+        let getters = getters(&module);
+        for op in [
+            Op::RayQueryGetIntersectionObjectRayOriginKHR,
+            Op::RayQueryGetIntersectionObjectRayDirectionKHR,
+        ] {
+            assert!(getters.contains(&op), "{flags:?}: {op:?}");
+        }
+        if flags.contains(spv::WriterFlags::SEPARATE_RAY_QUERY_GETTERS) {
+            assert_eq!(getters.len(), 4, "{flags:?}");
+        }
+    }
+}
+
+const REPEATED_GETTER_SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: vec3<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {}
+    let t = rayQueryGetCommittedIntersection(&rq).t;
+    let instance_id = rayQueryGetCommittedIntersection(&rq).instance_id;
+    let custom_index = rayQueryGetCommittedIntersection(&rq).instance_custom_index;
+    output = vec3<f32>(t, f32(instance_id), f32(custom_index));
+}
+";
+
+#[test]
+fn repeated_intersection_getters() {
+    let module = naga::front::wgsl::parse_str(REPEATED_GETTER_SHADER).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("validation failed");
+
+    for flags in [
+        spv::WriterFlags::empty(),
+        spv::WriterFlags::SEPARATE_RAY_QUERY_GETTERS,
+    ] {
+        let options = spv::Options {
+            lang_version: (1, 4),
+            flags,
+            ..spv::Options::default()
+        };
+        let words = spv::write_vec(&module, &info, &options, None).unwrap();
+        let module = rspirv::dr::load_words(words).unwrap();
+
+        // The three reads of the committed intersection share its getters.
+        let getters = getters(&module);
+        for op in [
+            Op::RayQueryGetIntersectionTypeKHR,
+            Op::RayQueryGetIntersectionTKHR,
+            Op::RayQueryGetIntersectionInstanceIdKHR,
+            Op::RayQueryGetIntersectionInstanceCustomIndexKHR,
+        ] {
+            let count = getters.iter().filter(|&&other| other == op).count();
+            assert_eq!(count, 1, "{flags:?}: {op:?}");
+        }
+        let mut deduplicated = getters.clone();
+        deduplicated.dedup();
+        assert_eq!(getters, deduplicated, "{flags:?}");
+    }
+}
+
+const ALPHA_TEST_SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: vec4<f32>;
+
+fn alpha(barycentrics: vec2<f32>) -> f32 {
+    return fract((barycentrics.x + barycentrics.y) * 8.0);
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {
+        let candidate = rayQueryGetCandidateIntersection(&rq);
+        if (candidate.kind == RAY_QUERY_INTERSECTION_TRIANGLE) {
+            if (alpha(candidate.barycentrics) > 0.5) {
+                rayQueryConfirmIntersection(&rq);
+            }
+        } else if (candidate.kind == RAY_QUERY_INTERSECTION_AABB) {
+            // A sphere of radius 1 around the origin of the instance.
+            let origin = candidate.world_to_object * vec4<f32>(0.0, 0.0, 0.0, 1.0);
+            let hit_t = length(origin) - 1.0;
+            if (hit_t > 0.1) {
+                rayQueryGenerateIntersection(&rq, hit_t);
+            }
+        }
+    }
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    output = vec4<f32>(f32(intersection.kind), intersection.t, intersection.barycentrics);
+}
+";
+
+#[test]
+fn candidate_intersection_confirm_and_generate() {
+    let module = naga::front::wgsl::parse_str(ALPHA_TEST_SHADER).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .expect("validation failed");
+
+    for flags in [
+        spv::WriterFlags::empty(),
+        spv::WriterFlags::SEPARATE_RAY_QUERY_GETTERS,
+    ] {
+        let options = spv::Options {
+            lang_version: (1, 4),
+            flags,
+            ..spv::Options::default()
+        };
+        let words = spv::write_vec(&module, &info, &options, None).unwrap();
+        let module = rspirv::dr::load_words(words).unwrap();
+
+        // The `Intersection` operand of each barycentrics getter, as a constant.
+        let constants: Vec<_> = module
+            .types_global_values
+            .iter()
+            .filter(|inst| inst.class.opcode == Op::Constant)
+            .map(|inst| (inst.result_id.unwrap(), inst.operands[0].clone()))
+            .collect();
+        let intersections: Vec<_> = module
+            .all_inst_iter()
+            .filter(|inst| inst.class.opcode == Op::RayQueryGetIntersectionBarycentricsKHR)
+            .map(|inst| {
+                let id = inst.operands[1].unwrap_id_ref();
+                constants.iter().find(|&&(c, _)| c == id).unwrap().1.clone()
+            })
+            .collect();
+        let candidate = rspirv::dr::Operand::LiteralInt32(
+            rspirv::spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR as u32,
+        );
+        let committed = rspirv::dr::Operand::LiteralInt32(
+            rspirv::spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR as u32,
+        );
+        assert!(intersections.contains(&candidate), "{flags:?}");
+        assert!(intersections.contains(&committed), "{flags:?}");
+
+        // The candidates are confirmed or generated inside the proceed loop.
+        let ops: Vec<Op> = module
+            .all_inst_iter()
+            .map(|inst| inst.class.opcode)
+            .collect();
+        let position = |op| ops.iter().position(|&other| other == op).unwrap();
+        let proceed = position(Op::RayQueryProceedKHR);
+        assert!(
+            proceed < position(Op::RayQueryConfirmIntersectionKHR),
+            "{flags:?}"
+        );
+        assert!(
+            proceed < position(Op::RayQueryGenerateIntersectionKHR),
+            "{flags:?}"
+        );
+    }
+}
+
+const LSS_SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>, 2>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {
+        let candidate = getCandidateHitLssPositions(&rq);
+        output[0] = vec4<f32>(candidate[0], 0.0);
+    }
+    let committed = getCommittedHitLssPositions(&rq);
+    output[1] = vec4<f32>(committed[1], 0.0);
+}
+";
+
+#[test]
+fn linear_swept_sphere_positions() {
+    use naga::valid::Capabilities;
+
+    const OP_CAPABILITY: u32 = Op::Capability as u32;
+    const OP_LSS_POSITIONS: u32 = 5429;
+    const RAY_TRACING_LINEAR_SWEPT_SPHERES_GEOMETRY: u32 = 5419;
+
+    let module = naga::front::wgsl::parse_str(LSS_SHADER).unwrap();
+
+    // The getter is gated behind its own capability.
+    let error = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        Capabilities::all() - Capabilities::RAY_TRACING_LINEAR_SWEPT_SPHERES,
+    )
+    .validate(&module)
+    .unwrap_err();
+    assert!(
+        format!("{:?}", error.as_inner()).contains("RAY_TRACING_LINEAR_SWEPT_SPHERES"),
+        "{error:?}"
+    );
+
+    let info =
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .expect("validation failed");
+    let options = spv::Options {
+        lang_version: (1, 4),
+        ..spv::Options::default()
+    };
+    let words = spv::write_vec(&module, &info, &options, None).unwrap();
+
+    // `rspirv` doesn't know the extension either, so walk the raw instructions.
+    let instructions = raw_instructions(&words);
+
+    assert!(instructions
+        .iter()
+        .any(|&(op, operands)| op == OP_CAPABILITY
+            && operands == [RAY_TRACING_LINEAR_SWEPT_SPHERES_GEOMETRY]));
+
+    // Result type, result, query and the `Intersection` operand.
+    let getters: Vec<_> = instructions
+        .iter()
+        .filter(|&&(op, _)| op == OP_LSS_POSITIONS)
+        .map(|&(_, operands)| operands)
+        .collect();
+    assert_eq!(getters.len(), 2);
+    assert!(getters.iter().all(|operands| operands.len() == 4));
+}
+
+const VERTEX_RETURN_SHADER: &str = "
+@group(0) @binding(0)
+var acc_struct: acceleration_structure<vertex_return>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec3<f32>, 2>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query<vertex_return>;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    while (rayQueryProceed(&rq)) {
+        let candidate = getCandidateHitVertexPositions(&rq);
+        output[0] = candidate[0];
+    }
+    let committed = getCommittedHitVertexPositions(&rq);
+    output[1] = committed[0];
+}
+";
+
+#[test]
+fn vertex_positions_only_with_vertex_return() {
+    const OP_CAPABILITY: u32 = spirv::Op::Capability as u32;
+    const OP_VERTEX_POSITIONS: u32 =
+        spirv::Op::RayQueryGetIntersectionTriangleVertexPositionsKHR as u32;
+    const POSITION_FETCH: u32 = spirv::Capability::RayQueryPositionFetchKHR as u32;
+
+    let options = spv::Options {
+        lang_version: (1, 4),
+        ..spv::Options::default()
+    };
+    let compile = |source: &str, options: &spv::Options| {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .expect("validation failed");
+        spv::write_vec(&module, &info, options, None)
+    };
+    let has_position_fetch = |instructions: &[(u32, &[u32])]| {
+        instructions
+            .iter()
+            .any(|&(op, operands)| op == OP_CAPABILITY && operands == [POSITION_FETCH])
+    };
+
+    // `rspirv` doesn't know position fetch, so walk the raw instructions.
+    let words = compile(VERTEX_RETURN_SHADER, &options).unwrap();
+    let instructions = raw_instructions(&words);
+    assert!(has_position_fetch(&instructions));
+
+    // The `Intersection` operand of each getter, as the value of its constant.
+    let constant = |id: u32| {
+        instructions
+            .iter()
+            .find(|&&(op, operands)| op == spirv::Op::Constant as u32 && operands[1] == id)
+            .map(|&(_, operands)| operands[2])
+            .unwrap()
+    };
+    let intersections: Vec<_> = instructions
+        .iter()
+        .filter(|&&(op, _)| op == OP_VERTEX_POSITIONS)
+        .map(|&(_, operands)| constant(operands[3]))
+        .collect();
+    assert_eq!(
+        intersections,
+        [
+            spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR as u32,
+            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR as u32,
+        ]
+    );
+
+    // Without `vertex_return`, neither the getter nor the capability are emitted.
+    let words = compile(SHADER, &options).unwrap();
+    let instructions = raw_instructions(&words);
+    assert!(!has_position_fetch(&instructions));
+    assert!(!instructions
+        .iter()
+        .any(|&(op, _)| op == OP_VERTEX_POSITIONS));
+
+    // The getter needs the capability to be available.
+    let options = spv::Options {
+        capabilities: Some(
+            [spv::Capability::Shader, spv::Capability::RayQueryKHR]
+                .into_iter()
+                .collect(),
+        ),
+        ..options
+    };
+    assert!(matches!(
+        compile(VERTEX_RETURN_SHADER, &options),
+        Err(spv::Error::MissingCapabilities(
+            "ray query vertex positions",
+            _
+        ))
+    ));
+}
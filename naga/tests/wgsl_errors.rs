@@ -2277,3 +2277,81 @@ fn too_many_unclosed_loops() {
         .join()
         .unwrap()
 }
+
+#[test]
+fn ray_query_skip_all_geometry() {
+    check_validation! {
+        "
+        @group(0) @binding(0) var acc_struct: acceleration_structure;
+        fn trace() {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_SKIP_TRIANGLES | RAY_FLAG_SKIP_AABBS, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+        }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::RayDescriptorSkipsAllGeometry(_),
+            ..
+        }),
+        naga::valid::Capabilities::RAY_QUERY
+    }
+
+    for flag in ["RAY_FLAG_SKIP_TRIANGLES", "RAY_FLAG_SKIP_AABBS"] {
+        let source = format!(
+            "
+            @group(0) @binding(0) var acc_struct: acceleration_structure;
+            fn trace() {{
+                var rq: ray_query;
+                rayQueryInitialize(&rq, acc_struct, RayDesc({flag}, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+            }}
+            "
+        );
+        validation_error(&source, naga::valid::Capabilities::RAY_QUERY).unwrap();
+    }
+}
+
+#[test]
+fn ray_query_candidate_outside_loop() {
+    check_validation! {
+        "
+        @group(0) @binding(0) var acc_struct: acceleration_structure;
+        fn trace() {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+            while (rayQueryProceed(&rq)) {}
+            rayQueryConfirmIntersection(&rq);
+        }
+        ",
+        "
+        @group(0) @binding(0) var acc_struct: acceleration_structure;
+        fn trace() {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+            while (rayQueryProceed(&rq)) {}
+            rayQueryGenerateIntersection(&rq, 1.0);
+        }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::RayQueryCandidateOutsideLoop(_),
+            ..
+        }),
+        naga::valid::Capabilities::RAY_QUERY
+    }
+
+    check_validation! {
+        "
+        @group(0) @binding(0) var acc_struct: acceleration_structure;
+        fn trace() {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.1, 100.0, vec3f(0.0), vec3f(0.0, 0.0, 1.0)));
+            while (rayQueryProceed(&rq)) {
+                rayQueryGenerateIntersection(&rq, 1u);
+            }
+        }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::InvalidRayQueryHitT(_),
+            ..
+        }),
+        naga::valid::Capabilities::RAY_QUERY
+    }
+}
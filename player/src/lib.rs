@@ -119,6 +119,24 @@ impl GlobalPlay for wgc::global::Global {
                     )
                     .unwrap();
                 }
+                trace::Command::CopyBlas { src, dst, copy } => self
+                    .command_encoder_copy_blas(encoder, src, dst, copy)
+                    .unwrap(),
+                trace::Command::CopyTlas { src, dst } => {
+                    self.command_encoder_copy_tlas(encoder, src, dst).unwrap()
+                }
+                trace::Command::ResolveBlasCompactedSize {
+                    blas,
+                    destination,
+                    destination_offset,
+                } => self
+                    .command_encoder_resolve_blas_compacted_size(
+                        encoder,
+                        blas,
+                        destination,
+                        destination_offset,
+                    )
+                    .unwrap(),
                 trace::Command::BuildAccelerationStructuresUnsafeTlas { blas, tlas } => {
                     let blas_iter = blas.iter().map(|x| {
                         let geometries = match &x.geometries {
@@ -133,6 +151,8 @@ impl GlobalPlay for wgc::global::Global {
                                         transform_buffer: tg.transform_buffer,
                                         first_vertex: tg.first_vertex,
                                         vertex_stride: tg.vertex_stride,
+                                        vertex_offset: tg.vertex_offset,
+                                        vertex_buffer_offset: tg.vertex_buffer_offset,
                                         index_buffer_offset: tg.index_buffer_offset,
                                         transform_buffer_offset: tg.transform_buffer_offset,
                                     }
@@ -158,6 +178,7 @@ impl GlobalPlay for wgc::global::Global {
                         wgc::ray_tracing::BlasBuildEntry {
                             blas_id: x.blas_id,
                             geometries,
+                            mode: x.mode,
                         }
                     });
 
@@ -172,7 +193,11 @@ impl GlobalPlay for wgc::global::Global {
                     )
                     .unwrap();
                 }
-                trace::Command::BuildAccelerationStructures { blas, tlas } => {
+                trace::Command::BuildAccelerationStructures {
+                    blas,
+                    tlas,
+                    scratch,
+                } => {
                     let blas_iter = blas.iter().map(|x| {
                         let geometries = match &x.geometries {
                             wgc::ray_tracing::TraceBlasGeometries::TriangleGeometries(
@@ -186,6 +211,8 @@ impl GlobalPlay for wgc::global::Global {
                                         transform_buffer: tg.transform_buffer,
                                         first_vertex: tg.first_vertex,
                                         vertex_stride: tg.vertex_stride,
+                                        vertex_offset: tg.vertex_offset,
+                                        vertex_buffer_offset: tg.vertex_buffer_offset,
                                         index_buffer_offset: tg.index_buffer_offset,
                                         transform_buffer_offset: tg.transform_buffer_offset,
                                     }
@@ -211,6 +238,7 @@ impl GlobalPlay for wgc::global::Global {
                         wgc::ray_tracing::BlasBuildEntry {
                             blas_id: x.blas_id,
                             geometries,
+                            mode: x.mode,
                         }
                     });
 
@@ -223,18 +251,29 @@ impl GlobalPlay for wgc::global::Global {
                                     transform: &instance.transform,
                                     custom_index: instance.custom_index,
                                     mask: instance.mask,
+                                    flags: instance.flags,
+                                    shader_binding_table_record_offset: instance
+                                        .shader_binding_table_record_offset,
                                 })
                         });
                         wgc::ray_tracing::TlasPackage {
                             tlas_id: x.tlas_id,
                             instances: Box::new(instances),
                             lowest_unmodified: x.lowest_unmodified,
+                            instance_upload: x.instance_upload,
+                            mode: x.mode,
                         }
                     });
 
-                    self.command_encoder_build_acceleration_structures(
-                        encoder, blas_iter, tlas_iter,
-                    )
+                    match scratch {
+                        None => self.command_encoder_build_acceleration_structures(
+                            encoder, blas_iter, tlas_iter,
+                        ),
+                        Some((buffer_id, offset)) => self
+                            .command_encoder_build_acceleration_structures_with_scratch(
+                                encoder, blas_iter, tlas_iter, buffer_id, offset,
+                            ),
+                    }
                     .unwrap();
                 }
             }
@@ -485,6 +524,32 @@ impl GlobalPlay for wgc::global::Global {
             Action::CreateBlas { id, desc, sizes } => {
                 self.device_create_blas(device, &desc, sizes, Some(id));
             }
+            Action::CreateBlasInBuffer {
+                id,
+                buffer_id,
+                offset,
+                desc,
+                sizes,
+            } => {
+                self.device_create_blas_in_buffer(
+                    device,
+                    buffer_id,
+                    offset,
+                    &desc,
+                    sizes,
+                    Some(id),
+                );
+            }
+            Action::CreateCompactedBlas {
+                id,
+                source_id,
+                label,
+            } => {
+                // The size is read back from the replayed build, which must have completed.
+                self.device_poll(device, wgt::Maintain::wait()).unwrap();
+                self.blas_compacted_size(source_id).unwrap();
+                self.device_create_compacted_blas(device, source_id, label, Some(id));
+            }
             Action::FreeBlas(id) => {
                 self.blas_destroy(id).unwrap();
             }
@@ -1,15 +1,91 @@
 use wgpu::{Adapter, Device, Instance, Queue};
 use wgt::{Backends, Features, Limits};
 
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    static CAPTURED_LOGS: std::cell::RefCell<Option<Vec<String>>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Maximum level of the `env_logger`, and the number of running [`capture_logs`] calls. The
+/// maximum log level is only raised to debug while one of them runs.
+#[cfg(not(target_arch = "wasm32"))]
+static LOG_LEVEL: std::sync::Mutex<(log::LevelFilter, usize)> =
+    std::sync::Mutex::new((log::LevelFilter::Off, 0));
+
+/// Forwards to `env_logger`, and records debug logs on threads inside [`capture_logs`].
+#[cfg(not(target_arch = "wasm32"))]
+struct TestLogger {
+    env_logger: env_logger::Logger,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl log::Log for TestLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.env_logger.enabled(metadata)
+            || (metadata.level() <= log::Level::Debug
+                && CAPTURED_LOGS.with(|logs| logs.borrow().is_some()))
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if record.level() <= log::Level::Debug {
+            CAPTURED_LOGS.with(|logs| {
+                if let Some(logs) = logs.borrow_mut().as_mut() {
+                    logs.push(record.args().to_string());
+                }
+            });
+        }
+        self.env_logger.log(record);
+    }
+
+    fn flush(&self) {
+        self.env_logger.flush();
+    }
+}
+
 /// Initialize the logger for the test runner.
 pub fn init_logger() {
-    // We don't actually care if it fails
     #[cfg(not(target_arch = "wasm32"))]
-    let _ = env_logger::try_init();
+    {
+        let env_logger = env_logger::Builder::from_default_env().build();
+        let max_level = env_logger.filter();
+        // We don't actually care if it fails
+        if log::set_boxed_logger(Box::new(TestLogger { env_logger })).is_ok() {
+            let mut level = LOG_LEVEL.lock().unwrap();
+            level.0 = max_level;
+            log::set_max_level(if level.1 > 0 {
+                max_level.max(log::LevelFilter::Debug)
+            } else {
+                max_level
+            });
+        }
+    }
     #[cfg(target_arch = "wasm32")]
     let _ = console_log::init_with_level(log::Level::Info);
 }
 
+/// Run `f`, and return the messages it logged on the current thread at debug level or above,
+/// regardless of `RUST_LOG`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn capture_logs(f: impl FnOnce()) -> Vec<String> {
+    {
+        let mut level = LOG_LEVEL.lock().unwrap();
+        level.1 += 1;
+        log::set_max_level(level.0.max(log::LevelFilter::Debug));
+    }
+    CAPTURED_LOGS.with(|logs| *logs.borrow_mut() = Some(Vec::new()));
+    f();
+    {
+        let mut level = LOG_LEVEL.lock().unwrap();
+        level.1 -= 1;
+        if level.1 == 0 {
+            log::set_max_level(level.0);
+        }
+    }
+    CAPTURED_LOGS
+        .with(|logs| logs.borrow_mut().take())
+        .unwrap_or_default()
+}
+
 /// Initialize a wgpu instance with the options from the environment.
 pub fn initialize_instance(force_fxc: bool) -> Instance {
     // We ignore `WGPU_BACKEND` for now, merely using test filtering to only run a single backend's tests.
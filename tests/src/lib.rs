@@ -13,6 +13,8 @@ mod poll;
 mod report;
 mod run;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use init::capture_logs;
 #[cfg(target_arch = "wasm32")]
 pub use init::initialize_html_canvas;
 
@@ -0,0 +1,131 @@
+//! Tests counting heap allocations.
+//!
+//! They replace the global allocator, so they live in their own test binary instead of
+//! affecting every test of `wgpu-test`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    iter,
+};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+/// Counts the allocations made by the current thread, so that tests running in parallel
+/// don't affect each other's counts.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made by `f` on the current thread.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const MANY_GEOMETRIES: u32 = 64;
+
+/// Builds BLASes from geometries kept in a slice across builds, as an engine would from its
+/// own scene data, and checks that converting the borrowed geometries for the build doesn't
+/// collect them: a build with [`MANY_GEOMETRIES`] geometries makes fewer additional
+/// allocations than it has additional geometries, compared to a build with a single one.
+fn build_from_borrowed_slice(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = [[-1.0f32, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let geometries: Vec<_> = (0..MANY_GEOMETRIES)
+        .map(|_| rt::BlasTriangleGeometry {
+            size: &size_desc,
+            vertex_buffer: &vertex_buf,
+            first_vertex: 0,
+            vertex_stride: None,
+            vertex_offset: 0,
+            vertex_buffer_offset: 0,
+            index_buffer: None,
+            index_buffer_offset: None,
+            transform_buffer: None,
+            transform_buffer_offset: None,
+        })
+        .collect();
+
+    // Allocations made by building a BLAS from the first `count` geometries.
+    let build_allocations = |count: u32| {
+        let blas = device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_BUILD,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            rt::BlasGeometrySizeDescriptors::Triangles {
+                desc: vec![size_desc.clone(); count as usize],
+            },
+        );
+        let entry = rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(geometries[..count as usize].into()),
+            mode: None,
+        };
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let allocations = count_allocations(|| {
+            encoder.build_acceleration_structures(iter::once(&entry), iter::empty());
+        });
+        ctx.queue.submit(Some(encoder.finish()));
+        allocations
+    };
+
+    // Warm up, so that lazily allocated state of the device doesn't count.
+    build_allocations(MANY_GEOMETRIES);
+
+    let single = build_allocations(1);
+    let many = build_allocations(MANY_GEOMETRIES);
+    assert!(
+        many.saturating_sub(single) < (MANY_GEOMETRIES - 1) as usize,
+        "Building {MANY_GEOMETRIES} geometries made {many} allocations, one geometry {single}"
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static BLAS_BUILD_FROM_BORROWED_SLICE: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(TestParameters::default().test_features_limits().features(
+            wgpu::Features::RAY_QUERY | wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE,
+        ))
+        .run_sync(build_from_borrowed_slice);
+
+wgpu_test::gpu_test_main!();
@@ -0,0 +1,2852 @@
+use std::{
+    iter, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+fn triangle(offset: f32) -> [[f32; 3]; 3] {
+    [
+        [-1.0 + offset, -1.0, 0.0],
+        [1.0 + offset, -1.0, 0.0],
+        [offset, 1.0, 0.0],
+    ]
+}
+
+/// Fully rebuilds the same BLAS several times with changing vertices and checks that the
+/// rebuilds reuse the memory that was allocated for the BLAS at creation.
+fn rebuild_in_place(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Buffer"),
+        size: mem::size_of::<[[f32; 3]; 3]>() as u64,
+        usage: wgpu::BufferUsages::BLAS_INPUT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Rebuilt BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_BUILD,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let handle = blas.handle();
+
+    let counters_before = device.get_internal_counters().hal;
+
+    for i in 0..10 {
+        ctx.queue
+            .write_buffer(&vertex_buf, 0, bytemuck::cast_slice(&triangle(i as f32)));
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(
+            iter::once(&rt::BlasBuildEntry {
+                blas: &blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &size_desc,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            }),
+            iter::empty(),
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+    }
+
+    device.poll(wgpu::Maintain::Wait);
+
+    let counters_after = device.get_internal_counters().hal;
+
+    assert_eq!(blas.handle(), handle);
+    assert_eq!(
+        counters_before.acceleration_structures.read(),
+        counters_after.acceleration_structures.read()
+    );
+    assert_eq!(
+        counters_before.acceleration_structure_memory.read(),
+        counters_after.acceleration_structure_memory.read()
+    );
+}
+
+#[gpu_test]
+static BLAS_REBUILD_IN_PLACE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(rebuild_in_place);
+
+/// Builds a throwaway BLAS holding `triangle_count` triangles.
+fn build_triangles(ctx: &TestingContext, triangle_count: usize) {
+    let vertices: Vec<[f32; 3]> = (0..triangle_count)
+        .flat_map(|i| triangle(i as f32))
+        .collect();
+
+    let vertex_buf = ctx
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = ctx.device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+}
+
+/// Checks that the scratch memory of a burst of large builds is released once the steady
+/// state only needs small builds for a few polls.
+fn scratch_pool_trim(ctx: TestingContext) {
+    let device = &ctx.device;
+    device.set_scratch_pool_idle_polls(4);
+
+    build_triangles(&ctx, 1);
+    device.poll(wgpu::Maintain::Wait);
+    let steady_state = device.get_internal_counters().hal.buffer_memory.read();
+
+    build_triangles(&ctx, 100_000);
+    device.poll(wgpu::Maintain::Wait);
+    let peak = device.get_internal_counters().hal.buffer_memory.read();
+    assert!(peak > steady_state);
+
+    for _ in 0..8 {
+        build_triangles(&ctx, 1);
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    let trimmed = device.get_internal_counters().hal.buffer_memory.read();
+    assert_eq!(trimmed, steady_state);
+}
+
+#[gpu_test]
+static SCRATCH_POOL_TRIM: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(scratch_pool_trim);
+
+const POOLED_BLAS_PER_FRAME: usize = 100;
+
+/// Creates and drops the same number of identically sized BLASes every frame, checking that
+/// later frames reuse the acceleration structures of the first instead of allocating new ones,
+/// until the pool is trimmed.
+fn blas_pool(ctx: TestingContext) {
+    let device = &ctx.device;
+    device.trim_blas_pool();
+    let live = || {
+        device
+            .get_internal_counters()
+            .hal
+            .acceleration_structures
+            .read()
+    };
+    let baseline = live();
+
+    let create_frame = || {
+        (0..POOLED_BLAS_PER_FRAME)
+            .map(|_| {
+                device.create_blas(
+                    &rt::CreateBlasDescriptor {
+                        label: Some("Particle BLAS"),
+                        flags: rt::AccelerationStructureFlags::PREFER_FAST_BUILD,
+                        update_mode: rt::AccelerationStructureUpdateMode::Build,
+                    },
+                    rt::BlasGeometrySizeDescriptors::Triangles {
+                        desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+                            vertex_format: wgpu::VertexFormat::Float32x3,
+                            vertex_count: 3,
+                            index_format: None,
+                            index_count: None,
+                            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+                        }],
+                    },
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let handles = |blases: &[rt::Blas]| -> Vec<u64> {
+        blases.iter().filter_map(|blas| blas.handle()).collect()
+    };
+
+    let first_frame = create_frame();
+    let first_handles = handles(&first_frame);
+    drop(first_frame);
+    device.poll(wgpu::Maintain::Wait);
+    assert_eq!(live(), baseline + POOLED_BLAS_PER_FRAME as isize);
+
+    for frame in 1..4 {
+        let blases = create_frame();
+        assert_eq!(
+            live(),
+            baseline + POOLED_BLAS_PER_FRAME as isize,
+            "frame {frame} allocated new acceleration structures"
+        );
+        for handle in handles(&blases) {
+            assert!(first_handles.contains(&handle));
+        }
+        drop(blases);
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    device.trim_blas_pool();
+    assert_eq!(live(), baseline);
+}
+
+#[gpu_test]
+static BLAS_POOL: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(blas_pool);
+
+const THREAD_COUNT: usize = 4;
+const BLAS_PER_THREAD: usize = 8;
+
+const MULTI_THREADED_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> hits: array<u32>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 3.0, 0.0, -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind != 0u) {
+        hits[id.x] = intersection.instance_custom_index;
+    } else {
+        hits[id.x] = 0xFFFFFFFFu;
+    }
+}
+"#;
+
+/// Records the builds of a set of BLASes on several threads into one encoder per thread, submits
+/// them together with a TLAS build referencing all of them and traces a ray at every instance.
+fn multi_threaded_blas_recording(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let recorded: Vec<(Vec<rt::Blas>, wgpu::CommandBuffer)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..THREAD_COUNT)
+            .map(|_| {
+                scope.spawn(|| {
+                    let blases: Vec<rt::Blas> = (0..BLAS_PER_THREAD)
+                        .map(|_| {
+                            device.create_blas(
+                                &rt::CreateBlasDescriptor {
+                                    label: None,
+                                    flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                                    update_mode: rt::AccelerationStructureUpdateMode::Build,
+                                },
+                                rt::BlasGeometrySizeDescriptors::Triangles {
+                                    desc: vec![size_desc.clone()],
+                                },
+                            )
+                        })
+                        .collect();
+
+                    let entries: Vec<rt::BlasBuildEntry> = blases
+                        .iter()
+                        .map(|blas| rt::BlasBuildEntry {
+                            blas,
+                            geometry: rt::BlasGeometries::TriangleGeometries(
+                                vec![rt::BlasTriangleGeometry {
+                                    size: &size_desc,
+                                    vertex_buffer: &vertex_buf,
+                                    first_vertex: 0,
+                                    vertex_stride: None,
+                                    vertex_offset: 0,
+                                    vertex_buffer_offset: 0,
+                                    index_buffer: None,
+                                    index_buffer_offset: None,
+                                    transform_buffer: None,
+                                    transform_buffer_offset: None,
+                                }]
+                                .into(),
+                            ),
+                            mode: None,
+                        })
+                        .collect();
+
+                    let mut encoder = device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    encoder.build_acceleration_structures(entries.iter(), iter::empty());
+                    drop(entries);
+
+                    (blases, encoder.finish())
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    let (blases, mut command_buffers): (Vec<Vec<rt::Blas>>, Vec<wgpu::CommandBuffer>) =
+        recorded.into_iter().unzip();
+    let blases: Vec<rt::Blas> = blases.into_iter().flatten().collect();
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: blases.len() as u32,
+    });
+
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        blases
+            .iter()
+            .enumerate()
+            .map(|(i, blas)| {
+                Some(rt::TlasInstance::new(
+                    blas,
+                    AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                        Vec3::new(i as f32 * 3.0, 0.0, 0.0),
+                    )),
+                    i as u32,
+                    0xff,
+                ))
+            })
+            .collect(),
+    );
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: (blases.len() * mem::size_of::<u32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(MULTI_THREADED_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(blases.len() as u32, 1, 1);
+    }
+    command_buffers.push(encoder.finish());
+
+    ctx.queue.submit(command_buffers);
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &hit_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let hits: &[u32] = bytemuck::cast_slice(&result);
+        let expected: Vec<u32> = (0..(THREAD_COUNT * BLAS_PER_THREAD) as u32).collect();
+        assert_eq!(hits, expected);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static BLAS_MULTI_THREADED_RECORDING: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(multi_threaded_blas_recording);
+
+/// Builds a BLAS and a TLAS referencing it with a scratch buffer provided by the test, which
+/// needs to hold the size reported by the device.
+fn user_scratch_buffer(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let blas_entry = rt::BlasBuildEntry {
+        blas: &blas,
+        geometry: rt::BlasGeometries::TriangleGeometries(
+            vec![rt::BlasTriangleGeometry {
+                size: &size_desc,
+                vertex_buffer: &vertex_buf,
+                first_vertex: 0,
+                vertex_stride: None,
+                vertex_offset: 0,
+                vertex_buffer_offset: 0,
+                index_buffer: None,
+                index_buffer_offset: None,
+                transform_buffer: None,
+                transform_buffer_offset: None,
+            }]
+            .into(),
+        ),
+        mode: None,
+    };
+
+    let scratch_size = device.build_scratch_size(&[&blas_entry], &[&tlas_package]);
+    assert!(scratch_size > 0);
+
+    let build = |scratch_buffer: &wgpu::Buffer| {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures_with_scratch(
+            iter::once(&blas_entry),
+            iter::once(&tlas_package),
+            scratch_buffer,
+            0,
+        );
+        encoder.finish()
+    };
+    let scratch_buffer = |size| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scratch Buffer"),
+            size,
+            usage: wgpu::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH,
+            mapped_at_creation: false,
+        })
+    };
+
+    let undersized = scratch_buffer(scratch_size - 4);
+    wgpu_test::fail(
+        device,
+        || build(&undersized),
+        Some("too small for the build"),
+    );
+
+    let scratch = scratch_buffer(scratch_size);
+    let command_buffer = wgpu_test::valid(device, || build(&scratch));
+    ctx.queue.submit(Some(command_buffer));
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static USER_SCRATCH_BUFFER: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(user_scratch_buffer);
+
+const SHARED_SCRATCH_BLAS_COUNT: usize = 100;
+
+/// Builds many BLASes one call at a time, all reusing a single scratch buffer sized from the
+/// reported build sizes, then a TLAS over them with the same buffer, and traces a ray at every
+/// BLAS. Each BLAS holds its triangle at a different position, so builds racing on the scratch
+/// memory would show up as missed or misattributed hits.
+fn shared_scratch_buffer(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: Vec<[[f32; 3]; 3]> = (0..SHARED_SCRATCH_BLAS_COUNT)
+        .map(|i| triangle(i as f32 * 3.0))
+        .collect();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![size_desc.clone()],
+    };
+    let blases: Vec<rt::Blas> = (0..SHARED_SCRATCH_BLAS_COUNT)
+        .map(|_| device.create_blas(&blas_desc, sizes.clone()))
+        .collect();
+
+    let tlas_desc = rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: SHARED_SCRATCH_BLAS_COUNT as u32,
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        device.create_tlas(&tlas_desc),
+        blases
+            .iter()
+            .enumerate()
+            .map(|(i, blas)| {
+                Some(rt::TlasInstance::new(
+                    blas,
+                    AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+                    i as u32,
+                    0xff,
+                ))
+            })
+            .collect(),
+    );
+
+    let entries: Vec<rt::BlasBuildEntry> = blases
+        .iter()
+        .enumerate()
+        .map(|(i, blas)| rt::BlasBuildEntry {
+            blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: (i * mem::size_of::<[[f32; 3]; 3]>()) as u64,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        })
+        .collect();
+
+    let blas_scratch_size = device
+        .blas_build_sizes(&blas_desc, &sizes)
+        .build_scratch_size;
+    assert_eq!(
+        blas_scratch_size,
+        device.build_scratch_size(&[&entries[0]], &[])
+    );
+    let scratch_size =
+        blas_scratch_size.max(device.tlas_build_sizes(&tlas_desc).build_scratch_size);
+    let scratch = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Scratch Buffer"),
+        size: scratch_size,
+        usage: wgpu::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH,
+        mapped_at_creation: false,
+    });
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: (SHARED_SCRATCH_BLAS_COUNT * mem::size_of::<u32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(MULTI_THREADED_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    for entry in &entries {
+        encoder.build_acceleration_structures_with_scratch(
+            iter::once(entry),
+            iter::empty(),
+            &scratch,
+            0,
+        );
+    }
+    encoder.build_acceleration_structures_with_scratch(
+        iter::empty(),
+        iter::once(&tlas_package),
+        &scratch,
+        0,
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(SHARED_SCRATCH_BLAS_COUNT as u32, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &hit_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let hits: &[u32] = bytemuck::cast_slice(&result);
+        let expected: Vec<u32> = (0..SHARED_SCRATCH_BLAS_COUNT as u32).collect();
+        assert_eq!(hits, expected);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static SHARED_SCRATCH_BUFFER: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(shared_scratch_buffer);
+
+/// Refits a BLAS with a scratch buffer of the size reported for the update, which only has to
+/// hold the update scratch size rather than the one of a full build.
+fn update_scratch_size(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::ALLOW_UPDATE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let entry = |mode| rt::BlasBuildEntry {
+        blas: &blas,
+        geometry: rt::BlasGeometries::TriangleGeometries(
+            vec![rt::BlasTriangleGeometry {
+                size: &size_desc,
+                vertex_buffer: &vertex_buf,
+                first_vertex: 0,
+                vertex_stride: None,
+                vertex_offset: 0,
+                vertex_buffer_offset: 0,
+                index_buffer: None,
+                index_buffer_offset: None,
+                transform_buffer: None,
+                transform_buffer_offset: None,
+            }]
+            .into(),
+        ),
+        mode,
+    };
+    let build_entry = entry(None);
+    let update_entry = entry(Some(rt::AccelerationStructureBuildMode::Update));
+
+    let build_scratch_size = device.build_scratch_size(&[&build_entry], &[]);
+    let update_scratch_size = device.build_scratch_size(&[&update_entry], &[]);
+    assert!(update_scratch_size > 0);
+    assert!(
+        update_scratch_size <= build_scratch_size,
+        "update scratch size {update_scratch_size} exceeds build scratch size {build_scratch_size}"
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::once(&build_entry), iter::empty());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let scratch = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Scratch Buffer"),
+        size: update_scratch_size,
+        usage: wgpu::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH,
+        mapped_at_creation: false,
+    });
+    let build = |entry: &rt::BlasBuildEntry<'_>| {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures_with_scratch(
+            iter::once(entry),
+            iter::empty(),
+            &scratch,
+            0,
+        );
+        encoder.finish()
+    };
+
+    let command_buffer = wgpu_test::valid(device, || build(&update_entry));
+    ctx.queue.submit(Some(command_buffer));
+    device.poll(wgpu::Maintain::Wait);
+
+    if update_scratch_size < build_scratch_size {
+        wgpu_test::fail(
+            device,
+            || build(&build_entry),
+            Some("too small for the build"),
+        );
+    }
+}
+
+#[gpu_test]
+static UPDATE_SCRATCH_SIZE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(update_scratch_size);
+
+/// Builds a TLAS with many instances of the same BLAS, and checks that the TLAS keeps a single
+/// reference to it.
+fn instanced_blas_referenced_once(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let instance_count = 1000;
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: instance_count,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        (0..instance_count)
+            .map(|i| {
+                Some(rt::TlasInstance::new(
+                    &blas,
+                    AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                        Vec3::new(i as f32 * 3.0, 0.0, 0.0),
+                    )),
+                    i,
+                    0xff,
+                ))
+            })
+            .collect(),
+    );
+
+    let references_before = device
+        .get_internal_counters()
+        .core
+        .tlas_blas_references
+        .read();
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+
+    let references_after = device
+        .get_internal_counters()
+        .core
+        .tlas_blas_references
+        .read();
+    assert_eq!(references_after - references_before, 1);
+
+    drop(tlas_package);
+    device.poll(wgpu::Maintain::Wait);
+
+    let references_dropped = device
+        .get_internal_counters()
+        .core
+        .tlas_blas_references
+        .read();
+    assert_eq!(references_dropped, references_before);
+}
+
+#[gpu_test]
+static TLAS_INSTANCED_BLAS_REFERENCED_ONCE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(instanced_blas_referenced_once);
+
+/// Checks the acceleration structure work a submission logs at debug level: the primitives
+/// actually built rather than the ones reserved, and refits separately from full builds.
+#[cfg(not(target_arch = "wasm32"))]
+fn submit_build_summary(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = [triangle(0.0), triangle(3.0), triangle(6.0)];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = |triangle_count: u32| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: triangle_count * 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    // Each BLAS reserves room for a triangle more than it is built with.
+    let blases: Vec<_> = [2, 3]
+        .into_iter()
+        .map(|reserved_triangles| {
+            device.create_blas(
+                &rt::CreateBlasDescriptor {
+                    label: None,
+                    flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                    update_mode: rt::AccelerationStructureUpdateMode::Build,
+                },
+                rt::BlasGeometrySizeDescriptors::Triangles {
+                    desc: vec![size_desc(reserved_triangles)],
+                },
+            )
+        })
+        .collect();
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+            | rt::AccelerationStructureFlags::ALLOW_UPDATE,
+        update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        max_instances: 4,
+    });
+    let mut tlas_package = rt::TlasPackage::new(tlas, 4);
+    for (i, blas) in blases.iter().enumerate() {
+        *tlas_package.get_mut_single(i).unwrap() = Some(rt::TlasInstance::new(
+            blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ));
+    }
+
+    let size_descs = [size_desc(1), size_desc(2)];
+    let geometries = [(0, 0), (1, 1)].map(|(i, first_triangle)| rt::BlasTriangleGeometry {
+        size: &size_descs[i],
+        vertex_buffer: &vertex_buf,
+        first_vertex: first_triangle * 3,
+        vertex_stride: None,
+        vertex_offset: 0,
+        vertex_buffer_offset: 0,
+        index_buffer: None,
+        index_buffer_offset: None,
+        transform_buffer: None,
+        transform_buffer_offset: None,
+    });
+    let entries = [0, 1].map(|i| rt::BlasBuildEntry {
+        blas: &blases[i],
+        geometry: rt::BlasGeometries::TriangleGeometries(
+            std::slice::from_ref(&geometries[i]).into(),
+        ),
+        mode: None,
+    });
+
+    tlas_package.set_build_mode(Some(rt::AccelerationStructureBuildMode::Build));
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(entries.iter(), iter::once(&tlas_package));
+    let command_buffer = encoder.finish();
+
+    let logs = wgpu_test::capture_logs(|| {
+        ctx.queue.submit(Some(command_buffer));
+    });
+    assert!(
+        logs.iter().any(|log| log
+            .ends_with("built 2 BLAS totaling 3 primitives, built 1 TLAS with 2 instances")),
+        "{logs:?}"
+    );
+
+    // Updating the TLAS alone is reported as a refit.
+    tlas_package.set_build_mode(Some(rt::AccelerationStructureBuildMode::Update));
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    let command_buffer = encoder.finish();
+
+    let logs = wgpu_test::capture_logs(|| {
+        ctx.queue.submit(Some(command_buffer));
+    });
+    assert!(
+        logs.iter()
+            .any(|log| log.ends_with(": refit 1 TLAS with 2 instances")),
+        "{logs:?}"
+    );
+
+    // Submissions without acceleration structure work don't log a summary.
+    let logs = wgpu_test::capture_logs(|| {
+        ctx.queue.submit(None);
+    });
+    assert!(!logs.iter().any(|log| log.contains("LAS")), "{logs:?}");
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[gpu_test]
+static SUBMIT_BUILD_SUMMARY: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(submit_build_summary);
+
+const RESERVED_TRIANGLES: u32 = 100;
+
+const RESERVED_CAPACITY_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> hits: array<u32>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 3.0, 0.0, -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind != 0u) {
+        hits[id.x] = intersection.primitive_index;
+    } else {
+        hits[id.x] = 0xFFFFFFFFu;
+    }
+}
+"#;
+
+/// Creates a BLAS with room for [`RESERVED_TRIANGLES`] triangles, builds it with fewer of them
+/// and then more, tracing a ray at every reserved triangle after each build, and checks that
+/// builds exceeding the reservation are rejected.
+fn reserved_capacity(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: Vec<[f32; 3]> = (0..RESERVED_TRIANGLES + 1)
+        .flat_map(|i| triangle(i as f32 * 3.0))
+        .collect();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = |triangle_count: u32| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: triangle_count * 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Reserved BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_BUILD,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc(RESERVED_TRIANGLES)],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: RESERVED_TRIANGLES as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(RESERVED_CAPACITY_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let encode_build = |triangle_count: u32| {
+        let size = size_desc(triangle_count);
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(
+            iter::once(&rt::BlasBuildEntry {
+                blas: &blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            }),
+            iter::once(&tlas_package),
+        );
+        encoder
+    };
+
+    for triangle_count in [50, 80] {
+        let mut encoder = encode_build(triangle_count);
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(RESERVED_TRIANGLES, 1, 1);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            &ctx.queue,
+            &hit_buf.slice(..),
+            move |result| {
+                let result = result.unwrap();
+                let hits: &[u32] = bytemuck::cast_slice(&result);
+                let expected: Vec<u32> = (0..RESERVED_TRIANGLES)
+                    .map(|i| if i < triangle_count { i } else { u32::MAX })
+                    .collect();
+                assert_eq!(hits, expected, "after building {triangle_count} triangles");
+            },
+        );
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    fail(
+        device,
+        || encode_build(RESERVED_TRIANGLES + 1).finish(),
+        Some("only 300 were reserved"),
+    );
+}
+
+#[gpu_test]
+static BLAS_RESERVED_CAPACITY: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(reserved_capacity);
+
+const INPUT_LIFETIME_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, vec3<f32>(0.0, 0.0, -1.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind != 0u) {
+        out[0] = intersection.t;
+    }
+}
+"#;
+
+/// Destroys and drops the vertex buffer right after submitting a BLAS build, then fills a
+/// new buffer with geometry the ray misses, and checks the BLAS was still built from the
+/// original vertices.
+fn geometry_buffer_outlives_submission(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    vertex_buf.destroy();
+    drop(vertex_buf);
+
+    // Would likely take over the memory of the vertex buffer if it were freed too early.
+    let _missed_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Missed Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(10.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let out_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Out"),
+        contents: bytemuck::cast_slice(&[-1.0f32]),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(INPUT_LIFETIME_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &out_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let out: &[f32] = bytemuck::cast_slice(&result);
+        assert_eq!(out, [1.0]);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static BLAS_GEOMETRY_BUFFER_OUTLIVES_SUBMISSION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(geometry_buffer_outlives_submission);
+
+const WAITED_BUILD_COUNT: usize = 4;
+
+/// Submits several BLAS builds and a TLAS build each on their own, waits for the builds with
+/// [`rt::QueueRayTracing::wait_for_acceleration_structure_builds`] and checks that all
+/// submitted work completed and the acceleration structures can be traced.
+fn wait_for_builds(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    // Nothing has been built yet, so this must not block.
+    ctx.queue.wait_for_acceleration_structure_builds();
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blases: Vec<rt::Blas> = (0..WAITED_BUILD_COUNT)
+        .map(|_| {
+            let blas = device.create_blas(
+                &rt::CreateBlasDescriptor {
+                    label: None,
+                    flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                    update_mode: rt::AccelerationStructureUpdateMode::Build,
+                },
+                rt::BlasGeometrySizeDescriptors::Triangles {
+                    desc: vec![size_desc.clone()],
+                },
+            );
+
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &blas,
+                    geometry: rt::BlasGeometries::TriangleGeometries(
+                        vec![rt::BlasTriangleGeometry {
+                            size: &size_desc,
+                            vertex_buffer: &vertex_buf,
+                            first_vertex: 0,
+                            vertex_stride: None,
+                            vertex_offset: 0,
+                            vertex_buffer_offset: 0,
+                            index_buffer: None,
+                            index_buffer_offset: None,
+                            transform_buffer: None,
+                            transform_buffer_offset: None,
+                        }]
+                        .into(),
+                    ),
+                    mode: None,
+                }),
+                iter::empty(),
+            );
+            ctx.queue.submit(Some(encoder.finish()));
+
+            blas
+        })
+        .collect();
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: WAITED_BUILD_COUNT as u32,
+    });
+
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        blases
+            .iter()
+            .enumerate()
+            .map(|(i, blas)| {
+                Some(rt::TlasInstance::new(
+                    blas,
+                    AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                        Vec3::new(i as f32 * 3.0, 0.0, 0.0),
+                    )),
+                    i as u32,
+                    0xff,
+                ))
+            })
+            .collect(),
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    ctx.queue.submit(Some(encoder.finish()));
+
+    // The TLAS build is the last submission, so all submitted work is done once it is.
+    let done = Arc::new(AtomicBool::new(false));
+    let done_clone = Arc::clone(&done);
+    ctx.queue.on_submitted_work_done(move || {
+        done_clone.store(true, Ordering::SeqCst);
+    });
+
+    ctx.queue.wait_for_acceleration_structure_builds();
+    assert!(done.load(Ordering::SeqCst));
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: (WAITED_BUILD_COUNT * mem::size_of::<u32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(MULTI_THREADED_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(WAITED_BUILD_COUNT as u32, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &hit_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let hits: &[u32] = bytemuck::cast_slice(&result);
+        let expected: Vec<u32> = (0..WAITED_BUILD_COUNT as u32).collect();
+        assert_eq!(hits, expected);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static QUEUE_WAIT_FOR_ACCELERATION_STRUCTURE_BUILDS: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(wait_for_builds);
+
+/// Destroys a built BLAS while keeping its handle alive, and checks that its memory is
+/// released on the next poll and that a TLAS build referencing it is rejected.
+fn destroy_blas(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Destroyed BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+
+    let memory_before = device
+        .get_internal_counters()
+        .hal
+        .acceleration_structure_memory
+        .read();
+
+    blas.destroy();
+    device.poll(wgpu::Maintain::Wait);
+
+    let memory_after = device
+        .get_internal_counters()
+        .hal
+        .acceleration_structure_memory
+        .read();
+    assert!(memory_after < memory_before);
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+            encoder.finish()
+        },
+        Some("is invalid or destroyed (for instance)"),
+    );
+}
+
+#[gpu_test]
+static BLAS_DESTROY: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(destroy_blas);
+
+/// Rebuilds a BLAS right after submitting a TLAS build referencing it, without waiting for that
+/// build, and checks that the TLAS built afterwards sees the new geometry. Building the same BLAS
+/// twice in one call, where the builds aren't ordered, is rejected.
+fn blas_rebuild_during_tlas_build(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_bufs = [0.0, 3.0].map(|offset| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&triangle(offset)),
+            usage: wgpu::BufferUsages::BLAS_INPUT,
+        })
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Rebuilt BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let build_entry = |vertex_buffer| rt::BlasBuildEntry {
+        blas: &blas,
+        geometry: rt::BlasGeometries::TriangleGeometries(
+            vec![rt::BlasTriangleGeometry {
+                size: &size_desc,
+                vertex_buffer,
+                first_vertex: 0,
+                vertex_stride: None,
+                vertex_offset: 0,
+                vertex_buffer_offset: 0,
+                index_buffer: None,
+                index_buffer_offset: None,
+                transform_buffer: None,
+                transform_buffer_offset: None,
+            }]
+            .into(),
+        ),
+        mode: None,
+    };
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            7,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::once(&build_entry(&vertex_bufs[0])), iter::empty());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    // The TLAS build reads the BLAS, which the next submission overwrites.
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::once(&build_entry(&vertex_bufs[1])), iter::empty());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: 2 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(MULTI_THREADED_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(2, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &hit_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let hits: &[u32] = bytemuck::cast_slice(&result);
+        assert_eq!(hits, [0xFFFF_FFFF, 7]);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(
+                [build_entry(&vertex_bufs[0]), build_entry(&vertex_bufs[1])].iter(),
+                iter::empty(),
+            );
+            encoder.finish()
+        },
+        Some("is built more than once by the same call"),
+    );
+}
+
+#[gpu_test]
+static BLAS_REBUILD_DURING_TLAS_BUILD: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(blas_rebuild_during_tlas_build);
+
+const REFIT_TRIANGLES: u32 = 4;
+
+/// Refits a BLAS and TLAS several times while moving triangles away from the rays and shifting
+/// the instance, then forces a full rebuild of both with fewer triangles, checking the traced hits
+/// after every build. Also checks that structures without `ALLOW_UPDATE` can't be updated.
+fn refit_then_rebuild(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Buffer"),
+        size: REFIT_TRIANGLES as u64 * mem::size_of::<[[f32; 3]; 3]>() as u64,
+        usage: wgpu::BufferUsages::BLAS_INPUT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let size_desc = |triangle_count: u32| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: triangle_count * 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let flags = rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+        | rt::AccelerationStructureFlags::ALLOW_UPDATE;
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Refitted BLAS"),
+            flags,
+            update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc(REFIT_TRIANGLES)],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: Some("Refitted TLAS"),
+        flags,
+        update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        max_instances: 1,
+    });
+    let mut tlas_package = rt::TlasPackage::new(tlas, 1);
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: REFIT_TRIANGLES as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(RESERVED_CAPACITY_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    // Triangle `i` is centered at x = 3 * i, rays are shot at x = 3 * k for every triangle `k`.
+    // Raised triangles are moved out of the way of the rays, shifting the instance by a triangle
+    // makes every ray hit the next one.
+    let mut step = |triangle_count: u32,
+                    raised: &[u32],
+                    shift: u32,
+                    mode: rt::AccelerationStructureBuildMode| {
+        let vertices: Vec<[f32; 3]> = (0..triangle_count)
+            .flat_map(|i| {
+                let lift = if raised.contains(&i) { 5.0 } else { 0.0 };
+                triangle(i as f32 * 3.0).map(|[x, y, z]| [x, y + lift, z])
+            })
+            .collect();
+        ctx.queue
+            .write_buffer(&vertex_buf, 0, bytemuck::cast_slice(&vertices));
+
+        *tlas_package.get_mut_single(0).unwrap() =
+            Some(rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    Vec3::new(-3.0 * shift as f32, 0.0, 0.0),
+                )),
+                0,
+                0xff,
+            ));
+        tlas_package.set_build_mode(Some(mode));
+
+        let size = size_desc(triangle_count);
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(
+            iter::once(&rt::BlasBuildEntry {
+                blas: &blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: Some(mode),
+            }),
+            iter::once(&tlas_package),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: tlas_package.as_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: hit_buf.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(REFIT_TRIANGLES, 1, 1);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let expected: Vec<u32> = (0..REFIT_TRIANGLES)
+            .map(|ray| {
+                let hit = ray + shift;
+                if hit < triangle_count && !raised.contains(&hit) {
+                    hit
+                } else {
+                    u32::MAX
+                }
+            })
+            .collect();
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            &ctx.queue,
+            &hit_buf.slice(..),
+            move |result| {
+                let result = result.unwrap();
+                let hits: &[u32] = bytemuck::cast_slice(&result);
+                assert_eq!(hits, expected, "after a {mode:?}");
+            },
+        );
+        device.poll(wgpu::Maintain::Wait);
+    };
+
+    step(
+        REFIT_TRIANGLES,
+        &[],
+        0,
+        rt::AccelerationStructureBuildMode::Build,
+    );
+    step(
+        REFIT_TRIANGLES,
+        &[0],
+        1,
+        rt::AccelerationStructureBuildMode::Update,
+    );
+    step(
+        REFIT_TRIANGLES,
+        &[0, 2],
+        0,
+        rt::AccelerationStructureBuildMode::Update,
+    );
+    step(
+        REFIT_TRIANGLES,
+        &[3],
+        1,
+        rt::AccelerationStructureBuildMode::Update,
+    );
+    // Dropping triangles changes the topology, which needs a full build.
+    step(2, &[], 0, rt::AccelerationStructureBuildMode::Build);
+
+    let static_blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Static BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc(1)],
+        },
+    );
+    let size = size_desc(1);
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &static_blas,
+                    geometry: rt::BlasGeometries::TriangleGeometries(
+                        vec![rt::BlasTriangleGeometry {
+                            size: &size,
+                            vertex_buffer: &vertex_buf,
+                            first_vertex: 0,
+                            vertex_stride: None,
+                            vertex_offset: 0,
+                            vertex_buffer_offset: 0,
+                            index_buffer: None,
+                            index_buffer_offset: None,
+                            transform_buffer: None,
+                            transform_buffer_offset: None,
+                        }]
+                        .into(),
+                    ),
+                    mode: Some(rt::AccelerationStructureBuildMode::Update),
+                }),
+                iter::empty(),
+            );
+            encoder.finish()
+        },
+        Some("wasn't created with flag ALLOW_UPDATE"),
+    );
+}
+
+#[gpu_test]
+static ACCELERATION_STRUCTURE_REFIT_THEN_REBUILD: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(refit_then_rebuild);
+
+const PREFER_UPDATE_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> t: f32;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0, 0.0, -5.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    t = select(-1.0, intersection.t, intersection.kind != 0u);
+}
+"#;
+
+/// Rebuilds a BLAS created with `PreferUpdate` without choosing a build mode while moving its
+/// triangles along the ray, checking that builds with unchanged counts are updates, which fit
+/// in a scratch buffer sized for updates, and that the traced distance follows the triangles.
+fn prefer_update_refit(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Buffer"),
+        size: 2 * mem::size_of::<[[f32; 3]; 3]>() as u64,
+        usage: wgpu::BufferUsages::BLAS_INPUT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let size_desc = |triangle_count: u32| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: triangle_count * 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Water"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+                | rt::AccelerationStructureFlags::ALLOW_UPDATE,
+            update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc(2)],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let t_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Distance"),
+        size: 4,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(PREFER_UPDATE_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: t_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let sizes = [size_desc(1), size_desc(2)];
+    let entry = |triangle_count: u32, mode| rt::BlasBuildEntry {
+        blas: &blas,
+        geometry: rt::BlasGeometries::TriangleGeometries(
+            vec![rt::BlasTriangleGeometry {
+                size: &sizes[triangle_count as usize - 1],
+                vertex_buffer: &vertex_buf,
+                first_vertex: 0,
+                vertex_stride: None,
+                vertex_offset: 0,
+                vertex_buffer_offset: 0,
+                index_buffer: None,
+                index_buffer_offset: None,
+                transform_buffer: None,
+                transform_buffer_offset: None,
+            }]
+            .into(),
+        ),
+        mode,
+    };
+    let build_scratch_size = device.build_scratch_size(&[&entry(2, None)], &[]);
+    let update_scratch_size = device.build_scratch_size(
+        &[&entry(2, Some(rt::AccelerationStructureBuildMode::Update))],
+        &[],
+    );
+    let update_scratch = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Update Scratch Buffer"),
+        size: update_scratch_size,
+        usage: wgpu::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH,
+        mapped_at_creation: false,
+    });
+
+    // Moves the triangles to `depth` and builds the BLAS from the first `triangle_count` of them.
+    let build = |depth: f32, triangle_count: u32, scratch: Option<&wgpu::Buffer>| {
+        let vertices = [triangle(0.0), triangle(3.0)].map(|t| t.map(|[x, y, _]| [x, y, depth]));
+        ctx.queue
+            .write_buffer(&vertex_buf, 0, bytemuck::cast_slice(&vertices));
+
+        let entry = entry(triangle_count, None);
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        match scratch {
+            Some(scratch) => encoder.build_acceleration_structures_with_scratch(
+                iter::once(&entry),
+                iter::empty(),
+                scratch,
+                0,
+            ),
+            None => encoder.build_acceleration_structures(iter::once(&entry), iter::empty()),
+        }
+        encoder.finish()
+    };
+    let trace = |build: wgpu::CommandBuffer| {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(1, 1, 1);
+        }
+        ctx.queue.submit([build, encoder.finish()]);
+
+        let t = Arc::new(std::sync::Mutex::new(0.0));
+        let result = t.clone();
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            &ctx.queue,
+            &t_buf.slice(..),
+            move |buffer| {
+                *result.lock().unwrap() = bytemuck::cast_slice::<_, f32>(&buffer.unwrap())[0];
+            },
+        );
+        device.poll(wgpu::Maintain::Wait);
+        let t = *t.lock().unwrap();
+        t
+    };
+
+    // The first build can't be an update.
+    assert_eq!(trace(build(3.0, 2, None)), 8.0);
+    // Later builds with the same counts refit the BLAS.
+    for depth in [4.0, 1.0] {
+        let command_buffer = wgpu_test::valid(device, || build(depth, 2, Some(&update_scratch)));
+        assert_eq!(trace(command_buffer), depth + 5.0);
+    }
+    // Dropping a triangle changes the counts, which falls back to a full build.
+    if update_scratch_size < build_scratch_size {
+        fail(
+            device,
+            || build(2.0, 1, Some(&update_scratch)),
+            Some("too small for the build"),
+        );
+    }
+    assert_eq!(trace(build(2.0, 1, None)), 7.0);
+}
+
+#[gpu_test]
+static BLAS_PREFER_UPDATE_REFIT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(prefer_update_refit);
+
+const DISPLACED_TRIANGLES: u32 = 4;
+
+const DISPLACE_SHADER: &str = r#"
+@group(0) @binding(0)
+var<storage, read_write> positions: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x < arrayLength(&positions) / 3u) {
+        positions[id.x * 3u] += 3.0;
+    }
+}
+"#;
+
+/// Displaces the vertices of a BLAS with a compute shader and builds the BLAS from them in the
+/// same encoder, checking that the build sees the displaced vertices.
+fn build_from_compute_written_vertices(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: Vec<[f32; 3]> = (0..DISPLACED_TRIANGLES)
+        .flat_map(|i| triangle(i as f32 * 3.0))
+        .collect();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT | wgpu::BufferUsages::STORAGE,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: DISPLACED_TRIANGLES * 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Displaced BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    // One ray per triangle slot, plus one for the slot the last triangle is moved into.
+    let ray_count = DISPLACED_TRIANGLES + 1;
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: ray_count as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let displace_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(DISPLACE_SHADER.into()),
+    });
+    let displace_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Displace"),
+        layout: None,
+        module: &displace_shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let displace_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &displace_pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: vertex_buf.as_entire_binding(),
+        }],
+    });
+
+    let trace_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(RESERVED_CAPACITY_SHADER.into()),
+    });
+    let trace_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Trace"),
+        layout: None,
+        module: &trace_shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let trace_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &trace_pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Displace"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&displace_pipeline);
+        cpass.set_bind_group(0, &displace_bind_group, &[]);
+        cpass.dispatch_workgroups((DISPLACED_TRIANGLES * 3).div_ceil(64), 1, 1);
+    }
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Trace"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&trace_pipeline);
+        cpass.set_bind_group(0, &trace_bind_group, &[]);
+        cpass.dispatch_workgroups(ray_count, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &hit_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let hits: &[u32] = bytemuck::cast_slice(&result);
+        // Every triangle moved one slot along x, so the first slot is empty.
+        let expected: Vec<u32> = iter::once(u32::MAX).chain(0..DISPLACED_TRIANGLES).collect();
+        assert_eq!(hits, expected);
+    });
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static BLAS_BUILD_FROM_COMPUTE_WRITTEN_VERTICES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(build_from_compute_written_vertices);
+
+/// Attempts a build while a compute pass is open on the encoder, which is rejected since builds
+/// are recorded outside of passes.
+fn build_inside_pass(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    let pass = encoder
+        .begin_compute_pass(&wgpu::ComputePassDescriptor::default())
+        .forget_lifetime();
+
+    fail(
+        device,
+        || {
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &blas,
+                    geometry: rt::BlasGeometries::TriangleGeometries(
+                        vec![rt::BlasTriangleGeometry {
+                            size: &size,
+                            vertex_buffer: &vertex_buf,
+                            first_vertex: 0,
+                            vertex_stride: None,
+                            vertex_offset: 0,
+                            vertex_buffer_offset: 0,
+                            index_buffer: None,
+                            index_buffer_offset: None,
+                            transform_buffer: None,
+                            transform_buffer_offset: None,
+                        }]
+                        .into(),
+                    ),
+                    mode: None,
+                }),
+                iter::empty(),
+            )
+        },
+        Some("Command encoder is locked"),
+    );
+
+    // The failed build invalidated the encoder, so ending the pass fails as well.
+    fail(device, || drop(pass), Some("Command encoder is invalid"));
+}
+
+#[gpu_test]
+static BUILD_ACCELERATION_STRUCTURES_INSIDE_PASS: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(build_inside_pass);
+
+/// Writes a third instance into an auto growing package with a capacity of two instances, and
+/// checks that the package reallocates its tlas and builds all three instances.
+fn tlas_auto_grow(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let create_tlas = || {
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: Some("Auto grow"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 2,
+        })
+    };
+    let instance =
+        |i: u32| {
+            rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    Vec3::new(i as f32 * 3.0, 0.0, 0.0),
+                )),
+                i,
+                0xff,
+            )
+        };
+
+    // Without auto growth, the package stays at its capacity.
+    let mut fixed_package = rt::TlasPackage::new(create_tlas(), 2);
+    assert!(!fixed_package.auto_grow());
+    assert!(fixed_package.get_mut_single(2).is_none());
+    assert!(!fixed_package.take_reallocated());
+
+    let mut tlas_package = rt::TlasPackage::new(create_tlas(), 2).with_auto_grow(true);
+    for i in 0..2 {
+        *tlas_package.get_mut_single(i as usize).unwrap() = Some(instance(i));
+    }
+    assert!(!tlas_package.take_reallocated());
+    *tlas_package.get_mut_single(2).unwrap() = Some(instance(2));
+    assert_eq!(tlas_package.get().len(), 4);
+    assert!(tlas_package.take_reallocated());
+    assert!(!tlas_package.take_reallocated());
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+
+    assert_eq!(
+        tlas_package.last_build_info(),
+        rt::TlasBuildInfo {
+            instance_count: 3,
+            dirty_count: 4,
+        }
+    );
+}
+
+#[gpu_test]
+static TLAS_AUTO_GROW: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(tlas_auto_grow);
+
+/// Checks that builds reject acceleration structures of another device, in particular TLAS
+/// instances referencing a BLAS of another device.
+fn cross_device_build(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let other_device = pollster::block_on(ctx.adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("Other Device"),
+            required_features: ctx.device_features,
+            required_limits: ctx.device_limits.clone(),
+            memory_hints: Default::default(),
+        },
+        None,
+    ));
+    let Ok((other_device, _)) = other_device else {
+        log::info!("Skipping test: can't create a second device");
+        return;
+    };
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let other_blas = other_device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: Some("Other BLAS"),
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: Some("TLAS"),
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &other_blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+            encoder.finish()
+        },
+        Some("label of tlas with 'tlas' label"),
+    );
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &other_blas,
+                    geometry: rt::BlasGeometries::TriangleGeometries(
+                        vec![rt::BlasTriangleGeometry {
+                            size: &size_desc,
+                            vertex_buffer: &vertex_buf,
+                            first_vertex: 0,
+                            vertex_stride: None,
+                            vertex_offset: 0,
+                            vertex_buffer_offset: 0,
+                            index_buffer: None,
+                            index_buffer_offset: None,
+                            transform_buffer: None,
+                            transform_buffer_offset: None,
+                        }]
+                        .into(),
+                    ),
+                    mode: None,
+                }),
+                iter::empty(),
+            );
+            encoder.finish()
+        },
+        Some("device with 'other device' label of blas with 'other blas' label doesn't match"),
+    );
+}
+
+#[gpu_test]
+static CROSS_DEVICE_BUILD: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(cross_device_build);
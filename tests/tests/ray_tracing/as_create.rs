@@ -0,0 +1,454 @@
+use wgpu_test::{fail, gpu_test, valid, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+
+use super::required_features;
+
+fn create_blas(ctx: &TestingContext, flags: rt::AccelerationStructureFlags) -> rt::Blas {
+    ctx.device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+                vertex_format: wgpu::VertexFormat::Float32x3,
+                vertex_count: 3,
+                index_format: None,
+                index_count: None,
+                flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+            }],
+        },
+    )
+}
+
+fn create_tlas(ctx: &TestingContext, flags: rt::AccelerationStructureFlags) -> rt::Tlas {
+    ctx.device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    })
+}
+
+/// Checks that contradicting flag combinations are rejected at creation, while other
+/// combinations are accepted.
+fn flag_combinations(ctx: TestingContext) {
+    let fast_trace_and_build = rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+        | rt::AccelerationStructureFlags::PREFER_FAST_BUILD;
+
+    fail(
+        &ctx.device,
+        || create_blas(&ctx, fast_trace_and_build),
+        Some("mutually exclusive"),
+    );
+    fail(
+        &ctx.device,
+        || {
+            create_tlas(
+                &ctx,
+                fast_trace_and_build | rt::AccelerationStructureFlags::ALLOW_UPDATE,
+            )
+        },
+        Some("mutually exclusive"),
+    );
+
+    // Allowed by the spec: compaction and updates are independent of each other.
+    let valid_flags = rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+        | rt::AccelerationStructureFlags::ALLOW_UPDATE
+        | rt::AccelerationStructureFlags::ALLOW_COMPACTION
+        | rt::AccelerationStructureFlags::LOW_MEMORY;
+
+    valid(&ctx.device, || create_blas(&ctx, valid_flags));
+    valid(&ctx.device, || create_tlas(&ctx, valid_flags));
+}
+
+#[gpu_test]
+static ACCELERATION_STRUCTURE_FLAG_COMBINATIONS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(flag_combinations);
+
+/// Checks the validation of BLASes created in a user buffer: the usage of the buffer, the
+/// alignment of the offset and that the acceleration structure fits.
+fn blas_in_buffer_validation(ctx: TestingContext) {
+    let desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+            vertex_format: wgpu::VertexFormat::Float32x3,
+            vertex_count: 3,
+            index_format: None,
+            index_count: None,
+            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+        }],
+    };
+    let blas_size = ctx.device.blas_size(&desc, &sizes);
+    assert!(blas_size > 0);
+
+    let create_buffer = |size, usage| {
+        ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    };
+    let offset = rt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT;
+    let buffer = create_buffer(
+        offset + blas_size,
+        wgpu::BufferUsages::ACCELERATION_STRUCTURE_STORAGE,
+    );
+
+    valid(&ctx.device, || {
+        ctx.device
+            .create_blas_in_buffer(&buffer, offset, &desc, sizes.clone())
+    });
+    fail(
+        &ctx.device,
+        || {
+            ctx.device
+                .create_blas_in_buffer(&buffer, offset / 2, &desc, sizes.clone())
+        },
+        Some("not a multiple of"),
+    );
+    fail(
+        &ctx.device,
+        || {
+            ctx.device
+                .create_blas_in_buffer(&buffer, offset * 2, &desc, sizes.clone())
+        },
+        Some("overruns the backing buffer"),
+    );
+
+    let storage_buffer = create_buffer(offset + blas_size, wgpu::BufferUsages::STORAGE);
+    fail(
+        &ctx.device,
+        || {
+            ctx.device
+                .create_blas_in_buffer(&storage_buffer, offset, &desc, sizes.clone())
+        },
+        Some("do not contain required usage flags"),
+    );
+}
+
+#[gpu_test]
+static BLAS_IN_BUFFER_VALIDATION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(blas_in_buffer_validation);
+
+/// Checks that a BLAS reports the size descriptors it was created with.
+fn blas_geometry_sizes(ctx: TestingContext) {
+    let triangles = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![
+            rt::BlasTriangleGeometrySizeDescriptor {
+                vertex_format: wgpu::VertexFormat::Float32x3,
+                vertex_count: 6,
+                index_format: Some(wgpu::IndexFormat::Uint16),
+                index_count: Some(12),
+                flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+            },
+            rt::BlasTriangleGeometrySizeDescriptor {
+                vertex_format: wgpu::VertexFormat::Float32x3,
+                vertex_count: 3,
+                index_format: None,
+                index_count: None,
+                flags: rt::AccelerationStructureGeometryFlags::empty(),
+            },
+        ],
+    };
+    let procedural = rt::BlasGeometrySizeDescriptors::AABBs {
+        desc: vec![rt::BlasProceduralGeometrySizeDescriptor {
+            primitive_count: 5,
+            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+        }],
+    };
+
+    for sizes in [triangles, procedural] {
+        let blas = ctx.device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            sizes.clone(),
+        );
+        assert_eq!(blas.geometry_sizes(), &sizes);
+    }
+}
+
+#[gpu_test]
+static BLAS_GEOMETRY_SIZES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(blas_geometry_sizes);
+
+/// Checks that a custom index of more than 24 bits is rejected instead of overwriting the mask,
+/// both when creating an instance and when building a package with an instance modified later.
+fn oversized_custom_index(ctx: TestingContext) {
+    let blas = create_blas(&ctx, rt::AccelerationStructureFlags::PREFER_FAST_TRACE);
+    let transform = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+    let instance = rt::TlasInstance::new(&blas, transform, rt::MAX_CUSTOM_INDEX, 0xff);
+    assert_eq!(instance.custom_index, 0x00FF_FFFF);
+    assert_eq!(instance.mask, 0xff);
+
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        rt::TlasInstance::new(&blas, transform, 0x0100_0000, 0xff)
+    }))
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("uses more than 24 bits"), "{message}");
+
+    let mut oversized = instance;
+    oversized.custom_index = 0x0100_0000;
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        create_tlas(&ctx, rt::AccelerationStructureFlags::PREFER_FAST_TRACE),
+        vec![Some(oversized)],
+    );
+    fail(
+        &ctx.device,
+        || {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            encoder.build_acceleration_structures(std::iter::empty(), Some(&tlas_package));
+            encoder.finish()
+        },
+        Some("instance 0 is invalid: custom index 0x1000000 uses more than 24 bits"),
+    );
+}
+
+#[gpu_test]
+static OVERSIZED_CUSTOM_INDEX: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(oversized_custom_index);
+
+/// Checks that every field of an instance that doesn't fit into its packed bits is reported
+/// together, by the builder as well as when building a package.
+fn invalid_instance_fields(ctx: TestingContext) {
+    let blas = create_blas(&ctx, rt::AccelerationStructureFlags::PREFER_FAST_TRACE);
+    let create_tlas = || {
+        ctx.device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 2,
+        })
+    };
+    let flags = rt::AccelerationStructureInstanceFlags::all()
+        - rt::AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE;
+
+    // The largest values that fit.
+    let instance = rt::TlasInstance::builder(&blas)
+        .custom_index(rt::MAX_CUSTOM_INDEX)
+        .mask(0xff)
+        .sbt_offset(rt::MAX_SHADER_BINDING_TABLE_RECORD_OFFSET)
+        .flags(flags)
+        .build();
+
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        rt::TlasInstance::builder(&blas)
+            .custom_index(rt::MAX_CUSTOM_INDEX + 1)
+            .mask(0xff)
+            .sbt_offset(rt::MAX_SHADER_BINDING_TABLE_RECORD_OFFSET + 1)
+            .flags(rt::AccelerationStructureInstanceFlags::all())
+            .build()
+    }))
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    for expected in [
+        "custom index 0x1000000",
+        "shader binding table record offset 0x1000000",
+        "both FORCE_OPAQUE and FORCE_NO_OPAQUE",
+    ] {
+        assert!(message.contains(expected), "{message}");
+    }
+
+    let tlas_package =
+        rt::TlasPackage::new_with_instances(create_tlas(), vec![Some(instance.clone()), None]);
+    valid(&ctx.device, || {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.build_acceleration_structures(std::iter::empty(), Some(&tlas_package));
+        encoder.finish()
+    });
+
+    // Only the offset is out of range, the fields around it have to be left alone.
+    let mut oversized_offset = instance.clone();
+    oversized_offset.shader_binding_table_record_offset += 1;
+    let mut oversized = instance;
+    oversized.custom_index += 1;
+    oversized.shader_binding_table_record_offset += 1;
+    oversized.flags = rt::AccelerationStructureInstanceFlags::all();
+    for (instances, expected) in [
+        (
+            vec![None, Some(oversized_offset)],
+            "instance 1 is invalid: shader binding table record offset 0x1000000 uses more than \
+             24 bits (max 0xffffff)",
+        ),
+        (
+            vec![Some(oversized)],
+            "instance 0 is invalid: custom index 0x1000000 uses more than 24 bits (max 0xffffff), \
+             shader binding table record offset 0x1000000 uses more than 24 bits (max 0xffffff), \
+             both FORCE_OPAQUE and FORCE_NO_OPAQUE flags are set",
+        ),
+    ] {
+        let tlas_package = rt::TlasPackage::new_with_instances(create_tlas(), instances);
+        fail(
+            &ctx.device,
+            || {
+                let mut encoder = ctx
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                encoder.build_acceleration_structures(std::iter::empty(), Some(&tlas_package));
+                encoder.finish()
+            },
+            Some(expected),
+        );
+    }
+}
+
+#[gpu_test]
+static TLAS_INVALID_INSTANCE_FIELDS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(invalid_instance_fields);
+
+/// Checks that a TLAS whose instance buffer cannot fit in a buffer is rejected at creation
+/// instead of allocating a truncated instance buffer.
+fn too_many_instances(ctx: TestingContext) {
+    fail(
+        &ctx.device,
+        || {
+            ctx.device.create_tlas(&rt::CreateTlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+                max_instances: u32::MAX,
+            })
+        },
+        Some("larger than the maximum buffer size"),
+    );
+}
+
+#[gpu_test]
+static TLAS_TOO_MANY_INSTANCES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(too_many_instances);
+
+/// Checks that the sizes the device reports for acceleration structures it hasn't created
+/// are nonzero and don't shrink as the number of primitives or instances grows.
+fn build_sizes(ctx: TestingContext) {
+    let flags = rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+        | rt::AccelerationStructureFlags::ALLOW_UPDATE;
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags,
+        update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+    };
+    let counts = [1, 64, 4096, 65536];
+
+    let check_monotonic = |what: &str, build_sizes: &[rt::AccelerationStructureBuildSizes]| {
+        for sizes in build_sizes {
+            assert!(sizes.acceleration_structure_size > 0, "{what}: {sizes:?}");
+            assert!(sizes.build_scratch_size > 0, "{what}: {sizes:?}");
+            assert!(sizes.update_scratch_size > 0, "{what}: {sizes:?}");
+        }
+        for pair in build_sizes.windows(2) {
+            assert!(
+                pair[0].acceleration_structure_size <= pair[1].acceleration_structure_size
+                    && pair[0].build_scratch_size <= pair[1].build_scratch_size
+                    && pair[0].update_scratch_size <= pair[1].update_scratch_size,
+                "{what}: {pair:?}"
+            );
+        }
+        let (first, last) = (build_sizes[0], build_sizes[build_sizes.len() - 1]);
+        assert!(
+            first.acceleration_structure_size < last.acceleration_structure_size,
+            "{what}: {first:?} {last:?}"
+        );
+    };
+
+    let triangles = counts.map(|count| {
+        let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+                vertex_format: wgpu::VertexFormat::Float32x3,
+                vertex_count: count * 3,
+                index_format: None,
+                index_count: None,
+                flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+            }],
+        };
+        let build_sizes = ctx.device.blas_build_sizes(&blas_desc, &sizes);
+        assert_eq!(
+            build_sizes.acceleration_structure_size,
+            ctx.device.blas_size(&blas_desc, &sizes)
+        );
+        build_sizes
+    });
+    check_monotonic("triangles", &triangles);
+
+    let aabbs = counts.map(|primitive_count| {
+        ctx.device.blas_build_sizes(
+            &blas_desc,
+            &rt::BlasGeometrySizeDescriptors::AABBs {
+                desc: vec![rt::BlasProceduralGeometrySizeDescriptor {
+                    primitive_count,
+                    flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+                }],
+            },
+        )
+    });
+    check_monotonic("AABBs", &aabbs);
+
+    let tlas_desc = |max_instances| rt::CreateTlasDescriptor {
+        label: None,
+        flags,
+        update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        max_instances,
+    };
+    let instances = counts.map(|count| ctx.device.tlas_build_sizes(&tlas_desc(count)));
+    check_monotonic("instances", &instances);
+
+    fail(
+        &ctx.device,
+        || ctx.device.tlas_build_sizes(&tlas_desc(u32::MAX)),
+        Some("larger than the maximum buffer size"),
+    );
+}
+
+#[gpu_test]
+static ACCELERATION_STRUCTURE_BUILD_SIZES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(build_sizes);
@@ -0,0 +1,480 @@
+use std::iter;
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::Affine3A;
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec2<u32>>;
+
+// Traces a ray straight down onto the terrain from above every cell.
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) size: vec3<u32>) {
+    let origin = vec3<f32>(f32(id.x) + 0.25, 10.0, f32(id.y) + 0.25);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, -1.0, 0.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var hit = vec2<u32>(0xFFFFFFFFu);
+    if (intersection.kind != 0u) {
+        hit = vec2<u32>(intersection.primitive_index, bitcast<u32>(intersection.t));
+    }
+    output[id.y * size.x + id.x] = hit;
+}
+"#;
+
+/// Number of cells along each side of the terrain.
+const TERRAIN_SIZE: u32 = 16;
+
+/// A heightfield of `TERRAIN_SIZE` by `TERRAIN_SIZE` cells, two triangles each.
+fn terrain() -> (Vec<[f32; 3]>, Vec<u32>) {
+    let side = TERRAIN_SIZE + 1;
+    let vertices = (0..side * side)
+        .map(|i| {
+            let (x, z) = ((i % side) as f32, (i / side) as f32);
+            [x, (x * 0.7).sin() + (z * 0.4).cos(), z]
+        })
+        .collect();
+    let indices = (0..TERRAIN_SIZE * TERRAIN_SIZE)
+        .flat_map(|cell| {
+            let corner = cell / TERRAIN_SIZE * side + cell % TERRAIN_SIZE;
+            [
+                corner,
+                corner + side,
+                corner + 1,
+                corner + 1,
+                corner + side,
+                corner + side + 1,
+            ]
+        })
+        .collect();
+    (vertices, indices)
+}
+
+/// Builds a terrain BLAS that allows compaction, compacts it and checks that rays traced
+/// against the compacted copy hit the same triangles at the same distances.
+fn compact_blas(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let (vertices, indices) = terrain();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: Some(wgpu::IndexFormat::Uint32),
+        index_count: Some(indices.len() as u32),
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: Some("Terrain"),
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+            | rt::AccelerationStructureFlags::ALLOW_COMPACTION,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![size.clone()],
+    };
+    let full_size = device.blas_size(&blas_desc, &sizes);
+    let blas = device.create_blas(&blas_desc, sizes);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: Some(&index_buf),
+                    index_buffer_offset: Some(0),
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+
+    let compacted_size = device.blas_compacted_size(&blas);
+    assert!(
+        compacted_size > 0 && compacted_size <= full_size,
+        "compacted size {compacted_size} of a {full_size} byte BLAS"
+    );
+    let compacted = device.create_compacted_blas(&blas, Some("Compacted terrain"));
+
+    let tlas_packages = [&blas, &compacted].map(|blas| {
+        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        });
+        rt::TlasPackage::new_with_instances(
+            tlas,
+            vec![Some(rt::TlasInstance::new(
+                blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+                0,
+                0xff,
+            ))],
+        )
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let output_size = (TERRAIN_SIZE * TERRAIN_SIZE) as u64 * 8;
+    let outputs = tlas_packages
+        .iter()
+        .map(|tlas_package| {
+            let output = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Output"),
+                size: output_size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: tlas_package.as_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: output.as_entire_binding(),
+                    },
+                ],
+            });
+            (output, bind_group)
+        })
+        .collect::<Vec<_>>();
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: output_size * 2,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_blas(&blas, &compacted, rt::AccelerationStructureCopy::Compact);
+    encoder.build_acceleration_structures(iter::empty(), &tlas_packages);
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        for (_, bind_group) in &outputs {
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch_workgroups(TERRAIN_SIZE, TERRAIN_SIZE, 1);
+        }
+    }
+    for (i, (output, _)) in outputs.iter().enumerate() {
+        encoder.copy_buffer_to_buffer(output, 0, &readback, i as u64 * output_size, output_size);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+    let hits: Vec<[u32; 2]> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    let (original, compacted) = hits.split_at(hits.len() / 2);
+
+    assert!(
+        original.iter().all(|hit| hit[0] != u32::MAX),
+        "every ray should hit the terrain"
+    );
+    assert_eq!(original, compacted);
+}
+
+#[gpu_test]
+static COMPACT_BLAS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(compact_blas);
+
+/// A BLAS can only be compacted after reading back its compacted size, which requires it to
+/// allow compaction.
+fn compact_blas_validation(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+            vertex_format: wgpu::VertexFormat::Float32x3,
+            vertex_count: 3,
+            index_format: None,
+            index_count: None,
+            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+        }],
+    };
+    let create_blas = |flags| {
+        device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            sizes.clone(),
+        )
+    };
+    let compactable = create_blas(rt::AccelerationStructureFlags::ALLOW_COMPACTION);
+    let fixed = create_blas(rt::AccelerationStructureFlags::PREFER_FAST_TRACE);
+
+    fail(
+        device,
+        || device.blas_compacted_size(&fixed),
+        Some("wasn't created with flag ALLOW_COMPACTION"),
+    );
+    fail(
+        device,
+        || device.blas_compacted_size(&compactable),
+        Some("is compacted before it is build"),
+    );
+    fail(
+        device,
+        || device.create_compacted_blas(&compactable, None),
+        Some("is compacted before it is build"),
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    fail(
+        device,
+        || encoder.copy_blas(&compactable, &fixed, rt::AccelerationStructureCopy::Compact),
+        Some("wasn't read since it was last built"),
+    );
+}
+
+#[gpu_test]
+static COMPACT_BLAS_VALIDATION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(compact_blas_validation);
+
+/// Builds a terrain BLAS that allows compaction, reads its compacted size back through a
+/// mapped buffer, and compacts it into a BLAS created from that size.
+fn compacted_size_readback(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let (vertices, indices) = terrain();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: Some(wgpu::IndexFormat::Uint32),
+        index_count: Some(indices.len() as u32),
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: Some("Terrain"),
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+            | rt::AccelerationStructureFlags::ALLOW_COMPACTION,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![size.clone()],
+    };
+    let full_size = device.blas_size(&blas_desc, &sizes);
+    let blas = device.create_blas(&blas_desc, sizes);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: Some(&index_buf),
+                    index_buffer_offset: Some(0),
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    rt::read_blas_compacted_size(device, &ctx.queue, &blas, move |result| {
+        sender.send(result.unwrap()).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    let compacted_size = receiver.recv().unwrap();
+    assert!(
+        compacted_size > 0 && compacted_size <= full_size,
+        "compacted size {compacted_size} of a {full_size} byte BLAS"
+    );
+
+    // Creating the compacted BLAS reads the size itself, since it wasn't read synchronously.
+    let compacted = device.create_compacted_blas(&blas, Some("Compacted terrain"));
+    assert_eq!(device.blas_compacted_size(&blas), compacted_size);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_blas(&blas, &compacted, rt::AccelerationStructureCopy::Compact);
+    ctx.queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static COMPACTED_SIZE_READBACK: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(compacted_size_readback);
+
+/// The compacted size can only be copied for a BLAS allowing compaction, into a query resolve
+/// buffer at an aligned offset, and once the BLAS is built.
+fn compacted_size_readback_validation(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+            vertex_format: wgpu::VertexFormat::Float32x3,
+            vertex_count: 3,
+            index_format: None,
+            index_count: None,
+            flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+        }],
+    };
+    let create_blas = |flags| {
+        device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            sizes.clone(),
+        )
+    };
+    let compactable = create_blas(rt::AccelerationStructureFlags::ALLOW_COMPACTION);
+    let fixed = create_blas(rt::AccelerationStructureFlags::PREFER_FAST_TRACE);
+
+    let create_buffer = |usage| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 2 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+            usage,
+            mapped_at_creation: false,
+        })
+    };
+    let resolve_buf = create_buffer(wgpu::BufferUsages::QUERY_RESOLVE);
+    let copy_buf = create_buffer(wgpu::BufferUsages::COPY_DST);
+
+    let resolve = |blas: &rt::Blas, buffer: &wgpu::Buffer, offset| {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.resolve_blas_compacted_size(blas, buffer, offset);
+        ctx.queue.submit(Some(encoder.finish()));
+    };
+
+    fail(
+        device,
+        || resolve(&fixed, &resolve_buf, 0),
+        Some("wasn't created with flag ALLOW_COMPACTION"),
+    );
+    fail(
+        device,
+        || resolve(&compactable, &copy_buf, 0),
+        Some("QUERY_RESOLVE"),
+    );
+    fail(
+        device,
+        || resolve(&compactable, &resolve_buf, 8),
+        Some("is not a multiple of 256"),
+    );
+    fail(
+        device,
+        || {
+            resolve(
+                &compactable,
+                &resolve_buf,
+                2 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+            )
+        },
+        Some("overruns"),
+    );
+    fail(
+        device,
+        || resolve(&compactable, &resolve_buf, 0),
+        Some("is used before it is build"),
+    );
+}
+
+#[gpu_test]
+static COMPACTED_SIZE_READBACK_VALIDATION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(compacted_size_readback_validation);
@@ -0,0 +1,265 @@
+use std::iter;
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::Affine3A;
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<f32, 4>;
+
+// Traces rays along +Z from a row of origins, the outer two of which miss the triangle.
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let origin = vec3<f32>(f32(id.x) * 0.5 - 0.75, 0.0, 0.0);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var t = -1.0;
+    if (intersection.kind != 0u) {
+        t = intersection.t;
+    }
+    output[id.x] = t;
+}
+"#;
+
+fn tlas_desc() -> rt::CreateTlasDescriptor<'static> {
+    rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+            | rt::AccelerationStructureFlags::ALLOW_UPDATE,
+        update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+        max_instances: 1,
+    }
+}
+
+/// Clones a built BLAS and TLAS, and checks that rays traced against a TLAS built over the
+/// cloned BLAS and against the cloned TLAS hit the same as against the originals.
+fn clone_acceleration_structures(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[-1.0, -1.0, 3.0], [1.0, -1.0, 3.0], [0.0, 1.0, 3.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let create_blas = || {
+        device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+                    | rt::AccelerationStructureFlags::ALLOW_UPDATE,
+                update_mode: rt::AccelerationStructureUpdateMode::PreferUpdate,
+            },
+            rt::BlasGeometrySizeDescriptors::Triangles {
+                desc: vec![size.clone()],
+            },
+        )
+    };
+    let blas = create_blas();
+    let blas_clone = create_blas();
+
+    let tlas_packages = [&blas, &blas_clone].map(|blas| {
+        rt::TlasPackage::new_with_instances(
+            device.create_tlas(&tlas_desc()),
+            vec![Some(rt::TlasInstance::new(
+                blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+                0,
+                0xff,
+            ))],
+        )
+    });
+    let tlas_clone = device.create_tlas(&tlas_desc());
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let tlas_s = [
+        tlas_packages[0].tlas(),
+        tlas_packages[1].tlas(),
+        &tlas_clone,
+    ];
+    let output_size = 4 * 4;
+    let outputs = tlas_s
+        .iter()
+        .map(|tlas| {
+            let output = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Output"),
+                size: output_size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::AccelerationStructure(tlas),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: output.as_entire_binding(),
+                    },
+                ],
+            });
+            (output, bind_group)
+        })
+        .collect::<Vec<_>>();
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: output_size * outputs.len() as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_packages[0]),
+    );
+    encoder.copy_blas(&blas, &blas_clone, rt::AccelerationStructureCopy::Clone);
+    encoder.copy_tlas(tlas_packages[0].tlas(), &tlas_clone);
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_packages[1]));
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        for (_, bind_group) in &outputs {
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch_workgroups(4, 1, 1);
+        }
+    }
+    for (i, (output, _)) in outputs.iter().enumerate() {
+        encoder.copy_buffer_to_buffer(output, 0, &readback, i as u64 * output_size, output_size);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+    let hits: Vec<[f32; 4]> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+
+    assert_eq!(hits[0], [-1.0, 3.0, 3.0, -1.0]);
+    assert_eq!(hits[1], hits[0], "TLAS over the cloned BLAS");
+    assert_eq!(hits[2], hits[0], "cloned TLAS");
+}
+
+#[gpu_test]
+static CLONE_ACCELERATION_STRUCTURES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(clone_acceleration_structures);
+
+/// Acceleration structures can only be cloned into one created with the same description.
+fn clone_validation(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let tlas = device.create_tlas(&tlas_desc());
+    let larger_tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        max_instances: 2,
+        ..tlas_desc()
+    });
+
+    let create_blas = |vertex_count| {
+        device.create_blas(
+            &rt::CreateBlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+            },
+            rt::BlasGeometrySizeDescriptors::Triangles {
+                desc: vec![rt::BlasTriangleGeometrySizeDescriptor {
+                    vertex_format: wgpu::VertexFormat::Float32x3,
+                    vertex_count,
+                    index_format: None,
+                    index_count: None,
+                    flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+                }],
+            },
+        )
+    };
+    let blas = create_blas(3);
+    let larger_blas = create_blas(6);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    fail(
+        device,
+        || encoder.copy_tlas(&tlas, &larger_tlas),
+        Some("which wasn't created with the same sizes and flags"),
+    );
+    fail(
+        device,
+        || encoder.copy_tlas(&tlas, &tlas),
+        Some("is copied into itself"),
+    );
+    fail(
+        device,
+        || encoder.copy_blas(&blas, &larger_blas, rt::AccelerationStructureCopy::Clone),
+        Some("which wasn't created with the same sizes and flags"),
+    );
+}
+
+#[gpu_test]
+static CLONE_VALIDATION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(clone_validation);
@@ -0,0 +1,255 @@
+use std::{iter, mem};
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.25, 0.25, 0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    // Never commit anything, so that traversal stops at every candidate.
+    while (rayQueryProceed(&rq)) {}
+}
+"#;
+
+const COUNTERS: rt::RayQueryCountersBinding = rt::RayQueryCountersBinding {
+    group: 0,
+    binding: 1,
+};
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    ray_query_counters: rt::RayQueryCountersBinding,
+) -> wgpu::ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Counters"),
+        layout: None,
+        module: shader,
+        entry_point: Some("main"),
+        compilation_options: wgpu::PipelineCompilationOptions {
+            ray_query_counters: Some(ray_query_counters),
+            ..Default::default()
+        },
+        cache: None,
+    })
+}
+
+/// Traces a ray through a non-opaque triangle and then an AABB, and checks that the counters
+/// saw three `rayQueryProceed` calls, one stopping at each candidate.
+fn count_candidates(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let aabb: [[f32; 3]; 2] = [[-1.0, -1.0, -0.5], [1.0, 1.0, 0.5]];
+    let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("AABB Buffer"),
+        contents: bytemuck::cast_slice(&aabb),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    // Neither geometry is opaque, so both are reported to the shader as candidates, once.
+    let triangle_size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+    };
+    let aabb_size = rt::BlasProceduralGeometrySizeDescriptor {
+        primitive_count: 1,
+        flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+    };
+
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let triangle_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![triangle_size.clone()],
+        },
+    );
+    let aabb_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::AABBs {
+            desc: vec![aabb_size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let instance = |blas, z| {
+        Some(rt::TlasInstance::new(
+            blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                0.0, 0.0, z,
+            ))),
+            0,
+            0xff,
+        ))
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![instance(&triangle_blas, 2.0), instance(&aabb_blas, 5.0)],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        [
+            rt::BlasBuildEntry {
+                blas: &triangle_blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &triangle_size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+            rt::BlasBuildEntry {
+                blas: &aabb_blas,
+                geometry: rt::BlasGeometries::ProceduralGeometries(
+                    vec![rt::BlasProceduralGeometry {
+                        size: &aabb_size,
+                        bounding_box_buffer: &aabb_buf,
+                        bounding_box_buffer_offset: 0,
+                        bounding_box_stride: mem::size_of::<[[f32; 3]; 2]>() as u64,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+        ]
+        .iter(),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = create_pipeline(device, &shader, COUNTERS);
+
+    let counters_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Counters"),
+        size: rt::RAY_QUERY_COUNTERS_SIZE,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::AccelerationStructure(tlas_package.tlas()),
+            },
+            wgpu::BindGroupEntry {
+                binding: COUNTERS.binding,
+                resource: counters_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &counters_buf.slice(..),
+        |result| {
+            let result = result.unwrap();
+            let counters: &[u32] = bytemuck::cast_slice(&result);
+            // Proceed calls, candidate triangles, candidate AABBs.
+            assert_eq!(counters, [3, 1, 1]);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COUNTERS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(count_candidates);
+
+/// The counters get a binding of their own, so they must not clash with the shader's.
+fn counters_binding_in_use(ctx: TestingContext) {
+    let shader = ctx
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+
+    fail(
+        &ctx.device,
+        || {
+            create_pipeline(
+                &ctx.device,
+                &shader,
+                rt::RayQueryCountersBinding {
+                    group: 0,
+                    binding: 0,
+                },
+            )
+        },
+        Some("ray query counters binding"),
+    );
+}
+
+#[gpu_test]
+static RAY_QUERY_COUNTERS_BINDING_IN_USE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(counters_binding_in_use);
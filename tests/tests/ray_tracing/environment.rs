@@ -0,0 +1,239 @@
+use std::iter;
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::Affine3A;
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var environment: texture_cube<f32>;
+
+@group(0) @binding(2)
+var environment_sampler: sampler;
+
+@group(0) @binding(3)
+var<storage, read_write> output: array<vec4<f32>, 6>;
+
+// The cube map face directions, in the order of the cube map layers.
+const DIRECTIONS = array<vec3<f32>, 6>(
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(-1.0, 0.0, 0.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, -1.0, 0.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 0.0, -1.0),
+);
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dir = DIRECTIONS[id.x];
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, vec3<f32>(0.0), dir));
+    rayQueryProceed(&rq);
+
+    var color = vec4<f32>(-1.0);
+    if (rayQueryGetCommittedIntersection(&rq).kind == 0u) {
+        color = textureSampleLevel(environment, environment_sampler, dir, 0.0);
+    }
+    output[id.x] = color;
+}
+"#;
+
+/// Color of every cube map face, in the order of the layers.
+const FACE_COLORS: [[u8; 4]; 6] = [
+    [255, 0, 0, 255],
+    [0, 255, 0, 255],
+    [0, 0, 255, 255],
+    [255, 255, 0, 255],
+    [0, 255, 255, 255],
+    [255, 0, 255, 255],
+];
+
+/// Traces a ray along every axis against a single triangle in front of the origin, which only
+/// the ray along +Z hits, and checks that the others sample the environment cube map face they
+/// point at.
+fn environment_on_miss(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[-1.0, -1.0, 3.0], [1.0, -1.0, 3.0], [0.0, 1.0, 3.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let environment = device.create_texture_with_data(
+        &ctx.queue,
+        &wgpu::TextureDescriptor {
+            label: Some("Environment"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        bytemuck::cast_slice(&FACE_COLORS),
+    );
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+    let output = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Output"),
+        size: 6 * 16,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: output.size(),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&environment.create_view(
+                    &wgpu::TextureViewDescriptor {
+                        dimension: Some(wgpu::TextureViewDimension::Cube),
+                        ..Default::default()
+                    },
+                )),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: output.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(6, 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, output.size());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+    let colors: Vec<[f32; 4]> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+
+    for (face, (color, expected)) in colors.iter().zip(FACE_COLORS).enumerate() {
+        // Only the ray along +Z hits the triangle.
+        let expected = if face == 4 {
+            [-1.0; 4]
+        } else {
+            expected.map(|c| f32::from(c) / 255.0)
+        };
+        assert_eq!(*color, expected, "face {face}");
+    }
+}
+
+#[gpu_test]
+static RAY_QUERY_ENVIRONMENT_ON_MISS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(environment_on_miss);
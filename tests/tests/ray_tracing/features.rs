@@ -0,0 +1,187 @@
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+}
+"#;
+
+fn create_pipeline(ctx: &TestingContext) -> wgpu::ComputePipeline {
+    let shader = ctx
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+    ctx.device
+        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        })
+}
+
+/// Building acceleration structures does not allow tracing rays through them.
+#[gpu_test]
+static RAY_QUERY_WITHOUT_FEATURE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE),
+    )
+    .run_sync(|ctx| {
+        fail(&ctx.device, || create_pipeline(&ctx), Some("RAY_QUERY"));
+    });
+
+#[gpu_test]
+static RAY_QUERY_WITH_FEATURE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(wgpu::Features::RAY_QUERY),
+    )
+    .run_sync(|ctx| {
+        create_pipeline(&ctx);
+    });
+
+#[gpu_test]
+static RAY_TRACING_BUILD_PROPERTIES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE),
+    )
+    .run_sync(|ctx| {
+        let properties = ctx.adapter.ray_tracing_build_properties().unwrap();
+        assert_ne!(properties.max_geometry_count, 0);
+        assert_ne!(properties.max_instance_count, 0);
+        assert_ne!(properties.max_primitive_count, 0);
+        assert!(properties.min_scratch_offset_alignment.is_power_of_two());
+    });
+
+/// Adapters without acceleration structures report no build properties.
+#[gpu_test]
+static RAY_TRACING_BUILD_PROPERTIES_MATCH_FEATURES: GpuTestConfiguration =
+    GpuTestConfiguration::new().run_sync(|ctx| {
+        assert_eq!(
+            ctx.adapter.ray_tracing_build_properties().is_some(),
+            ctx.adapter
+                .features()
+                .contains(wgpu::Features::RAY_TRACING_ACCELERATION_STRUCTURE)
+        );
+    });
+
+const VERTEX_RETURN_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure<vertex_return>;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<vec4<f32>, 3>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query<vertex_return>;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+    let vertices = getCommittedHitVertexPositions(&rq);
+    out[0] = vec4<f32>(vertices[0], 1.0);
+    out[1] = vec4<f32>(vertices[1], 1.0);
+    out[2] = vec4<f32>(vertices[2], 1.0);
+}
+"#;
+
+fn create_vertex_return_layout(ctx: &TestingContext) -> wgpu::BindGroupLayout {
+    ctx.device
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::AccelerationStructure {
+                        vertex_return: true,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        })
+}
+
+/// Fetching hit vertex positions needs its own feature, for bind group layouts and shaders.
+#[gpu_test]
+static RAY_HIT_VERTEX_RETURN_WITHOUT_FEATURE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(wgpu::Features::RAY_QUERY),
+    )
+    .run_sync(|ctx| {
+        fail(
+            &ctx.device,
+            || create_vertex_return_layout(&ctx),
+            Some("RAY_HIT_VERTEX_RETURN"),
+        );
+        fail(
+            &ctx.device,
+            || {
+                ctx.device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: None,
+                        source: wgpu::ShaderSource::Wgsl(VERTEX_RETURN_SHADER.into()),
+                    })
+            },
+            None,
+        );
+    });
+
+#[gpu_test]
+static RAY_HIT_VERTEX_RETURN_WITH_FEATURE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(wgpu::Features::RAY_QUERY | wgpu::Features::RAY_HIT_VERTEX_RETURN),
+    )
+    .run_sync(|ctx| {
+        let bind_group_layout = create_vertex_return_layout(&ctx);
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let shader = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(VERTEX_RETURN_SHADER.into()),
+            });
+        ctx.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+    });
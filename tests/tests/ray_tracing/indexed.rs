@@ -0,0 +1,294 @@
+use std::{iter, mem};
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Quat, Vec3};
+
+use super::{
+    mesh_gen::{self, AccelerationStructureInstance, Vertex},
+    required_features,
+};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec2<f32>, 4>;
+
+const ORIGINS = array<vec2<f32>, 4>(
+    vec2<f32>(0.0, 1.5),
+    vec2<f32>(0.0, -1.5),
+    vec2<f32>(1.5, 0.0),
+    vec2<f32>(0.0, 2.5),
+);
+
+// Traces rays along +Z, writing the distance and primitive index of the hit or -1 for a miss.
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var origins = ORIGINS;
+    let origin = vec3<f32>(origins[id.x], 0.0);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var result = vec2<f32>(-1.0);
+    if (intersection.kind != 0u) {
+        result = vec2<f32>(intersection.t, f32(intersection.primitive_index));
+    }
+    output[id.x] = result;
+}
+"#;
+
+/// Indices the build has to skip over by honoring the index buffer offset.
+const SKIPPED_INDICES: usize = 6;
+
+/// Builds the cube from an index buffer and a transform buffer, both read at an offset, and
+/// checks where rays hit it.
+fn indexed_transformed_build(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let (vertices, indices) = mesh_gen::create_vertices();
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&[&[0; SKIPPED_INDICES], &indices[..]].concat()),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    // Stretches the cube along x, then turns it so that it spans -1..1 in x, -2..2 in y and
+    // 9..11 in z. Read as column major, or from the identity in front of it, it would not.
+    let transform = Affine3A::from_scale_rotation_translation(
+        Vec3::new(2.0, 1.0, 1.0),
+        Quat::from_rotation_z(90.0_f32.to_radians()),
+        Vec3::new(0.0, 0.0, 10.0),
+    );
+    let transform_offset = mem::size_of::<[f32; 12]>() as wgpu::BufferAddress;
+    let transform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Transform Buffer"),
+        contents: bytemuck::cast_slice(
+            &[
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+                AccelerationStructureInstance::affine_to_rows(&transform),
+            ]
+            .concat(),
+        ),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: Some(wgpu::IndexFormat::Uint16),
+        index_count: Some(indices.len() as u32),
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        }),
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let output_size = mem::size_of::<[[f32; 2]; 4]>() as u64;
+    let output = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Output"),
+        size: output_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: output_size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: output.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: Some(mem::size_of::<Vertex>() as u64),
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: Some(&index_buf),
+                    index_buffer_offset: Some(
+                        (SKIPPED_INDICES * mem::size_of::<u16>()) as wgpu::BufferAddress,
+                    ),
+                    transform_buffer: Some(&transform_buf),
+                    transform_buffer_offset: Some(transform_offset),
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(4, 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, output_size);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+    let hits: Vec<[f32; 2]> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+
+    // Both rays inside the cube's extent hit its bottom face, which is moved to z = 9. The
+    // stretch and turn put them on opposite sides of the diagonal splitting that face into
+    // primitives 2 and 3.
+    assert_eq!(hits[0], [9.0, 2.0], "hit above the center");
+    assert_eq!(hits[1], [9.0, 3.0], "hit below the center");
+    assert_eq!(hits[2][0], -1.0, "miss right of the cube");
+    assert_eq!(hits[3][0], -1.0, "miss above the cube");
+}
+
+#[gpu_test]
+static BLAS_INDEXED_TRANSFORMED_BUILD: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(indexed_transformed_build);
+
+/// An index buffer needs an index format and count in the geometry size.
+fn index_buffer_without_format(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[0.0, 0.0, 1.0], [0.0, 1.0, 1.0], [1.0, 0.0, 1.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&[0u16, 1, 2]),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+
+    fail(
+        device,
+        || {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.build_acceleration_structures(
+                iter::once(&rt::BlasBuildEntry {
+                    blas: &blas,
+                    geometry: rt::BlasGeometries::TriangleGeometries(
+                        vec![rt::BlasTriangleGeometry {
+                            size: &size,
+                            vertex_buffer: &vertex_buf,
+                            first_vertex: 0,
+                            vertex_stride: None,
+                            vertex_offset: 0,
+                            vertex_buffer_offset: 0,
+                            index_buffer: Some(&index_buf),
+                            index_buffer_offset: Some(0),
+                            transform_buffer: None,
+                            transform_buffer_offset: None,
+                        }]
+                        .into(),
+                    ),
+                    mode: None,
+                }),
+                iter::empty(),
+            );
+            encoder.finish()
+        },
+        Some("associated data contains none"),
+    );
+}
+
+#[gpu_test]
+static BLAS_INDEX_BUFFER_WITHOUT_FORMAT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(index_buffer_without_format);
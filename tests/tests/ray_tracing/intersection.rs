@@ -0,0 +1,2197 @@
+use std::{iter, mem};
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Quat, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<u32, 34>;
+
+fn write_matrix(offset: u32, matrix: mat4x3<f32>) {
+    var m = matrix;
+    for (var c = 0u; c < 4u; c++) {
+        for (var r = 0u; r < 3u; r++) {
+            out[offset + c * 3u + r] = bitcast<u32>(m[c][r]);
+        }
+    }
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0, 2.5, 0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    out[0] = intersection.kind;
+    out[1] = bitcast<u32>(intersection.t);
+    out[2] = intersection.instance_custom_index;
+    out[3] = intersection.instance_id;
+    out[4] = intersection.sbt_record_offset;
+    out[5] = intersection.geometry_index;
+    out[6] = intersection.primitive_index;
+    out[7] = bitcast<u32>(intersection.barycentrics.x);
+    out[8] = bitcast<u32>(intersection.barycentrics.y);
+    out[9] = u32(intersection.front_face);
+    write_matrix(10u, intersection.object_to_world);
+    write_matrix(22u, intersection.world_to_object);
+}
+"#;
+
+const RAY_QUERY_INTERSECTION_NONE: u32 = 0;
+const RAY_QUERY_INTERSECTION_TRIANGLE: u32 = 1;
+const CUSTOM_INDEX: u32 = 0xABCDE;
+
+fn triangle(offset: [f32; 3]) -> [[f32; 3]; 3] {
+    // Clockwise when viewed along +z, so front facing for a ray travelling in that direction.
+    [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]
+        .map(|v| [v[0] + offset[0], v[1] + offset[1], v[2] + offset[2]])
+}
+
+fn columns(affine: &Affine3A) -> [f32; 12] {
+    let mut columns = [0.0; 12];
+    columns[0..3].copy_from_slice(&affine.matrix3.x_axis.to_array());
+    columns[3..6].copy_from_slice(&affine.matrix3.y_axis.to_array());
+    columns[6..9].copy_from_slice(&affine.matrix3.z_axis.to_array());
+    columns[9..12].copy_from_slice(&affine.translation.to_array());
+    columns
+}
+
+/// Records the query shader against `tlas_package` after whatever `encoder` already holds,
+/// submits it and returns the buffer the shader writes the committed intersection to.
+fn dispatch_query(
+    ctx: &TestingContext,
+    tlas_package: &rt::TlasPackage,
+    mut encoder: wgpu::CommandEncoder,
+) -> wgpu::Buffer {
+    let device = &ctx.device;
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Intersection"),
+        size: 34 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    out_buf
+}
+
+/// Traces a single ray at the second primitive of the second geometry of the last instance in
+/// the TLAS and checks every field of the committed intersection.
+fn committed_intersection(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    // Geometry 0 holds one triangle, geometry 1 two; only the last of them lies on the ray.
+    let vertices: Vec<[f32; 3]> = [
+        triangle([-10.0, 0.0, 0.0]),
+        triangle([10.0, 0.0, 0.0]),
+        triangle([0.0, 0.0, 0.0]),
+    ]
+    .concat();
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_descs = [3, 6].map(|vertex_count| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    });
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: size_descs.to_vec(),
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 3,
+    });
+
+    // Maps the object space hit point (0.25, 0.5, 0) to (0, 2.5, 3) in world space.
+    let transform = Affine3A::from_scale_rotation_translation(
+        Vec3::splat(2.0),
+        Quat::from_rotation_z(90.0_f32.to_radians()),
+        Vec3::new(1.0, 2.0, 3.0),
+    );
+
+    let instance = |transform: &Affine3A, custom_index| {
+        Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(transform),
+            custom_index,
+            0xff,
+        ))
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![
+            instance(&Affine3A::from_translation(Vec3::new(100.0, 0.0, 0.0)), 0),
+            instance(&Affine3A::from_translation(Vec3::new(200.0, 0.0, 0.0)), 0),
+            instance(&transform, CUSTOM_INDEX),
+        ],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![
+                    rt::BlasTriangleGeometry {
+                        size: &size_descs[0],
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    },
+                    rt::BlasTriangleGeometry {
+                        size: &size_descs[1],
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 3,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    },
+                ]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+    let object_to_world = columns(&transform);
+    let world_to_object = columns(&transform.inverse());
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+            let float = |i: usize| f32::from_bits(out[i]);
+            let assert_float = |name: &str, i: usize, expected: f32| {
+                assert!(
+                    (float(i) - expected).abs() < 1e-4,
+                    "{name}: got {}, expected {expected}",
+                    float(i)
+                );
+            };
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+            assert_float("t", 1, 3.0);
+            assert_eq!(out[2], CUSTOM_INDEX, "instance_custom_index");
+            assert_eq!(out[3], 2, "instance_id");
+            assert_eq!(out[4], 0, "sbt_record_offset");
+            assert_eq!(out[5], 1, "geometry_index");
+            assert_eq!(out[6], 1, "primitive_index");
+            // Weights of the second and third vertex for the hit point (0.25, 0.5, 0).
+            assert_float("barycentrics.x", 7, 0.5);
+            assert_float("barycentrics.y", 8, 0.25);
+            assert_eq!(out[9], 1, "front_face");
+            for (i, &expected) in object_to_world.iter().enumerate() {
+                assert_float("object_to_world", 10 + i, expected);
+            }
+            for (i, &expected) in world_to_object.iter().enumerate() {
+                assert_float("world_to_object", 22 + i, expected);
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMMITTED_INTERSECTION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(committed_intersection);
+
+/// Traces a ray that misses every instance and checks that the committed transforms read back
+/// as the identity instead of leftovers from the TLAS.
+fn committed_miss_transforms(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+
+    // A non-trivial transform that would be easy to tell apart from the identity.
+    let transform = Affine3A::from_scale_rotation_translation(
+        Vec3::splat(2.0),
+        Quat::from_rotation_z(90.0_f32.to_radians()),
+        Vec3::new(100.0, 2.0, 3.0),
+    );
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&transform),
+            CUSTOM_INDEX,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+    let identity = columns(&Affine3A::IDENTITY);
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_NONE, "kind");
+            for (i, &expected) in identity.iter().enumerate() {
+                assert_eq!(
+                    f32::from_bits(out[10 + i]),
+                    expected,
+                    "object_to_world[{i}]"
+                );
+                assert_eq!(
+                    f32::from_bits(out[22 + i]),
+                    expected,
+                    "world_to_object[{i}]"
+                );
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMMITTED_MISS_TRANSFORMS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(committed_miss_transforms);
+
+/// Traces a triangle placed by both a geometry transform inside the BLAS and an instance
+/// transform, and checks that the committed transforms are only those of the instance.
+fn committed_transforms_exclude_geometry_transform(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    // Moved back to the origin by the geometry transform.
+    let vertices = triangle([-5.0, 0.0, 0.0]);
+    let geometry_transform = Affine3A::from_translation(Vec3::new(5.0, 0.0, 0.0));
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let transform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Transform Buffer"),
+        contents: bytemuck::cast_slice(&AccelerationStructureInstance::affine_to_rows(
+            &geometry_transform,
+        )),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+
+    // Maps the hit point (0.25, 0.5, 0) in the space of the BLAS to (0, 2.5, 3) in world space.
+    let instance_transform = Affine3A::from_scale_rotation_translation(
+        Vec3::splat(2.0),
+        Quat::from_rotation_z(90.0_f32.to_radians()),
+        Vec3::new(1.0, 2.0, 3.0),
+    );
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&instance_transform),
+            CUSTOM_INDEX,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: Some(&transform_buf),
+                    transform_buffer_offset: Some(0),
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+    let object_to_world = columns(&instance_transform);
+    let world_to_object = columns(&instance_transform.inverse());
+    // What `object_to_world` would be if it included the geometry transform.
+    let combined = columns(&(instance_transform * geometry_transform));
+    assert_ne!(object_to_world, combined);
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+            let float = |i: usize| f32::from_bits(out[i]);
+            let assert_float = |name: &str, i: usize, expected: f32| {
+                assert!(
+                    (float(i) - expected).abs() < 1e-4,
+                    "{name}: got {}, expected {expected}",
+                    float(i)
+                );
+            };
+
+            // Only hit if the geometry transform was applied to the vertices.
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+            assert_float("t", 1, 3.0);
+            for (i, &expected) in object_to_world.iter().enumerate() {
+                assert_float("object_to_world", 10 + i, expected);
+            }
+            for (i, &expected) in world_to_object.iter().enumerate() {
+                assert_float("world_to_object", 22 + i, expected);
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMMITTED_TRANSFORMS_EXCLUDE_GEOMETRY_TRANSFORM: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(committed_transforms_exclude_geometry_transform);
+
+const BARYCENTRICS_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> attributes: array<vec4<f32>, 3>;
+
+@group(0) @binding(2)
+var<storage, read_write> out: array<vec4<f32>, 2>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.5, 0.25, 0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    let weights = barycentricWeights(intersection.barycentrics);
+    out[0] = vec4<f32>(weights, 0.0);
+    out[1] = weights.x * attributes[0] + weights.y * attributes[1] + weights.z * attributes[2];
+}
+"#;
+
+/// Interpolates a per-vertex attribute at a triangle hit with `barycentricWeights`, and checks
+/// it against the attribute computed from the hit point.
+fn barycentric_interpolation(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[0.0, 0.0, 1.0], [2.0, 0.0, 1.0], [0.0, 2.0, 1.0]];
+    // An affine function of the position, which interpolates exactly across the triangle.
+    let attribute = |[x, y, z]: [f32; 3]| [3.0 * x + 1.0, 2.0 - y, x + y, z];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let attribute_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Attributes"),
+        contents: bytemuck::cast_slice(&vertices.map(attribute)),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Output"),
+        size: mem::size_of::<[[f32; 4]; 2]>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        }),
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(BARYCENTRICS_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: attribute_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    // The ray hits (0.5, 0.25, 1), a quarter of the way to the second vertex along x and an
+    // eighth of the way to the third along y.
+    let expected_weights = [0.625, 0.25, 0.125, 0.0];
+    let expected_attribute = attribute([0.5, 0.25, 1.0]);
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[[f32; 4]] = bytemuck::cast_slice(&result);
+            for (name, values, expected) in [
+                ("weights", out[0], expected_weights),
+                ("attribute", out[1], expected_attribute),
+            ] {
+                for (value, expected) in values.iter().zip(expected) {
+                    assert!(
+                        (value - expected).abs() < 1e-4,
+                        "{name}: got {values:?}, expected {expected:?}"
+                    );
+                }
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_BARYCENTRIC_INTERPOLATION: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(barycentric_interpolation);
+
+/// Builds the TLAS in one submission and traces it in a later one, then moves the instance out
+/// of the ray's way and does the same again, so the shader reads only rely on barriers recorded
+/// in earlier submissions.
+fn build_in_earlier_submit(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+
+    // Maps the object space hit point (0.25, 0.5, 0) to (0, 2.5, 3) in world space.
+    let transform = Affine3A::from_translation(Vec3::new(-0.25, 2.0, 3.0));
+    let mut tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&transform),
+            CUSTOM_INDEX,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let hit_buf = dispatch_query(
+        &ctx,
+        &tlas_package,
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }),
+    );
+
+    // Rebuild the TLAS with the instance moved away; stale data would still report the hit.
+    let instance = tlas_package.get_mut_single(0).unwrap().as_mut().unwrap();
+    instance.transform = AccelerationStructureInstance::affine_to_rows(
+        &Affine3A::from_translation(Vec3::new(100.0, 2.0, 3.0)),
+    );
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let miss_buf = dispatch_query(
+        &ctx,
+        &tlas_package,
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }),
+    );
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &hit_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+            assert_eq!(f32::from_bits(out[1]), 3.0, "t");
+            assert_eq!(out[2], CUSTOM_INDEX, "instance_custom_index");
+        },
+    );
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &miss_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_NONE, "kind after rebuild");
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_TLAS_BUILT_IN_EARLIER_SUBMIT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(build_in_earlier_submit);
+
+/// Checks that `kind` tells a hit right in front of the ray origin apart from a ray fired into
+/// empty space, where `t` can't be relied on.
+fn committed_kind_at_small_t(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas_package = |translation: Vec3| {
+        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        });
+        rt::TlasPackage::new_with_instances(
+            tlas,
+            vec![Some(rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    translation,
+                )),
+                CUSTOM_INDEX,
+                0xff,
+            ))],
+        )
+    };
+
+    // The ray starts at (0, 2.5, 0), so this puts the object space point (0.25, 0.5, 0) just in
+    // front of it, and the other instance far to its side.
+    const SMALL_T: f32 = 1e-3;
+    let near_package = tlas_package(Vec3::new(-0.25, 2.0, SMALL_T));
+    let empty_package = tlas_package(Vec3::new(100.0, 2.0, 3.0));
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        [&near_package, &empty_package],
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let new_encoder =
+        || device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let near_buf = dispatch_query(&ctx, &near_package, new_encoder());
+    let empty_buf = dispatch_query(&ctx, &empty_package, new_encoder());
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &near_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+            let t = f32::from_bits(out[1]);
+            assert!((t - SMALL_T).abs() < 1e-5, "t: got {t}, expected {SMALL_T}");
+        },
+    );
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &empty_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_NONE, "kind");
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMMITTED_KIND_AT_SMALL_T: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(committed_kind_at_small_t);
+
+/// Creates the BLAS at a non-zero offset of a buffer owned by the test, and checks that tracing
+/// against it finds the triangle.
+fn blas_in_buffer(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: vec![size_desc.clone()],
+    };
+
+    let offset = 2 * rt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT;
+    let storage = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Acceleration Structure Storage"),
+        size: offset + device.blas_size(&blas_desc, &sizes),
+        usage: wgpu::BufferUsages::ACCELERATION_STRUCTURE_STORAGE,
+        mapped_at_creation: false,
+    });
+    let blas = device.create_blas_in_buffer(&storage, offset, &blas_desc, sizes);
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    // Maps the object space hit point (0.25, 0.5, 0) to (0, 2.5, 3) in world space.
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                -0.25, 2.0, 3.0,
+            ))),
+            CUSTOM_INDEX,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+            assert_eq!(f32::from_bits(out[1]), 3.0, "t");
+            assert_eq!(out[2], CUSTOM_INDEX, "instance_custom_index");
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_BLAS_IN_BUFFER: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(blas_in_buffer);
+
+/// Builds a TLAS with one instance of a non-opaque triangle in front of the query's ray,
+/// using `flags` for the instance, and encodes the build.
+fn non_opaque_triangle_package(
+    device: &wgpu::Device,
+    flags: rt::AccelerationStructureInstanceFlags,
+) -> (rt::TlasPackage, wgpu::CommandEncoder) {
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::empty(),
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let mut instance = rt::TlasInstance::new(
+        &blas,
+        AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+            -0.25, 2.0, 3.0,
+        ))),
+        CUSTOM_INDEX,
+        0xff,
+    );
+    instance.flags = flags;
+    let tlas_package = rt::TlasPackage::new_with_instances(tlas, vec![Some(instance)]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    (tlas_package, encoder)
+}
+
+/// Checks that `FORCE_OPAQUE` on an instance takes precedence over its non-opaque geometry.
+///
+/// The query shader calls `rayQueryProceed` once and never commits a candidate, so the triangle
+/// only ends up committed if traversal treated it as opaque instead of stopping at it.
+fn force_opaque_instance(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    for (flags, expected_kind) in [
+        (
+            rt::AccelerationStructureInstanceFlags::FORCE_OPAQUE,
+            RAY_QUERY_INTERSECTION_TRIANGLE,
+        ),
+        (
+            rt::AccelerationStructureInstanceFlags::empty(),
+            RAY_QUERY_INTERSECTION_NONE,
+        ),
+    ] {
+        let (tlas_package, encoder) = non_opaque_triangle_package(device, flags);
+        let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            &ctx.queue,
+            &out_buf.slice(..),
+            move |result| {
+                let result = result.unwrap();
+                let out: &[u32] = bytemuck::cast_slice(&result);
+                assert_eq!(out[0], expected_kind, "kind with instance flags {flags:?}");
+            },
+        );
+
+        device.poll(wgpu::Maintain::Wait);
+    }
+}
+
+#[gpu_test]
+static RAY_QUERY_FORCE_OPAQUE_INSTANCE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(force_opaque_instance);
+
+fn conflicting_instance_flags(ctx: TestingContext) {
+    fail(
+        &ctx.device,
+        || {
+            non_opaque_triangle_package(
+                &ctx.device,
+                rt::AccelerationStructureInstanceFlags::FORCE_OPAQUE
+                    | rt::AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE,
+            )
+            .1
+            .finish()
+        },
+        Some("both FORCE_OPAQUE and FORCE_NO_OPAQUE"),
+    );
+}
+
+#[gpu_test]
+static RAY_QUERY_CONFLICTING_INSTANCE_FLAGS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(conflicting_instance_flags);
+
+const MULTIPLE_QUERIES_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<u32, 6>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var shadow: ray_query;
+    var reflection: ray_query;
+
+    // Both queries are initialized before either proceeds, so they are live at the same time.
+    rayQueryInitialize(&shadow, acc_struct, RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.0, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 1.0, 0.0)));
+    rayQueryInitialize(&reflection, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&shadow);
+    rayQueryProceed(&reflection);
+
+    let shadow_hit = rayQueryGetCommittedIntersection(&shadow);
+    let reflection_hit = rayQueryGetCommittedIntersection(&reflection);
+    out[0] = shadow_hit.kind;
+    out[1] = bitcast<u32>(shadow_hit.t);
+    out[2] = shadow_hit.instance_custom_index;
+    out[3] = reflection_hit.kind;
+    out[4] = bitcast<u32>(reflection_hit.t);
+    out[5] = reflection_hit.instance_custom_index;
+}
+"#;
+
+/// Traces two rays from the same origin with two `ray_query` variables in one invocation, each
+/// towards a different instance, and checks that each query reports its own intersection.
+fn multiple_queries(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+
+    const SHADOW_CUSTOM_INDEX: u32 = 7;
+    const REFLECTION_CUSTOM_INDEX: u32 = 11;
+    const SHADOW_T: f32 = 5.0;
+    const REFLECTION_T: f32 = 2.0;
+
+    // Both transforms move the object space point (0.25, 0.5, 0) onto the ray they are meant
+    // for: the first lays the triangle flat above the origin, the second puts it straight ahead.
+    let shadow_transform = Affine3A::from_rotation_translation(
+        Quat::from_rotation_x(90.0_f32.to_radians()),
+        Vec3::new(-0.25, SHADOW_T, -0.5),
+    );
+    let reflection_transform = Affine3A::from_translation(Vec3::new(-0.25, -0.5, REFLECTION_T));
+
+    let instance = |transform: &Affine3A, custom_index| {
+        Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(transform),
+            custom_index,
+            0xff,
+        ))
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![
+            instance(&shadow_transform, SHADOW_CUSTOM_INDEX),
+            instance(&reflection_transform, REFLECTION_CUSTOM_INDEX),
+        ],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Intersections"),
+        size: 6 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(MULTIPLE_QUERIES_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+            let assert_hit = |name: &str, offset: usize, t: f32, custom_index: u32| {
+                assert_eq!(out[offset], RAY_QUERY_INTERSECTION_TRIANGLE, "{name} kind");
+                let got = f32::from_bits(out[offset + 1]);
+                assert!((got - t).abs() < 1e-4, "{name} t: got {got}, expected {t}");
+                assert_eq!(out[offset + 2], custom_index, "{name} custom index");
+            };
+
+            assert_hit("shadow", 0, SHADOW_T, SHADOW_CUSTOM_INDEX);
+            assert_hit("reflection", 3, REFLECTION_T, REFLECTION_CUSTOM_INDEX);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_MULTIPLE_QUERIES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(multiple_queries);
+
+/// Hides the only instance in the ray's way and shows it again, rebuilding the TLAS in between,
+/// and checks that the ray only hits it while it is visible.
+fn toggle_instance_visibility(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+
+    // Puts the object space point (0.25, 0.5, 0) on the ray, at (0, 2.5, 3).
+    let mut tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                -0.25, 2.0, 3.0,
+            ))),
+            CUSTOM_INDEX,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    for visible in [true, false, true] {
+        let instance = tlas_package.get_mut_single(0).unwrap().as_mut().unwrap();
+        instance.set_visible(visible);
+        assert_eq!(instance.mask, 0xff);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.build_acceleration_structures(iter::empty(), iter::once(&tlas_package));
+        let out_buf = dispatch_query(&ctx, &tlas_package, encoder);
+
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            &ctx.queue,
+            &out_buf.slice(..),
+            move |result| {
+                let result = result.unwrap();
+                let out: &[u32] = bytemuck::cast_slice(&result);
+
+                if visible {
+                    assert_eq!(out[0], RAY_QUERY_INTERSECTION_TRIANGLE, "kind");
+                    assert_eq!(out[2], CUSTOM_INDEX, "instance_custom_index");
+                } else {
+                    assert_eq!(out[0], RAY_QUERY_INTERSECTION_NONE, "kind");
+                }
+            },
+        );
+        device.poll(wgpu::Maintain::Wait);
+    }
+}
+
+#[gpu_test]
+static RAY_QUERY_TOGGLE_INSTANCE_VISIBILITY: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(toggle_instance_visibility);
+
+const FUNCTION_ARGUMENT_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct_a: acceleration_structure;
+
+@group(0) @binding(1)
+var acc_struct_b: acceleration_structure;
+
+@group(0) @binding(2)
+var<storage, read_write> out: array<u32, 2>;
+
+fn trace(acs: acceleration_structure) -> u32 {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acs, RayDesc(0u, 0xFFu, 0.0, 100.0, vec3<f32>(0.0, 2.5, 0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind == 0u) {
+        return 0xFFFFFFFFu;
+    }
+    return intersection.instance_custom_index;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    out[0] = trace(acc_struct_a);
+    out[1] = trace(acc_struct_b);
+}
+"#;
+
+/// Calls a helper taking the acceleration structure as a parameter with two different TLASes,
+/// each holding an instance of the same BLAS with its own custom index, and checks that each
+/// call traces the TLAS it was given.
+fn acceleration_structure_argument(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = triangle([0.0, 0.0, 0.0]);
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    // Both instances put the object space point (0.25, 0.5, 0) on the ray, at (0, 2.5, 3).
+    let tlas_packages = [1, 2].map(|custom_index| {
+        let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        });
+        rt::TlasPackage::new_with_instances(
+            tlas,
+            vec![Some(rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    Vec3::new(-0.25, 2.0, 3.0),
+                )),
+                custom_index,
+                0xff,
+            ))],
+        )
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        tlas_packages.iter(),
+    );
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Custom Indices"),
+        size: 2 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(FUNCTION_ARGUMENT_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_packages[0].as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: tlas_packages[1].as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+            assert_eq!(out, [1, 2]);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_ACCELERATION_STRUCTURE_ARGUMENT: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(acceleration_structure_argument);
+
+const SELF_INTERSECTION_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+struct SelfHits {
+    naive: atomic<u32>,
+    offset: atomic<u32>,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> self_hits: SelfHits;
+
+@group(0) @binding(2)
+var<uniform> normal: vec3<f32>;
+
+fn hits(ray: RayDesc) -> bool {
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, ray);
+    rayQueryProceed(&rq);
+    return rayQueryGetCommittedIntersectionType(&rq) != RAY_QUERY_INTERSECTION_NONE;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dir = normalize(vec3<f32>(vec2<f32>(id.xy) / 64.0 - 0.5, 1.0));
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.0, 10000.0, vec3<f32>(0.0), dir));
+    rayQueryProceed(&rq);
+    let hit = rayQueryGetCommittedIntersection(&rq);
+    if (hit.kind == RAY_QUERY_INTERSECTION_NONE) {
+        return;
+    }
+
+    // Trace back towards the camera, where nothing but the plane itself can be hit.
+    let position = dir * hit.t;
+    if (hits(RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.0, 10000.0, position, normal))) {
+        atomicAdd(&self_hits.naive, 1u);
+    }
+    if (hits(RayDesc(RAY_FLAG_TERMINATE_ON_FIRST_HIT, 0xFFu, 0.0, 10000.0, offsetRayOrigin(position, normal), normal))) {
+        atomicAdd(&self_hits.offset, 1u);
+    }
+}
+"#;
+
+/// Traces rays at a large tilted plane far from the origin and, from each hit, a ray leaving
+/// the plane towards the camera. Rays starting at the reconstructed hit position hit the plane
+/// again whenever rounding put that position behind it, while rays whose origin was moved with
+/// `offsetRayOrigin` never do.
+fn offset_origin_self_intersection(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let plane = [
+        Vec3::new(-5000.0, -5000.0, 900.0),
+        Vec3::new(5000.0, -5000.0, 1100.0),
+        Vec3::new(0.0, 5000.0, 1000.0),
+    ];
+    let normal = (plane[2] - plane[0]).cross(plane[1] - plane[0]).normalize();
+    assert!(normal.z < 0.0, "the normal must face the camera");
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&plane.map(|v| v.to_array())),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let normal_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Normal"),
+        contents: bytemuck::cast_slice(&normal.extend(0.0).to_array()),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let self_hits_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Self Hits"),
+        contents: bytemuck::cast_slice(&[0u32; 2]),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SELF_INTERSECTION_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: self_hits_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: normal_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(8, 8, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &self_hits_buf.slice(..),
+        |result| {
+            let result = result.unwrap();
+            let [naive, offset]: [u32; 2] = bytemuck::cast_slice(&result).try_into().unwrap();
+            assert_eq!(
+                offset, 0,
+                "{offset} offset rays hit the plane they start on"
+            );
+            assert!(
+                naive > offset,
+                "expected naive rays to hit the plane they start on"
+            );
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_OFFSET_ORIGIN_SELF_INTERSECTION: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(offset_origin_self_intersection);
+
+const WORLD_NORMAL_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure<vertex_return>;
+
+struct Ray {
+    origin: vec4<f32>,
+    dir: vec4<f32>,
+}
+
+@group(0) @binding(1)
+var<storage, read> rays: array<Ray>;
+
+struct Normals {
+    outward: vec4<f32>,
+    facing: vec4<f32>,
+}
+
+@group(0) @binding(2)
+var<storage, read_write> normals: array<Normals>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let ray = rays[id.x];
+    var rq: ray_query<vertex_return>;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.0, 100.0, ray.origin.xyz, ray.dir.xyz));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind == RAY_QUERY_INTERSECTION_NONE) {
+        normals[id.x] = Normals(vec4<f32>(0.0), vec4<f32>(0.0));
+        return;
+    }
+    let v = getCommittedHitVertexPositions(&rq);
+    normals[id.x] = Normals(
+        vec4<f32>(computeWorldNormal(v[0], v[1], v[2], intersection.object_to_world), 0.0),
+        vec4<f32>(computeWorldNormal(v[0], v[1], v[2], intersection.object_to_world, intersection.front_face), 0.0),
+    );
+}
+"#;
+
+/// Hits a triangle under a non-uniformly scaled and rotated instance from both sides and checks
+/// `computeWorldNormal` against the normal transformed on the CPU by the inverse transpose,
+/// which differs from the normal transformed like a direction.
+fn compute_world_normal(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices = [
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ];
+    let object_normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+    let transform = Affine3A::from_scale_rotation_translation(
+        Vec3::new(2.0, 1.0, 1.0),
+        Quat::from_rotation_y(30.0_f32.to_radians()),
+        Vec3::new(0.0, 0.0, 5.0),
+    );
+    let expected = (transform.matrix3.inverse().transpose() * object_normal).normalize();
+    assert!(
+        expected.dot((transform.matrix3 * object_normal).normalize()) < 0.99,
+        "the transform doesn't tell normals and directions apart"
+    );
+
+    // One ray towards each side of the triangle, aimed at its centroid.
+    let centroid = vertices
+        .iter()
+        .map(|&v| transform.transform_point3(v))
+        .sum::<Vec3>()
+        / 3.0;
+    let rays = [expected, -expected].map(|side| {
+        [
+            (centroid + side * 2.0).extend(1.0).to_array(),
+            (-side).extend(0.0).to_array(),
+        ]
+    });
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices.map(|v| v.to_array())),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let ray_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Rays"),
+        contents: bytemuck::cast_slice(&rays),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let normal_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Normals"),
+        size: (rays.len() * mem::size_of::<[[f32; 4]; 2]>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+                | rt::AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE
+            | rt::AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&transform),
+            0,
+            0xff,
+        ))],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(WORLD_NORMAL_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: ray_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: normal_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(rays.len() as u32, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &normal_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let normals: &[[[f32; 4]; 2]] = bytemuck::cast_slice(&result);
+            for (ray, [outward, facing]) in rays.iter().zip(normals) {
+                let dir = Vec3::from_slice(&ray[1]);
+                let outward = Vec3::from_slice(outward);
+                let facing = Vec3::from_slice(facing);
+                assert!(
+                    outward.abs_diff_eq(expected, 1e-4),
+                    "ray along {dir}: got normal {outward}, expected {expected}"
+                );
+                // Flipped by `front_face` towards the side the ray came from.
+                assert!(
+                    facing.abs_diff_eq(-dir, 1e-4),
+                    "ray along {dir}: got facing normal {facing}, expected {}",
+                    -dir
+                );
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMPUTE_WORLD_NORMAL: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features() | wgpu::Features::RAY_HIT_VERTEX_RETURN),
+    )
+    .run_sync(compute_world_normal);
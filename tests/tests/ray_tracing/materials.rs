@@ -0,0 +1,699 @@
+use std::{iter, mem};
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> geometry_offsets: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read> materials: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> out: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> hits: array<vec2<u32>>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 10.0 - 9.75, 0.25, -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var material = 0xFFFFFFFFu;
+    hits[id.x] = vec2<u32>(0xFFFFFFFFu);
+    if (intersection.kind != RAY_QUERY_INTERSECTION_NONE) {
+        hits[id.x] = vec2<u32>(intersection.geometry_index, intersection.primitive_index);
+        let primitive = geometry_offsets[intersection.geometry_index] + intersection.primitive_index;
+        material = materials[primitive];
+    }
+    out[id.x] = material;
+}
+"#;
+
+/// Number of rays, spaced 10 units apart along x so each one hits a different triangle.
+const RAY_COUNT: u32 = 4;
+const MISS: u32 = 0xFFFFFFFF;
+
+fn triangle(x: f32) -> [[f32; 3]; 3] {
+    [[x, 0.0, 0.0], [x, 1.0, 0.0], [x + 1.0, 0.0, 0.0]]
+}
+
+/// Looks up a per-primitive material from the geometry and primitive index of each hit in a BLAS
+/// with two geometries, using the offsets from
+/// [`rt::BlasGeometrySizeDescriptors::primitive_offsets`], and checks that the reported primitive
+/// indices cover [`rt::BlasGeometrySizeDescriptors::primitive_index_ranges`].
+fn material_lookup(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    // Geometry 0 holds the triangle at x = -10, geometry 1 the ones at x = 0 and x = 10. The
+    // last ray at x = 20 misses.
+    let vertices = [triangle(-10.0), triangle(0.0), triangle(10.0)].concat();
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_descs = [3, 6].map(|vertex_count| rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    });
+    let geometry_sizes = rt::BlasGeometrySizeDescriptors::Triangles {
+        desc: size_descs.to_vec(),
+    };
+    let geometry_offsets = geometry_sizes.primitive_offsets();
+    assert_eq!(geometry_offsets, [0, 1]);
+    let primitive_index_ranges = geometry_sizes.primitive_index_ranges();
+    assert_eq!(primitive_index_ranges, [0..1, 0..2]);
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        geometry_sizes,
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let offsets_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Geometry Offsets"),
+        contents: bytemuck::cast_slice(&geometry_offsets),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    let materials: [u32; 3] = [7, 11, 13];
+    let materials_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Materials"),
+        contents: bytemuck::cast_slice(&materials),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Out"),
+        size: RAY_COUNT as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let hits_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: RAY_COUNT as u64 * mem::size_of::<[u32; 2]>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: offsets_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: materials_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: out_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: hits_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![
+                    rt::BlasTriangleGeometry {
+                        size: &size_descs[0],
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    },
+                    rt::BlasTriangleGeometry {
+                        size: &size_descs[1],
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 3,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    },
+                ]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(RAY_COUNT, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out, [materials[0], materials[1], materials[2], MISS]);
+        },
+    );
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &hits_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let hits: &[[u32; 2]] = bytemuck::cast_slice(&result);
+
+            // Every primitive of every geometry is hit exactly once, so the hits must be exactly
+            // the indices in the ranges.
+            let expected: Vec<[u32; 2]> = primitive_index_ranges
+                .iter()
+                .enumerate()
+                .flat_map(|(geometry, range)| range.clone().map(move |p| [geometry as u32, p]))
+                .chain(iter::once([MISS, MISS]))
+                .collect();
+            assert_eq!(hits, expected);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_MATERIAL_LOOKUP: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(material_lookup);
+
+const SBT_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> materials: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> out: array<u32>;
+
+const PROCEDURAL = 0x80000000u;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 10.0 + 0.25, 0.25, -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    // Procedural candidates go to the intersection logic, which never commits them.
+    var result = 0u;
+    while (rayQueryProceed(&rq)) {
+        if (rayQueryGetCandidateIntersectionType(&rq) == RAY_QUERY_INTERSECTION_AABB) {
+            result = PROCEDURAL;
+        }
+    }
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind != RAY_QUERY_INTERSECTION_NONE) {
+        result = materials[intersection.sbt_record_offset];
+    }
+    out[id.x] = result;
+}
+"#;
+
+const PROCEDURAL: u32 = 0x80000000;
+
+/// Builds a TLAS mixing triangle instances at shader binding table record offsets 0, 1 and 2
+/// with a procedural instance at offset 2, traces one ray through each, and checks that the
+/// triangle hits pick their material by `sbt_record_offset` while the procedural one reaches
+/// the intersection logic.
+fn sbt_record_offset_routing(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let aabb: [[f32; 3]; 2] = [[0.0, 0.0, -0.5], [1.0, 1.0, 0.5]];
+    let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("AABB Buffer"),
+        contents: bytemuck::cast_slice(&aabb),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let triangle_size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let aabb_size = rt::BlasProceduralGeometrySizeDescriptor {
+        primitive_count: 1,
+        flags: rt::AccelerationStructureGeometryFlags::empty(),
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let triangle_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![triangle_size.clone()],
+        },
+    );
+    let aabb_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::AABBs {
+            desc: vec![aabb_size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: RAY_COUNT,
+    });
+    // The custom indices run the other way, so the routing can't come from them.
+    let instance = |blas, x: f32, custom_index, sbt_offset| {
+        let mut instance = rt::TlasInstance::new(
+            blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                x, 0.0, 0.0,
+            ))),
+            custom_index,
+            0xff,
+        );
+        instance.shader_binding_table_record_offset = sbt_offset;
+        Some(instance)
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![
+            instance(&triangle_blas, 0.0, 2, 0),
+            instance(&triangle_blas, 10.0, 1, 1),
+            instance(&triangle_blas, 20.0, 0, 2),
+            instance(&aabb_blas, 30.0, 0, 2),
+        ],
+    );
+
+    let materials: [u32; 3] = [7, 11, 13];
+    let materials_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Materials"),
+        contents: bytemuck::cast_slice(&materials),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Out"),
+        size: RAY_COUNT as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SBT_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: materials_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        [
+            rt::BlasBuildEntry {
+                blas: &triangle_blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &triangle_size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+            rt::BlasBuildEntry {
+                blas: &aabb_blas,
+                geometry: rt::BlasGeometries::ProceduralGeometries(
+                    vec![rt::BlasProceduralGeometry {
+                        size: &aabb_size,
+                        bounding_box_buffer: &aabb_buf,
+                        bounding_box_buffer_offset: 0,
+                        bounding_box_stride: mem::size_of::<[[f32; 3]; 2]>() as u64,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+        ]
+        .iter(),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(RAY_COUNT, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out, [materials[0], materials[1], materials[2], PROCEDURAL]);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_SBT_RECORD_OFFSET_ROUTING: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(sbt_record_offset_routing);
+
+const OBJECT_ID_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> object_ids: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> out: array<u32>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 10.0 + 0.25, 0.25, -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    out[id.x] = 0xFFFFFFFFu;
+    if (intersection.kind != RAY_QUERY_INTERSECTION_NONE) {
+        out[id.x] = object_ids[intersection.instance_id] + intersection.instance_custom_index;
+    }
+}
+"#;
+
+/// Recovers object ids that don't fit into the 24 bit custom index from hits through
+/// [`rt::TlasPackage::object_id_table`], with an empty slot in the package that doesn't take up
+/// an instance id.
+fn object_id_table(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&triangle(0.0)),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    // (x, custom index, object id) of the instances, the last one keeps its custom index as
+    // object id. The last ray at x = 30 misses.
+    let objects = [
+        (0.0, 5, Some(0x0100_0005)),
+        (10.0, 0xABCDE, Some(0xFFFF_FFF0)),
+        (20.0, 3, None),
+    ];
+    let mut instances: Vec<Option<rt::TlasInstance>> = objects
+        .iter()
+        .map(|&(x, custom_index, object_id)| {
+            let mut instance = rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    Vec3::new(x, 0.0, 0.0),
+                )),
+                custom_index,
+                0xff,
+            );
+            instance.set_object_id(object_id);
+            Some(instance)
+        })
+        .collect();
+    instances.insert(1, None);
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: instances.len() as u32,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(tlas, instances);
+
+    let object_id_table = tlas_package.object_id_table();
+    assert_eq!(
+        object_id_table,
+        [0x0100_0000, 0xFFFF_FFF0u32.wrapping_sub(0xABCDE), 0]
+    );
+
+    let table_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Object Ids"),
+        contents: bytemuck::cast_slice(&object_id_table),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Out"),
+        size: RAY_COUNT as u64 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(OBJECT_ID_SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: table_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(RAY_COUNT, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &out_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let out: &[u32] = bytemuck::cast_slice(&result);
+
+            assert_eq!(out, [0x0100_0005, 0xFFFF_FFF0, 3, MISS]);
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_OBJECT_ID_TABLE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(object_id_table);
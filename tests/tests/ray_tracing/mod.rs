@@ -10,7 +10,23 @@ use glam::{Affine3A, Quat, Vec3};
 
 use mesh_gen::{AccelerationStructureInstance, Vertex};
 
+mod as_build;
+mod as_create;
+mod binding;
+mod compaction;
+mod copy;
+mod counters;
+mod environment;
+mod features;
+mod indexed;
+mod intersection;
+mod materials;
 mod mesh_gen;
+mod procedural;
+mod raw_instances;
+mod ray_flags;
+mod storage_output;
+mod vertex_formats;
 
 fn required_features() -> wgpu::Features {
     wgpu::Features::TEXTURE_BINDING_ARRAY
@@ -91,16 +107,22 @@ fn execute(ctx: TestingContext) {
         encoder.build_acceleration_structures(
             iter::once(&rt::BlasBuildEntry {
                 blas: &blas,
-                geometry: rt::BlasGeometries::TriangleGeometries(vec![rt::BlasTriangleGeometry {
-                    size: &blas_geo_size_desc,
-                    vertex_buffer: &vertex_buf,
-                    first_vertex: 0,
-                    vertex_stride: mem::size_of::<Vertex>() as u64,
-                    index_buffer: Some(&index_buf),
-                    index_buffer_offset: Some(0),
-                    transform_buffer: None,
-                    transform_buffer_offset: None,
-                }]),
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &blas_geo_size_desc,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: Some(mem::size_of::<Vertex>() as u64),
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: Some(&index_buf),
+                        index_buffer_offset: Some(0),
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
             }),
             // iter::empty(),
             iter::once(&tlas_package),
@@ -0,0 +1,390 @@
+use std::{iter, mem};
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const INSTANCE_ID_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<f32>;
+
+// Both instances use the same unit AABB, the sphere inside it is picked by instance id.
+const CENTERS = array<vec3<f32>, 2>(vec3<f32>(0.0, 0.0, 5.0), vec3<f32>(0.0, 0.0, 10.0));
+const RADII = array<f32, 2>(1.0, 0.5);
+
+@compute @workgroup_size(1)
+fn main() {
+    let origin = vec3<f32>(0.0);
+    let dir = vec3<f32>(0.0, 0.0, 1.0);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.0, 100.0, origin, dir));
+    // Never commit anything, so that both instances are seen.
+    while (rayQueryProceed(&rq)) {
+        let id = rayQueryGetCandidateIntersectionInstanceId(&rq);
+        var centers = CENTERS;
+        var radii = RADII;
+        let oc = origin - centers[id];
+        let b = dot(oc, dir);
+        let c = dot(oc, oc) - radii[id] * radii[id];
+        let disc = b * b - c;
+        if (disc >= 0.0) {
+            out[id] = -b - sqrt(disc);
+        }
+    }
+}
+"#;
+
+/// Traces a ray through two procedural instances sharing one AABB BLAS, where the
+/// shader picks each instance's sphere radius from the candidate instance id, and
+/// checks the analytic hit distance computed for each instance.
+fn candidate_instance_id(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let aabb: [[f32; 3]; 2] = [[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]];
+    let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("AABB Buffer"),
+        contents: bytemuck::cast_slice(&aabb),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let aabb_size = rt::BlasProceduralGeometrySizeDescriptor {
+        primitive_count: 1,
+        flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::AABBs {
+            desc: vec![aabb_size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let instance = |z| {
+        Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                0.0, 0.0, z,
+            ))),
+            0,
+            0xff,
+        ))
+    };
+    let tlas_package =
+        rt::TlasPackage::new_with_instances(tlas, vec![instance(5.0), instance(10.0)]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::ProceduralGeometries(
+                vec![rt::BlasProceduralGeometry {
+                    size: &aabb_size,
+                    bounding_box_buffer: &aabb_buf,
+                    bounding_box_buffer_offset: 0,
+                    bounding_box_stride: mem::size_of::<[[f32; 3]; 2]>() as u64,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(INSTANCE_ID_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let out_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Out"),
+        contents: bytemuck::cast_slice(&[-1.0f32; 2]),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &out_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let out: &[f32] = bytemuck::cast_slice(&result);
+        // The front of a sphere of radius 1 at z = 5 and of radius 0.5 at z = 10.
+        assert_eq!(out, [4.0, 9.5]);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_CANDIDATE_INSTANCE_ID: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(candidate_instance_id);
+
+const COMMITTED_RESULT_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<array<f32, 7>, 3>;
+
+// Through the triangle, through the AABB only, and through nothing.
+const ORIGINS = array<vec3<f32>, 3>(vec3<f32>(0.0), vec3<f32>(10.0, 0.0, 0.0), vec3<f32>(-10.0, 0.0, 0.0));
+
+@compute @workgroup_size(3)
+fn main(@builtin(local_invocation_index) index: u32) {
+    var origins = ORIGINS;
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.0, 100.0, origins[index], vec3<f32>(0.0, 0.0, 1.0)));
+    // The opaque triangle commits itself, the AABB candidate is never committed.
+    while (rayQueryProceed(&rq)) {}
+    let hit = rayQueryGetCommittedIntersection(&rq);
+    out[index] = array<f32, 7>(
+        f32(hit.kind),
+        hit.t,
+        hit.barycentrics.x,
+        hit.barycentrics.y,
+        select(0.0, 1.0, hit.front_face),
+        hit.object_to_world[0].x,
+        hit.object_to_world[3].z,
+    );
+}
+"#;
+
+/// Traces a ray that hits a triangle, one that only passes through an AABB and one
+/// that misses everything, and checks that the committed results only carry the
+/// fields defined for their kind, with defaults for the rest.
+fn committed_result(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let aabb: [[f32; 3]; 2] = [[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]];
+    let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("AABB Buffer"),
+        contents: bytemuck::cast_slice(&aabb),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let triangle_size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let aabb_size = rt::BlasProceduralGeometrySizeDescriptor {
+        primitive_count: 1,
+        flags: rt::AccelerationStructureGeometryFlags::empty(),
+    };
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let triangle_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![triangle_size.clone()],
+        },
+    );
+    let aabb_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::AABBs {
+            desc: vec![aabb_size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let instance = |blas, x| {
+        Some(rt::TlasInstance::new(
+            blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                x, 0.0, 5.0,
+            ))),
+            0,
+            0xff,
+        ))
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![instance(&triangle_blas, 0.0), instance(&aabb_blas, 10.0)],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        [
+            rt::BlasBuildEntry {
+                blas: &triangle_blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &triangle_size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+            rt::BlasBuildEntry {
+                blas: &aabb_blas,
+                geometry: rt::BlasGeometries::ProceduralGeometries(
+                    vec![rt::BlasProceduralGeometry {
+                        size: &aabb_size,
+                        bounding_box_buffer: &aabb_buf,
+                        bounding_box_buffer_offset: 0,
+                        bounding_box_stride: mem::size_of::<[[f32; 3]; 2]>() as u64,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+        ]
+        .iter(),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(COMMITTED_RESULT_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let out_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Out"),
+        contents: bytemuck::cast_slice(&[-1.0f32; 3 * 7]),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &out_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let out: &[[f32; 7]] = bytemuck::cast_slice(&result);
+
+        // Kind, t, barycentrics and the instance transform of the triangle hit.
+        let triangle = out[0];
+        assert_eq!(triangle[0], 1.0);
+        assert!((triangle[1] - 5.0).abs() < 1e-4, "t: {}", triangle[1]);
+        assert!((triangle[2] - 0.25).abs() < 1e-4, "u: {}", triangle[2]);
+        assert!((triangle[3] - 0.5).abs() < 1e-4, "v: {}", triangle[3]);
+        assert_eq!(triangle[5..], [1.0, 5.0]);
+
+        // A ray only seeing the uncommitted AABB misses like one seeing nothing.
+        for miss in &out[1..] {
+            assert_eq!(*miss, [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        }
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_COMMITTED_RESULT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(committed_result);
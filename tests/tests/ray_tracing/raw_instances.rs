@@ -0,0 +1,844 @@
+use std::{iter, mem};
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Quat, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> hits: array<vec2<u32>>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) * 0.5 - 4.0, f32(id.y) * 0.5 - 4.0, -10.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var hit = vec2<u32>(0xFFFFFFFFu, 0u);
+    if (intersection.kind != 0u) {
+        hit = vec2<u32>(intersection.instance_custom_index, bitcast<u32>(intersection.t));
+    }
+    hits[id.y * 16u + id.x] = hit;
+}
+"#;
+
+const GRID_SIZE: u32 = 16;
+
+/// Traces a grid of rays against `tlas_package` and returns the custom index and distance of
+/// every committed hit.
+fn trace_grid(ctx: &TestingContext, tlas_package: &rt::TlasPackage) -> Vec<[u32; 2]> {
+    let (command_buffer, hit_buf) = encode_trace_grid(ctx, tlas_package);
+    ctx.queue.submit(Some(command_buffer));
+    read_hits(ctx, &hit_buf)
+}
+
+/// Records building `tlas_package` and tracing a grid of rays against it, returning the
+/// commands and the buffer receiving the hits.
+fn encode_trace_grid(
+    ctx: &TestingContext,
+    tlas_package: &rt::TlasPackage,
+) -> (wgpu::CommandBuffer, wgpu::Buffer) {
+    let device = &ctx.device;
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: (GRID_SIZE * GRID_SIZE) as u64 * mem::size_of::<[u32; 2]>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(iter::empty(), iter::once(tlas_package));
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(GRID_SIZE, GRID_SIZE, 1);
+    }
+
+    (encoder.finish(), hit_buf)
+}
+
+/// Reads back the hits traced by [`encode_trace_grid`].
+fn read_hits(ctx: &TestingContext, hit_buf: &wgpu::Buffer) -> Vec<[u32; 2]> {
+    let device = &ctx.device;
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &hit_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            sender
+                .send(bytemuck::cast_slice::<u8, [u32; 2]>(&result).to_vec())
+                .unwrap();
+        },
+    );
+    device.poll(wgpu::Maintain::Wait);
+
+    receiver.recv().unwrap()
+}
+
+/// Creates and builds a BLAS holding a single triangle around the origin.
+fn build_triangle_blas(ctx: &TestingContext) -> rt::Blas {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size_desc,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::empty(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    blas
+}
+
+/// Fills one package through [`rt::TlasInstance`]s and another one through raw instance
+/// records describing the same instances, and checks that both trace identically.
+fn raw_instances_match_typed(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+
+    let transforms = [
+        Affine3A::from_translation(Vec3::new(-2.0, -2.0, 0.0)),
+        Affine3A::from_scale_rotation_translation(
+            Vec3::splat(1.5),
+            Quat::from_rotation_z(45.0_f32.to_radians()),
+            Vec3::new(2.0, 1.0, 2.0),
+        ),
+        Affine3A::from_rotation_translation(
+            Quat::from_rotation_y(30.0_f32.to_radians()),
+            Vec3::new(-1.0, 2.5, 4.0),
+        ),
+    ];
+
+    let create_tlas = || {
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: transforms.len() as u32 + 1,
+        })
+    };
+
+    // The first slot is left empty in both packages, to exercise a non-zero raw offset.
+    let mut typed_package = rt::TlasPackage::new(create_tlas(), transforms.len() as u32 + 1);
+    for (i, transform) in transforms.iter().enumerate() {
+        *typed_package.get_mut_single(i + 1).unwrap() = Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(transform),
+            i as u32 + 1,
+            0xff,
+        ));
+    }
+
+    let raw_instances: Vec<AccelerationStructureInstance> = transforms
+        .iter()
+        .enumerate()
+        .map(|(i, transform)| {
+            AccelerationStructureInstance::new(
+                transform,
+                i as u32 + 1,
+                0xff,
+                0,
+                0,
+                blas.handle().unwrap(),
+            )
+        })
+        .collect();
+    let mut raw_package = rt::TlasPackage::new(create_tlas(), transforms.len() as u32 + 1);
+    raw_package.write_instances_raw(1, bytemuck::cast_slice(&raw_instances));
+
+    for (typed, raw) in typed_package.get().iter().zip(raw_package.get()) {
+        assert_eq!(typed.is_some(), raw.is_some());
+        if let (Some(typed), Some(raw)) = (typed, raw) {
+            assert_eq!(typed.transform, raw.transform);
+            assert_eq!(typed.custom_index, raw.custom_index);
+            assert_eq!(typed.mask, raw.mask);
+        }
+    }
+
+    let typed_hits = trace_grid(&ctx, &typed_package);
+    let raw_hits = trace_grid(&ctx, &raw_package);
+
+    for custom_index in 1..=transforms.len() as u32 {
+        assert!(
+            typed_hits.iter().any(|hit| hit[0] == custom_index),
+            "no ray hit instance {custom_index}"
+        );
+    }
+    assert_eq!(typed_hits, raw_hits);
+}
+
+#[gpu_test]
+static TLAS_RAW_INSTANCES_MATCH_TYPED: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(raw_instances_match_typed);
+
+/// Traces the same package once built from staged and once from mapped instances, and checks
+/// that both trace identically.
+fn instance_upload_strategies(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+
+    let transforms = [
+        Affine3A::from_translation(Vec3::new(-2.0, -2.0, 0.0)),
+        Affine3A::from_translation(Vec3::new(2.0, 1.0, 2.0)),
+    ];
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: transforms.len() as u32,
+    });
+    let mut tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        transforms
+            .iter()
+            .enumerate()
+            .map(|(i, transform)| {
+                Some(rt::TlasInstance::new(
+                    &blas,
+                    AccelerationStructureInstance::affine_to_rows(transform),
+                    i as u32 + 1,
+                    0xff,
+                ))
+            })
+            .collect(),
+    );
+
+    tlas_package.set_instance_upload(Some(rt::TlasInstanceUpload::Staging));
+    let staged_hits = trace_grid(&ctx, &tlas_package);
+    tlas_package.set_instance_upload(Some(rt::TlasInstanceUpload::Mapped));
+    let mapped_hits = trace_grid(&ctx, &tlas_package);
+
+    for custom_index in 1..=transforms.len() as u32 {
+        assert!(
+            staged_hits.iter().any(|hit| hit[0] == custom_index),
+            "no ray hit instance {custom_index}"
+        );
+    }
+    assert_eq!(staged_hits, mapped_hits);
+}
+
+#[gpu_test]
+static TLAS_INSTANCE_UPLOAD_STRATEGIES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(instance_upload_strategies);
+
+/// Records two builds of the same package from mapped instances, moving the instance in
+/// between, before submitting either, and checks that each build traces its own instances
+/// rather than the ones last written.
+fn mapped_instances_recorded_before_submit(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let mut tlas_package = rt::TlasPackage::new(tlas, 1);
+    tlas_package.set_instance_upload(Some(rt::TlasInstanceUpload::Mapped));
+    let set_instance = |tlas_package: &mut rt::TlasPackage, x: f32, custom_index: u32| {
+        *tlas_package.get_mut_single(0).unwrap() = Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                x, 0.0, 0.0,
+            ))),
+            custom_index,
+            0xff,
+        ));
+    };
+
+    set_instance(&mut tlas_package, -2.0, 1);
+    let first_hits = trace_grid(&ctx, &tlas_package);
+    set_instance(&mut tlas_package, 2.0, 2);
+    let second_hits = trace_grid(&ctx, &tlas_package);
+    assert_ne!(first_hits, second_hits);
+
+    set_instance(&mut tlas_package, -2.0, 1);
+    let (first_commands, first_hit_buf) = encode_trace_grid(&ctx, &tlas_package);
+    set_instance(&mut tlas_package, 2.0, 2);
+    let (second_commands, second_hit_buf) = encode_trace_grid(&ctx, &tlas_package);
+
+    ctx.queue.submit(Some(first_commands));
+    assert_eq!(read_hits(&ctx, &first_hit_buf), first_hits);
+    ctx.queue.submit(Some(second_commands));
+    assert_eq!(read_hits(&ctx, &second_hit_buf), second_hits);
+}
+
+#[gpu_test]
+static TLAS_MAPPED_INSTANCES_RECORDED_BEFORE_SUBMIT: GpuTestConfiguration =
+    GpuTestConfiguration::new()
+        .parameters(
+            TestParameters::default()
+                .test_features_limits()
+                .features(required_features()),
+        )
+        .run_sync(mapped_instances_recorded_before_submit);
+
+/// Mutates a package, snapshots it, mutates it further and restores the snapshot, checking
+/// that the TLAS built after restoring traces like the one built at the snapshot.
+fn snapshot_restore(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let instance = |x: f32, y: f32, custom_index: u32| {
+        Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                x, y, 0.0,
+            ))),
+            custom_index,
+            0xff,
+        ))
+    };
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 3,
+    });
+    let mut tlas_package = rt::TlasPackage::new(tlas, 3);
+    *tlas_package.get_mut_single(0).unwrap() = instance(-2.0, -2.0, 1);
+    trace_grid(&ctx, &tlas_package);
+
+    *tlas_package.get_mut_single(1).unwrap() = instance(2.0, 1.0, 2);
+    let snapshot = tlas_package.snapshot();
+    let snapshot_hits = trace_grid(&ctx, &tlas_package);
+
+    *tlas_package.get_mut_single(0).unwrap() = instance(0.0, 2.0, 4);
+    *tlas_package.get_mut_single(1).unwrap() = None;
+    *tlas_package.get_mut_single(2).unwrap() = instance(-2.0, 2.0, 3);
+    let mutated_hits = trace_grid(&ctx, &tlas_package);
+    assert_ne!(mutated_hits, snapshot_hits);
+
+    tlas_package.restore(&snapshot);
+    assert_eq!(tlas_package.get()[1].as_ref().unwrap().custom_index, 2);
+    assert!(tlas_package.get()[2].is_none());
+    assert_eq!(trace_grid(&ctx, &tlas_package), snapshot_hits);
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_SNAPSHOT_RESTORE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(snapshot_restore);
+
+/// Reads instances back through the shared accessor of a built package, checking that reading
+/// doesn't modify the package, so tracing it again gives the same hits.
+fn get_single(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let transform = AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+        Vec3::new(1.0, -1.0, 0.0),
+    ));
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![None, Some(rt::TlasInstance::new(&blas, transform, 7, 0xff))],
+    );
+    let hits = trace_grid(&ctx, &tlas_package);
+
+    assert!(tlas_package.get_single(0).is_none());
+    assert!(tlas_package.get_single(2).is_none());
+    let instance = tlas_package.get_single(1).unwrap();
+    assert_eq!(instance.transform, transform);
+    assert_eq!(instance.custom_index, 7);
+
+    assert_eq!(trace_grid(&ctx, &tlas_package), hits);
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_GET_SINGLE: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(get_single);
+
+/// Populates one package instance by instance and another one with bulk writes, then removes a
+/// row of the grid from both, checking that both trace identically each time.
+fn bulk_instances_match_single(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+
+    // A 3x3 grid of triangles, stored after two empty slots and followed by one.
+    const OFFSET: usize = 2;
+    let instances: Vec<_> = (0..9)
+        .map(|i| {
+            let (x, y) = ((i % 3) as f32 * 2.5 - 2.5, (i / 3) as f32 * 2.5 - 2.5);
+            Some(rt::TlasInstance::new(
+                &blas,
+                AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(
+                    Vec3::new(x, y, 0.0),
+                )),
+                i as u32 + 1,
+                0xff,
+            ))
+        })
+        .collect();
+    let capacity = OFFSET + instances.len() + 1;
+
+    let create_package = || {
+        rt::TlasPackage::new(
+            device.create_tlas(&rt::CreateTlasDescriptor {
+                label: None,
+                flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+                update_mode: rt::AccelerationStructureUpdateMode::Build,
+                max_instances: capacity as u32,
+            }),
+            capacity as u32,
+        )
+    };
+
+    let mut single_package = create_package();
+    for (i, instance) in instances.iter().enumerate() {
+        *single_package.get_mut_single(OFFSET + i).unwrap() = instance.clone();
+    }
+    let mut bulk_package = create_package();
+    bulk_package.set_instances(OFFSET, &instances);
+
+    let single_hits = trace_grid(&ctx, &single_package);
+    for custom_index in 1..=instances.len() as u32 {
+        assert!(
+            single_hits.iter().any(|hit| hit[0] == custom_index),
+            "no ray hit instance {custom_index}"
+        );
+    }
+    assert_eq!(trace_grid(&ctx, &bulk_package), single_hits);
+
+    // Remove the middle row after the packages were built, which has to mark it as modified
+    // again for the next build to drop it.
+    let middle_row = OFFSET + 3..OFFSET + 6;
+    for i in middle_row.clone() {
+        *single_package.get_mut_single(i).unwrap() = None;
+    }
+    bulk_package.fill(middle_row, None);
+
+    let single_hits = trace_grid(&ctx, &single_package);
+    for custom_index in 4..=6 {
+        assert!(
+            !single_hits.iter().any(|hit| hit[0] == custom_index),
+            "ray hit removed instance {custom_index}"
+        );
+    }
+    assert_eq!(trace_grid(&ctx, &bulk_package), single_hits);
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_BULK_INSTANCES: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(bulk_instances_match_single);
+
+/// Moves every instance of a built package through its mutable iterator, and checks that it
+/// traces like a package created with the moved instances.
+fn iter_mut_instances(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let instance = |x: f32, custom_index: u32| {
+        Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                x, 0.0, 0.0,
+            ))),
+            custom_index,
+            0xff,
+        ))
+    };
+    let create_tlas = || {
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 3,
+        })
+    };
+
+    let mut tlas_package = rt::TlasPackage::new_with_instances(
+        create_tlas(),
+        vec![instance(-2.5, 1), None, instance(2.5, 3)],
+    );
+    let hits = trace_grid(&ctx, &tlas_package);
+
+    assert_eq!(tlas_package.len(), 3);
+    assert_eq!(
+        tlas_package
+            .iter()
+            .map(|(i, instance)| (i, instance.as_ref().map(|instance| instance.custom_index)))
+            .collect::<Vec<_>>(),
+        [(0, Some(1)), (1, None), (2, Some(3))]
+    );
+
+    for (_, instance) in tlas_package.iter_mut() {
+        if let Some(instance) = instance {
+            instance.transform[3] += 1.0;
+        }
+    }
+    let moved_hits = trace_grid(&ctx, &tlas_package);
+    assert_ne!(moved_hits, hits);
+
+    let moved_package = rt::TlasPackage::new_with_instances(
+        create_tlas(),
+        vec![instance(-1.5, 1), None, instance(3.5, 3)],
+    );
+    assert_eq!(trace_grid(&ctx, &moved_package), moved_hits);
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_ITER_MUT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(iter_mut_instances);
+
+/// Builds the same instance through the builder, the positional constructor, a raw instance
+/// record and a packed instance, and checks that all of them agree, as well as the defaults of
+/// the builder.
+fn instance_builder(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let affine = Affine3A::from_rotation_translation(
+        Quat::from_rotation_z(30.0_f32.to_radians()),
+        Vec3::new(1.0, 2.0, 3.0),
+    );
+    let transform = AccelerationStructureInstance::affine_to_rows(&affine);
+    let flags = rt::AccelerationStructureInstanceFlags::FORCE_OPAQUE;
+
+    let built = rt::TlasInstance::builder(&blas)
+        .mask(0x0f)
+        .custom_index(0x12345)
+        .flags(flags)
+        .sbt_offset(3)
+        .transform(transform)
+        .build();
+
+    let mut positional = rt::TlasInstance::new(&blas, transform, 0x12345, 0x0f);
+    positional.flags = flags;
+    positional.shader_binding_table_record_offset = 3;
+
+    let raw = AccelerationStructureInstance::new(
+        &affine,
+        0x12345,
+        0x0f,
+        3,
+        flags.bits(),
+        blas.handle().unwrap(),
+    );
+    let mut raw_package = rt::TlasPackage::new(
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 1,
+        }),
+        1,
+    );
+    raw_package.write_instances_raw(0, bytemuck::bytes_of(&raw));
+    let packed = raw_package.get_single(0).unwrap();
+
+    let from_packed = rt::TlasInstance::from_packed(
+        &blas,
+        &rt::PackedInstance::new(
+            rt::transform_rows_from_columns(affine.to_cols_array_2d()),
+            0x12345,
+            0x0f,
+            3,
+            flags,
+            0,
+        ),
+    );
+
+    for instance in [&positional, packed, &from_packed] {
+        assert_eq!(built.transform, instance.transform);
+        assert_eq!(built.custom_index, instance.custom_index);
+        assert_eq!(built.mask, instance.mask);
+        assert_eq!(built.flags, instance.flags);
+        assert_eq!(
+            built.shader_binding_table_record_offset,
+            instance.shader_binding_table_record_offset
+        );
+    }
+
+    let default = rt::TlasInstance::builder(&blas).build();
+    assert_eq!(
+        default.transform,
+        AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY)
+    );
+    assert_eq!(default.custom_index, 0);
+    assert_eq!(default.mask, 0xff);
+    assert_eq!(
+        default.flags,
+        rt::AccelerationStructureInstanceFlags::empty()
+    );
+    assert_eq!(default.shader_binding_table_record_offset, 0);
+}
+
+#[gpu_test]
+static TLAS_INSTANCE_BUILDER: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(instance_builder);
+
+/// Builds a sparse package and checks that its build info only counts the filled slots.
+fn last_build_info(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 6,
+    });
+    let instance = |custom_index| {
+        Some(
+            rt::TlasInstance::builder(&blas)
+                .transform(AccelerationStructureInstance::affine_to_rows(
+                    &Affine3A::from_translation(Vec3::new(0.0, 0.0, custom_index as f32)),
+                ))
+                .custom_index(custom_index)
+                .build(),
+        )
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![None, instance(1), None, instance(3), instance(4), None],
+    );
+    assert_eq!(tlas_package.last_build_info(), rt::TlasBuildInfo::default());
+
+    // The instances are stacked along the rays, so the closest one is hit.
+    let hits = trace_grid(&ctx, &tlas_package);
+    assert!(hits.iter().any(|hit| hit[0] == 1));
+
+    assert_eq!(
+        tlas_package.last_build_info(),
+        rt::TlasBuildInfo {
+            instance_count: 3,
+            dirty_count: 6,
+        }
+    );
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_LAST_BUILD_INFO: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(last_build_info);
+
+/// Sets and clears instances through every way of writing a package, and checks that the
+/// tracked instance count agrees with counting the filled slots after each of them.
+fn active_instance_count(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let blas = build_triangle_blas(&ctx);
+    let instance = |custom_index| {
+        Some(
+            rt::TlasInstance::builder(&blas)
+                .transform(AccelerationStructureInstance::affine_to_rows(
+                    &Affine3A::from_translation(Vec3::new(0.0, 0.0, custom_index as f32)),
+                ))
+                .custom_index(custom_index)
+                .build(),
+        )
+    };
+    let raw = AccelerationStructureInstance::new(
+        &Affine3A::IDENTITY,
+        9,
+        0xff,
+        0,
+        0,
+        blas.handle().unwrap(),
+    );
+
+    let mut tlas_package = rt::TlasPackage::new(
+        device.create_tlas(&rt::CreateTlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+            max_instances: 8,
+        }),
+        8,
+    );
+    let check = |tlas_package: &rt::TlasPackage, expected: usize, step: &str| {
+        let filled = tlas_package
+            .iter()
+            .filter(|(_, instance)| instance.is_some())
+            .count();
+        assert_eq!(filled, expected, "{step}");
+        assert_eq!(tlas_package.active_instance_count(), expected, "{step}");
+    };
+    check(&tlas_package, 0, "new");
+
+    *tlas_package.get_mut_single(1).unwrap() = instance(1);
+    check(&tlas_package, 1, "get_mut_single");
+    *tlas_package.get_mut_single(1).unwrap() = instance(2);
+    check(&tlas_package, 1, "get_mut_single replacing an instance");
+
+    tlas_package.set_instances(2, &[instance(2), None, instance(4)]);
+    check(&tlas_package, 3, "set_instances");
+
+    tlas_package.get_mut_slice(0..4).unwrap().fill(None);
+    check(&tlas_package, 1, "get_mut_slice");
+
+    tlas_package.fill(4..8, instance(5));
+    check(&tlas_package, 4, "fill");
+    let snapshot = tlas_package.snapshot();
+
+    for (i, instance) in tlas_package.iter_mut() {
+        if i % 2 == 0 {
+            *instance = None;
+        }
+    }
+    check(&tlas_package, 2, "iter_mut");
+
+    tlas_package.write_instances_raw(0, bytemuck::bytes_of(&raw));
+    check(&tlas_package, 3, "write_instances_raw into an empty slot");
+    tlas_package.write_instances_raw(5, bytemuck::bytes_of(&raw));
+    check(&tlas_package, 3, "write_instances_raw over an instance");
+
+    tlas_package.restore(&snapshot);
+    check(&tlas_package, 4, "restore");
+
+    trace_grid(&ctx, &tlas_package);
+    assert_eq!(tlas_package.last_build_info().instance_count, 4);
+}
+
+#[gpu_test]
+static TLAS_PACKAGE_ACTIVE_INSTANCE_COUNT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(active_instance_count);
@@ -0,0 +1,444 @@
+use std::{iter, mem};
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::{Affine3A, Vec3};
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<u32>;
+
+const TRIANGLE_SEEN = 1u;
+const AABB_SEEN = 2u;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var flags = array<u32, 3>(RAY_FLAG_SKIP_AABBS, RAY_FLAG_SKIP_TRIANGLES, RAY_FLAG_NONE);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(flags[id.x], 0xFFu, 0.0, 100.0, vec3<f32>(0.25, 0.25, 0.0), vec3<f32>(0.0, 0.0, 1.0)));
+    // Never commit anything, so that every candidate is seen.
+    var seen = 0u;
+    while (rayQueryProceed(&rq)) {
+        if (rayQueryGetCandidateIntersectionType(&rq) == RAY_QUERY_INTERSECTION_TRIANGLE) {
+            seen |= TRIANGLE_SEEN;
+        } else {
+            seen |= AABB_SEEN;
+        }
+    }
+    out[id.x] = seen;
+}
+"#;
+
+const TRIANGLE_SEEN: u32 = 1;
+const AABB_SEEN: u32 = 2;
+
+/// Traces the same ray through a non-opaque triangle and an AABB with `SKIP_AABBS`,
+/// `SKIP_TRIANGLES` and no flags, and checks which kinds of candidates each query saw.
+fn skip_geometry_flags(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+    let aabb: [[f32; 3]; 2] = [[-1.0, -1.0, -0.5], [1.0, 1.0, 0.5]];
+    let aabb_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("AABB Buffer"),
+        contents: bytemuck::cast_slice(&aabb),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let triangle_size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+    };
+    let aabb_size = rt::BlasProceduralGeometrySizeDescriptor {
+        primitive_count: 1,
+        flags: rt::AccelerationStructureGeometryFlags::NO_DUPLICATE_ANY_HIT_INVOCATION,
+    };
+
+    let blas_desc = rt::CreateBlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+    };
+    let triangle_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![triangle_size.clone()],
+        },
+    );
+    let aabb_blas = device.create_blas(
+        &blas_desc,
+        rt::BlasGeometrySizeDescriptors::AABBs {
+            desc: vec![aabb_size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let instance = |blas, z| {
+        Some(rt::TlasInstance::new(
+            blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::from_translation(Vec3::new(
+                0.0, 0.0, z,
+            ))),
+            0,
+            0xff,
+        ))
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![instance(&triangle_blas, 2.0), instance(&aabb_blas, 5.0)],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        [
+            rt::BlasBuildEntry {
+                blas: &triangle_blas,
+                geometry: rt::BlasGeometries::TriangleGeometries(
+                    vec![rt::BlasTriangleGeometry {
+                        size: &triangle_size,
+                        vertex_buffer: &vertex_buf,
+                        first_vertex: 0,
+                        vertex_stride: None,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+            rt::BlasBuildEntry {
+                blas: &aabb_blas,
+                geometry: rt::BlasGeometries::ProceduralGeometries(
+                    vec![rt::BlasProceduralGeometry {
+                        size: &aabb_size,
+                        bounding_box_buffer: &aabb_buf,
+                        bounding_box_buffer_offset: 0,
+                        bounding_box_stride: mem::size_of::<[[f32; 3]; 2]>() as u64,
+                    }]
+                    .into(),
+                ),
+                mode: None,
+            },
+        ]
+        .iter(),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Out"),
+        size: 3 * mem::size_of::<u32>() as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(3, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &out_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let out: &[u32] = bytemuck::cast_slice(&result);
+        assert_eq!(out, [TRIANGLE_SEEN, AABB_SEEN, TRIANGLE_SEEN | AABB_SEEN]);
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_SKIP_GEOMETRY_FLAGS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(skip_geometry_flags);
+
+const FACING_SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read_write> out: array<vec2<u32>>;
+
+// No culling, `CULL_BACK_FACING` and `CULL_FRONT_FACING`.
+const FLAGS = array<u32, 3>(0u, 0x10u, 0x20u);
+
+// Traces a ray at quad `id.z` from its side `id.x` with flags `id.y`, writing the custom index
+// of the hit plus one, or zero for a miss, and whether the hit was front facing.
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var flags = FLAGS;
+    let side = f32(id.x) * 2.0 - 1.0;
+    let origin = vec3<f32>(f32(id.z) * 10.0 + 0.25, -0.5, 5.0 + side * 5.0);
+    var rq: ray_query;
+    rayQueryInitialize(&rq, acc_struct, RayDesc(flags[id.y], 0xFFu, 0.0, 100.0, origin, vec3<f32>(0.0, 0.0, -side)));
+    while (rayQueryProceed(&rq)) {}
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var result = vec2<u32>(0u);
+    if (intersection.kind != RAY_QUERY_INTERSECTION_NONE) {
+        result = vec2<u32>(intersection.instance_custom_index + 1u, u32(intersection.front_face));
+    }
+    out[(id.z * 3u + id.y) * 2u + id.x] = result;
+}
+"#;
+
+const FACING_FLAG_COUNT: usize = 3;
+const NO_CULLING: usize = 0;
+const CULL_BACK_FACING: usize = 1;
+const CULL_FRONT_FACING: usize = 2;
+
+/// Traces rays at both sides of a quad with every combination of the culling ray flags, once
+/// for an instance that can be culled and once for one with facing culling disabled, and
+/// checks which sides are hit and that `front_face` agrees with the culling.
+fn front_face_culling(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 6] = [
+        [-1.0, -1.0, 0.0],
+        [1.0, -1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [-1.0, -1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [-1.0, 1.0, 0.0],
+    ];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: vertices.len() as u32,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 2,
+    });
+    let instance = |index: u32, flags| {
+        Some(
+            rt::TlasInstance::builder(&blas)
+                .transform(AccelerationStructureInstance::affine_to_rows(
+                    &Affine3A::from_translation(Vec3::new(index as f32 * 10.0, 0.0, 5.0)),
+                ))
+                .custom_index(index)
+                .flags(flags)
+                .build(),
+        )
+    };
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![
+            instance(0, rt::AccelerationStructureInstanceFlags::empty()),
+            instance(
+                1,
+                rt::AccelerationStructureInstanceFlags::TRIANGLE_FACING_CULL_DISABLE,
+            ),
+        ],
+    );
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(FACING_SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let out_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Out"),
+        size: (2 * FACING_FLAG_COUNT * 2 * mem::size_of::<[u32; 2]>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: out_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(2, FACING_FLAG_COUNT as u32, 2);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    wgpu::util::DownloadBuffer::read_buffer(device, &ctx.queue, &out_buf.slice(..), |result| {
+        let result = result.unwrap();
+        let out: &[[u32; 2]] = bytemuck::cast_slice(&result);
+        // Whether the ray at `quad` from `side` with `flags` hit a front face, `None` if it missed.
+        let front_face = |quad: usize, flags: usize, side: usize| {
+            let [hit, front_face] = out[(quad * FACING_FLAG_COUNT + flags) * 2 + side];
+            if hit == 0 {
+                return None;
+            }
+            assert_eq!(
+                hit as usize,
+                quad + 1,
+                "quad {quad}, flags {flags}, side {side}"
+            );
+            Some(front_face != 0)
+        };
+
+        // Without culling, one side of each quad is front facing and the other one isn't.
+        let unculled = [0, 1].map(|quad| [0, 1].map(|side| front_face(quad, NO_CULLING, side)));
+        assert_eq!(unculled[0], unculled[1]);
+        let front_side = match unculled[0] {
+            [Some(true), Some(false)] => 0,
+            [Some(false), Some(true)] => 1,
+            other => panic!("Unculled quad hits {other:?}"),
+        };
+        let back_side = 1 - front_side;
+
+        // Only front faces are left when culling back faces, and the other way around.
+        assert_eq!(front_face(0, CULL_BACK_FACING, front_side), Some(true));
+        assert_eq!(front_face(0, CULL_BACK_FACING, back_side), None);
+        assert_eq!(front_face(0, CULL_FRONT_FACING, front_side), None);
+        assert_eq!(front_face(0, CULL_FRONT_FACING, back_side), Some(false));
+
+        // Instances with facing culling disabled are hit from both sides regardless.
+        for flags in [CULL_BACK_FACING, CULL_FRONT_FACING] {
+            assert_eq!(
+                [0, 1].map(|side| front_face(1, flags, side)),
+                unculled[1],
+                "flags {flags}"
+            );
+        }
+    });
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+#[gpu_test]
+static RAY_QUERY_FRONT_FACE_CULLING: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(front_face_culling);
@@ -0,0 +1,233 @@
+use std::iter;
+
+use wgpu_test::{gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::Affine3A;
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var output: texture_storage_2d<FORMAT, write>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(f32(id.x) - 1.5, -0.5, -3.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    var color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if (intersection.kind != 0u) {
+        color = vec4<f32>(intersection.t, 16.0, 1.5, 1.0);
+    }
+    textureStore(output, id.xy, color);
+}
+"#;
+
+const WIDTH: u32 = 4;
+
+/// Traces a row of rays, of which the middle two hit a triangle, writing HDR colors into a
+/// storage texture of `format`, and returns the texels of the row.
+fn trace_to_storage_texture(ctx: &TestingContext, format: wgpu::TextureFormat) -> Vec<u8> {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 3] = [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size.clone()],
+        },
+    );
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let extent = wgpu::Extent3d {
+        width: WIDTH,
+        height: 1,
+        depth_or_array_layers: 1,
+    };
+    let output = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Output"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let storage_format = match format {
+        wgpu::TextureFormat::Rgba16Float => "rgba16float",
+        wgpu::TextureFormat::Rgba32Float => "rgba32float",
+        _ => unreachable!(),
+    };
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.replace("FORMAT", storage_format).into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    &output.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                vec![rt::BlasTriangleGeometry {
+                    size: &size,
+                    vertex_buffer: &vertex_buf,
+                    first_vertex: 0,
+                    vertex_stride: None,
+                    vertex_offset: 0,
+                    vertex_buffer_offset: 0,
+                    index_buffer: None,
+                    index_buffer_offset: None,
+                    transform_buffer: None,
+                    transform_buffer_offset: None,
+                }]
+                .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(WIDTH, 1, 1);
+    }
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture: &output,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+        },
+        extent,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+    let texel_size = format.block_copy_size(None).unwrap() as usize;
+    let texels = slice.get_mapped_range()[..WIDTH as usize * texel_size].to_vec();
+    texels
+}
+
+/// Writes HDR values larger than 1 from a ray query into `Rgba16Float` and `Rgba32Float`
+/// storage textures, and checks that they are read back without being clipped.
+fn hdr_storage_output(ctx: TestingContext) {
+    // 3.0 is the distance of the hit.
+    let hit = [3.0, 16.0, 1.5, 1.0];
+    let miss = [0.0, 0.0, 0.0, 1.0];
+    let expected: Vec<f32> = [miss, hit, hit, miss].into_iter().flatten().collect();
+    // The same values as half precision floats.
+    let half_hit = [0x4200, 0x4C00, 0x3E00, 0x3C00];
+    let half_miss = [0x0000, 0x0000, 0x0000, 0x3C00];
+    let half_expected: Vec<u16> = [half_miss, half_hit, half_hit, half_miss]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let half_texels: Vec<u16> = trace_to_storage_texture(&ctx, wgpu::TextureFormat::Rgba16Float)
+        .chunks_exact(2)
+        .map(|bytes| u16::from_ne_bytes(bytes.try_into().unwrap()))
+        .collect();
+    assert_eq!(half_texels, half_expected, "Rgba16Float");
+
+    let float_texels: Vec<f32> = trace_to_storage_texture(&ctx, wgpu::TextureFormat::Rgba32Float)
+        .chunks_exact(4)
+        .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+        .collect();
+    assert_eq!(float_texels, expected, "Rgba32Float");
+}
+
+#[gpu_test]
+static RAY_QUERY_HDR_STORAGE_OUTPUT: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(hdr_storage_output);
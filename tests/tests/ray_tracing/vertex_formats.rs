@@ -0,0 +1,582 @@
+use std::{iter, mem};
+
+use wgpu_test::{fail, gpu_test, GpuTestConfiguration, TestParameters, TestingContext};
+
+use wgpu::ray_tracing::{self as rt, traits::*};
+use wgpu::util::DeviceExt;
+
+use glam::Affine3A;
+
+use super::{mesh_gen::AccelerationStructureInstance, required_features};
+
+const SHADER: &str = r#"
+@group(0) @binding(0)
+var acc_struct: acceleration_structure;
+
+@group(0) @binding(1)
+var<storage, read> origins: array<vec2<f32>>;
+
+@group(0) @binding(2)
+var<storage, read_write> hits: array<f32>;
+
+@compute @workgroup_size(1)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var rq: ray_query;
+    let origin = vec3<f32>(origins[id.x], -1.0);
+    rayQueryInitialize(&rq, acc_struct, RayDesc(0u, 0xFFu, 0.0, 10.0, origin, vec3<f32>(0.0, 0.0, 1.0)));
+    rayQueryProceed(&rq);
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind != 0u) {
+        hits[id.x] = intersection.t;
+    } else {
+        hits[id.x] = -1.0;
+    }
+}
+"#;
+
+/// Where the positions of a geometry are in its vertex buffer, see [`rt::BlasTriangleGeometry`].
+#[derive(Clone, Copy, Default)]
+struct VertexLayout {
+    first_vertex: u32,
+    vertex_stride: Option<u64>,
+    vertex_offset: u64,
+    vertex_buffer_offset: u64,
+}
+
+/// Builds a BLAS from `geometries`, all reading from `vertex_buf`, and checks the `t` of the hit
+/// of a ray shot along z from each of `origins`, -1 for a miss.
+fn trace_positions(
+    ctx: &TestingContext,
+    geometries: &[(rt::BlasTriangleGeometrySizeDescriptor, VertexLayout)],
+    vertex_buf: &wgpu::Buffer,
+    origins: &[[f32; 2]],
+    expected: &[f32],
+) {
+    let device = &ctx.device;
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: geometries
+                .iter()
+                .map(|(size_desc, _)| size_desc.clone())
+                .collect(),
+        },
+    );
+
+    let tlas = device.create_tlas(&rt::CreateTlasDescriptor {
+        label: None,
+        flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+        update_mode: rt::AccelerationStructureUpdateMode::Build,
+        max_instances: 1,
+    });
+
+    let tlas_package = rt::TlasPackage::new_with_instances(
+        tlas,
+        vec![Some(rt::TlasInstance::new(
+            &blas,
+            AccelerationStructureInstance::affine_to_rows(&Affine3A::IDENTITY),
+            0,
+            0xff,
+        ))],
+    );
+
+    let origin_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Ray Origins"),
+        contents: bytemuck::cast_slice(origins),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    let hit_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Hits"),
+        size: mem::size_of_val(expected) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tlas_package.as_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: origin_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: hit_buf.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.build_acceleration_structures(
+        iter::once(&rt::BlasBuildEntry {
+            blas: &blas,
+            geometry: rt::BlasGeometries::TriangleGeometries(
+                geometries
+                    .iter()
+                    .map(|(size_desc, layout)| rt::BlasTriangleGeometry {
+                        size: size_desc,
+                        vertex_buffer: vertex_buf,
+                        first_vertex: layout.first_vertex,
+                        vertex_stride: layout.vertex_stride,
+                        vertex_offset: layout.vertex_offset,
+                        vertex_buffer_offset: layout.vertex_buffer_offset,
+                        index_buffer: None,
+                        index_buffer_offset: None,
+                        transform_buffer: None,
+                        transform_buffer_offset: None,
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            mode: None,
+        }),
+        iter::once(&tlas_package),
+    );
+
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(origins.len() as u32, 1, 1);
+    }
+
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let origins = origins.to_vec();
+    let expected = expected.to_vec();
+    wgpu::util::DownloadBuffer::read_buffer(
+        device,
+        &ctx.queue,
+        &hit_buf.slice(..),
+        move |result| {
+            let result = result.unwrap();
+            let hits: &[f32] = bytemuck::cast_slice(&result);
+            for (i, (&hit, &expected)) in hits.iter().zip(expected.iter()).enumerate() {
+                assert!(
+                    (hit - expected).abs() < 1e-3,
+                    "ray {i} from {:?}: got t = {hit}, expected {expected}",
+                    origins[i]
+                );
+            }
+        },
+    );
+
+    device.poll(wgpu::Maintain::Wait);
+}
+
+/// Builds a BLAS from `Unorm16x4` positions and checks that rays hit the triangle at its
+/// dequantized position.
+fn unorm16x4_positions(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let properties = ctx.adapter.ray_tracing_build_properties().unwrap();
+    if !properties.supports_vertex_format(wgpu::VertexFormat::Unorm16x4) {
+        return;
+    }
+
+    // Dequantizes to (0, 0, 0.5), (1, 0, 0.5), (0, 1, 0.5), the fourth component is ignored.
+    let half = u16::MAX / 2 + 1;
+    let vertices: [[u16; 4]; 3] = [
+        [0, 0, half, 0],
+        [u16::MAX, 0, half, 0],
+        [0, u16::MAX, half, 0],
+    ];
+    let expected_t = 1.0 + half as f32 / u16::MAX as f32;
+
+    let origins: [[f32; 2]; 4] = [[0.25, 0.25], [0.1, 0.8], [0.75, 0.75], [1.5, 0.1]];
+    let expected: [f32; 4] = [expected_t, expected_t, -1.0, -1.0];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Unorm16x4,
+        vertex_count: vertices.len() as u32,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    trace_positions(
+        &ctx,
+        &[(
+            size_desc,
+            VertexLayout {
+                vertex_stride: Some(mem::size_of::<[u16; 4]>() as u64),
+                ..Default::default()
+            },
+        )],
+        &vertex_buf,
+        &origins,
+        &expected,
+    );
+}
+
+#[gpu_test]
+static BLAS_UNORM16X4_POSITIONS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(unorm16x4_positions);
+
+/// Builds a BLAS from tightly packed `Float32x3` positions without giving a vertex stride, starting
+/// past the first vertex so a wrong default stride would move the triangle, and checks that
+/// strides that aren't a multiple of the component size or are smaller than a vertex are rejected.
+fn tightly_packed_positions(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    let vertices: [[f32; 3]; 4] = [
+        [9.0, 9.0, 9.0],
+        [0.0, 0.0, 0.5],
+        [1.0, 0.0, 0.5],
+        [0.0, 1.0, 0.5],
+    ];
+
+    let origins: [[f32; 2]; 4] = [[0.25, 0.25], [0.1, 0.8], [0.75, 0.75], [1.5, 0.1]];
+    let expected: [f32; 4] = [1.5, 1.5, -1.0, -1.0];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    trace_positions(
+        &ctx,
+        &[(
+            size_desc.clone(),
+            VertexLayout {
+                first_vertex: 1,
+                ..Default::default()
+            },
+        )],
+        &vertex_buf,
+        &origins,
+        &expected,
+    );
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    for vertex_stride in [6, 8] {
+        fail(
+            device,
+            || {
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                encoder.build_acceleration_structures(
+                    iter::once(&rt::BlasBuildEntry {
+                        blas: &blas,
+                        geometry: rt::BlasGeometries::TriangleGeometries(
+                            vec![rt::BlasTriangleGeometry {
+                                size: &size_desc,
+                                vertex_buffer: &vertex_buf,
+                                first_vertex: 0,
+                                vertex_stride: Some(vertex_stride),
+                                vertex_offset: 0,
+                                vertex_buffer_offset: 0,
+                                index_buffer: None,
+                                index_buffer_offset: None,
+                                transform_buffer: None,
+                                transform_buffer_offset: None,
+                            }]
+                            .into(),
+                        ),
+                        mode: None,
+                    }),
+                    iter::empty(),
+                );
+                encoder.finish()
+            },
+            Some("vertex stride"),
+        );
+    }
+}
+
+#[gpu_test]
+static BLAS_TIGHTLY_PACKED_POSITIONS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(tightly_packed_positions);
+
+/// Builds a BLAS from interleaved vertices whose position follows their normal, starting past the
+/// first vertex, and checks that rays hit the triangle at the positions and that offsets which
+/// don't leave room for the position within the stride or aren't aligned to its components are
+/// rejected.
+fn interleaved_positions(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Vertex {
+        normal: [f32; 3],
+        position: [f32; 3],
+    }
+    let vertex = |position| Vertex {
+        normal: [0.0, 0.0, -1.0],
+        position,
+    };
+    // Treating the normals as positions would give a degenerate triangle that is never hit.
+    let vertices = [
+        vertex([9.0, 9.0, 9.0]),
+        vertex([0.0, 0.0, 0.5]),
+        vertex([1.0, 0.0, 0.5]),
+        vertex([0.0, 1.0, 0.5]),
+    ];
+
+    let origins: [[f32; 2]; 4] = [[0.25, 0.25], [0.1, 0.8], [0.75, 0.75], [1.5, 0.1]];
+    let expected: [f32; 4] = [1.5, 1.5, -1.0, -1.0];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+    let vertex_stride = mem::size_of::<Vertex>() as u64;
+
+    trace_positions(
+        &ctx,
+        &[(
+            size_desc.clone(),
+            VertexLayout {
+                first_vertex: 1,
+                vertex_stride: Some(vertex_stride),
+                // The position follows the normal.
+                vertex_offset: mem::size_of::<[f32; 3]>() as u64,
+                ..Default::default()
+            },
+        )],
+        &vertex_buf,
+        &origins,
+        &expected,
+    );
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    for vertex_offset in [16, 2] {
+        fail(
+            device,
+            || {
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                encoder.build_acceleration_structures(
+                    iter::once(&rt::BlasBuildEntry {
+                        blas: &blas,
+                        geometry: rt::BlasGeometries::TriangleGeometries(
+                            vec![rt::BlasTriangleGeometry {
+                                size: &size_desc,
+                                vertex_buffer: &vertex_buf,
+                                first_vertex: 0,
+                                vertex_stride: Some(vertex_stride),
+                                vertex_offset,
+                                vertex_buffer_offset: 0,
+                                index_buffer: None,
+                                index_buffer_offset: None,
+                                transform_buffer: None,
+                                transform_buffer_offset: None,
+                            }]
+                            .into(),
+                        ),
+                        mode: None,
+                    }),
+                    iter::empty(),
+                );
+                encoder.finish()
+            },
+            Some("vertex offset"),
+        );
+    }
+}
+
+#[gpu_test]
+static BLAS_INTERLEAVED_POSITIONS: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(interleaved_positions);
+
+/// Builds a BLAS from two geometries packed into one vertex buffer at byte offsets that aren't
+/// multiples of the vertex size, and checks that rays hit each at its own positions and that
+/// offsets which aren't aligned to the vertex components or overrun the buffer are rejected.
+fn shared_vertex_buffer(ctx: TestingContext) {
+    let device = &ctx.device;
+
+    // A header word, the triangle at z = 0.5, two words of other data and the triangle at z = 1,
+    // which is moved to the right.
+    let near: [[f32; 3]; 3] = [[0.0, 0.0, 0.5], [1.0, 0.0, 0.5], [0.0, 1.0, 0.5]];
+    let far: [[f32; 3]; 3] = [[2.0, 0.0, 1.0], [3.0, 0.0, 1.0], [2.0, 1.0, 1.0]];
+    let contents: Vec<f32> = [
+        &[7.0][..],
+        bytemuck::cast_slice(&near),
+        &[7.0, 7.0],
+        bytemuck::cast_slice(&far),
+    ]
+    .concat();
+    let near_offset = mem::size_of::<f32>() as u64;
+    let far_offset =
+        near_offset + mem::size_of_val(&near) as u64 + 2 * mem::size_of::<f32>() as u64;
+
+    let origins: [[f32; 2]; 4] = [[0.25, 0.25], [2.25, 0.25], [2.1, 0.8], [1.5, 0.1]];
+    let expected: [f32; 4] = [1.5, 2.0, 2.0, -1.0];
+
+    let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&contents),
+        usage: wgpu::BufferUsages::BLAS_INPUT,
+    });
+
+    let size_desc = rt::BlasTriangleGeometrySizeDescriptor {
+        vertex_format: wgpu::VertexFormat::Float32x3,
+        vertex_count: 3,
+        index_format: None,
+        index_count: None,
+        flags: rt::AccelerationStructureGeometryFlags::OPAQUE,
+    };
+
+    trace_positions(
+        &ctx,
+        &[near_offset, far_offset].map(|vertex_buffer_offset| {
+            (
+                size_desc.clone(),
+                VertexLayout {
+                    vertex_buffer_offset,
+                    ..Default::default()
+                },
+            )
+        }),
+        &vertex_buf,
+        &origins,
+        &expected,
+    );
+
+    let blas = device.create_blas(
+        &rt::CreateBlasDescriptor {
+            label: None,
+            flags: rt::AccelerationStructureFlags::PREFER_FAST_TRACE,
+            update_mode: rt::AccelerationStructureUpdateMode::Build,
+        },
+        rt::BlasGeometrySizeDescriptors::Triangles {
+            desc: vec![size_desc.clone()],
+        },
+    );
+    for (vertex_buffer_offset, message) in [
+        (
+            near_offset + 2,
+            "isn't a multiple of the size of the components",
+        ),
+        (far_offset + 4, "size is insufficient"),
+    ] {
+        fail(
+            device,
+            || {
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                encoder.build_acceleration_structures(
+                    iter::once(&rt::BlasBuildEntry {
+                        blas: &blas,
+                        geometry: rt::BlasGeometries::TriangleGeometries(
+                            vec![rt::BlasTriangleGeometry {
+                                size: &size_desc,
+                                vertex_buffer: &vertex_buf,
+                                first_vertex: 0,
+                                vertex_stride: None,
+                                vertex_offset: 0,
+                                vertex_buffer_offset,
+                                index_buffer: None,
+                                index_buffer_offset: None,
+                                transform_buffer: None,
+                                transform_buffer_offset: None,
+                            }]
+                            .into(),
+                        ),
+                        mode: None,
+                    }),
+                    iter::empty(),
+                );
+                encoder.finish()
+            },
+            Some(message),
+        );
+    }
+}
+
+#[gpu_test]
+static BLAS_SHARED_VERTEX_BUFFER: GpuTestConfiguration = GpuTestConfiguration::new()
+    .parameters(
+        TestParameters::default()
+            .test_features_limits()
+            .features(required_features()),
+    )
+    .run_sync(shared_vertex_buffer);
@@ -6,8 +6,8 @@ use crate::{
     init_tracker::{BufferInitTrackerAction, TextureInitTrackerAction},
     pipeline::{ComputePipeline, RenderPipeline},
     resource::{
-        Buffer, DestroyedResourceError, Labeled, MissingBufferUsageError, MissingTextureUsageError,
-        ResourceErrorIdent, Sampler, TextureView, TrackingData,
+        AccelerationStructure, Buffer, DestroyedResourceError, Labeled, MissingBufferUsageError,
+        MissingTextureUsageError, ResourceErrorIdent, Sampler, TextureView, TrackingData,
     },
     resource_log,
     snatch::{SnatchGuard, Snatchable},
@@ -937,6 +937,9 @@ impl BindGroup {
         for texture in &self.used_texture_ranges {
             texture.texture.try_raw(guard)?;
         }
+        for tlas in &self.used.acceleration_structures {
+            tlas.try_raw(guard)?;
+        }
 
         self.raw
             .get(guard)
@@ -33,6 +33,9 @@ mod compat {
             inner: MultiError,
         },
         Missing,
+        UnboundAccelerationStructure {
+            binding: u32,
+        },
     }
 
     #[derive(Debug, Clone)]
@@ -68,12 +71,35 @@ mod compat {
             self.expected.is_none() || !self.is_valid()
         }
 
+        /// The first acceleration structure binding of the expected layout
+        /// that has no acceleration structure in the assigned layout.
+        fn unbound_acceleration_structure(&self) -> Option<u32> {
+            let expected_bgl = self.expected.as_ref()?;
+            expected_bgl
+                .entries
+                .iter()
+                .filter(|(_, entry)| matches!(entry.ty, BindingType::AccelerationStructure { .. }))
+                .map(|(&binding, _)| binding)
+                .find(|&binding| {
+                    let assigned_ty = self
+                        .assigned
+                        .as_ref()
+                        .and_then(|assigned_bgl| assigned_bgl.entries.get(binding))
+                        .map(|entry| entry.ty);
+                    !matches!(assigned_ty, Some(BindingType::AccelerationStructure { .. }))
+                })
+        }
+
         fn check(&self) -> Result<(), Error> {
             if let Some(expected_bgl) = self.expected.as_ref() {
                 if let Some(assigned_bgl) = self.assigned.as_ref() {
                     if expected_bgl.is_equal(assigned_bgl) {
                         Ok(())
                     } else {
+                        if let Some(binding) = self.unbound_acceleration_structure() {
+                            return Err(Error::UnboundAccelerationStructure { binding });
+                        }
+
                         #[derive(Clone, Debug, Error)]
                         #[error(
                             "Exclusive pipelines don't match: expected {expected}, got {assigned}"
@@ -180,6 +206,8 @@ mod compat {
                             inner: MultiError::new(errors.drain(..)).unwrap(),
                         })
                     }
+                } else if let Some(binding) = self.unbound_acceleration_structure() {
+                    Err(Error::UnboundAccelerationStructure { binding })
                 } else {
                     Err(Error::Missing)
                 }
@@ -273,6 +301,12 @@ pub enum BinderError {
         #[source]
         inner: crate::error::MultiError,
     },
+    #[error("Ray query references unbound acceleration structure at group {group} binding {binding} of {pipeline}")]
+    UnboundAccelerationStructure {
+        group: usize,
+        binding: u32,
+        pipeline: ResourceErrorIdent,
+    },
 }
 
 #[derive(Debug)]
@@ -428,6 +462,13 @@ impl Binder {
                     index,
                     pipeline: pipeline.error_ident(),
                 },
+                compat::Error::UnboundAccelerationStructure { binding } => {
+                    BinderError::UnboundAccelerationStructure {
+                        group: index,
+                        binding,
+                        pipeline: pipeline.error_ident(),
+                    }
+                }
             })
         })
     }
@@ -21,6 +21,7 @@ pub use self::{
     render::*, render_command::RenderCommand, transfer::*,
 };
 pub(crate) use allocator::CommandAllocator;
+pub(crate) use ray_tracing::{aligned_scratch_size, scratch_buffer_size};
 
 pub(crate) use timestamp_writes::ArcPassTimestampWrites;
 pub use timestamp_writes::PassTimestampWrites;
@@ -33,7 +34,7 @@ use crate::snatch::SnatchGuard;
 
 use crate::init_tracker::BufferInitTrackerAction;
 use crate::ray_tracing::{BlasAction, TlasAction};
-use crate::resource::Labeled;
+use crate::resource::{Labeled, TlasInstanceBufferUse};
 use crate::track::{DeviceTracker, Tracker, UsageScope};
 use crate::LabelHelpers;
 use crate::{api_log, global::Global, id, resource_log, Label};
@@ -247,6 +248,7 @@ pub(crate) struct BakedCommands {
     texture_memory_actions: CommandBufferTextureMemoryActions,
     blas_actions: Vec<BlasAction>,
     tlas_actions: Vec<TlasAction>,
+    pub(crate) tlas_instance_buffer_uses: Vec<TlasInstanceBufferUse>,
 }
 
 /// The mutable state of a [`CommandBuffer`].
@@ -275,6 +277,8 @@ pub struct CommandBufferMutable {
     pub(crate) pending_query_resets: QueryResetMap,
     blas_actions: Vec<BlasAction>,
     tlas_actions: Vec<TlasAction>,
+    /// The TLAS instance buffers read by the builds recorded so far.
+    tlas_instance_buffer_uses: Vec<TlasInstanceBufferUse>,
     #[cfg(feature = "trace")]
     pub(crate) commands: Option<Vec<TraceCommand>>,
 }
@@ -365,6 +369,7 @@ impl CommandBuffer {
                     pending_query_resets: QueryResetMap::new(),
                     blas_actions: Default::default(),
                     tlas_actions: Default::default(),
+                    tlas_instance_buffer_uses: Default::default(),
                     #[cfg(feature = "trace")]
                     commands: if device.trace.lock().is_some() {
                         Some(Vec::new())
@@ -523,6 +528,7 @@ impl CommandBuffer {
             texture_memory_actions: data.texture_memory_actions,
             blas_actions: data.blas_actions,
             tlas_actions: data.tlas_actions,
+            tlas_instance_buffer_uses: data.tlas_instance_buffer_uses,
         }
     }
 
@@ -1,13 +1,15 @@
 use crate::{
-    device::queue::TempResource,
+    device::{queue::TempResource, Device},
     global::Global,
-    id::CommandEncoderId,
+    id::{BlasId, BufferId, CommandEncoderId, TlasId},
     init_tracker::MemoryInitKind,
     lock::RwLockReadGuard,
     ray_tracing::{
-        tlas_instance_into_bytes, BlasAction, BlasBuildEntry, BlasGeometries,
-        BuildAccelerationStructureError, TlasAction, TlasBuildEntry, TlasPackage,
-        ValidateBlasActionsError, ValidateTlasActionsError,
+        tlas_instance_into_bytes, vertex_component_size, BlasAction, BlasBuildEntry,
+        BlasGeometries, BlasGeometryCounts, BuildAccelerationStructureError, CompactBlasError,
+        CopyAccelerationStructureError, ResolveCompactedSizeError, SubmissionBuildSummary,
+        TlasAction, TlasBuildEntry, TlasPackage, ValidateBlasActionsError,
+        ValidateTlasActionsError,
     },
     resource::{Blas, Tlas},
     FastHashSet,
@@ -18,7 +20,8 @@ use wgt::{math::align_to, BufferAddress, BufferUsages};
 use super::{BakedCommands, CommandBufferMutable, CommandEncoderError};
 use crate::ray_tracing::BlasGeometry;
 use crate::resource::{
-    AccelerationStructure, Buffer, Labeled, ScratchBuffer, StagingBuffer, Trackable,
+    AccelerationStructure, Buffer, Labeled, ParentDevice, ResourceErrorIdent, ScratchBuffer,
+    StagingBuffer, Trackable,
 };
 use crate::snatch::SnatchGuard;
 use crate::storage::Storage;
@@ -26,7 +29,7 @@ use crate::track::PendingTransition;
 use hal::BufferUses;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::{cmp::max, num::NonZeroU64, ops::Range};
+use std::{cmp::max, mem, num::NonZeroU64, ops::Range};
 
 type BufferStorage<'a> = Vec<(
     Arc<Buffer>,
@@ -34,17 +37,122 @@ type BufferStorage<'a> = Vec<(
     Option<(Arc<Buffer>, Option<PendingTransition<BufferUses>>)>,
     Option<(Arc<Buffer>, Option<PendingTransition<BufferUses>>)>,
     BlasGeometry<'a>,
-    Option<Arc<Blas>>,
+    Option<(Arc<Blas>, wgt::AccelerationStructureBuildMode)>,
 )>;
 
 type BlasStorage<'a> = Vec<(
     Arc<Blas>,
     hal::AccelerationStructureEntries<'a, dyn hal::DynBuffer>,
     u64,
+    wgt::AccelerationStructureBuildMode,
 )>;
 
 // This should be queried from the device, maybe the the hal api should pre aline it, since I am unsure how else we can idiomatically get this value.
-const SCRATCH_BUFFER_ALIGNMENT: u32 = 256;
+const SCRATCH_BUFFER_ALIGNMENT: BufferAddress = wgt::ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT;
+
+/// Size of the scratch buffer needed to build acceleration structures with the given sizes,
+/// each in the given mode, laid out one after another.
+pub(crate) fn scratch_buffer_size<'a>(
+    builds: impl Iterator<
+        Item = (
+            &'a hal::AccelerationStructureBuildSizes,
+            wgt::AccelerationStructureBuildMode,
+        ),
+    >,
+) -> BufferAddress {
+    builds
+        .map(|(size_info, mode)| aligned_scratch_size(size_info, mode))
+        .sum()
+}
+
+/// Records a copy of `src` into `dst`, ordered against earlier builds and uses of both
+/// like a build (see `build_blas`).
+fn copy_acceleration_structure(
+    cmd_buf_raw: &mut dyn hal::DynCommandEncoder,
+    src: &dyn hal::DynAccelerationStructure,
+    dst: &dyn hal::DynAccelerationStructure,
+    copy: wgt::AccelerationStructureCopy,
+) {
+    let copy = match copy {
+        wgt::AccelerationStructureCopy::Clone => hal::AccelerationStructureCopy::Clone,
+        wgt::AccelerationStructureCopy::Compact => hal::AccelerationStructureCopy::Compact,
+    };
+    unsafe {
+        cmd_buf_raw.place_acceleration_structure_barrier(hal::AccelerationStructureBarrier {
+            usage: hal::AccelerationStructureUses::BUILD_INPUT
+                | hal::AccelerationStructureUses::BUILD_OUTPUT
+                ..hal::AccelerationStructureUses::BUILD_INPUT
+                    | hal::AccelerationStructureUses::BUILD_OUTPUT,
+        });
+        cmd_buf_raw.copy_acceleration_structure(src, dst, copy);
+        cmd_buf_raw.place_acceleration_structure_barrier(hal::AccelerationStructureBarrier {
+            usage: hal::AccelerationStructureUses::BUILD_OUTPUT
+                ..hal::AccelerationStructureUses::BUILD_INPUT
+                    | hal::AccelerationStructureUses::SHADER_INPUT,
+        });
+    }
+}
+
+/// Resolves the mode of a build of an acceleration structure with `flags`, rejecting
+/// updates of acceleration structures that can't be updated.
+fn resolve_build_mode(
+    mode: Option<wgt::AccelerationStructureBuildMode>,
+    flags: wgt::AccelerationStructureFlags,
+    ident: impl FnOnce() -> ResourceErrorIdent,
+) -> Result<wgt::AccelerationStructureBuildMode, BuildAccelerationStructureError> {
+    let mode = mode.unwrap_or(wgt::AccelerationStructureBuildMode::Build);
+    if mode == wgt::AccelerationStructureBuildMode::Update
+        && !flags.contains(wgt::AccelerationStructureFlags::ALLOW_UPDATE)
+    {
+        return Err(BuildAccelerationStructureError::UpdateNotAllowed(ident()));
+    }
+    Ok(mode)
+}
+
+/// Resolves the mode of a build of `blas` with `counts` that doesn't choose one.
+///
+/// A BLAS created with [`wgt::AccelerationStructureUpdateMode::PreferUpdate`] is updated in
+/// place if its last submitted build has the same geometry counts, and built from scratch
+/// otherwise. Builds of it that are recorded but not yet submitted aren't taken into account,
+/// and a build with different counts submitted before this one fails the update at submission,
+/// like an explicit update would.
+fn preferred_blas_build_mode(
+    blas: &Blas,
+    counts: &[BlasGeometryCounts],
+) -> wgt::AccelerationStructureBuildMode {
+    if blas.update_mode == wgt::AccelerationStructureUpdateMode::PreferUpdate
+        && blas
+            .flags
+            .contains(wgt::AccelerationStructureFlags::ALLOW_UPDATE)
+        && (*blas.built_index.read()).is_some()
+        && *blas.built_counts.read() == counts
+    {
+        wgt::AccelerationStructureBuildMode::Update
+    } else {
+        wgt::AccelerationStructureBuildMode::Build
+    }
+}
+
+/// Scratch memory used by a single build in `mode`, aligned for the next build.
+pub(crate) fn aligned_scratch_size(
+    size_info: &hal::AccelerationStructureBuildSizes,
+    mode: wgt::AccelerationStructureBuildMode,
+) -> u64 {
+    let size = match mode {
+        wgt::AccelerationStructureBuildMode::Build => size_info.build_scratch_size,
+        wgt::AccelerationStructureBuildMode::Update => size_info.update_scratch_size,
+    };
+    align_to(size, SCRATCH_BUFFER_ALIGNMENT)
+}
+
+fn map_build_mode(
+    mode: wgt::AccelerationStructureBuildMode,
+) -> hal::AccelerationStructureBuildMode {
+    match mode {
+        wgt::AccelerationStructureBuildMode::Build => hal::AccelerationStructureBuildMode::Build,
+        wgt::AccelerationStructureBuildMode::Update => hal::AccelerationStructureBuildMode::Update,
+    }
+}
 
 impl Global {
     pub fn command_encoder_build_acceleration_structures_unsafe_tlas<'a>(
@@ -94,6 +202,8 @@ impl Global {
                                     transform_buffer: tg.transform_buffer,
                                     first_vertex: tg.first_vertex,
                                     vertex_stride: tg.vertex_stride,
+                                    vertex_offset: tg.vertex_offset,
+                                    vertex_buffer_offset: tg.vertex_buffer_offset,
                                     index_buffer_offset: tg.index_buffer_offset,
                                     transform_buffer_offset: tg.transform_buffer_offset,
                                 })
@@ -116,6 +226,7 @@ impl Global {
                 crate::ray_tracing::TraceBlasBuildEntry {
                     blas_id: x.blas_id,
                     geometries,
+                    mode: x.mode,
                 }
             })
             .collect();
@@ -149,6 +260,8 @@ impl Global {
                             transform_buffer: tg.transform_buffer,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                             transform_buffer_offset: tg.transform_buffer_offset,
                         }
@@ -172,6 +285,7 @@ impl Global {
             BlasBuildEntry {
                 blas_id: x.blas_id,
                 geometries,
+                mode: x.mode,
             }
         });
 
@@ -188,6 +302,7 @@ impl Global {
 
         iter_blas(
             blas_iter,
+            device,
             cmd_buf_data,
             build_command_index,
             &buffer_guard,
@@ -211,6 +326,7 @@ impl Global {
             &Tlas,
             hal::AccelerationStructureEntries<dyn hal::DynBuffer>,
             u64,
+            wgt::AccelerationStructureBuildMode,
         )>::new();
         let mut tlas_buf_storage = Vec::<(
             Arc<Buffer>,
@@ -232,6 +348,7 @@ impl Global {
             tlas_buf_storage.push((instance_buffer.clone(), data, entry.clone()));
         }
 
+        let mut built_tlas = FastHashSet::default();
         for tlas_buf in &mut tlas_buf_storage {
             let entry = &tlas_buf.2;
             let instance_buffer = {
@@ -256,6 +373,13 @@ impl Global {
             let tlas = tlas_guard
                 .get(entry.tlas_id)
                 .map_err(|_| BuildAccelerationStructureError::InvalidTlasId)?;
+            tlas.same_device(device)?;
+            if !built_tlas.insert(tlas.tracker_index()) {
+                return Err(BuildAccelerationStructureError::DuplicateTlasBuild(
+                    tlas.error_ident(),
+                ));
+            }
+            let mode = resolve_build_mode(entry.mode, tlas.flags, || tlas.error_ident())?;
             cmd_buf_data.trackers.tlas_s.set_single(tlas.clone());
 
             cmd_buf_data.tlas_actions.push(TlasAction {
@@ -263,14 +387,13 @@ impl Global {
                 kind: crate::ray_tracing::TlasActionKind::Build {
                     build_index: build_command_index,
                     dependencies: Vec::new(),
+                    instance_count: entry.instance_count,
+                    mode,
                 },
             });
 
             let scratch_buffer_offset = scratch_buffer_tlas_size;
-            scratch_buffer_tlas_size += align_to(
-                tlas.size_info.build_scratch_size as u32,
-                SCRATCH_BUFFER_ALIGNMENT,
-            ) as u64;
+            scratch_buffer_tlas_size += aligned_scratch_size(&tlas.size_info, mode);
 
             tlas_storage.push((
                 tlas,
@@ -280,6 +403,7 @@ impl Global {
                     count: entry.instance_count,
                 }),
                 scratch_buffer_offset,
+                mode,
             ));
         }
 
@@ -300,25 +424,25 @@ impl Global {
 
         let blas_descriptors = blas_storage
             .iter()
-            .map(|storage| map_blas(storage, scratch_buffer.raw()));
+            .map(|storage| map_blas(storage, scratch_buffer.raw(), 0, &snatch_guard))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let tlas_descriptors =
-            tlas_storage
-                .iter()
-                .map(|&(tlas, ref entries, ref scratch_buffer_offset)| {
-                    if tlas.update_mode == wgt::AccelerationStructureUpdateMode::PreferUpdate {
-                        log::info!("only rebuild implemented")
-                    }
-                    hal::BuildAccelerationStructureDescriptor {
-                        entries,
-                        mode: hal::AccelerationStructureBuildMode::Build,
-                        flags: tlas.flags,
-                        source_acceleration_structure: None,
-                        destination_acceleration_structure: tlas.raw(),
-                        scratch_buffer: scratch_buffer.raw(),
-                        scratch_buffer_offset: *scratch_buffer_offset,
-                    }
-                });
+        let tlas_descriptors = tlas_storage
+            .iter()
+            .map(|&(tlas, ref entries, ref scratch_buffer_offset, mode)| {
+                Ok(hal::BuildAccelerationStructureDescriptor {
+                    entries,
+                    mode: map_build_mode(mode),
+                    flags: tlas.flags,
+                    source_acceleration_structure: None,
+                    destination_acceleration_structure: tlas.try_raw(&snatch_guard).map_err(
+                        |_| BuildAccelerationStructureError::InvalidTlas(tlas.error_ident()),
+                    )?,
+                    scratch_buffer: scratch_buffer.raw(),
+                    scratch_buffer_offset: *scratch_buffer_offset,
+                })
+            })
+            .collect::<Result<Vec<_>, BuildAccelerationStructureError>>()?;
 
         let blas_present = !blas_storage.is_empty();
         let tlas_present = !tlas_storage.is_empty();
@@ -330,14 +454,18 @@ impl Global {
             blas_present,
             tlas_present,
             input_barriers,
-            &blas_descriptors.collect::<Vec<_>>(),
+            &blas_descriptors,
             scratch_buffer_barrier,
         );
 
         if tlas_present {
             unsafe {
-                cmd_buf_raw.build_acceleration_structures(&tlas_descriptors.collect::<Vec<_>>());
+                cmd_buf_raw.build_acceleration_structures(&tlas_descriptors);
 
+                // Make the builds visible to shaders reading the TLASes. Since the barrier
+                // orders against everything later in submission order, this also covers
+                // shaders recorded into other command buffers and later submissions, so the
+                // usage tracker doesn't need to transition TLASes when they're bound.
                 cmd_buf_raw.place_acceleration_structure_barrier(
                     hal::AccelerationStructureBarrier {
                         usage: hal::AccelerationStructureUses::BUILD_OUTPUT
@@ -360,6 +488,348 @@ impl Global {
         command_encoder_id: CommandEncoderId,
         blas_iter: impl Iterator<Item = BlasBuildEntry<'a>>,
         tlas_iter: impl Iterator<Item = TlasPackage<'a>>,
+    ) -> Result<(), BuildAccelerationStructureError> {
+        self.build_acceleration_structures(command_encoder_id, blas_iter, tlas_iter, None)
+    }
+
+    /// Like [`Global::command_encoder_build_acceleration_structures`], but uses the range of
+    /// `scratch_buffer_id` starting at `scratch_buffer_offset` as scratch memory instead of
+    /// an internal scratch buffer.
+    ///
+    /// The buffer needs the `ACCELERATION_STRUCTURE_SCRATCH` usage, the offset must be a
+    /// multiple of [`wgt::ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT`] and the range must hold
+    /// at least [`Global::device_get_build_scratch_size`] bytes for the built structures.
+    pub fn command_encoder_build_acceleration_structures_with_scratch<'a>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        blas_iter: impl Iterator<Item = BlasBuildEntry<'a>>,
+        tlas_iter: impl Iterator<Item = TlasPackage<'a>>,
+        scratch_buffer_id: BufferId,
+        scratch_buffer_offset: BufferAddress,
+    ) -> Result<(), BuildAccelerationStructureError> {
+        self.build_acceleration_structures(
+            command_encoder_id,
+            blas_iter,
+            tlas_iter,
+            Some((scratch_buffer_id, scratch_buffer_offset)),
+        )
+    }
+
+    /// Copy the last build of `src_blas_id` into `dst_blas_id`, as described by `copy`.
+    ///
+    /// A clone needs `dst_blas_id` to have been created with the same sizes and flags as
+    /// `src_blas_id`. A compacting copy needs it to hold the compacted size of `src_blas_id`,
+    /// as [`Global::device_create_compacted_blas`] does, and the build of `src_blas_id` must not
+    /// have changed since its compacted size was read with [`Global::blas_compacted_size`],
+    /// which is checked when the command buffer is submitted.
+    pub fn command_encoder_copy_blas(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        src_blas_id: BlasId,
+        dst_blas_id: BlasId,
+        copy: wgt::AccelerationStructureCopy,
+    ) -> Result<(), CopyAccelerationStructureError> {
+        profiling::scope!("CommandEncoder::copy_blas");
+
+        let hub = &self.hub;
+
+        let cmd_buf = match hub
+            .command_buffers
+            .get(command_encoder_id.into_command_buffer_id())
+        {
+            Ok(cmd_buf) => cmd_buf,
+            Err(_) => return Err(CommandEncoderError::Invalid.into()),
+        };
+        cmd_buf.check_recording()?;
+
+        let device = &cmd_buf.device;
+
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.data.lock().as_mut().unwrap().commands {
+            list.push(crate::device::trace::Command::CopyBlas {
+                src: src_blas_id,
+                dst: dst_blas_id,
+                copy,
+            });
+        }
+
+        let src = hub
+            .blas_s
+            .get(src_blas_id)
+            .map_err(|_| CopyAccelerationStructureError::InvalidBlasId)?;
+        let dst = hub
+            .blas_s
+            .get(dst_blas_id)
+            .map_err(|_| CopyAccelerationStructureError::InvalidBlasId)?;
+        src.same_device(device)?;
+        dst.same_device(device)?;
+        if Arc::ptr_eq(&src, &dst) {
+            return Err(CopyAccelerationStructureError::SameSourceAndDestination(
+                src.error_ident(),
+            ));
+        }
+
+        let (required, compacted_size) = match copy {
+            wgt::AccelerationStructureCopy::Clone => {
+                let flags =
+                    |blas: &Blas| blas.flags - wgt::AccelerationStructureFlags::ALLOW_COMPACTION;
+                if src.sizes != dst.sizes || flags(&src) != flags(&dst) {
+                    return Err(CopyAccelerationStructureError::IncompatibleClone {
+                        src: src.error_ident(),
+                        dst: dst.error_ident(),
+                    });
+                }
+                (src.size_info.acceleration_structure_size, None)
+            }
+            wgt::AccelerationStructureCopy::Compact => {
+                if !src
+                    .flags
+                    .contains(wgt::AccelerationStructureFlags::ALLOW_COMPACTION)
+                {
+                    return Err(CompactBlasError::CompactionNotAllowed(src.error_ident()).into());
+                }
+                let compacted_size = src
+                    .compacted_size
+                    .lock()
+                    .ok_or_else(|| CompactBlasError::CompactedSizeUnknown(src.error_ident()))?;
+                (compacted_size, Some(compacted_size))
+            }
+        };
+        if dst.size_info.acceleration_structure_size < required {
+            return Err(
+                CopyAccelerationStructureError::InsufficientDestinationSize {
+                    src: src.error_ident(),
+                    dst: dst.error_ident(),
+                    size: dst.size_info.acceleration_structure_size,
+                    required,
+                },
+            );
+        }
+
+        let build_command_index = NonZeroU64::new(
+            device
+                .last_acceleration_structure_build_command_index
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                + 1,
+        )
+        .unwrap();
+
+        let mut cmd_buf_data = cmd_buf.data.lock();
+        let cmd_buf_data = cmd_buf_data.as_mut().unwrap();
+        cmd_buf_data.trackers.blas_s.set_single(src.clone());
+        cmd_buf_data.trackers.blas_s.set_single(dst.clone());
+
+        let snatch_guard = device.snatchable_lock.read();
+        let cmd_buf_raw = cmd_buf_data.encoder.open()?;
+        copy_acceleration_structure(
+            cmd_buf_raw,
+            src.try_raw(&snatch_guard)?,
+            dst.try_raw(&snatch_guard)?,
+            copy,
+        );
+
+        cmd_buf_data.blas_actions.push(BlasAction {
+            blas: dst,
+            kind: crate::ray_tracing::BlasActionKind::Copy {
+                build_index: build_command_index,
+                source: src,
+                compacted_size,
+            },
+        });
+
+        Ok(())
+    }
+
+    /// Clone the last build of `src_tlas_id` into `dst_tlas_id`, which must have been created
+    /// with the same maximum instance count and flags.
+    ///
+    /// The clone references the same BLASes as `src_tlas_id`, so it can be updated while
+    /// `src_tlas_id` is still in use.
+    pub fn command_encoder_copy_tlas(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        src_tlas_id: TlasId,
+        dst_tlas_id: TlasId,
+    ) -> Result<(), CopyAccelerationStructureError> {
+        profiling::scope!("CommandEncoder::copy_tlas");
+
+        let hub = &self.hub;
+
+        let cmd_buf = match hub
+            .command_buffers
+            .get(command_encoder_id.into_command_buffer_id())
+        {
+            Ok(cmd_buf) => cmd_buf,
+            Err(_) => return Err(CommandEncoderError::Invalid.into()),
+        };
+        cmd_buf.check_recording()?;
+
+        let device = &cmd_buf.device;
+
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.data.lock().as_mut().unwrap().commands {
+            list.push(crate::device::trace::Command::CopyTlas {
+                src: src_tlas_id,
+                dst: dst_tlas_id,
+            });
+        }
+
+        let src = hub
+            .tlas_s
+            .get(src_tlas_id)
+            .map_err(|_| CopyAccelerationStructureError::InvalidTlasId)?;
+        let dst = hub
+            .tlas_s
+            .get(dst_tlas_id)
+            .map_err(|_| CopyAccelerationStructureError::InvalidTlasId)?;
+        src.same_device(device)?;
+        dst.same_device(device)?;
+        if Arc::ptr_eq(&src, &dst) {
+            return Err(CopyAccelerationStructureError::SameSourceAndDestination(
+                src.error_ident(),
+            ));
+        }
+        if src.max_instance_count != dst.max_instance_count || src.flags != dst.flags {
+            return Err(CopyAccelerationStructureError::IncompatibleClone {
+                src: src.error_ident(),
+                dst: dst.error_ident(),
+            });
+        }
+
+        let mut cmd_buf_data = cmd_buf.data.lock();
+        let cmd_buf_data = cmd_buf_data.as_mut().unwrap();
+        cmd_buf_data.trackers.tlas_s.set_single(src.clone());
+        cmd_buf_data.trackers.tlas_s.set_single(dst.clone());
+
+        let snatch_guard = device.snatchable_lock.read();
+        let cmd_buf_raw = cmd_buf_data.encoder.open()?;
+        copy_acceleration_structure(
+            cmd_buf_raw,
+            src.try_raw(&snatch_guard)?,
+            dst.try_raw(&snatch_guard)?,
+            wgt::AccelerationStructureCopy::Clone,
+        );
+
+        cmd_buf_data.tlas_actions.push(TlasAction {
+            tlas: dst,
+            kind: crate::ray_tracing::TlasActionKind::Clone { source: src },
+        });
+
+        Ok(())
+    }
+
+    /// Copy the compacted size of `blas_id`, as of its last build, into `destination` at
+    /// `destination_offset` as a `u64`, so it can be mapped instead of read back with the
+    /// blocking [`Global::blas_compacted_size`].
+    ///
+    /// `blas_id` must have been created with flag `ALLOW_COMPACTION`, and built by this or an
+    /// earlier command buffer, which is checked when the command buffer is submitted.
+    pub fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        blas_id: BlasId,
+        destination: BufferId,
+        destination_offset: BufferAddress,
+    ) -> Result<(), ResolveCompactedSizeError> {
+        profiling::scope!("CommandEncoder::resolve_blas_compacted_size");
+
+        let hub = &self.hub;
+
+        let cmd_buf = match hub
+            .command_buffers
+            .get(command_encoder_id.into_command_buffer_id())
+        {
+            Ok(cmd_buf) => cmd_buf,
+            Err(_) => return Err(CommandEncoderError::Invalid.into()),
+        };
+        cmd_buf.check_recording()?;
+
+        let device = &cmd_buf.device;
+
+        #[cfg(feature = "trace")]
+        if let Some(ref mut list) = cmd_buf.data.lock().as_mut().unwrap().commands {
+            list.push(crate::device::trace::Command::ResolveBlasCompactedSize {
+                blas: blas_id,
+                destination,
+                destination_offset,
+            });
+        }
+
+        let blas = hub
+            .blas_s
+            .get(blas_id)
+            .map_err(|_| ResolveCompactedSizeError::InvalidBlasId)?;
+        let dst_buffer = hub
+            .buffers
+            .get(destination)
+            .map_err(|_| ResolveCompactedSizeError::InvalidBufferId)?;
+        blas.same_device(device)?;
+        dst_buffer.same_device(device)?;
+
+        if !blas
+            .flags
+            .contains(wgt::AccelerationStructureFlags::ALLOW_COMPACTION)
+        {
+            return Err(CompactBlasError::CompactionNotAllowed(blas.error_ident()).into());
+        }
+        dst_buffer.check_usage(BufferUsages::QUERY_RESOLVE)?;
+        if destination_offset % wgt::QUERY_RESOLVE_BUFFER_ALIGNMENT != 0 {
+            return Err(ResolveCompactedSizeError::UnalignedOffset(
+                destination_offset,
+            ));
+        }
+        let end_offset = destination_offset + wgt::QUERY_SIZE as BufferAddress;
+        if end_offset > dst_buffer.size {
+            return Err(ResolveCompactedSizeError::BufferOverrun {
+                buffer: dst_buffer.error_ident(),
+                offset: destination_offset,
+                size: dst_buffer.size,
+            });
+        }
+
+        let mut cmd_buf_data = cmd_buf.data.lock();
+        let cmd_buf_data = cmd_buf_data.as_mut().unwrap();
+        cmd_buf_data.trackers.blas_s.set_single(blas.clone());
+        let dst_pending = cmd_buf_data
+            .trackers
+            .buffers
+            .set_single(&dst_buffer, BufferUses::COPY_DST);
+
+        let snatch_guard = device.snatchable_lock.read();
+        let dst_barrier = dst_pending.map(|pending| pending.into_hal(&dst_buffer, &snatch_guard));
+
+        cmd_buf_data.buffer_memory_init_actions.extend(
+            dst_buffer.initialization_status.read().create_action(
+                &dst_buffer,
+                destination_offset..end_offset,
+                MemoryInitKind::ImplicitlyInitialized,
+            ),
+        );
+
+        let cmd_buf_raw = cmd_buf_data.encoder.open()?;
+        unsafe {
+            cmd_buf_raw.transition_buffers(dst_barrier.as_slice());
+            cmd_buf_raw.copy_acceleration_structure_compacted_size(
+                blas.try_raw(&snatch_guard)?,
+                dst_buffer.try_raw(&snatch_guard)?,
+                destination_offset,
+            );
+        }
+
+        cmd_buf_data.blas_actions.push(BlasAction {
+            blas,
+            kind: crate::ray_tracing::BlasActionKind::Use,
+        });
+
+        Ok(())
+    }
+
+    fn build_acceleration_structures<'a>(
+        &self,
+        command_encoder_id: CommandEncoderId,
+        blas_iter: impl Iterator<Item = BlasBuildEntry<'a>>,
+        tlas_iter: impl Iterator<Item = TlasPackage<'a>>,
+        user_scratch: Option<(BufferId, BufferAddress)>,
     ) -> Result<(), BuildAccelerationStructureError> {
         profiling::scope!("CommandEncoder::build_acceleration_structures");
 
@@ -402,6 +872,8 @@ impl Global {
                                     transform_buffer: tg.transform_buffer,
                                     first_vertex: tg.first_vertex,
                                     vertex_stride: tg.vertex_stride,
+                                    vertex_offset: tg.vertex_offset,
+                                    vertex_buffer_offset: tg.vertex_buffer_offset,
                                     index_buffer_offset: tg.index_buffer_offset,
                                     transform_buffer_offset: tg.transform_buffer_offset,
                                 })
@@ -424,6 +896,7 @@ impl Global {
                 crate::ray_tracing::TraceBlasBuildEntry {
                     blas_id: x.blas_id,
                     geometries,
+                    mode: x.mode,
                 }
             })
             .collect();
@@ -439,6 +912,9 @@ impl Global {
                             transform: *instance.transform,
                             custom_index: instance.custom_index,
                             mask: instance.mask,
+                            flags: instance.flags,
+                            shader_binding_table_record_offset: instance
+                                .shader_binding_table_record_offset,
                         })
                     })
                     .collect();
@@ -446,6 +922,8 @@ impl Global {
                     tlas_id: x.tlas_id,
                     instances,
                     lowest_unmodified: x.lowest_unmodified,
+                    instance_upload: x.instance_upload,
+                    mode: x.mode,
                 }
             })
             .collect();
@@ -455,6 +933,7 @@ impl Global {
             list.push(crate::device::trace::Command::BuildAccelerationStructures {
                 blas: trace_blas.clone(),
                 tlas: trace_tlas.clone(),
+                scratch: user_scratch,
             });
         }
 
@@ -472,6 +951,8 @@ impl Global {
                             transform_buffer: tg.transform_buffer,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                             transform_buffer_offset: tg.transform_buffer_offset,
                         }
@@ -495,6 +976,7 @@ impl Global {
             BlasBuildEntry {
                 blas_id: x.blas_id,
                 geometries,
+                mode: x.mode,
             }
         });
 
@@ -508,12 +990,17 @@ impl Global {
                         transform: &instance.transform,
                         custom_index: instance.custom_index,
                         mask: instance.mask,
+                        flags: instance.flags,
+                        shader_binding_table_record_offset: instance
+                            .shader_binding_table_record_offset,
                     })
             });
             TlasPackage {
                 tlas_id: x.tlas_id,
                 instances: Box::new(instances),
                 lowest_unmodified: x.lowest_unmodified,
+                instance_upload: x.instance_upload,
+                mode: x.mode,
             }
         });
 
@@ -527,6 +1014,7 @@ impl Global {
 
         iter_blas(
             blas_iter,
+            device,
             cmd_buf_data,
             build_command_index,
             &buffer_guard,
@@ -547,10 +1035,18 @@ impl Global {
         let mut tlas_lock_store =
             Vec::<(&dyn hal::DynBuffer, Option<TlasPackage>, Arc<Tlas>)>::new();
 
+        let mut built_tlas = FastHashSet::default();
         for package in tlas_iter {
             let tlas = tlas_guard
                 .get(package.tlas_id)
                 .map_err(|_| BuildAccelerationStructureError::InvalidTlasId)?;
+            tlas.same_device(device)?;
+            if !built_tlas.insert(tlas.tracker_index()) {
+                return Err(BuildAccelerationStructureError::DuplicateTlasBuild(
+                    tlas.error_ident(),
+                ));
+            }
+            resolve_build_mode(package.mode, tlas.flags, || tlas.error_ident())?;
 
             cmd_buf_data.trackers.tlas_s.set_single(tlas.clone());
             tlas_lock_store.push((tlas.instance_buffer.as_ref(), Some(package), tlas.clone()))
@@ -559,39 +1055,80 @@ impl Global {
         let mut scratch_buffer_tlas_size = 0;
         let mut tlas_storage = Vec::<(
             &Tlas,
-            hal::AccelerationStructureEntries<dyn hal::DynBuffer>,
+            &dyn hal::DynBuffer,
+            u32,
+            wgt::TlasInstanceUpload,
             u64,
             Range<usize>,
+            wgt::AccelerationStructureBuildMode,
         )>::new();
         let mut instance_buffer_staging_source = Vec::<u8>::new();
+        let mut instance_buffer_mapped_source = Vec::<u8>::new();
+        let default_instance_upload =
+            wgt::TlasInstanceUpload::for_device_type(device.adapter.raw.info.device_type);
 
         for entry in &mut tlas_lock_store {
             let package = entry.1.take().unwrap();
             let tlas = &entry.2;
 
+            let (instance_buffer_use, write_from_host) = tlas.use_instance_buffer(
+                package.instance_upload.unwrap_or(default_instance_upload)
+                    == wgt::TlasInstanceUpload::Mapped,
+            );
+            cmd_buf_data
+                .tlas_instance_buffer_uses
+                .push(instance_buffer_use);
+            let instance_upload = if write_from_host {
+                wgt::TlasInstanceUpload::Mapped
+            } else {
+                wgt::TlasInstanceUpload::Staging
+            };
+            let mode = package
+                .mode
+                .unwrap_or(wgt::AccelerationStructureBuildMode::Build);
+            let instance_source = match instance_upload {
+                wgt::TlasInstanceUpload::Staging => &mut instance_buffer_staging_source,
+                wgt::TlasInstanceUpload::Mapped => &mut instance_buffer_mapped_source,
+            };
+
             let scratch_buffer_offset = scratch_buffer_tlas_size;
-            scratch_buffer_tlas_size += align_to(
-                tlas.size_info.build_scratch_size as u32,
-                SCRATCH_BUFFER_ALIGNMENT,
-            ) as u64;
+            scratch_buffer_tlas_size += aligned_scratch_size(&tlas.size_info, mode);
 
-            let first_byte_index = instance_buffer_staging_source.len();
+            let first_byte_index = instance_source.len();
 
+            // Heavily instanced scenes reference the same BLAS many times, so track and keep
+            // alive every BLAS only once per build.
             let mut dependencies = Vec::new();
+            let mut dependency_ids = FastHashSet::default();
 
             let mut instance_count = 0;
-            for instance in package.instances.flatten() {
-                if instance.custom_index >= (1u32 << 24u32) {
-                    return Err(BuildAccelerationStructureError::TlasInvalidCustomIndex(
+            for (index, instance) in package.instances.enumerate() {
+                let Some(instance) = instance else {
+                    continue;
+                };
+                let errors = wgt::instance_packing::InstanceFieldErrors::check(
+                    instance.custom_index,
+                    instance.shader_binding_table_record_offset,
+                    instance.flags,
+                );
+                if !errors.is_empty() {
+                    return Err(BuildAccelerationStructureError::TlasInvalidInstance(
                         tlas.error_ident(),
+                        index,
+                        errors,
                     ));
                 }
                 let blas = blas_guard
                     .get(instance.blas_id)
                     .map_err(|_| BuildAccelerationStructureError::InvalidBlasIdForInstance)?
                     .clone();
+                // Instances store the device address of their BLAS, which is meaningless to
+                // the device of another TLAS.
+                blas.same_device_as(tlas.as_ref())?;
 
-                cmd_buf_data.trackers.blas_s.set_single(blas.clone());
+                blas.try_raw(&snatch_guard).map_err(|_| {
+                    BuildAccelerationStructureError::InvalidBlasForInstance(blas.error_ident())
+                })?;
 
                 if tlas
                     .flags
@@ -606,17 +1143,18 @@ impl Global {
                     ));
                 }
 
-                instance_buffer_staging_source
-                    .extend(tlas_instance_into_bytes(&instance, blas.handle));
+                instance_source.extend(tlas_instance_into_bytes(&instance, blas.handle));
 
                 instance_count += 1;
 
-                dependencies.push(blas.clone());
-
-                cmd_buf_data.blas_actions.push(BlasAction {
-                    blas: blas.clone(),
-                    kind: crate::ray_tracing::BlasActionKind::Use,
-                });
+                if dependency_ids.insert(instance.blas_id) {
+                    cmd_buf_data.trackers.blas_s.set_single(blas.clone());
+                    cmd_buf_data.blas_actions.push(BlasAction {
+                        blas: blas.clone(),
+                        kind: crate::ray_tracing::BlasActionKind::Use,
+                    });
+                    dependencies.push(blas);
+                }
             }
 
             cmd_buf_data.tlas_actions.push(TlasAction {
@@ -624,6 +1162,8 @@ impl Global {
                 kind: crate::ray_tracing::TlasActionKind::Build {
                     build_index: build_command_index,
                     dependencies,
+                    instance_count,
+                    mode,
                 },
             });
 
@@ -637,13 +1177,12 @@ impl Global {
 
             tlas_storage.push((
                 tlas,
-                hal::AccelerationStructureEntries::Instances(hal::AccelerationStructureInstances {
-                    buffer: Some(entry.0),
-                    offset: 0,
-                    count: instance_count,
-                }),
+                entry.0,
+                instance_count,
+                instance_upload,
                 scratch_buffer_offset,
-                first_byte_index..instance_buffer_staging_source.len(),
+                first_byte_index..instance_source.len(),
+                mode,
             ));
         }
 
@@ -654,35 +1193,85 @@ impl Global {
                 Some(size) => size,
             };
 
-        let scratch_buffer =
-            ScratchBuffer::new(device, scratch_size).map_err(crate::device::DeviceError::from)?;
+        let user_scratch_buffer = match user_scratch {
+            None => None,
+            Some((buffer_id, offset)) => {
+                let buffer = buffer_guard
+                    .get(buffer_id)
+                    .map_err(|_| BuildAccelerationStructureError::InvalidBufferId)?;
+                buffer.same_device(device)?;
+                if !buffer
+                    .usage
+                    .contains(BufferUsages::ACCELERATION_STRUCTURE_SCRATCH)
+                {
+                    return Err(BuildAccelerationStructureError::MissingScratchUsageFlag(
+                        buffer.error_ident(),
+                    ));
+                }
+                if offset % wgt::ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT != 0 {
+                    return Err(
+                        BuildAccelerationStructureError::UnalignedScratchBufferOffset(offset),
+                    );
+                }
+                if offset
+                    .checked_add(scratch_size.get())
+                    .map_or(true, |end| end > buffer.size)
+                {
+                    return Err(
+                        BuildAccelerationStructureError::InsufficientScratchBufferSize(
+                            buffer.error_ident(),
+                            offset,
+                            scratch_size.get(),
+                            buffer.size,
+                        ),
+                    );
+                }
+                Some((buffer, offset))
+            }
+        };
+
+        let mut scratch_buffer = None;
+        let (scratch_buffer_raw, scratch_base_offset) = match user_scratch_buffer {
+            None => {
+                let scratch_buffer = scratch_buffer.insert(
+                    ScratchBuffer::new(device, scratch_size)
+                        .map_err(crate::device::DeviceError::from)?,
+                );
+                (scratch_buffer.raw(), 0)
+            }
+            Some((buffer, offset)) => {
+                if let Some(barrier) = cmd_buf_data
+                    .trackers
+                    .buffers
+                    .set_single(buffer, BufferUses::ACCELERATION_STRUCTURE_SCRATCH)
+                    .map(|pending| pending.into_hal(buffer, &snatch_guard))
+                {
+                    input_barriers.push(barrier);
+                }
+                let raw = buffer.raw(&snatch_guard).ok_or(
+                    BuildAccelerationStructureError::InvalidBuffer(buffer.error_ident()),
+                )?;
+                (raw, offset)
+            }
+        };
 
         let scratch_buffer_barrier = hal::BufferBarrier::<dyn hal::DynBuffer> {
-            buffer: scratch_buffer.raw(),
+            buffer: scratch_buffer_raw,
             usage: BufferUses::ACCELERATION_STRUCTURE_SCRATCH
                 ..BufferUses::ACCELERATION_STRUCTURE_SCRATCH,
         };
 
         let blas_descriptors = blas_storage
             .iter()
-            .map(|storage| map_blas(storage, scratch_buffer.raw()));
-
-        let mut tlas_descriptors = Vec::with_capacity(tlas_storage.len());
-
-        for &(tlas, ref entries, ref scratch_buffer_offset, _) in &tlas_storage {
-            if tlas.update_mode == wgt::AccelerationStructureUpdateMode::PreferUpdate {
-                log::info!("only rebuild implemented")
-            }
-            tlas_descriptors.push(hal::BuildAccelerationStructureDescriptor {
-                entries,
-                mode: hal::AccelerationStructureBuildMode::Build,
-                flags: tlas.flags,
-                source_acceleration_structure: None,
-                destination_acceleration_structure: tlas.raw.as_ref(),
-                scratch_buffer: scratch_buffer.raw(),
-                scratch_buffer_offset: *scratch_buffer_offset,
+            .map(|storage| {
+                map_blas(
+                    storage,
+                    scratch_buffer_raw,
+                    scratch_base_offset,
+                    &snatch_guard,
+                )
             })
-        }
+            .collect::<Result<Vec<_>, _>>()?;
 
         let blas_present = !blas_storage.is_empty();
         let tlas_present = !tlas_storage.is_empty();
@@ -694,7 +1283,7 @@ impl Global {
             blas_present,
             tlas_present,
             input_barriers,
-            &blas_descriptors.collect::<Vec<_>>(),
+            &blas_descriptors,
             scratch_buffer_barrier,
         );
 
@@ -712,6 +1301,22 @@ impl Global {
                 None
             };
 
+            // Mapped instances are written straight into the host visible instance buffer,
+            // which no other build reads, so there is nothing to copy for them.
+            for &(tlas, _, _, instance_upload, _, ref range, _) in &tlas_storage {
+                if instance_upload != wgt::TlasInstanceUpload::Mapped || range.is_empty() {
+                    continue;
+                }
+                unsafe { tlas.write_instances(&instance_buffer_mapped_source[range.clone()]) }?;
+                unsafe {
+                    cmd_buf_raw.transition_buffers(&[hal::BufferBarrier::<dyn hal::DynBuffer> {
+                        buffer: tlas.instance_buffer.as_ref(),
+                        usage: hal::BufferUses::MAP_WRITE
+                            ..hal::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT,
+                    }]);
+                }
+            }
+
             unsafe {
                 if let Some(ref staging_buffer) = staging_buffer {
                     cmd_buf_raw.transition_buffers(&[hal::BufferBarrier::<dyn hal::DynBuffer> {
@@ -721,8 +1326,41 @@ impl Global {
                 }
             }
 
+            let tlas_entries = tlas_storage
+                .iter()
+                .map(|&(_, instance_buffer, count, _, _, _, _)| {
+                    hal::AccelerationStructureEntries::Instances(
+                        hal::AccelerationStructureInstances {
+                            buffer: Some(instance_buffer),
+                            offset: 0,
+                            count,
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let mut tlas_descriptors = Vec::with_capacity(tlas_storage.len());
+            for (&(tlas, _, _, _, scratch_buffer_offset, _, mode), entries) in
+                tlas_storage.iter().zip(&tlas_entries)
+            {
+                tlas_descriptors.push(hal::BuildAccelerationStructureDescriptor {
+                    entries,
+                    mode: map_build_mode(mode),
+                    flags: tlas.flags,
+                    source_acceleration_structure: None,
+                    destination_acceleration_structure: tlas.try_raw(&snatch_guard).map_err(
+                        |_| BuildAccelerationStructureError::InvalidTlas(tlas.error_ident()),
+                    )?,
+                    scratch_buffer: scratch_buffer_raw,
+                    scratch_buffer_offset: scratch_base_offset + scratch_buffer_offset,
+                })
+            }
+
             let mut instance_buffer_barriers = Vec::new();
-            for &(tlas, _, _, ref range) in &tlas_storage {
+            for &(tlas, _, _, instance_upload, _, ref range, _) in &tlas_storage {
+                if instance_upload == wgt::TlasInstanceUpload::Mapped {
+                    continue;
+                }
                 let size = match wgt::BufferSize::new((range.end - range.start) as u64) {
                     None => continue,
                     Some(size) => size,
@@ -756,6 +1394,7 @@ impl Global {
 
                 cmd_buf_raw.build_acceleration_structures(&tlas_descriptors);
 
+                // See `command_encoder_build_acceleration_structures_unsafe_tlas`.
                 cmd_buf_raw.place_acceleration_structure_barrier(
                     hal::AccelerationStructureBarrier {
                         usage: hal::AccelerationStructureUses::BUILD_OUTPUT
@@ -772,25 +1411,72 @@ impl Global {
             }
         }
 
-        device
-            .pending_writes
-            .lock()
-            .consume_temp(TempResource::ScratchBuffer(scratch_buffer));
+        if let Some(scratch_buffer) = scratch_buffer {
+            device
+                .pending_writes
+                .lock()
+                .consume_temp(TempResource::ScratchBuffer(scratch_buffer));
+        }
 
         Ok(())
     }
 }
 
 impl BakedCommands {
+    /// Add the acceleration structure builds of this command buffer to `summary`.
+    pub(crate) fn summarize_builds(&self, summary: &mut SubmissionBuildSummary) {
+        summary.add_actions(&self.blas_actions, &self.tlas_actions);
+    }
+
     // makes sure a blas is build before it is used
     pub(crate) fn validate_blas_actions(&mut self) -> Result<(), ValidateBlasActionsError> {
         profiling::scope!("CommandEncoder::[submission]::validate_blas_actions");
         let mut built = FastHashSet::default();
         for action in self.blas_actions.drain(..) {
             match action.kind {
-                crate::ray_tracing::BlasActionKind::Build(id) => {
+                crate::ray_tracing::BlasActionKind::Build {
+                    build_index,
+                    mode,
+                    counts,
+                } => {
+                    if mode == wgt::AccelerationStructureBuildMode::Update {
+                        if (*action.blas.built_index.read()).is_none() {
+                            return Err(ValidateBlasActionsError::UpdatedUnbuilt(
+                                action.blas.error_ident(),
+                            ));
+                        }
+                        if *action.blas.built_counts.read() != counts {
+                            return Err(ValidateBlasActionsError::IncompatibleUpdate(
+                                action.blas.error_ident(),
+                            ));
+                        }
+                    }
                     built.insert(action.blas.tracker_index());
-                    *action.blas.built_index.write() = Some(id);
+                    *action.blas.built_index.write() = Some(build_index);
+                    *action.blas.built_counts.write() = counts;
+                    *action.blas.compacted_size.lock() = None;
+                }
+                crate::ray_tracing::BlasActionKind::Copy {
+                    build_index,
+                    source,
+                    compacted_size,
+                } => {
+                    if (*source.built_index.read()).is_none() {
+                        return Err(ValidateBlasActionsError::CopiedUnbuilt(
+                            source.error_ident(),
+                        ));
+                    }
+                    // A build of the source since its compacted size was read, in this
+                    // submission or an earlier one, invalidates the size.
+                    if compacted_size.is_some() && *source.compacted_size.lock() != compacted_size {
+                        return Err(ValidateBlasActionsError::StaleCompactedSize(
+                            source.error_ident(),
+                        ));
+                    }
+                    built.insert(action.blas.tracker_index());
+                    *action.blas.built_index.write() = Some(build_index);
+                    *action.blas.built_counts.write() = source.built_counts.read().clone();
+                    *action.blas.compacted_size.lock() = None;
                 }
                 crate::ray_tracing::BlasActionKind::Use => {
                     if !built.contains(&action.blas.tracker_index())
@@ -807,27 +1493,83 @@ impl BakedCommands {
     }
 
     // makes sure a tlas is build before it is used
-    pub(crate) fn validate_tlas_actions(&mut self) -> Result<(), ValidateTlasActionsError> {
+    pub(crate) fn validate_tlas_actions(
+        &mut self,
+        snatch_guard: &SnatchGuard,
+    ) -> Result<(), ValidateTlasActionsError> {
         profiling::scope!("CommandEncoder::[submission]::validate_tlas_actions");
         for action in self.tlas_actions.drain(..) {
             match action.kind {
                 crate::ray_tracing::TlasActionKind::Build {
                     build_index,
                     dependencies,
+                    instance_count,
+                    mode,
                 } => {
+                    if mode == wgt::AccelerationStructureBuildMode::Update {
+                        if (*action.tlas.built_index.read()).is_none() {
+                            return Err(ValidateTlasActionsError::UpdatedUnbuilt(
+                                action.tlas.error_ident(),
+                            ));
+                        }
+                        let built_instance_count = *action.tlas.built_instance_count.read();
+                        if built_instance_count != instance_count {
+                            return Err(ValidateTlasActionsError::IncompatibleUpdate(
+                                action.tlas.error_ident(),
+                                instance_count,
+                                built_instance_count,
+                            ));
+                        }
+                    }
                     *action.tlas.built_index.write() = Some(build_index);
-                    *action.tlas.dependencies.write() = dependencies;
+                    *action.tlas.built_instance_count.write() = instance_count;
+                    let counter = &action.tlas.device.counters.tlas_blas_references;
+                    counter.add(dependencies.len() as isize);
+                    let previous =
+                        mem::replace(&mut *action.tlas.dependencies.write(), dependencies);
+                    counter.sub(previous.len() as isize);
+                }
+                crate::ray_tracing::TlasActionKind::Clone { source } => {
+                    let built_index = *source.built_index.read();
+                    if built_index.is_none() {
+                        return Err(ValidateTlasActionsError::ClonedUnbuilt(
+                            source.error_ident(),
+                        ));
+                    }
+                    // The clone holds the same build as the source, so the BLASes it
+                    // references must not be newer than that build either.
+                    *action.tlas.built_index.write() = built_index;
+                    *action.tlas.built_instance_count.write() = *source.built_instance_count.read();
+                    let dependencies = source.dependencies.read().clone();
+                    let counter = &action.tlas.device.counters.tlas_blas_references;
+                    counter.add(dependencies.len() as isize);
+                    let previous =
+                        mem::replace(&mut *action.tlas.dependencies.write(), dependencies);
+                    counter.sub(previous.len() as isize);
                 }
                 crate::ray_tracing::TlasActionKind::Use => {
                     let tlas_build_index = action.tlas.built_index.read();
                     let dependencies = action.tlas.dependencies.read();
 
+                    action.tlas.try_raw(snatch_guard).map_err(|_| {
+                        ValidateTlasActionsError::InvalidTlas(action.tlas.error_ident())
+                    })?;
                     if (*tlas_build_index).is_none() {
                         return Err(ValidateTlasActionsError::UsedUnbuilt(
                             action.tlas.error_ident(),
                         ));
                     }
+                    // Track the tlas and the blases it references so that destroying
+                    // them while this submission is in flight is deferred.
+                    self.trackers.tlas_s.set_single(action.tlas.clone());
                     for blas in dependencies.deref() {
+                        blas.try_raw(snatch_guard).map_err(|_| {
+                            ValidateTlasActionsError::DestroyedBlas(
+                                blas.error_ident(),
+                                action.tlas.error_ident(),
+                            )
+                        })?;
+                        self.trackers.blas_s.set_single(blas.clone());
                         let blas_build_index = *blas.built_index.read();
                         if blas_build_index.is_none() {
                             return Err(ValidateTlasActionsError::UsedUnbuilt(
@@ -851,22 +1593,33 @@ impl BakedCommands {
 ///iterates over the blas iterator, and it's geometry, pushing the buffers into a storage vector (and also some validation).
 fn iter_blas<'a>(
     blas_iter: impl Iterator<Item = BlasBuildEntry<'a>>,
+    device: &Arc<Device>,
     cmd_buf_data: &mut CommandBufferMutable,
     build_command_index: NonZeroU64,
     buffer_guard: &RwLockReadGuard<Storage<Buffer>>,
     blas_guard: &RwLockReadGuard<Storage<Blas>>,
     buf_storage: &mut BufferStorage<'a>,
 ) -> Result<(), BuildAccelerationStructureError> {
+    let mut built = FastHashSet::default();
     for entry in blas_iter {
         let blas = blas_guard
             .get(entry.blas_id)
             .map_err(|_| BuildAccelerationStructureError::InvalidBlasId)?;
+        blas.same_device(device)?;
+        if !built.insert(blas.tracker_index()) {
+            return Err(BuildAccelerationStructureError::DuplicateBlasBuild(
+                blas.error_ident(),
+            ));
+        }
+        let mode = resolve_build_mode(entry.mode, blas.flags, || blas.error_ident())?;
+        if blas.compacted {
+            return Err(BuildAccelerationStructureError::CompactedBlasBuilt(
+                blas.error_ident(),
+            ));
+        }
         cmd_buf_data.trackers.blas_s.set_single(blas.clone());
 
-        cmd_buf_data.blas_actions.push(BlasAction {
-            blas: blas.clone(),
-            kind: crate::ray_tracing::BlasActionKind::Build(build_command_index),
-        });
+        let mut geometry_counts = Vec::new();
 
         match entry.geometries {
             BlasGeometries::TriangleGeometries(triangle_geometries) => {
@@ -889,20 +1642,62 @@ fn iter_blas<'a>(
                     let size_desc = &size_desc[i];
 
                     if size_desc.flags != mesh.size.flags
-                        || size_desc.vertex_count < mesh.size.vertex_count
                         || size_desc.vertex_format != mesh.size.vertex_format
                         || size_desc.index_count.is_none() != mesh.size.index_count.is_none()
-                        || (size_desc.index_count.is_none()
-                            || size_desc.index_count.unwrap() < mesh.size.index_count.unwrap())
-                        || size_desc.index_format.is_none() != mesh.size.index_format.is_none()
-                        || (size_desc.index_format.is_none()
-                            || size_desc.index_format.unwrap() != mesh.size.index_format.unwrap())
+                        || size_desc.index_format != mesh.size.index_format
                     {
                         return Err(BuildAccelerationStructureError::IncompatibleBlasBuildSizes(
                             blas.error_ident(),
                         ));
                     }
 
+                    // The sizes the BLAS was created with are only an upper bound, builds
+                    // may use fewer vertices and indices than that.
+                    let counts = [
+                        ("vertices", mesh.size.vertex_count, size_desc.vertex_count),
+                        (
+                            "indices",
+                            mesh.size.index_count.unwrap_or(0),
+                            size_desc.index_count.unwrap_or(0),
+                        ),
+                    ];
+                    for (kind, count, reserved) in counts {
+                        if count > reserved {
+                            return Err(
+                                BuildAccelerationStructureError::BlasBuildExceedsReservedCount(
+                                    blas.error_ident(),
+                                    i,
+                                    count,
+                                    kind,
+                                    reserved,
+                                ),
+                            );
+                        }
+                    }
+
+                    let vertex_stride = mesh.effective_vertex_stride();
+                    let vertex_format = mesh.size.vertex_format;
+                    if vertex_stride < vertex_format.size()
+                        || vertex_stride % vertex_component_size(vertex_format) != 0
+                        || vertex_stride > u32::MAX as BufferAddress
+                    {
+                        return Err(BuildAccelerationStructureError::InvalidVertexStride(
+                            blas.error_ident(),
+                            vertex_stride,
+                            vertex_format,
+                        ));
+                    }
+                    if mesh.vertex_offset + vertex_format.size() > vertex_stride
+                        || mesh.vertex_offset % vertex_component_size(vertex_format) != 0
+                    {
+                        return Err(BuildAccelerationStructureError::InvalidVertexOffset(
+                            blas.error_ident(),
+                            mesh.vertex_offset,
+                            vertex_stride,
+                            vertex_format,
+                        ));
+                    }
+
                     if size_desc.index_count.is_some() && mesh.index_buffer.is_none() {
                         return Err(BuildAccelerationStructureError::MissingIndexBuffer(
                             blas.error_ident(),
@@ -923,7 +1718,7 @@ fn iter_blas<'a>(
                         };
                         if mesh.index_buffer_offset.is_none()
                             || mesh.size.index_count.is_none()
-                            || mesh.size.index_count.is_none()
+                            || mesh.size.index_format.is_none()
                         {
                             return Err(BuildAccelerationStructureError::MissingAssociatedData(
                                 index_buffer.error_ident(),
@@ -955,6 +1750,11 @@ fn iter_blas<'a>(
                     } else {
                         None
                     };
+                    geometry_counts.push(BlasGeometryCounts::Triangles {
+                        vertex_count: mesh.size.vertex_count,
+                        index_count: mesh.size.index_count,
+                        transform: transform_data.is_some(),
+                    });
                     buf_storage.push((
                         vertex_buffer.clone(),
                         vertex_pending,
@@ -964,10 +1764,6 @@ fn iter_blas<'a>(
                         None,
                     ));
                 }
-
-                if let Some(last) = buf_storage.last_mut() {
-                    last.5 = Some(blas.clone());
-                }
             }
             BlasGeometries::ProceduralGeometries(procedural_geometries) => {
                 for (i, mesh) in procedural_geometries.enumerate() {
@@ -988,13 +1784,22 @@ fn iter_blas<'a>(
                     }
                     let size_desc = &size_desc[i];
 
-                    if size_desc.flags != mesh.size.flags
-                        || size_desc.primitive_count < mesh.size.primitive_count
-                    {
+                    if size_desc.flags != mesh.size.flags {
                         return Err(BuildAccelerationStructureError::IncompatibleBlasBuildSizes(
                             blas.error_ident(),
                         ));
                     }
+                    if mesh.size.primitive_count > size_desc.primitive_count {
+                        return Err(
+                            BuildAccelerationStructureError::BlasBuildExceedsReservedCount(
+                                blas.error_ident(),
+                                i,
+                                mesh.size.primitive_count,
+                                "primitives",
+                                size_desc.primitive_count,
+                            ),
+                        );
+                    }
 
                     let bounding_box_buffer = match buffer_guard.get(mesh.bounding_box_buffer) {
                         Ok(buffer) => buffer,
@@ -1004,6 +1809,9 @@ fn iter_blas<'a>(
                         bounding_box_buffer,
                         BufferUses::BOTTOM_LEVEL_ACCELERATION_STRUCTURE_INPUT,
                     );
+                    geometry_counts.push(BlasGeometryCounts::Aabbs {
+                        primitive_count: mesh.size.primitive_count,
+                    });
                     buf_storage.push((
                         bounding_box_buffer.clone(),
                         bounding_box_pending,
@@ -1013,12 +1821,25 @@ fn iter_blas<'a>(
                         None,
                     ));
                 }
-
-                if let Some(last) = buf_storage.last_mut() {
-                    last.5 = Some(blas.clone());
-                }
             }
         }
+
+        let mode = match entry.mode {
+            Some(_) => mode,
+            None => preferred_blas_build_mode(blas, &geometry_counts),
+        };
+        if let Some(last) = buf_storage.last_mut() {
+            last.5 = Some((blas.clone(), mode));
+        }
+
+        cmd_buf_data.blas_actions.push(BlasAction {
+            blas: blas.clone(),
+            kind: crate::ray_tracing::BlasActionKind::Build {
+                build_index: build_command_index,
+                mode,
+                counts: geometry_counts,
+            },
+        });
     }
     Ok(())
 }
@@ -1059,23 +1880,32 @@ fn iter_buffers<'a, 'b>(
                     {
                         input_barriers.push(barrier);
                     }
-                    if vertex_buffer.size
-                        < (mesh.size.vertex_count + mesh.first_vertex) as u64 * mesh.vertex_stride
-                    {
+                    let vertex_stride = mesh.effective_vertex_stride();
+                    let vertex_format = mesh.size.vertex_format;
+                    if mesh.vertex_buffer_offset % vertex_component_size(vertex_format) != 0 {
+                        return Err(
+                            BuildAccelerationStructureError::UnalignedVertexBufferOffset(
+                                vertex_buffer.error_ident(),
+                                mesh.vertex_buffer_offset,
+                                vertex_format,
+                            ),
+                        );
+                    }
+                    let vertex_buffer_offset =
+                        mesh.vertex_buffer_offset + mesh.first_vertex as u64 * vertex_stride;
+                    let vertices_end =
+                        vertex_buffer_offset + mesh.size.vertex_count as u64 * vertex_stride;
+                    if vertex_buffer.size < vertices_end {
                         return Err(BuildAccelerationStructureError::InsufficientBufferSize(
                             vertex_buffer.error_ident(),
                             vertex_buffer.size,
-                            (mesh.size.vertex_count + mesh.first_vertex) as u64
-                                * mesh.vertex_stride,
+                            vertices_end,
                         ));
                     }
-                    let vertex_buffer_offset = mesh.first_vertex as u64 * mesh.vertex_stride;
                     cmd_buf_data.buffer_memory_init_actions.extend(
                         vertex_buffer.initialization_status.read().create_action(
                             buffer_guard.get(mesh.vertex_buffer).unwrap(),
-                            vertex_buffer_offset
-                                ..(vertex_buffer_offset
-                                    + mesh.size.vertex_count as u64 * mesh.vertex_stride),
+                            vertex_buffer_offset..vertices_end,
                             MemoryInitKind::NeedsInitializedMemory,
                         ),
                     );
@@ -1185,7 +2015,7 @@ fn iter_buffers<'a, 'b>(
                             transform_buffer.initialization_status.read().create_action(
                                 transform_buffer,
                                 mesh.transform_buffer_offset.unwrap()
-                                    ..(mesh.index_buffer_offset.unwrap() + 48),
+                                    ..(mesh.transform_buffer_offset.unwrap() + 48),
                                 MemoryInitKind::NeedsInitializedMemory,
                             ),
                         );
@@ -1200,7 +2030,9 @@ fn iter_buffers<'a, 'b>(
                     vertex_format: mesh.size.vertex_format,
                     first_vertex: mesh.first_vertex,
                     vertex_count: mesh.size.vertex_count,
-                    vertex_stride: mesh.vertex_stride,
+                    vertex_stride: mesh.effective_vertex_stride(),
+                    vertex_offset: mesh.vertex_offset,
+                    vertex_buffer_offset: mesh.vertex_buffer_offset,
                     indices: index_buffer.map(|index_buffer| {
                         hal::AccelerationStructureTriangleIndices::<dyn hal::DynBuffer> {
                             format: mesh.size.index_format.unwrap(),
@@ -1274,26 +2106,34 @@ fn iter_buffers<'a, 'b>(
             }
         }
 
-        if let Some(blas) = buf.5.take() {
+        if let Some((blas, mode)) = buf.5.take() {
+            // The memory of a BLAS created in a user buffer goes away with the buffer.
+            if let Some(backing_buffer) = &blas.backing_buffer {
+                if backing_buffer.raw.get(snatch_guard).is_none() {
+                    return Err(BuildAccelerationStructureError::InvalidBuffer(
+                        backing_buffer.error_ident(),
+                    ));
+                }
+            }
+
             let scratch_buffer_offset = *scratch_buffer_blas_size;
-            *scratch_buffer_blas_size += align_to(
-                blas.size_info.build_scratch_size as u32,
-                SCRATCH_BUFFER_ALIGNMENT,
-            ) as u64;
+            *scratch_buffer_blas_size += aligned_scratch_size(&blas.size_info, mode);
 
-            if triangle_entries.len() > 0 {
+            if !triangle_entries.is_empty() {
                 blas_storage.push((
                     blas.clone(),
                     hal::AccelerationStructureEntries::Triangles(triangle_entries),
                     scratch_buffer_offset,
+                    mode,
                 ));
                 triangle_entries = Vec::new();
             }
-            if procedural_entries.len() > 0 {
+            if !procedural_entries.is_empty() {
                 blas_storage.push((
                     blas.clone(),
                     hal::AccelerationStructureEntries::AABBs(procedural_entries),
                     scratch_buffer_offset,
+                    mode,
                 ));
                 procedural_entries = Vec::new();
             }
@@ -1307,26 +2147,31 @@ fn map_blas<'a>(
         Arc<Blas>,
         hal::AccelerationStructureEntries<dyn hal::DynBuffer>,
         BufferAddress,
+        wgt::AccelerationStructureBuildMode,
     ),
     scratch_buffer: &'a dyn hal::DynBuffer,
-) -> hal::BuildAccelerationStructureDescriptor<
-    'a,
-    dyn hal::DynBuffer,
-    dyn hal::DynAccelerationStructure,
+    scratch_base_offset: BufferAddress,
+    snatch_guard: &'a SnatchGuard,
+) -> Result<
+    hal::BuildAccelerationStructureDescriptor<
+        'a,
+        dyn hal::DynBuffer,
+        dyn hal::DynAccelerationStructure,
+    >,
+    BuildAccelerationStructureError,
 > {
-    let (blas, entries, scratch_buffer_offset) = storage;
-    if blas.update_mode == wgt::AccelerationStructureUpdateMode::PreferUpdate {
-        log::info!("only rebuild implemented")
-    }
-    hal::BuildAccelerationStructureDescriptor {
+    let (blas, entries, scratch_buffer_offset, mode) = storage;
+    Ok(hal::BuildAccelerationStructureDescriptor {
         entries,
-        mode: hal::AccelerationStructureBuildMode::Build,
+        mode: map_build_mode(*mode),
         flags: blas.flags,
         source_acceleration_structure: None,
-        destination_acceleration_structure: blas.raw.as_ref(),
+        destination_acceleration_structure: blas
+            .try_raw(snatch_guard)
+            .map_err(|_| BuildAccelerationStructureError::InvalidBlas(blas.error_ident()))?,
         scratch_buffer,
-        scratch_buffer_offset: *scratch_buffer_offset,
-    }
+        scratch_buffer_offset: scratch_base_offset + *scratch_buffer_offset,
+    })
 }
 
 fn build_blas<'a>(
@@ -1345,13 +2190,24 @@ fn build_blas<'a>(
         cmd_buf_raw.transition_buffers(&input_barriers);
     }
 
+    // Builds recorded into other command buffers (possibly on other threads) and submitted
+    // earlier or in the same submission may have written to structures these builds read
+    // (or the other way around), so order against all previous builds. In particular, a TLAS
+    // build that is still in flight reads the BLASes its instances reference, which must not be
+    // overwritten by a rebuild until it is done. The BLAS and TLAS builds of this call are
+    // only ordered against each other by the barrier below, which is why a call may not build
+    // the same acceleration structure twice.
+    unsafe {
+        cmd_buf_raw.place_acceleration_structure_barrier(hal::AccelerationStructureBarrier {
+            usage: hal::AccelerationStructureUses::BUILD_INPUT
+                | hal::AccelerationStructureUses::BUILD_OUTPUT
+                ..hal::AccelerationStructureUses::BUILD_INPUT
+                    | hal::AccelerationStructureUses::BUILD_OUTPUT,
+        });
+    }
+
     if blas_present {
         unsafe {
-            cmd_buf_raw.place_acceleration_structure_barrier(hal::AccelerationStructureBarrier {
-                usage: hal::AccelerationStructureUses::BUILD_INPUT
-                    ..hal::AccelerationStructureUses::BUILD_OUTPUT,
-            });
-
             cmd_buf_raw.build_acceleration_structures(blas_descriptors);
         }
     }
@@ -1377,4 +2233,19 @@ fn build_blas<'a>(
             usage: source_usage..destination_usage,
         });
     }
+
+    // The barrier above orders the writes after the builds they measure.
+    if blas_present {
+        for descriptor in blas_descriptors.iter().filter(|descriptor| {
+            descriptor
+                .flags
+                .contains(hal::AccelerationStructureBuildFlags::ALLOW_COMPACTION)
+        }) {
+            unsafe {
+                cmd_buf_raw.write_acceleration_structure_compacted_size(
+                    descriptor.destination_acceleration_structure,
+                );
+            }
+        }
+    }
 }
@@ -101,6 +101,14 @@ pub fn map_buffer_usage(usage: wgt::BufferUsages) -> hal::BufferUses {
         hal::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT,
         usage.contains(wgt::BufferUsages::TLAS_INPUT),
     );
+    u.set(
+        hal::BufferUses::ACCELERATION_STRUCTURE_STORAGE,
+        usage.contains(wgt::BufferUsages::ACCELERATION_STRUCTURE_STORAGE),
+    );
+    u.set(
+        hal::BufferUses::ACCELERATION_STRUCTURE_SCRATCH,
+        usage.contains(wgt::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH),
+    );
     u
 }
 
@@ -248,3 +256,10 @@ pub fn bind_group_layout_flags(features: wgt::Features) -> hal::BindGroupLayoutF
     );
     flags
 }
+
+pub fn map_ray_query_counters(binding: wgt::RayQueryCountersBinding) -> naga::ResourceBinding {
+    naga::ResourceBinding {
+        group: binding.group,
+        binding: binding.binding,
+    }
+}
@@ -1357,6 +1357,7 @@ impl Global {
                         .vertex
                         .stage
                         .zero_initialize_workgroup_memory,
+                    ray_query_counters: desc.vertex.stage.ray_query_counters,
                 };
                 ResolvedVertexState {
                     stage,
@@ -1383,6 +1384,7 @@ impl Global {
                         .vertex
                         .stage
                         .zero_initialize_workgroup_memory,
+                    ray_query_counters: state.stage.ray_query_counters,
                 };
                 Some(ResolvedFragmentState {
                     stage,
@@ -1597,6 +1599,7 @@ impl Global {
                 entry_point: desc.stage.entry_point.clone(),
                 constants: desc.stage.constants.clone(),
                 zero_initialize_workgroup_memory: desc.stage.zero_initialize_workgroup_memory,
+                ray_query_counters: desc.stage.ray_query_counters,
             };
 
             let desc = ResolvedComputePipelineDescriptor {
@@ -2319,7 +2322,7 @@ impl Global {
         if let Ok(device) = hub.devices.get(device_id) {
             wgt::InternalCounters {
                 hal: device.get_hal_counters(),
-                core: wgt::CoreCounters {},
+                core: device.counters.clone(),
             }
         } else {
             Default::default()
@@ -459,6 +459,14 @@ pub fn create_validator(
     downlevel: wgt::DownlevelFlags,
     flags: naga::valid::ValidationFlags,
 ) -> naga::valid::Validator {
+    naga::valid::Validator::new(flags, naga_capabilities(features, downlevel))
+}
+
+/// The shader capabilities enabled by the given features and downlevel flags.
+pub fn naga_capabilities(
+    features: wgt::Features,
+    downlevel: wgt::DownlevelFlags,
+) -> naga::valid::Capabilities {
     use naga::valid::Capabilities as Caps;
     let mut caps = Caps::empty();
     caps.set(
@@ -529,9 +537,13 @@ pub fn create_validator(
         Caps::SUBGROUP_BARRIER,
         features.intersects(wgt::Features::SUBGROUP_BARRIER),
     );
+    // `RAY_QUERY` itself is checked per entry point at pipeline creation, see
+    // `StageError::MissingRayQueryFeature`.
     caps.set(
         Caps::RAY_QUERY,
-        features.intersects(wgt::Features::RAY_QUERY),
+        features.intersects(
+            wgt::Features::RAY_QUERY | wgt::Features::RAY_TRACING_ACCELERATION_STRUCTURE,
+        ),
     );
     caps.set(
         Caps::SUBGROUP_VERTEX_STAGE,
@@ -542,5 +554,5 @@ pub fn create_validator(
         features.intersects(wgt::Features::RAY_HIT_VERTEX_RETURN),
     );
 
-    naga::valid::Validator::new(flags, caps)
+    caps
 }
@@ -15,9 +15,10 @@ use crate::{
     init_tracker::{has_copy_partial_init_tracker_coverage, TextureInitRange},
     lock::RwLockWriteGuard,
     resource::{
-        Buffer, BufferAccessError, BufferMapState, DestroyedBuffer, DestroyedResourceError,
-        DestroyedTexture, FlushedStagingBuffer, Labeled, ParentDevice, ResourceErrorIdent,
-        StagingBuffer, Texture, TextureInner, Trackable,
+        AccelerationStructure, Buffer, BufferAccessError, BufferMapState,
+        DestroyedAccelerationStructure, DestroyedBuffer, DestroyedResourceError, DestroyedTexture,
+        FlushedStagingBuffer, Labeled, ParentDevice, ResourceErrorIdent, StagingBuffer, Texture,
+        TextureInner, TlasInstanceBufferUse, Trackable,
     },
     resource_log,
     track::{self, Tracker, TrackerIndex},
@@ -26,7 +27,7 @@ use crate::{
 
 use smallvec::SmallVec;
 
-use crate::resource::{Blas, ScratchBuffer, Tlas};
+use crate::resource::ScratchBuffer;
 use std::{
     iter,
     mem::{self, ManuallyDrop},
@@ -146,8 +147,7 @@ pub enum TempResource {
     ScratchBuffer(ScratchBuffer),
     DestroyedBuffer(DestroyedBuffer),
     DestroyedTexture(DestroyedTexture),
-    Blas(Arc<Blas>),
-    Tlas(Arc<Tlas>),
+    DestroyedAccelerationStructure(DestroyedAccelerationStructure),
 }
 
 /// A series of raw [`CommandBuffer`]s that have been submitted to a
@@ -164,6 +164,8 @@ pub(crate) struct EncoderInFlight {
     pub(crate) pending_buffers: FastHashMap<TrackerIndex, Arc<Buffer>>,
     /// These are the textures that have been tracked by `PendingWrites`.
     pub(crate) pending_textures: FastHashMap<TrackerIndex, Arc<Texture>>,
+    /// The TLAS instance buffers read by the builds of the command buffers.
+    tlas_instance_buffer_uses: Vec<TlasInstanceBufferUse>,
 }
 
 impl EncoderInFlight {
@@ -180,6 +182,7 @@ impl EncoderInFlight {
             drop(self.trackers);
             drop(self.pending_buffers);
             drop(self.pending_textures);
+            drop(self.tlas_instance_buffer_uses);
         }
         self.raw
     }
@@ -291,6 +294,7 @@ impl PendingWrites {
                 trackers: Tracker::new(),
                 pending_buffers,
                 pending_textures,
+                tlas_instance_buffer_uses: Vec::new(),
             };
             Ok(Some(encoder))
         } else {
@@ -341,6 +345,15 @@ pub enum QueueWriteError {
     DestroyedResource(#[from] DestroyedResourceError),
 }
 
+#[derive(Clone, Debug, Error)]
+#[non_exhaustive]
+pub enum QueueWaitError {
+    #[error("QueueId is invalid")]
+    InvalidQueueId,
+    #[error(transparent)]
+    WaitIdle(#[from] WaitIdleError),
+}
+
 #[derive(Clone, Debug, Error)]
 #[non_exhaustive]
 pub enum QueueSubmitError {
@@ -1100,6 +1113,8 @@ impl Global {
             // This avoids vulkan deadlocking from the same surface texture being submitted multiple times.
             let mut submit_surface_textures_owned = FastHashMap::default();
 
+            let mut build_summary = crate::ray_tracing::SubmissionBuildSummary::default();
+
             {
                 let mut command_buffer_guard = hub.command_buffers.write();
 
@@ -1171,6 +1186,15 @@ impl Global {
                                     }
                                 }
                             }
+                            {
+                                profiling::scope!("acceleration structures");
+                                for blas in cmd_buf_trackers.blas_s.used_resources() {
+                                    blas.try_raw(&snatch_guard)?;
+                                }
+                                for tlas in cmd_buf_trackers.tlas_s.used_resources() {
+                                    tlas.try_raw(&snatch_guard)?;
+                                }
+                            }
                             {
                                 profiling::scope!("textures");
                                 for texture in cmd_buf_trackers.textures.used_resources() {
@@ -1216,8 +1240,9 @@ impl Global {
                         let mut trackers = device.trackers.lock();
                         baked.initialize_buffer_memory(&mut trackers, &snatch_guard)?;
                         baked.initialize_texture_memory(&mut trackers, device, &snatch_guard)?;
+                        baked.summarize_builds(&mut build_summary);
                         baked.validate_blas_actions()?;
-                        baked.validate_tlas_actions()?;
+                        baked.validate_tlas_actions(&snatch_guard)?;
                         //Note: stateless trackers are not merged:
                         // device already knows these resources exist.
                         CommandBuffer::insert_barriers_from_device_tracker(
@@ -1265,6 +1290,7 @@ impl Global {
                             trackers: baked.trackers,
                             pending_buffers: FastHashMap::default(),
                             pending_textures: FastHashMap::default(),
+                            tlas_instance_buffer_uses: baked.tlas_instance_buffer_uses,
                         });
                     }
                 }
@@ -1349,6 +1375,11 @@ impl Global {
                 device
                     .last_successful_submission_index
                     .fetch_max(submit_index, Ordering::SeqCst);
+                if !build_summary.is_empty() {
+                    device
+                        .last_acceleration_structure_build_index
+                        .fetch_max(submit_index, Ordering::SeqCst);
+                }
             }
 
             profiling::scope!("cleanup");
@@ -1372,6 +1403,10 @@ impl Global {
                     Err(WaitIdleError::WrongSubmissionIndex(..)) => unreachable!(),
                 };
 
+            if !build_summary.is_empty() {
+                log::debug!("Queue::submit {submit_index}: {build_summary}");
+            }
+
             (submit_index, closures)
         };
 
@@ -1406,4 +1441,40 @@ impl Global {
         }
         Ok(())
     }
+
+    /// Block until every acceleration structure build submitted to `queue_id`
+    /// so far has completed, without waiting for later submissions.
+    pub fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        queue_id: QueueId,
+    ) -> Result<(), QueueWaitError> {
+        api_log!("Queue::wait_for_acceleration_structure_builds {queue_id:?}");
+
+        let hub = &self.hub;
+        let queue = hub
+            .queues
+            .get(queue_id)
+            .map_err(|_| QueueWaitError::InvalidQueueId)?;
+        let device = &queue.device;
+
+        let submission_index = device
+            .last_acceleration_structure_build_index
+            .load(Ordering::Acquire);
+        if submission_index == 0 {
+            return Ok(());
+        }
+
+        let snatch_guard = device.snatchable_lock.read();
+        let fence = device.fence.read();
+        let (closures, _) = device.maintain(
+            fence,
+            wgt::Maintain::WaitForSubmissionIndex(submission_index),
+            snatch_guard,
+        )?;
+        device.deferred_resource_destruction();
+
+        closures.fire();
+
+        Ok(())
+    }
 }
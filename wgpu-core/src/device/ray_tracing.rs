@@ -1,27 +1,295 @@
+use std::borrow::Cow;
 use std::mem::ManuallyDrop;
-use std::sync::Arc;
+use std::sync::{atomic::AtomicUsize, Arc};
 
 use hal::AccelerationStructureTriangleIndices;
 
 #[cfg(feature = "trace")]
 use crate::device::trace;
 use crate::lock::rank;
-use crate::resource::TrackingData;
+use crate::resource::{AccelerationStructure, Labeled, ParentDevice, TrackingData};
 use crate::{
-    device::{queue::TempResource, Device, DeviceError},
+    command::{aligned_scratch_size, scratch_buffer_size},
+    device::{Device, DeviceError},
     global::Global,
     id::{self, BlasId, TlasId},
-    lock::RwLock,
-    ray_tracing::{get_raw_tlas_instance_size, CreateBlasError, CreateTlasError},
-    resource, LabelHelpers,
+    lock::{Mutex, RwLock},
+    ray_tracing::{
+        get_raw_tlas_instance_size, BuildAccelerationStructureError, CompactBlasError,
+        CreateBlasError, CreateTlasError, InstanceReferenceError,
+    },
+    resource, resource_log,
+    snatch::Snatchable,
+    FastHashMap, LabelHelpers,
 };
 
+/// Combinations of acceleration structure flags that contradict each other,
+/// following the valid usage rules of `VkAccelerationStructureBuildGeometryInfoKHR`.
+const MUTUALLY_EXCLUSIVE_FLAGS: &[wgt::AccelerationStructureFlags] =
+    &[wgt::AccelerationStructureFlags::PREFER_FAST_TRACE
+        .union(wgt::AccelerationStructureFlags::PREFER_FAST_BUILD)];
+
+/// Returns the first combination in [`MUTUALLY_EXCLUSIVE_FLAGS`] that is fully set in `flags`.
+fn find_incompatible_flags(
+    flags: wgt::AccelerationStructureFlags,
+) -> Option<wgt::AccelerationStructureFlags> {
+    MUTUALLY_EXCLUSIVE_FLAGS
+        .iter()
+        .copied()
+        .find(|&combination| flags.contains(combination))
+}
+
+/// Report the sizes in `size_info`, with the scratch sizes as much as a build takes up in a
+/// scratch buffer, so that they can be added up to size a buffer for several builds.
+fn build_sizes_from_hal(
+    size_info: hal::AccelerationStructureBuildSizes,
+) -> wgt::AccelerationStructureBuildSizes {
+    wgt::AccelerationStructureBuildSizes {
+        acceleration_structure_size: size_info.acceleration_structure_size,
+        build_scratch_size: aligned_scratch_size(
+            &size_info,
+            wgt::AccelerationStructureBuildMode::Build,
+        ),
+        update_scratch_size: aligned_scratch_size(
+            &size_info,
+            wgt::AccelerationStructureBuildMode::Update,
+        ),
+    }
+}
+
+/// Number of polls a free scratch buffer may stay unused before
+/// [`ScratchBufferPool::maintain`] releases it.
+pub(crate) const DEFAULT_SCRATCH_POOL_IDLE_POLLS: u32 = 64;
+
+struct FreeScratchBuffer {
+    raw: Box<dyn hal::DynBuffer>,
+    size: wgt::BufferAddress,
+    idle_polls: u32,
+}
+
+struct ScratchBufferPoolInner {
+    free_buffers: Vec<FreeScratchBuffer>,
+    idle_poll_threshold: u32,
+}
+
+/// A pool of free acceleration structure scratch buffers, owned by a `Device`.
+///
+/// Scratch buffers are only needed while a build executes, so once the
+/// submission using one has completed it is returned here to be reused by
+/// later builds instead of being destroyed. Buffers that haven't been reused
+/// for more than the configured number of polls are released, so a burst of
+/// large builds doesn't keep its peak scratch memory alive.
+pub(crate) struct ScratchBufferPool {
+    inner: Mutex<ScratchBufferPoolInner>,
+}
+
+impl ScratchBufferPool {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Mutex::new(
+                rank::SCRATCH_BUFFER_POOL_INNER,
+                ScratchBufferPoolInner {
+                    free_buffers: Vec::new(),
+                    idle_poll_threshold: DEFAULT_SCRATCH_POOL_IDLE_POLLS,
+                },
+            ),
+        }
+    }
+
+    /// Return a scratch buffer of at least `size` bytes, and its actual size.
+    ///
+    /// Takes the smallest free buffer that fits, as long as it is less than
+    /// twice as large as requested. Otherwise, creates a new one on `device`.
+    pub(crate) fn acquire(
+        &self,
+        device: &dyn hal::DynDevice,
+        size: wgt::BufferSize,
+    ) -> Result<(Box<dyn hal::DynBuffer>, wgt::BufferAddress), hal::DeviceError> {
+        let size = size.get();
+        let mut inner = self.inner.lock();
+        let best_fit = inner
+            .free_buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| buffer.size >= size && buffer.size / 2 < size)
+            .min_by_key(|(_, buffer)| buffer.size)
+            .map(|(index, _)| index);
+        if let Some(index) = best_fit {
+            let buffer = inner.free_buffers.swap_remove(index);
+            return Ok((buffer.raw, buffer.size));
+        }
+        drop(inner);
+
+        let raw = unsafe {
+            device.create_buffer(&hal::BufferDescriptor {
+                label: Some("(wgpu) scratch buffer"),
+                size,
+                usage: hal::BufferUses::ACCELERATION_STRUCTURE_SCRATCH | hal::BufferUses::MAP_WRITE,
+                memory_flags: hal::MemoryFlags::empty(),
+            })
+        }?;
+        Ok((raw, size))
+    }
+
+    /// Add `raw` back to the free pool.
+    pub(crate) fn release(&self, raw: Box<dyn hal::DynBuffer>, size: wgt::BufferAddress) {
+        let mut inner = self.inner.lock();
+        inner.free_buffers.push(FreeScratchBuffer {
+            raw,
+            size,
+            idle_polls: 0,
+        });
+    }
+
+    /// Set how many polls a free buffer may stay unused before being released.
+    pub(crate) fn set_idle_poll_threshold(&self, polls: u32) {
+        self.inner.lock().idle_poll_threshold = polls;
+    }
+
+    /// Age all free buffers by one poll, destroying those that have been idle
+    /// for longer than the threshold.
+    ///
+    /// This is called on every `Device::maintain`.
+    pub(crate) fn maintain(&self, device: &dyn hal::DynDevice) {
+        let mut inner = self.inner.lock();
+        let threshold = inner.idle_poll_threshold;
+        let (expired, kept) = std::mem::take(&mut inner.free_buffers)
+            .into_iter()
+            .map(|mut buffer| {
+                buffer.idle_polls += 1;
+                buffer
+            })
+            .partition(|buffer| buffer.idle_polls > threshold);
+        inner.free_buffers = kept;
+        drop(inner);
+
+        for buffer in expired {
+            resource_log!("ScratchBufferPool::maintain releases {} bytes", buffer.size);
+            unsafe { device.destroy_buffer(buffer.raw) };
+        }
+    }
+
+    /// Free the pool of scratch buffers.
+    ///
+    /// This is only called when the `Device` is dropped.
+    pub(crate) fn dispose(&self, device: &dyn hal::DynDevice) {
+        let mut inner = self.inner.lock();
+        resource_log!(
+            "ScratchBufferPool::dispose buffers {}",
+            inner.free_buffers.len()
+        );
+        for buffer in inner.free_buffers.drain(..) {
+            unsafe { device.destroy_buffer(buffer.raw) };
+        }
+    }
+}
+
+/// Number of polls a free BLAS may stay unused before [`BlasPool::maintain`]
+/// releases it.
+pub(crate) const BLAS_POOL_IDLE_POLLS: u32 = 64;
+
+struct FreeBlas {
+    raw: Box<dyn hal::DynAccelerationStructure>,
+    idle_polls: u32,
+}
+
+/// A pool of the memory of dropped BLASes, owned by a `Device`.
+///
+/// Dynamic geometry, like particle systems, often creates and drops many
+/// identically sized BLASes every frame. A BLAS is only dropped once no
+/// submission uses it anymore, so its raw acceleration structure is returned
+/// here, keyed by its size, and reused by the next BLAS of that size instead
+/// of being destroyed. Identical size descriptors always need the same size.
+/// Acceleration structures that haven't been reused for more than
+/// [`BLAS_POOL_IDLE_POLLS`] polls are released, like in the
+/// [`ScratchBufferPool`], so sizes that are no longer used don't pile up.
+pub(crate) struct BlasPool {
+    free: Mutex<FastHashMap<wgt::BufferAddress, Vec<FreeBlas>>>,
+}
+
+impl BlasPool {
+    pub(crate) fn new() -> Self {
+        Self {
+            free: Mutex::new(rank::BLAS_POOL_FREE, FastHashMap::default()),
+        }
+    }
+
+    /// Take a free acceleration structure of exactly `size` bytes, if there is one.
+    fn acquire(&self, size: wgt::BufferAddress) -> Option<Box<dyn hal::DynAccelerationStructure>> {
+        let mut free = self.free.lock();
+        let bucket = free.get_mut(&size)?;
+        let blas = bucket.pop();
+        if bucket.is_empty() {
+            free.remove(&size);
+        }
+        blas.map(|blas| blas.raw)
+    }
+
+    /// Add the acceleration structure `raw` of `size` bytes to the pool.
+    pub(crate) fn release(
+        &self,
+        raw: Box<dyn hal::DynAccelerationStructure>,
+        size: wgt::BufferAddress,
+    ) {
+        self.free
+            .lock()
+            .entry(size)
+            .or_default()
+            .push(FreeBlas { raw, idle_polls: 0 });
+    }
+
+    /// Age all free acceleration structures by one poll, destroying those that
+    /// have been idle for longer than [`BLAS_POOL_IDLE_POLLS`].
+    ///
+    /// This is called on every `Device::maintain`.
+    pub(crate) fn maintain(&self, device: &dyn hal::DynDevice) {
+        let mut free = self.free.lock();
+        let mut expired = Vec::new();
+        free.retain(|_, bucket| {
+            let (bucket_expired, kept) = std::mem::take(bucket)
+                .into_iter()
+                .map(|mut blas| {
+                    blas.idle_polls += 1;
+                    blas
+                })
+                .partition::<Vec<_>, _>(|blas| blas.idle_polls > BLAS_POOL_IDLE_POLLS);
+            expired.extend(bucket_expired);
+            *bucket = kept;
+            !bucket.is_empty()
+        });
+        drop(free);
+
+        if !expired.is_empty() {
+            resource_log!(
+                "BlasPool::maintain releases acceleration structures {}",
+                expired.len()
+            );
+        }
+        for blas in expired {
+            unsafe { device.destroy_acceleration_structure(blas.raw) };
+        }
+    }
+
+    /// Destroy all acceleration structures in the pool.
+    pub(crate) fn trim(&self, device: &dyn hal::DynDevice) {
+        let free = std::mem::take(&mut *self.free.lock());
+        resource_log!(
+            "BlasPool::trim acceleration structures {}",
+            free.values().map(Vec::len).sum::<usize>()
+        );
+        for blas in free.into_values().flatten() {
+            unsafe { device.destroy_acceleration_structure(blas.raw) };
+        }
+    }
+}
+
 impl Device {
-    fn create_blas(
-        self: &Arc<Self>,
+    /// Validate a BLAS descriptor and compute the sizes needed to create and build it.
+    fn blas_build_sizes(
+        &self,
         blas_desc: &resource::BlasDescriptor,
-        sizes: wgt::BlasGeometrySizeDescriptors,
-    ) -> Result<Arc<resource::Blas>, CreateBlasError> {
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> Result<hal::AccelerationStructureBuildSizes, CreateBlasError> {
         if blas_desc
             .flags
             .contains(wgt::AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN)
@@ -29,8 +297,11 @@ impl Device {
         {
             return Err(CreateBlasError::MissingVertexReturnFeature);
         }
+        if let Some(flags) = find_incompatible_flags(blas_desc.flags) {
+            return Err(CreateBlasError::IncompatibleFlags(flags));
+        }
 
-        let size_info = match &sizes {
+        let size_info = match sizes {
             wgt::BlasGeometrySizeDescriptors::Triangles { desc } => {
                 let mut entries =
                     Vec::<hal::AccelerationStructureTriangles<dyn hal::DynBuffer>>::with_capacity(
@@ -40,6 +311,17 @@ impl Device {
                     if x.index_count.is_some() != x.index_format.is_some() {
                         return Err(CreateBlasError::MissingIndexData);
                     }
+                    if !self
+                        .adapter
+                        .raw
+                        .capabilities
+                        .ray_tracing
+                        .is_some_and(|properties| {
+                            properties.supports_vertex_format(x.vertex_format)
+                        })
+                    {
+                        return Err(CreateBlasError::UnsupportedVertexFormat(x.vertex_format));
+                    }
                     let indices =
                         x.index_count
                             .map(|count| AccelerationStructureTriangleIndices::<
@@ -56,6 +338,8 @@ impl Device {
                         first_vertex: 0,
                         vertex_count: x.vertex_count,
                         vertex_stride: 0,
+                        vertex_offset: 0,
+                        vertex_buffer_offset: 0,
                         indices,
                         transform: None,
                         flags: x.flags,
@@ -89,13 +373,60 @@ impl Device {
             }
         };
 
-        let raw = unsafe {
-            self.raw()
-                .create_acceleration_structure(&hal::AccelerationStructureDescriptor {
-                    label: blas_desc.label.as_deref(),
-                    size: size_info.acceleration_structure_size,
-                    format: hal::AccelerationStructureFormat::BottomLevel,
-                })
+        Ok(size_info)
+    }
+
+    /// Create a BLAS, in `backing_buffer` at the given offset if provided, or
+    /// in memory of its own otherwise.
+    fn create_blas(
+        self: &Arc<Self>,
+        blas_desc: &resource::BlasDescriptor,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+        backing_buffer: Option<(Arc<resource::Buffer>, wgt::BufferAddress)>,
+    ) -> Result<Arc<resource::Blas>, CreateBlasError> {
+        let size_info = self.blas_build_sizes(blas_desc, &sizes)?;
+
+        let hal_desc = hal::AccelerationStructureDescriptor {
+            label: blas_desc.label.as_deref(),
+            size: size_info.acceleration_structure_size,
+            format: hal::AccelerationStructureFormat::BottomLevel,
+            allow_compaction: blas_desc
+                .flags
+                .contains(wgt::AccelerationStructureFlags::ALLOW_COMPACTION),
+        };
+        let raw = match backing_buffer {
+            // Pooled acceleration structures can't have their compacted size queried.
+            None if hal_desc.allow_compaction => unsafe {
+                self.raw().create_acceleration_structure(&hal_desc)
+            },
+            None => match self.blas_pool.acquire(hal_desc.size) {
+                Some(raw) => Ok(raw),
+                None => unsafe { self.raw().create_acceleration_structure(&hal_desc) },
+            },
+            Some((ref buffer, offset)) => {
+                buffer.same_device(self)?;
+                buffer.check_usage(wgt::BufferUsages::ACCELERATION_STRUCTURE_STORAGE)?;
+                if offset % wgt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT != 0 {
+                    return Err(CreateBlasError::UnalignedBufferOffset(offset));
+                }
+                if offset
+                    .checked_add(hal_desc.size)
+                    .map_or(true, |end| end > buffer.size)
+                {
+                    return Err(CreateBlasError::BufferOverrun {
+                        offset,
+                        size: hal_desc.size,
+                        buffer_size: buffer.size,
+                    });
+                }
+
+                let snatch_guard = self.snatchable_lock.read();
+                let raw_buffer = buffer.try_raw(&snatch_guard)?;
+                unsafe {
+                    self.raw()
+                        .create_acceleration_structure_in_buffer(&hal_desc, raw_buffer, offset)
+                }
+            }
         }
         .map_err(DeviceError::from)?;
 
@@ -105,23 +436,112 @@ impl Device {
         };
 
         Ok(Arc::new(resource::Blas {
-            raw: ManuallyDrop::new(raw),
+            raw: Snatchable::new(raw),
             device: self.clone(),
             size_info,
             sizes,
             flags: blas_desc.flags,
             update_mode: blas_desc.update_mode,
             handle,
+            backing_buffer: backing_buffer.map(|(buffer, _)| buffer),
             label: blas_desc.label.to_string(),
             built_index: RwLock::new(rank::BLAS_BUILT_INDEX, None),
+            built_counts: RwLock::new(rank::BLAS_BUILT_COUNTS, Vec::new()),
+            compacted_size: Mutex::new(rank::BLAS_COMPACTED_SIZE, None),
+            compacted: false,
             tracking_data: TrackingData::new(self.tracker_indices.blas_s.clone()),
         }))
     }
 
-    fn create_tlas(
+    /// Read back the compacted size of `blas`, and remember it until `blas` is built again.
+    fn read_blas_compacted_size(
+        &self,
+        blas: &Arc<resource::Blas>,
+    ) -> Result<wgt::BufferAddress, CompactBlasError> {
+        if !blas
+            .flags
+            .contains(wgt::AccelerationStructureFlags::ALLOW_COMPACTION)
+        {
+            return Err(CompactBlasError::CompactionNotAllowed(blas.error_ident()));
+        }
+        if blas.built_index.read().is_none() {
+            return Err(CompactBlasError::NotBuilt(blas.error_ident()));
+        }
+        if self
+            .lock_life()
+            .get_blas_latest_submission_index(blas)
+            .is_some()
+        {
+            return Err(CompactBlasError::InFlight(blas.error_ident()));
+        }
+
+        let snatch_guard = self.snatchable_lock.read();
+        let raw = blas.try_raw(&snatch_guard)?;
+        let size = unsafe { self.raw().get_acceleration_structure_compacted_size(raw) }
+            .map_err(DeviceError::from)?;
+        *blas.compacted_size.lock() = Some(size);
+
+        Ok(size)
+    }
+
+    /// Create an unbuilt BLAS just large enough to hold `source` once compacted.
+    fn create_compacted_blas(
         self: &Arc<Self>,
+        source: &Arc<resource::Blas>,
+        label: &crate::Label,
+    ) -> Result<Arc<resource::Blas>, CompactBlasError> {
+        source.same_device(self)?;
+        // The size may have been read back through a buffer instead, see
+        // `Global::command_encoder_resolve_blas_compacted_size`.
+        let compacted_size = *source.compacted_size.lock();
+        let compacted_size = match compacted_size {
+            Some(size) => size,
+            None => self.read_blas_compacted_size(source)?,
+        };
+
+        let hal_desc = hal::AccelerationStructureDescriptor {
+            label: label.as_deref(),
+            size: compacted_size,
+            format: hal::AccelerationStructureFormat::BottomLevel,
+            allow_compaction: false,
+        };
+        let raw = match self.blas_pool.acquire(compacted_size) {
+            Some(raw) => raw,
+            None => unsafe { self.raw().create_acceleration_structure(&hal_desc) }
+                .map_err(DeviceError::from)?,
+        };
+
+        let handle = unsafe {
+            self.raw()
+                .get_acceleration_structure_device_address(raw.as_ref())
+        };
+
+        Ok(Arc::new(resource::Blas {
+            raw: Snatchable::new(raw),
+            device: self.clone(),
+            size_info: hal::AccelerationStructureBuildSizes {
+                acceleration_structure_size: compacted_size,
+                ..source.size_info
+            },
+            sizes: source.sizes.clone(),
+            flags: source.flags - wgt::AccelerationStructureFlags::ALLOW_COMPACTION,
+            update_mode: source.update_mode,
+            handle,
+            backing_buffer: None,
+            label: label.to_string(),
+            built_index: RwLock::new(rank::BLAS_BUILT_INDEX, None),
+            built_counts: RwLock::new(rank::BLAS_BUILT_COUNTS, Vec::new()),
+            compacted_size: Mutex::new(rank::BLAS_COMPACTED_SIZE, None),
+            compacted: true,
+            tracking_data: TrackingData::new(self.tracker_indices.blas_s.clone()),
+        }))
+    }
+
+    /// Validate a TLAS descriptor and compute the sizes needed to create and build it.
+    fn tlas_build_sizes(
+        &self,
         desc: &resource::TlasDescriptor,
-    ) -> Result<Arc<resource::Tlas>, CreateTlasError> {
+    ) -> Result<hal::AccelerationStructureBuildSizes, CreateTlasError> {
         if desc
             .flags
             .contains(wgt::AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN)
@@ -129,6 +549,18 @@ impl Device {
         {
             return Err(CreateTlasError::MissingVertexReturnFeature);
         }
+        if let Some(flags) = find_incompatible_flags(desc.flags) {
+            return Err(CreateTlasError::IncompatibleFlags(flags));
+        }
+
+        let instance_buffer_size = (get_raw_tlas_instance_size() as u64)
+            .checked_mul(std::cmp::max(desc.max_instances, 1) as u64);
+        if instance_buffer_size.map_or(true, |size| size > self.limits.max_buffer_size) {
+            return Err(CreateTlasError::TooManyInstances {
+                max_instances: desc.max_instances,
+                max_buffer_size: self.limits.max_buffer_size,
+            });
+        }
 
         let size_info = unsafe {
             self.raw().get_acceleration_structure_build_sizes(
@@ -145,38 +577,67 @@ impl Device {
             )
         };
 
+        Ok(size_info)
+    }
+
+    fn create_tlas(
+        self: &Arc<Self>,
+        desc: &resource::TlasDescriptor,
+    ) -> Result<Arc<resource::Tlas>, CreateTlasError> {
+        let size_info = self.tlas_build_sizes(desc)?;
+        // Doesn't overflow, `tlas_build_sizes` checked it against the max buffer size.
+        let instance_buffer_size =
+            get_raw_tlas_instance_size() as u64 * std::cmp::max(desc.max_instances, 1) as u64;
+
         let raw = unsafe {
             self.raw()
                 .create_acceleration_structure(&hal::AccelerationStructureDescriptor {
                     label: desc.label.as_deref(),
                     size: size_info.acceleration_structure_size,
                     format: hal::AccelerationStructureFormat::TopLevel,
+                    allow_compaction: false,
                 })
         }
         .map_err(DeviceError::from)?;
 
-        let instance_buffer_size =
-            get_raw_tlas_instance_size() * std::cmp::max(desc.max_instances, 1) as usize;
+        let handle = unsafe {
+            self.raw()
+                .get_acceleration_structure_device_address(raw.as_ref())
+        };
+
+        // Adapters sharing their memory with the host build from host visible instances about
+        // as fast as from any others, so builds can write them in place instead of staging them.
+        let instance_buffer_host_visible =
+            wgt::TlasInstanceUpload::for_device_type(self.adapter.raw.info.device_type)
+                == wgt::TlasInstanceUpload::Mapped;
+        let mut instance_buffer_usage =
+            hal::BufferUses::COPY_DST | hal::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT;
+        if instance_buffer_host_visible {
+            instance_buffer_usage |= hal::BufferUses::MAP_WRITE;
+        }
         let instance_buffer = unsafe {
             self.raw().create_buffer(&hal::BufferDescriptor {
                 label: Some("(wgpu-core) instances_buffer"),
-                size: instance_buffer_size as u64,
-                usage: hal::BufferUses::COPY_DST
-                    | hal::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT,
+                size: instance_buffer_size,
+                usage: instance_buffer_usage,
                 memory_flags: hal::MemoryFlags::PREFER_COHERENT,
             })
         }
         .map_err(DeviceError::from)?;
 
         Ok(Arc::new(resource::Tlas {
-            raw: ManuallyDrop::new(raw),
+            raw: Snatchable::new(raw),
             device: self.clone(),
             size_info,
             flags: desc.flags,
             update_mode: desc.update_mode,
             built_index: RwLock::new(rank::TLAS_BUILT_INDEX, None),
+            built_instance_count: RwLock::new(rank::TLAS_BUILT_INSTANCE_COUNT, 0),
             dependencies: RwLock::new(rank::TLAS_DEPENDENCIES, Vec::new()),
+            handle,
             instance_buffer: ManuallyDrop::new(instance_buffer),
+            instance_buffer_host_visible,
+            instance_buffer_users: AtomicUsize::new(0),
             label: desc.label.to_string(),
             max_instance_count: desc.max_instances,
             tracking_data: TrackingData::new(self.tracker_indices.tlas_s.clone()),
@@ -216,7 +677,7 @@ impl Global {
                 });
             }
 
-            let blas = match device.create_blas(desc, sizes) {
+            let blas = match device.create_blas(desc, sizes, None) {
                 Ok(blas) => blas,
                 Err(e) => break 'error e,
             };
@@ -232,6 +693,255 @@ impl Global {
         (id, None, Some(error))
     }
 
+    /// Create a BLAS whose memory is `buffer`, starting at `offset`, instead of
+    /// memory allocated for it.
+    ///
+    /// The buffer needs the `ACCELERATION_STRUCTURE_STORAGE` usage, `offset` must be a
+    /// multiple of [`wgt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT`] and the buffer must
+    /// hold [`Global::device_get_blas_size`] bytes from `offset` on.
+    pub fn device_create_blas_in_buffer(
+        &self,
+        device_id: id::DeviceId,
+        buffer_id: id::BufferId,
+        offset: wgt::BufferAddress,
+        desc: &resource::BlasDescriptor,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+        id_in: Option<BlasId>,
+    ) -> (BlasId, Option<u64>, Option<CreateBlasError>) {
+        profiling::scope!("Device::create_blas_in_buffer");
+
+        let hub = &self.hub;
+        let fid = hub.blas_s.prepare(device_id.backend(), id_in);
+
+        let device_guard = hub.devices.read();
+        let error = 'error: {
+            let device = match device_guard.get(device_id) {
+                Ok(device) => device,
+                Err(_) => break 'error DeviceError::InvalidDeviceId.into(),
+            };
+            if !device.is_valid() {
+                break 'error DeviceError::Lost.into();
+            }
+            let buffer = match hub.buffers.get(buffer_id) {
+                Ok(buffer) => buffer,
+                Err(_) => break 'error CreateBlasError::InvalidBuffer(buffer_id),
+            };
+
+            #[cfg(feature = "trace")]
+            if let Some(trace) = device.trace.lock().as_mut() {
+                trace.add(trace::Action::CreateBlasInBuffer {
+                    id: fid.id(),
+                    buffer_id,
+                    offset,
+                    desc: desc.clone(),
+                    sizes: sizes.clone(),
+                });
+            }
+
+            let blas = match device.create_blas(desc, sizes, Some((buffer, offset))) {
+                Ok(blas) => blas,
+                Err(e) => break 'error e,
+            };
+            let handle = blas.handle;
+
+            let id = fid.assign(blas.clone());
+            log::info!(
+                "Created blas {:?} in buffer {:?} at offset {} with {:?}",
+                id,
+                buffer_id,
+                offset,
+                desc
+            );
+
+            return (id, Some(handle), None);
+        };
+
+        let id = fid.assign_error();
+        (id, None, Some(error))
+    }
+
+    /// Create an unbuilt BLAS with room for `source_id` once compacted, which
+    /// [`Global::command_encoder_copy_blas`] can then copy it into.
+    ///
+    /// If the compacted size of `source_id` wasn't read with [`Global::blas_compacted_size`]
+    /// since it was last built, it is read now, so the submission building it must have
+    /// completed. The new BLAS takes the sizes, flags and update mode of `source_id`, and can't
+    /// be built itself.
+    pub fn device_create_compacted_blas(
+        &self,
+        device_id: id::DeviceId,
+        source_id: BlasId,
+        label: crate::Label,
+        id_in: Option<BlasId>,
+    ) -> (BlasId, Option<u64>, Option<CreateBlasError>) {
+        profiling::scope!("Device::create_compacted_blas");
+
+        let hub = &self.hub;
+        let fid = hub.blas_s.prepare(device_id.backend(), id_in);
+
+        let device_guard = hub.devices.read();
+        let error = 'error: {
+            let device = match device_guard.get(device_id) {
+                Ok(device) => device,
+                Err(_) => break 'error DeviceError::InvalidDeviceId.into(),
+            };
+            if !device.is_valid() {
+                break 'error DeviceError::Lost.into();
+            }
+            let source = match hub.blas_s.get(source_id) {
+                Ok(blas) => blas,
+                Err(_) => break 'error CompactBlasError::InvalidBlasId.into(),
+            };
+
+            #[cfg(feature = "trace")]
+            if let Some(trace) = device.trace.lock().as_mut() {
+                trace.add(trace::Action::CreateCompactedBlas {
+                    id: fid.id(),
+                    source_id,
+                    label: label.clone(),
+                });
+            }
+
+            let blas = match device.create_compacted_blas(&source, &label) {
+                Ok(blas) => blas,
+                Err(e) => break 'error e.into(),
+            };
+            let handle = blas.handle;
+
+            let id = fid.assign(blas);
+            log::info!("Created blas {:?} compacted from {:?}", id, source_id);
+
+            return (id, Some(handle), None);
+        };
+
+        let id = fid.assign_error();
+        (id, None, Some(error))
+    }
+
+    /// Read back the number of bytes `blas_id` occupies once compacted.
+    ///
+    /// The BLAS must have been created with flag `ALLOW_COMPACTION`, and the submission
+    /// building it must have completed. The size stays valid until the BLAS is built again.
+    pub fn blas_compacted_size(
+        &self,
+        blas_id: BlasId,
+    ) -> Result<wgt::BufferAddress, CompactBlasError> {
+        profiling::scope!("Blas::compacted_size");
+
+        let blas = self
+            .hub
+            .blas_s
+            .get(blas_id)
+            .map_err(|_| CompactBlasError::InvalidBlasId)?;
+        blas.device.read_blas_compacted_size(&blas)
+    }
+
+    /// Return the number of bytes a BLAS created with `desc` and `sizes` occupies, which
+    /// a buffer passed to [`Global::device_create_blas_in_buffer`] must hold.
+    pub fn device_get_blas_size(
+        &self,
+        device_id: id::DeviceId,
+        desc: &resource::BlasDescriptor,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> Result<wgt::BufferAddress, CreateBlasError> {
+        let device = self
+            .hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+
+        let size_info = device.blas_build_sizes(desc, sizes)?;
+        Ok(size_info.acceleration_structure_size)
+    }
+
+    /// Return the sizes of a BLAS created with `desc` and `sizes` and of the scratch memory
+    /// its builds and updates use, without creating it.
+    pub fn device_get_blas_build_sizes(
+        &self,
+        device_id: id::DeviceId,
+        desc: &resource::BlasDescriptor,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> Result<wgt::AccelerationStructureBuildSizes, CreateBlasError> {
+        let device = self
+            .hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+
+        let size_info = device.blas_build_sizes(desc, sizes)?;
+        Ok(build_sizes_from_hal(size_info))
+    }
+
+    /// Return the sizes of a TLAS created with `desc` and of the scratch memory its builds
+    /// and updates use, without creating it.
+    pub fn device_get_tlas_build_sizes(
+        &self,
+        device_id: id::DeviceId,
+        desc: &resource::TlasDescriptor,
+    ) -> Result<wgt::AccelerationStructureBuildSizes, CreateTlasError> {
+        let device = self
+            .hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+
+        let size_info = device.tlas_build_sizes(desc)?;
+        Ok(build_sizes_from_hal(size_info))
+    }
+
+    /// Size of the scratch buffer range needed to build `blas` and `tlas` in a single
+    /// [`Global::command_encoder_build_acceleration_structures_with_scratch`] call.
+    ///
+    /// Each acceleration structure is paired with the mode it is built in, a full build if
+    /// `None`. Updates only need the usually smaller update scratch size.
+    pub fn device_get_build_scratch_size(
+        &self,
+        device_id: id::DeviceId,
+        blas: &[(BlasId, Option<wgt::AccelerationStructureBuildMode>)],
+        tlas: &[(TlasId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> Result<wgt::BufferAddress, BuildAccelerationStructureError> {
+        let hub = &self.hub;
+        let device = hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+
+        let blas_guard = hub.blas_s.read();
+        let blas_s = blas
+            .iter()
+            .map(|&(id, mode)| {
+                let blas = blas_guard
+                    .get(id)
+                    .map_err(|_| BuildAccelerationStructureError::InvalidBlasId)?;
+                blas.same_device(&device)?;
+                Ok((
+                    blas,
+                    mode.unwrap_or(wgt::AccelerationStructureBuildMode::Build),
+                ))
+            })
+            .collect::<Result<Vec<_>, BuildAccelerationStructureError>>()?;
+        let tlas_guard = hub.tlas_s.read();
+        let tlas_s = tlas
+            .iter()
+            .map(|&(id, mode)| {
+                let tlas = tlas_guard
+                    .get(id)
+                    .map_err(|_| BuildAccelerationStructureError::InvalidTlasId)?;
+                tlas.same_device(&device)?;
+                Ok((
+                    tlas,
+                    mode.unwrap_or(wgt::AccelerationStructureBuildMode::Build),
+                ))
+            })
+            .collect::<Result<Vec<_>, BuildAccelerationStructureError>>()?;
+
+        let blas_size =
+            scratch_buffer_size(blas_s.iter().map(|(blas, mode)| (&blas.size_info, *mode)));
+        let tlas_size =
+            scratch_buffer_size(tlas_s.iter().map(|(tlas, mode)| (&tlas.size_info, *mode)));
+        Ok(blas_size.max(tlas_size))
+    }
+
     pub fn device_create_tlas(
         &self,
         device_id: id::DeviceId,
@@ -272,35 +982,151 @@ impl Global {
         (id, Some(error))
     }
 
+    /// Create a new tlas like `tlas_id` (on the same device, with the same label, flags and
+    /// update mode), but with room for `max_instances` instances.
+    ///
+    /// The new tlas is unbuilt, and `tlas_id` is left untouched.
+    pub fn tlas_create_resized(
+        &self,
+        tlas_id: TlasId,
+        max_instances: u32,
+        id_in: Option<TlasId>,
+    ) -> (TlasId, Option<CreateTlasError>) {
+        profiling::scope!("Tlas::create_resized");
+
+        let hub = &self.hub;
+        let fid = hub.tlas_s.prepare(tlas_id.backend(), id_in);
+
+        let error = 'error: {
+            let tlas = match hub.tlas_s.get(tlas_id) {
+                Ok(tlas) => tlas,
+                Err(_) => break 'error CreateTlasError::InvalidTlasId,
+            };
+            let desc = resource::TlasDescriptor {
+                label: Some(Cow::Owned(tlas.label.clone())),
+                max_instances,
+                flags: tlas.flags,
+                update_mode: tlas.update_mode,
+            };
+            let device = &tlas.device;
+            #[cfg(feature = "trace")]
+            if let Some(trace) = device.trace.lock().as_mut() {
+                trace.add(trace::Action::CreateTlas {
+                    id: fid.id(),
+                    desc: desc.clone(),
+                });
+            }
+
+            let resized = match device.create_tlas(&desc) {
+                Ok(tlas) => tlas,
+                Err(e) => break 'error e,
+            };
+
+            let id = fid.assign(resized);
+            log::info!(
+                "Created tlas {:?} resized from {:?} with {:?}",
+                id,
+                tlas_id,
+                desc
+            );
+
+            return (id, None);
+        };
+
+        let id = fid.assign_error();
+        (id, Some(error))
+    }
+
+    /// Set how many polls an unused acceleration structure scratch buffer is
+    /// kept around for reuse before it is released.
+    pub fn device_set_scratch_pool_idle_polls(
+        &self,
+        device_id: id::DeviceId,
+        polls: u32,
+    ) -> Result<(), DeviceError> {
+        let hub = &self.hub;
+
+        let device = hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+        device.scratch_pool.set_idle_poll_threshold(polls);
+
+        Ok(())
+    }
+
+    /// Destroy the memory of dropped BLASes that is kept around to be reused by
+    /// BLASes of the same size.
+    pub fn device_trim_blas_pool(&self, device_id: id::DeviceId) -> Result<(), DeviceError> {
+        let hub = &self.hub;
+
+        let device = hub
+            .devices
+            .get(device_id)
+            .map_err(|_| DeviceError::InvalidDeviceId)?;
+        device.blas_pool.trim(device.raw());
+
+        Ok(())
+    }
+
+    /// Look up the bottom level acceleration structures whose raw handles (as written into
+    /// the acceleration structure reference of a raw instance) are `handles`.
+    ///
+    /// Handles of top level acceleration structures are rejected, since instances can't
+    /// reference them, as are handles that don't belong to a live acceleration structure.
+    pub fn blas_ids_from_handles(
+        &self,
+        backend: wgt::Backend,
+        handles: &[u64],
+    ) -> Vec<Result<BlasId, InstanceReferenceError>> {
+        // Only the requested handles are looked up, instead of indexing the whole storage.
+        let mut blas_ids = handles
+            .iter()
+            .map(|&handle| (handle, None))
+            .collect::<FastHashMap<u64, Option<BlasId>>>();
+        for (id, blas) in self.hub.blas_s.read().iter(backend) {
+            if let Some(slot) = blas_ids.get_mut(&blas.handle) {
+                *slot = Some(id);
+            }
+        }
+        let mut tlas_ids = FastHashMap::default();
+        if blas_ids.values().any(Option::is_none) {
+            for (id, tlas) in self.hub.tlas_s.read().iter(backend) {
+                if blas_ids.get(&tlas.handle) == Some(&None) {
+                    tlas_ids.insert(tlas.handle, id);
+                }
+            }
+        }
+
+        handles
+            .iter()
+            .map(|handle| match blas_ids[handle] {
+                Some(id) => Ok(id),
+                None => Err(match tlas_ids.get(handle) {
+                    Some(&id) => InstanceReferenceError::TopLevel(*handle, id),
+                    None => InstanceReferenceError::Unknown(*handle),
+                }),
+            })
+            .collect()
+    }
+
     pub fn blas_destroy(&self, blas_id: BlasId) -> Result<(), resource::DestroyError> {
         profiling::scope!("Blas::destroy");
 
         let hub = &self.hub;
 
         log::info!("Blas {:?} is destroyed", blas_id);
-        let blas_guard = hub.blas_s.write();
-        let blas = blas_guard
+        let blas = hub
+            .blas_s
             .get(blas_id)
-            .map_err(|_| resource::DestroyError::Invalid)?
-            .clone();
-        drop(blas_guard);
-        let device = &blas.device;
+            .map_err(|_| resource::DestroyError::Invalid)?;
 
         #[cfg(feature = "trace")]
-        if let Some(trace) = device.trace.lock().as_mut() {
+        if let Some(trace) = blas.device.trace.lock().as_mut() {
             trace.add(trace::Action::FreeBlas(blas_id));
         }
 
-        let temp = TempResource::Blas(blas.clone());
-        {
-            let mut device_lock = device.lock_life();
-            let last_submit_index = device_lock.get_blas_latest_submission_index(blas.as_ref());
-            if let Some(last_submit_index) = last_submit_index {
-                device_lock.schedule_resource_destruction(temp, last_submit_index);
-            }
-        }
-
-        Ok(())
+        blas.destroy()
     }
 
     pub fn blas_drop(&self, blas_id: BlasId) {
@@ -332,30 +1158,17 @@ impl Global {
         let hub = &self.hub;
 
         log::info!("Tlas {:?} is destroyed", tlas_id);
-        let tlas_guard = hub.tlas_s.write();
-        let tlas = tlas_guard
+        let tlas = hub
+            .tlas_s
             .get(tlas_id)
-            .map_err(|_| resource::DestroyError::Invalid)?
-            .clone();
-        drop(tlas_guard);
-
-        let device = &mut tlas.device.clone();
+            .map_err(|_| resource::DestroyError::Invalid)?;
 
         #[cfg(feature = "trace")]
-        if let Some(trace) = device.trace.lock().as_mut() {
+        if let Some(trace) = tlas.device.trace.lock().as_mut() {
             trace.add(trace::Action::FreeTlas(tlas_id));
         }
 
-        let temp = TempResource::Tlas(tlas.clone());
-        {
-            let mut device_lock = device.lock_life();
-            let last_submit_index = device_lock.get_tlas_latest_submission_index(tlas.as_ref());
-            if let Some(last_submit_index) = last_submit_index {
-                device_lock.schedule_resource_destruction(temp, last_submit_index);
-            }
-        }
-
-        Ok(())
+        tlas.destroy()
     }
 
     pub fn tlas_drop(&self, tlas_id: TlasId) {
@@ -4,10 +4,11 @@ use crate::{
     binding_model::{self, BindGroup, BindGroupLayout, BindGroupLayoutEntryError},
     command, conv,
     device::{
-        bgl, create_validator,
+        bgl,
         life::{LifetimeTracker, WaitIdleError},
-        map_buffer,
+        map_buffer, naga_capabilities,
         queue::PendingWrites,
+        ray_tracing::{BlasPool, ScratchBufferPool},
         AttachmentData, DeviceLostInvocation, HostMap, MissingDownlevelFlags, MissingFeatures,
         RenderPassContext, CLEANUP_WAIT_MS,
     },
@@ -87,6 +88,10 @@ pub struct Device {
     label: String,
 
     pub(crate) command_allocator: command::CommandAllocator,
+    /// Pool of free acceleration structure scratch buffers.
+    pub(crate) scratch_pool: ScratchBufferPool,
+    /// Pool of the acceleration structures of dropped BLASes, to reuse them.
+    pub(crate) blas_pool: BlasPool,
 
     /// The index of the last command submission that was attempted.
     ///
@@ -108,6 +113,10 @@ pub struct Device {
     /// [`active_submission_index`]: Device::active_submission_index
     pub(crate) last_successful_submission_index: hal::AtomicFenceValue,
 
+    /// The index of the last successful submission that built acceleration
+    /// structures, or 0 if there was none.
+    pub(crate) last_acceleration_structure_build_index: hal::AtomicFenceValue,
+
     // NOTE: if both are needed, the `snatchable_lock` must be consistently acquired before the
     // `fence` lock to avoid deadlocks.
     pub(crate) fence: RwLock<ManuallyDrop<Box<dyn hal::DynFence>>>,
@@ -147,6 +156,7 @@ pub struct Device {
     pub(crate) trace: Mutex<Option<trace::Trace>>,
     pub(crate) usage_scopes: UsageScopePool,
     pub(crate) last_acceleration_structure_build_command_index: AtomicU64,
+    pub(crate) counters: wgt::CoreCounters,
 }
 
 pub(crate) enum DeferredDestroy {
@@ -178,6 +188,8 @@ impl Drop for Device {
         let fence = unsafe { ManuallyDrop::take(&mut self.fence.write()) };
         pending_writes.dispose(raw.as_ref());
         self.command_allocator.dispose(raw.as_ref());
+        self.scratch_pool.dispose(raw.as_ref());
+        self.blas_pool.trim(raw.as_ref());
         unsafe {
             raw.destroy_buffer(zero_buffer);
             raw.destroy_fence(fence);
@@ -282,8 +294,11 @@ impl Device {
             zero_buffer: ManuallyDrop::new(zero_buffer),
             label: desc.label.to_string(),
             command_allocator,
+            scratch_pool: ScratchBufferPool::new(),
+            blas_pool: BlasPool::new(),
             active_submission_index: AtomicU64::new(0),
             last_successful_submission_index: AtomicU64::new(0),
+            last_acceleration_structure_build_index: AtomicU64::new(0),
             fence: RwLock::new(rank::DEVICE_FENCE, ManuallyDrop::new(fence)),
             snatchable_lock: unsafe { SnatchLock::new(rank::DEVICE_SNATCHABLE_LOCK) },
             valid: AtomicBool::new(true),
@@ -320,6 +335,7 @@ impl Device {
             deferred_destroy: Mutex::new(rank::DEVICE_DEFERRED_DESTROY, Vec::new()),
             usage_scopes: Mutex::new(rank::DEVICE_USAGE_SCOPES, Default::default()),
             last_acceleration_structure_build_command_index: AtomicU64::new(0),
+            counters: Default::default(),
         })
     }
 
@@ -496,6 +512,9 @@ impl Device {
         drop(fence);
         drop(snatch_guard);
 
+        self.scratch_pool.maintain(self.raw());
+        self.blas_pool.maintain(self.raw());
+
         if should_release_gpu_resource {
             self.release_gpu_resources();
         }
@@ -536,6 +555,13 @@ impl Device {
             return Err(resource::CreateBufferError::InvalidUsage(desc.usage));
         }
 
+        if desc.usage.intersects(
+            wgt::BufferUsages::ACCELERATION_STRUCTURE_STORAGE
+                | wgt::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH,
+        ) {
+            self.require_features(wgt::Features::RAY_TRACING_ACCELERATION_STRUCTURE)?;
+        }
+
         if !self
             .features
             .contains(wgt::Features::MAPPABLE_PRIMARY_BUFFERS)
@@ -1518,24 +1544,23 @@ impl Device {
                 None
             };
 
-        let info = create_validator(
-            self.features,
-            self.downlevel.flags,
-            naga::valid::ValidationFlags::all(),
-        )
-        .validate(&module)
-        .map_err(|inner| {
-            pipeline::CreateShaderModuleError::Validation(naga::error::ShaderError {
-                source,
-                label: desc.label.as_ref().map(|l| l.to_string()),
-                inner: Box::new(inner),
-            })
-        })?;
+        let capabilities = naga_capabilities(self.features, self.downlevel.flags);
+        let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(&module)
+            .map_err(|inner| {
+                pipeline::CreateShaderModuleError::Validation(naga::error::ShaderError {
+                    source,
+                    label: desc.label.as_ref().map(|l| l.to_string()),
+                    inner: Box::new(inner),
+                })
+            })?;
 
-        let interface = validation::Interface::new(&module, &info, self.limits.clone());
+        let interface =
+            validation::Interface::new(&module, &info, self.limits.clone(), self.features);
         let hal_shader = hal::ShaderInput::Naga(hal::NagaShader {
             module,
             info,
+            capabilities,
             debug_source,
         });
         let hal_desc = hal::ShaderModuleDescriptor {
@@ -1793,7 +1818,12 @@ impl Device {
                         },
                     )
                 }
-                Bt::AccelerationStructure { .. } => (None, WritableStorage::No),
+                Bt::AccelerationStructure { vertex_return } => {
+                    if vertex_return {
+                        required_features |= wgt::Features::RAY_HIT_VERTEX_RETURN;
+                    }
+                    (None, WritableStorage::No)
+                }
             };
 
             // Validate the count parameter
@@ -2118,6 +2148,7 @@ impl Device {
         binding: u32,
         decl: &wgt::BindGroupLayoutEntry,
         tlas: &'a Arc<Tlas>,
+        snatch_guard: &'a SnatchGuard<'a>,
     ) -> Result<&'a dyn hal::DynAccelerationStructure, binding_model::CreateBindGroupError> {
         use crate::binding_model::CreateBindGroupError as Error;
 
@@ -2136,7 +2167,8 @@ impl Device {
             }
         }
 
-        Ok(tlas.raw())
+        tlas.try_raw(snatch_guard)
+            .map_err(|_| Error::InvalidTlas(tlas.error_ident()))
     }
 
     // This function expects the provided bind group layout to be resolved
@@ -2293,7 +2325,8 @@ impl Device {
                         }
                     }
 
-                    let tlas = self.create_tlas_binding(&mut used, binding, decl, tlas)?;
+                    let tlas =
+                        self.create_tlas_binding(&mut used, binding, decl, tlas, &snatch_guard)?;
 
                     let res_index = hal_tlas_s.len();
                     hal_tlas_s.push(tlas);
@@ -2741,6 +2774,15 @@ impl Device {
                     io,
                     None,
                 )?;
+                if let Some(counters) = desc.stage.ray_query_counters {
+                    self.require_features(wgt::Features::RAY_QUERY)?;
+                    interface.check_ray_query_counters(
+                        &mut binding_layout_source,
+                        &mut shader_binding_sizes,
+                        counters,
+                        stage,
+                    )?;
+                }
             }
         }
 
@@ -2773,6 +2815,10 @@ impl Device {
                 entry_point: final_entry_point_name.as_ref(),
                 constants: desc.stage.constants.as_ref(),
                 zero_initialize_workgroup_memory: desc.stage.zero_initialize_workgroup_memory,
+                ray_query_counters: desc
+                    .stage
+                    .ray_query_counters
+                    .map(conv::map_ray_query_counters),
             },
             cache: cache.as_ref().map(|it| it.raw()),
         };
@@ -3186,6 +3232,17 @@ impl Device {
                         desc.depth_stencil.as_ref().map(|d| d.depth_compare),
                     )
                     .map_err(stage_err)?;
+                if let Some(counters) = stage_desc.ray_query_counters {
+                    self.require_features(wgt::Features::RAY_QUERY)?;
+                    interface
+                        .check_ray_query_counters(
+                            &mut binding_layout_source,
+                            &mut shader_binding_sizes,
+                            counters,
+                            stage,
+                        )
+                        .map_err(stage_err)?;
+                }
                 validated_stages |= stage;
             }
 
@@ -3194,6 +3251,9 @@ impl Device {
                 entry_point: &vertex_entry_point_name,
                 constants: stage_desc.constants.as_ref(),
                 zero_initialize_workgroup_memory: stage_desc.zero_initialize_workgroup_memory,
+                ray_query_counters: stage_desc
+                    .ray_query_counters
+                    .map(conv::map_ray_query_counters),
             }
         };
 
@@ -3230,6 +3290,17 @@ impl Device {
                                 desc.depth_stencil.as_ref().map(|d| d.depth_compare),
                             )
                             .map_err(stage_err)?;
+                        if let Some(counters) = fragment_state.stage.ray_query_counters {
+                            self.require_features(wgt::Features::RAY_QUERY)?;
+                            interface
+                                .check_ray_query_counters(
+                                    &mut binding_layout_source,
+                                    &mut shader_binding_sizes,
+                                    counters,
+                                    stage,
+                                )
+                                .map_err(stage_err)?;
+                        }
                         validated_stages |= stage;
                     }
                 }
@@ -3250,6 +3321,10 @@ impl Device {
                     zero_initialize_workgroup_memory: fragment_state
                         .stage
                         .zero_initialize_workgroup_memory,
+                    ray_query_counters: fragment_state
+                        .stage
+                        .ray_query_counters
+                        .map(conv::map_ray_query_counters),
                 })
             }
             None => None,
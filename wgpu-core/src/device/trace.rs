@@ -132,6 +132,18 @@ pub enum Action<'a> {
         desc: crate::resource::BlasDescriptor<'a>,
         sizes: wgt::BlasGeometrySizeDescriptors,
     },
+    CreateBlasInBuffer {
+        id: id::BlasId,
+        buffer_id: id::BufferId,
+        offset: wgt::BufferAddress,
+        desc: crate::resource::BlasDescriptor<'a>,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+    },
+    CreateCompactedBlas {
+        id: id::BlasId,
+        source_id: id::BlasId,
+        label: crate::Label<'a>,
+    },
     FreeBlas(id::BlasId),
     DestroyBlas(id::BlasId),
     CreateTlas {
@@ -208,6 +220,22 @@ pub enum Command {
     BuildAccelerationStructures {
         blas: Vec<crate::ray_tracing::TraceBlasBuildEntry>,
         tlas: Vec<crate::ray_tracing::TraceTlasPackage>,
+        #[cfg_attr(feature = "replay", serde(default))]
+        scratch: Option<(id::BufferId, wgt::BufferAddress)>,
+    },
+    CopyBlas {
+        src: id::BlasId,
+        dst: id::BlasId,
+        copy: wgt::AccelerationStructureCopy,
+    },
+    CopyTlas {
+        src: id::TlasId,
+        dst: id::TlasId,
+    },
+    ResolveBlasCompactedSize {
+        blas: id::BlasId,
+        destination: id::BufferId,
+        destination_offset: wgt::BufferAddress,
     },
 }
 
@@ -928,6 +928,17 @@ impl Global {
             .map_err(|_| InvalidAdapter)
     }
 
+    pub fn adapter_ray_tracing_build_properties(
+        &self,
+        adapter_id: AdapterId,
+    ) -> Result<Option<wgt::RayTracingBuildProperties>, InvalidAdapter> {
+        self.hub
+            .adapters
+            .get(adapter_id)
+            .map(|adapter| adapter.raw.capabilities.ray_tracing)
+            .map_err(|_| InvalidAdapter)
+    }
+
     pub fn adapter_get_presentation_timestamp(
         &self,
         adapter_id: AdapterId,
@@ -92,11 +92,15 @@ define_lock_ranks! {
         DEVICE_USAGE_SCOPES,
         SHARED_TRACKER_INDEX_ALLOCATOR_INNER,
         BUFFER_MAP_STATE,
+        SCRATCH_BUFFER_POOL_INNER,
+        BLAS_POOL_FREE,
     }
     rank DEVICE_SNATCHABLE_LOCK "Device::snatchable_lock" followed by {
         SHARED_TRACKER_INDEX_ALLOCATOR_INNER,
         DEVICE_TRACE,
         BUFFER_MAP_STATE,
+        SCRATCH_BUFFER_POOL_INNER,
+        BLAS_POOL_FREE,
         // Uncomment this to see an interesting cycle.
         // COMMAND_BUFFER_DATA,
     }
@@ -109,10 +113,14 @@ define_lock_ranks! {
         COMMAND_ALLOCATOR_FREE_ENCODERS,
         SHARED_TRACKER_INDEX_ALLOCATOR_INNER,
         DEVICE_LIFE_TRACKER,
+        SCRATCH_BUFFER_POOL_INNER,
+        BLAS_POOL_FREE,
     }
     rank DEVICE_LIFE_TRACKER "Device::life_tracker" followed by {
         COMMAND_ALLOCATOR_FREE_ENCODERS,
         DEVICE_TRACE,
+        SCRATCH_BUFFER_POOL_INNER,
+        BLAS_POOL_FREE,
     }
     rank COMMAND_ALLOCATOR_FREE_ENCODERS "CommandAllocator::free_encoders" followed by {
         SHARED_TRACKER_INDEX_ALLOCATOR_INNER,
@@ -129,6 +137,7 @@ define_lock_ranks! {
     rank IDENTITY_MANAGER_VALUES "IdentityManager::values" followed by { }
     rank REGISTRY_STORAGE "Registry::storage" followed by { }
     rank RESOURCE_POOL_INNER "ResourcePool::inner" followed by { }
+    rank SCRATCH_BUFFER_POOL_INNER "ScratchBufferPool::inner" followed by { }
     rank SHARED_TRACKER_INDEX_ALLOCATOR_INNER "SharedTrackerIndexAllocator::inner" followed by { }
     rank SURFACE_PRESENTATION "Surface::presentation" followed by { }
     rank TEXTURE_BIND_GROUPS "Texture::bind_groups" followed by { }
@@ -137,6 +146,10 @@ define_lock_ranks! {
     rank BLAS_BUILT_INDEX "Blas::built_index" followed by { }
     rank TLAS_BUILT_INDEX "Tlas::built_index" followed by { }
     rank TLAS_DEPENDENCIES "Tlas::dependencies" followed by { }
+    rank BLAS_BUILT_COUNTS "Blas::built_counts" followed by { }
+    rank BLAS_COMPACTED_SIZE "Blas::compacted_size" followed by { }
+    rank BLAS_POOL_FREE "BlasPool::free" followed by { }
+    rank TLAS_BUILT_INSTANCE_COUNT "Tlas::built_instance_count" followed by { }
 
     #[cfg(test)]
     rank PAWN "pawn" followed by { ROOK, BISHOP }
@@ -145,6 +145,9 @@ pub struct ProgrammableStageDescriptor<'a> {
     /// This is required by the WebGPU spec, but may have overhead which can be avoided
     /// for cross-platform applications
     pub zero_initialize_workgroup_memory: bool,
+    /// If set, count the work done by ray queries in this stage into the storage buffer at
+    /// this binding.
+    pub ray_query_counters: Option<wgt::RayQueryCountersBinding>,
 }
 
 /// Describes a programmable pipeline stage.
@@ -172,6 +175,9 @@ pub struct ResolvedProgrammableStageDescriptor<'a> {
     /// This is required by the WebGPU spec, but may have overhead which can be avoided
     /// for cross-platform applications
     pub zero_initialize_workgroup_memory: bool,
+    /// If set, count the work done by ray queries in this stage into the storage buffer at
+    /// this binding.
+    pub ray_query_counters: Option<wgt::RayQueryCountersBinding>,
 }
 
 /// Number of implicit bind groups derived at pipeline creation.
@@ -235,6 +241,8 @@ pub enum CreateComputePipelineError {
     #[error("Pipeline constant error: {0}")]
     PipelineConstants(String),
     #[error(transparent)]
+    MissingFeatures(#[from] MissingFeatures),
+    #[error(transparent)]
     MissingDownlevelFlags(#[from] MissingDownlevelFlags),
 }
 
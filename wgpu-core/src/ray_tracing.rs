@@ -2,7 +2,7 @@ use crate::{
     command::CommandEncoderError,
     device::DeviceError,
     id::{BlasId, BufferId, TlasId},
-    resource::CreateBufferError,
+    resource::{CreateBufferError, DestroyedResourceError, MissingBufferUsageError},
 };
 use std::sync::Arc;
 /// Ray tracing
@@ -13,7 +13,7 @@ use std::sync::Arc;
 /// - maybe share scratch and instance staging buffer allocation
 /// - partial instance buffer uploads (api surface already designed with this in mind)
 /// - ([non performance] extract function in build (rust function extraction with guards is a pain))
-use std::{num::NonZeroU64, slice};
+use std::{fmt, num::NonZeroU64};
 
 use crate::resource::{Blas, ResourceErrorIdent, Tlas};
 use thiserror::Error;
@@ -31,6 +31,106 @@ pub enum CreateBlasError {
     MissingIndexData,
     #[error("To use flag ALLOW_RAY_HIT_VERTEX_RETURN device feature RAY_HIT_VERTEX_RETURN must be used too")]
     MissingVertexReturnFeature,
+    #[error("Vertex format {0:?} is not supported for acceleration structures by the adapter")]
+    UnsupportedVertexFormat(wgt::VertexFormat),
+    #[error("Flags {0:?} are mutually exclusive")]
+    IncompatibleFlags(wgt::AccelerationStructureFlags),
+    #[error("Buffer {0:?} is invalid or destroyed")]
+    InvalidBuffer(BufferId),
+    #[error(transparent)]
+    DestroyedResource(#[from] DestroyedResourceError),
+    #[error(transparent)]
+    MissingBufferUsage(#[from] MissingBufferUsageError),
+    #[error("Offset {0} into the backing buffer is not a multiple of {align}", align = wgt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT)]
+    UnalignedBufferOffset(BufferAddress),
+    #[error("Acceleration structure of {size} bytes at offset {offset} overruns the backing buffer of {buffer_size} bytes")]
+    BufferOverrun {
+        offset: BufferAddress,
+        size: BufferAddress,
+        buffer_size: BufferAddress,
+    },
+    #[error(transparent)]
+    Compaction(#[from] CompactBlasError),
+}
+
+/// Error encountered while reading the compacted size of a BLAS, or compacting it.
+#[derive(Clone, Debug, Error)]
+pub enum CompactBlasError {
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+    #[error("BlasId is invalid or destroyed")]
+    InvalidBlasId,
+    #[error(transparent)]
+    DestroyedResource(#[from] DestroyedResourceError),
+    #[error("Blas {0:?} is compacted, but wasn't created with flag ALLOW_COMPACTION")]
+    CompactionNotAllowed(ResourceErrorIdent),
+    #[error("Blas {0:?} is compacted before it is build")]
+    NotBuilt(ResourceErrorIdent),
+    #[error("Blas {0:?} is used by a submission that hasn't completed yet")]
+    InFlight(ResourceErrorIdent),
+    #[error("The compacted size of Blas {0:?} wasn't read since it was last built")]
+    CompactedSizeUnknown(ResourceErrorIdent),
+}
+
+/// Error encountered while recording a copy of an acceleration structure.
+#[derive(Clone, Debug, Error)]
+pub enum CopyAccelerationStructureError {
+    #[error(transparent)]
+    Encoder(#[from] CommandEncoderError),
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+    #[error("BlasId is invalid or destroyed")]
+    InvalidBlasId,
+    #[error("TlasId is invalid or destroyed")]
+    InvalidTlasId,
+    #[error(transparent)]
+    DestroyedResource(#[from] DestroyedResourceError),
+    #[error(transparent)]
+    Compaction(#[from] CompactBlasError),
+    #[error("{0:?} is copied into itself")]
+    SameSourceAndDestination(ResourceErrorIdent),
+    #[error("{src:?} is cloned into {dst:?}, which wasn't created with the same sizes and flags")]
+    IncompatibleClone {
+        src: ResourceErrorIdent,
+        dst: ResourceErrorIdent,
+    },
+    #[error("{dst:?} of {size} bytes is too small to hold {src:?}, which needs {required} bytes")]
+    InsufficientDestinationSize {
+        src: ResourceErrorIdent,
+        dst: ResourceErrorIdent,
+        size: BufferAddress,
+        required: BufferAddress,
+    },
+}
+
+/// Error encountered while recording a copy of the compacted size of a BLAS into a buffer.
+#[derive(Clone, Debug, Error)]
+pub enum ResolveCompactedSizeError {
+    #[error(transparent)]
+    Encoder(#[from] CommandEncoderError),
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+    #[error("BlasId is invalid or destroyed")]
+    InvalidBlasId,
+    #[error("BufferId is invalid or destroyed")]
+    InvalidBufferId,
+    #[error(transparent)]
+    DestroyedResource(#[from] DestroyedResourceError),
+    #[error(transparent)]
+    Compaction(#[from] CompactBlasError),
+    #[error(transparent)]
+    MissingBufferUsage(#[from] MissingBufferUsageError),
+    #[error(
+        "Destination offset {0} is not a multiple of {}",
+        wgt::QUERY_RESOLVE_BUFFER_ALIGNMENT
+    )]
+    UnalignedOffset(BufferAddress),
+    #[error("Writing the compacted size at offset {offset} overruns {buffer:?} of {size} bytes")]
+    BufferOverrun {
+        buffer: ResourceErrorIdent,
+        offset: BufferAddress,
+        size: BufferAddress,
+    },
 }
 
 #[derive(Clone, Debug, Error)]
@@ -41,6 +141,15 @@ pub enum CreateTlasError {
     CreateBufferError(#[from] CreateBufferError),
     #[error("To use flag ALLOW_RAY_HIT_VERTEX_RETURN device feature RAY_HIT_VERTEX_RETURN must be used too")]
     MissingVertexReturnFeature,
+    #[error("Flags {0:?} are mutually exclusive")]
+    IncompatibleFlags(wgt::AccelerationStructureFlags),
+    #[error("Tlas with {max_instances} max instances needs an instance buffer larger than the maximum buffer size ({max_buffer_size})")]
+    TooManyInstances {
+        max_instances: u32,
+        max_buffer_size: u64,
+    },
+    #[error("TlasId is invalid or destroyed")]
+    InvalidTlasId,
     #[error("Unimplemented Tlas error: this error is not yet implemented")]
     Unimplemented,
 }
@@ -71,6 +180,9 @@ pub enum BuildAccelerationStructureError {
     #[error("Buffer {0:?} associated offset doesn't align with the index type")]
     UnalignedIndexBufferOffset(ResourceErrorIdent),
 
+    #[error("Buffer {0:?} vertex buffer offset {1} isn't a multiple of the size of the components of vertex format {2:?}")]
+    UnalignedVertexBufferOffset(ResourceErrorIdent, BufferAddress, wgt::VertexFormat),
+
     #[error("Buffer {0:?} associated offset is unaligned")]
     UnalignedTransformBufferOffset(ResourceErrorIdent),
 
@@ -85,6 +197,26 @@ pub enum BuildAccelerationStructureError {
     )]
     IncompatibleBlasBuildSizes(ResourceErrorIdent),
 
+    #[error(
+        "Blas {0:?} geometry {1} is built with {2} {3} but only {4} were reserved by the descriptor at creation"
+    )]
+    BlasBuildExceedsReservedCount(ResourceErrorIdent, usize, u32, &'static str, u32),
+
+    #[error(
+        "Blas {0:?} vertex stride {1} is invalid for vertex format {2:?}, it must be at least the size of a vertex, a multiple of the size of its components and fit into 32 bits"
+    )]
+    InvalidVertexStride(ResourceErrorIdent, BufferAddress, wgt::VertexFormat),
+
+    #[error(
+        "Blas {0:?} vertex offset {1} is invalid for vertex stride {2} and vertex format {3:?}, the vertex must fit into the stride after the offset, which must be a multiple of the size of its components"
+    )]
+    InvalidVertexOffset(
+        ResourceErrorIdent,
+        BufferAddress,
+        BufferAddress,
+        wgt::VertexFormat,
+    ),
+
     #[error("Blas {0:?} build sizes require index buffer but none was provided")]
     MissingIndexBuffer(ResourceErrorIdent),
 
@@ -95,9 +227,16 @@ pub enum BuildAccelerationStructureError {
     InvalidBlas(ResourceErrorIdent),
 
     #[error(
-        "Tlas {0:?} an associated instances contains an invalid custom index (more than 24bits)"
+        "Blas {0:?} is built more than once by the same call, builds of one call may run concurrently"
     )]
-    TlasInvalidCustomIndex(ResourceErrorIdent),
+    DuplicateBlasBuild(ResourceErrorIdent),
+
+    #[error("Tlas {0:?} instance {1} is invalid: {2}")]
+    TlasInvalidInstance(
+        ResourceErrorIdent,
+        usize,
+        wgt::instance_packing::InstanceFieldErrors,
+    ),
 
     #[error(
         "Tlas {0:?} has {1} active instances but only {2} are allowed as specified by the descriptor at creation"
@@ -116,11 +255,35 @@ pub enum BuildAccelerationStructureError {
     #[error("Tlas {0:?} is invalid or destroyed")]
     InvalidTlas(ResourceErrorIdent),
 
+    #[error(
+        "Tlas {0:?} is built more than once by the same call, builds of one call may run concurrently"
+    )]
+    DuplicateTlasBuild(ResourceErrorIdent),
+
     #[error("Buffer {0:?} is missing `TLAS_INPUT` usage flag")]
     MissingTlasInputUsageFlag(ResourceErrorIdent),
 
+    #[error("Buffer {0:?} is missing `ACCELERATION_STRUCTURE_SCRATCH` usage flag")]
+    MissingScratchUsageFlag(ResourceErrorIdent),
+
+    #[error(
+        "Scratch buffer offset {0} is not a multiple of `ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT`"
+    )]
+    UnalignedScratchBufferOffset(BufferAddress),
+
+    #[error(
+        "Scratch buffer {0:?} is too small for the build (offset: {1}, required: {2}, buffer size: {3})"
+    )]
+    InsufficientScratchBufferSize(ResourceErrorIdent, u64, u64, u64),
+
     #[error("Blas {0:?} was missing flag ALLOW_RAY_HIT_VERTEX_RETURN while tlas {1:?} had flag")]
     MissingBlasVertexReturn(BlasId, TlasId),
+
+    #[error("{0:?} is updated, but wasn't created with flag ALLOW_UPDATE")]
+    UpdateNotAllowed(ResourceErrorIdent),
+
+    #[error("Blas {0:?} holds a compacted copy and can't be built or updated")]
+    CompactedBlasBuilt(ResourceErrorIdent),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -130,6 +293,18 @@ pub enum ValidateBlasActionsError {
 
     #[error("Blas {0:?} is used before it is build")]
     UsedUnbuilt(ResourceErrorIdent),
+
+    #[error("Blas {0:?} is updated before it is build")]
+    UpdatedUnbuilt(ResourceErrorIdent),
+
+    #[error("Blas {0:?} is updated with different counts than it was last built with")]
+    IncompatibleUpdate(ResourceErrorIdent),
+
+    #[error("Blas {0:?} is copied before it is build")]
+    CopiedUnbuilt(ResourceErrorIdent),
+
+    #[error("Blas {0:?} is compacted, but was rebuilt after its compacted size was read")]
+    StaleCompactedSize(ResourceErrorIdent),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -148,6 +323,27 @@ pub enum ValidateTlasActionsError {
 
     #[error("Blas {0:?} is newer than the containing Tlas {1:?}")]
     BlasNewerThenTlas(ResourceErrorIdent, ResourceErrorIdent),
+
+    #[error("Blas {0:?} is destroyed (in Tlas {1:?})")]
+    DestroyedBlas(ResourceErrorIdent, ResourceErrorIdent),
+
+    #[error("Tlas {0:?} is updated before it is build")]
+    UpdatedUnbuilt(ResourceErrorIdent),
+
+    #[error("Tlas {0:?} is updated with {1} instances, but was last built with {2}")]
+    IncompatibleUpdate(ResourceErrorIdent, u32, u32),
+
+    #[error("Tlas {0:?} is cloned before it is build")]
+    ClonedUnbuilt(ResourceErrorIdent),
+}
+
+/// Error encountered when resolving the acceleration structure referenced by a raw instance.
+#[derive(Clone, Debug, Error)]
+pub enum InstanceReferenceError {
+    #[error("Handle {0:#x} belongs to top level acceleration structure {1:?}, but instances can only reference bottom level acceleration structures")]
+    TopLevel(u64, TlasId),
+    #[error("Handle {0:#x} doesn't belong to a live bottom level acceleration structure")]
+    Unknown(u64),
 }
 
 #[derive(Debug)]
@@ -157,11 +353,49 @@ pub struct BlasTriangleGeometry<'a> {
     pub index_buffer: Option<BufferId>,
     pub transform_buffer: Option<BufferId>,
     pub first_vertex: u32,
-    pub vertex_stride: BufferAddress,
+    pub vertex_stride: Option<BufferAddress>,
+    pub vertex_offset: BufferAddress,
+    pub vertex_buffer_offset: BufferAddress,
     pub index_buffer_offset: Option<BufferAddress>,
     pub transform_buffer_offset: Option<BufferAddress>,
 }
 
+impl BlasTriangleGeometry<'_> {
+    /// The vertex stride, which defaults to the size of the vertex format for tightly packed
+    /// vertices.
+    pub fn effective_vertex_stride(&self) -> BufferAddress {
+        self.vertex_stride
+            .unwrap_or_else(|| self.size.vertex_format.size())
+    }
+}
+
+/// Size of a single component of a vertex format, which vertex strides must be a multiple of.
+pub(crate) fn vertex_component_size(format: wgt::VertexFormat) -> BufferAddress {
+    use wgt::VertexFormat as Vf;
+    match format {
+        Vf::Uint8x2
+        | Vf::Uint8x4
+        | Vf::Sint8x2
+        | Vf::Sint8x4
+        | Vf::Unorm8x2
+        | Vf::Unorm8x4
+        | Vf::Snorm8x2
+        | Vf::Snorm8x4 => 1,
+        Vf::Uint16x2
+        | Vf::Uint16x4
+        | Vf::Sint16x2
+        | Vf::Sint16x4
+        | Vf::Unorm16x2
+        | Vf::Unorm16x4
+        | Vf::Snorm16x2
+        | Vf::Snorm16x4
+        | Vf::Float16x2
+        | Vf::Float16x4 => 2,
+        Vf::Float64 | Vf::Float64x2 | Vf::Float64x3 | Vf::Float64x4 => 8,
+        _ => 4,
+    }
+}
+
 #[derive(Debug)]
 pub struct BlasProceduralGeometry<'a> {
     pub size: &'a wgt::BlasProceduralGeometrySizeDescriptor,
@@ -187,6 +421,8 @@ pub enum BlasGeometries<'a> {
 pub struct BlasBuildEntry<'a> {
     pub blas_id: BlasId,
     pub geometries: BlasGeometries<'a>,
+    /// How to build the BLAS, a full build if `None`.
+    pub mode: Option<wgt::AccelerationStructureBuildMode>,
 }
 
 #[derive(Debug, Clone)]
@@ -195,6 +431,8 @@ pub struct TlasBuildEntry {
     pub tlas_id: TlasId,
     pub instance_buffer_id: BufferId,
     pub instance_count: u32,
+    /// How to build the TLAS, a full build if `None`.
+    pub mode: Option<wgt::AccelerationStructureBuildMode>,
 }
 
 #[derive(Debug)]
@@ -203,17 +441,47 @@ pub struct TlasInstance<'a> {
     pub transform: &'a [f32; 12],
     pub custom_index: u32,
     pub mask: u8,
+    pub flags: wgt::AccelerationStructureInstanceFlags,
+    pub shader_binding_table_record_offset: u32,
 }
 
 pub struct TlasPackage<'a> {
     pub tlas_id: TlasId,
     pub instances: Box<dyn Iterator<Item = Option<TlasInstance<'a>>> + 'a>,
     pub lowest_unmodified: u32,
+    /// How to upload the instances, picked from the adapter type if `None`.
+    pub instance_upload: Option<wgt::TlasInstanceUpload>,
+    /// How to build the TLAS, a full build if `None`.
+    pub mode: Option<wgt::AccelerationStructureBuildMode>,
+}
+
+/// Counts of a geometry of a BLAS build, which updates of the BLAS have to keep.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum BlasGeometryCounts {
+    Triangles {
+        vertex_count: u32,
+        index_count: Option<u32>,
+        transform: bool,
+    },
+    Aabbs {
+        primitive_count: u32,
+    },
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub(crate) enum BlasActionKind {
-    Build(NonZeroU64),
+    Build {
+        build_index: NonZeroU64,
+        mode: wgt::AccelerationStructureBuildMode,
+        counts: Vec<BlasGeometryCounts>,
+    },
+    /// The BLAS is the destination of a copy of `source`, compacted to `compacted_size` if
+    /// there is one.
+    Copy {
+        build_index: NonZeroU64,
+        source: Arc<Blas>,
+        compacted_size: Option<BufferAddress>,
+    },
     Use,
 }
 
@@ -222,6 +490,12 @@ pub(crate) enum TlasActionKind {
     Build {
         build_index: NonZeroU64,
         dependencies: Vec<Arc<Blas>>,
+        instance_count: u32,
+        mode: wgt::AccelerationStructureBuildMode,
+    },
+    /// The TLAS is the destination of a clone of `source`.
+    Clone {
+        source: Arc<Tlas>,
     },
     Use,
 }
@@ -238,6 +512,109 @@ pub(crate) struct TlasAction {
     pub kind: TlasActionKind,
 }
 
+impl BlasGeometryCounts {
+    /// Number of triangles or AABBs built from the geometry.
+    pub fn primitive_count(&self) -> u32 {
+        match *self {
+            Self::Triangles {
+                vertex_count,
+                index_count,
+                ..
+            } => index_count.unwrap_or(vertex_count) / 3,
+            Self::Aabbs { primitive_count } => primitive_count,
+        }
+    }
+}
+
+/// Number of acceleration structures built with one mode, and the number of primitives or
+/// instances built into them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BuildTotals {
+    pub count: u32,
+    pub elements: u64,
+}
+
+impl BuildTotals {
+    fn add(&mut self, elements: u64) {
+        self.count += 1;
+        self.elements += elements;
+    }
+}
+
+/// Acceleration structure work of a queue submission, logged at debug level to help spot
+/// accidental rebuilds.
+///
+/// Full builds and updates (refits) are counted separately, with the primitives and instances
+/// actually built rather than the capacity reserved at creation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SubmissionBuildSummary {
+    pub blas_built: BuildTotals,
+    pub blas_refit: BuildTotals,
+    pub tlas_built: BuildTotals,
+    pub tlas_refit: BuildTotals,
+}
+
+impl SubmissionBuildSummary {
+    pub fn add_actions(&mut self, blas_actions: &[BlasAction], tlas_actions: &[TlasAction]) {
+        for action in blas_actions {
+            if let BlasActionKind::Build {
+                mode, ref counts, ..
+            } = action.kind
+            {
+                let totals = match mode {
+                    wgt::AccelerationStructureBuildMode::Build => &mut self.blas_built,
+                    wgt::AccelerationStructureBuildMode::Update => &mut self.blas_refit,
+                };
+                totals.add(counts.iter().map(|c| c.primitive_count() as u64).sum());
+            }
+        }
+        for action in tlas_actions {
+            if let TlasActionKind::Build {
+                instance_count,
+                mode,
+                ..
+            } = action.kind
+            {
+                let totals = match mode {
+                    wgt::AccelerationStructureBuildMode::Build => &mut self.tlas_built,
+                    wgt::AccelerationStructureBuildMode::Update => &mut self.tlas_refit,
+                };
+                totals.add(instance_count as u64);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blas_built.count == 0
+            && self.blas_refit.count == 0
+            && self.tlas_built.count == 0
+            && self.tlas_refit.count == 0
+    }
+}
+
+impl fmt::Display for SubmissionBuildSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ("built", "BLAS totaling", "primitives", self.blas_built),
+            ("refit", "BLAS totaling", "primitives", self.blas_refit),
+            ("built", "TLAS with", "instances", self.tlas_built),
+            ("refit", "TLAS with", "instances", self.tlas_refit),
+        ];
+        let mut separator = "";
+        for (verb, kind, elements, totals) in parts {
+            if totals.count != 0 {
+                write!(
+                    f,
+                    "{separator}{verb} {} {kind} {} {elements}",
+                    totals.count, totals.elements
+                )?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TraceBlasTriangleGeometry {
@@ -246,7 +623,9 @@ pub struct TraceBlasTriangleGeometry {
     pub index_buffer: Option<BufferId>,
     pub transform_buffer: Option<BufferId>,
     pub first_vertex: u32,
-    pub vertex_stride: BufferAddress,
+    pub vertex_stride: Option<BufferAddress>,
+    pub vertex_offset: BufferAddress,
+    pub vertex_buffer_offset: BufferAddress,
     pub index_buffer_offset: Option<BufferAddress>,
     pub transform_buffer_offset: Option<BufferAddress>,
 }
@@ -272,6 +651,7 @@ pub enum TraceBlasGeometries {
 pub struct TraceBlasBuildEntry {
     pub blas_id: BlasId,
     pub geometries: TraceBlasGeometries,
+    pub mode: Option<wgt::AccelerationStructureBuildMode>,
 }
 
 #[derive(Debug, Clone)]
@@ -281,6 +661,8 @@ pub struct TraceTlasInstance {
     pub transform: [f32; 12],
     pub custom_index: u32,
     pub mask: u8,
+    pub flags: wgt::AccelerationStructureInstanceFlags,
+    pub shader_binding_table_record_offset: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -289,34 +671,29 @@ pub struct TraceTlasPackage {
     pub tlas_id: TlasId,
     pub instances: Vec<Option<TraceTlasInstance>>,
     pub lowest_unmodified: u32,
+    pub instance_upload: Option<wgt::TlasInstanceUpload>,
+    pub mode: Option<wgt::AccelerationStructureBuildMode>,
 }
 
 pub(crate) fn get_raw_tlas_instance_size() -> usize {
     // TODO: this should be provided by the backend
-    64
+    wgt::instance_packing::PACKED_INSTANCE_SIZE
 }
 
-#[derive(Clone)]
-#[repr(C)]
-struct RawTlasInstance {
-    transform: [f32; 12],
-    custom_index_and_mask: u32,
-    shader_binding_table_record_offset_and_flags: u32,
-    acceleration_structure_reference: u64,
-}
-
-pub(crate) fn tlas_instance_into_bytes(instance: &TlasInstance, blas_address: u64) -> Vec<u8> {
+/// Packs a validated instance, whose custom index and shader binding table record offset
+/// fit into 24 bits.
+pub(crate) fn tlas_instance_into_bytes(
+    instance: &TlasInstance,
+    blas_address: u64,
+) -> [u8; wgt::instance_packing::PACKED_INSTANCE_SIZE] {
     // TODO: get the device to do this
-    const MAX_U24: u32 = (1u32 << 24u32) - 1u32;
-    let temp = RawTlasInstance {
-        transform: *instance.transform,
-        custom_index_and_mask: (instance.custom_index & MAX_U24) | (u32::from(instance.mask) << 24),
-        shader_binding_table_record_offset_and_flags: 0,
-        acceleration_structure_reference: blas_address,
-    };
-    let temp: *const _ = &temp;
-    unsafe {
-        slice::from_raw_parts::<u8>(temp.cast::<u8>(), std::mem::size_of::<RawTlasInstance>())
-            .to_vec()
-    }
+    wgt::instance_packing::PackedInstance::new(
+        *instance.transform,
+        instance.custom_index,
+        instance.mask,
+        instance.shader_binding_table_record_offset,
+        instance.flags,
+        blas_address,
+    )
+    .to_ne_bytes()
 }
@@ -20,7 +20,6 @@ use crate::{
 use smallvec::SmallVec;
 use thiserror::Error;
 
-use hal::BufferUses;
 use std::num::NonZeroU64;
 use std::{
     borrow::{Borrow, Cow},
@@ -28,7 +27,10 @@ use std::{
     mem::{self, ManuallyDrop},
     ops::Range,
     ptr::NonNull,
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
 };
 
 /// Information about the wgpu-core resource.
@@ -751,6 +753,8 @@ pub enum CreateBufferError {
     MaxBufferSize { requested: u64, maximum: u64 },
     #[error(transparent)]
     MissingDownlevelFlags(#[from] MissingDownlevelFlags),
+    #[error(transparent)]
+    MissingFeatures(#[from] MissingFeatures),
 }
 
 crate::impl_resource_type!(Buffer);
@@ -943,24 +947,19 @@ impl Drop for FlushedStagingBuffer {
 #[derive(Debug)]
 pub struct ScratchBuffer {
     raw: ManuallyDrop<Box<dyn hal::DynBuffer>>,
+    size: wgt::BufferAddress,
     device: Arc<Device>,
 }
 
 impl ScratchBuffer {
     pub(crate) fn new(device: &Arc<Device>, size: wgt::BufferSize) -> Result<Self, DeviceError> {
-        let raw = unsafe {
-            device
-                .raw()
-                .create_buffer(&hal::BufferDescriptor {
-                    label: Some("(wgpu) scratch buffer"),
-                    size: size.get(),
-                    usage: BufferUses::ACCELERATION_STRUCTURE_SCRATCH | BufferUses::MAP_WRITE,
-                    memory_flags: hal::MemoryFlags::empty(),
-                })
-                .map_err(crate::device::DeviceError::from)?
-        };
+        let (raw, size) = device
+            .scratch_pool
+            .acquire(device.raw(), size)
+            .map_err(crate::device::DeviceError::from)?;
         Ok(Self {
             raw: ManuallyDrop::new(raw),
+            size,
             device: device.clone(),
         })
     }
@@ -971,10 +970,10 @@ impl ScratchBuffer {
 
 impl Drop for ScratchBuffer {
     fn drop(&mut self) {
-        resource_log!("Destroy raw StagingBuffer");
+        resource_log!("Release raw ScratchBuffer to the pool");
         // SAFETY: We are in the Drop impl and we don't use self.raw anymore after this point.
         let raw = unsafe { ManuallyDrop::take(&mut self.raw) };
-        unsafe { self.device.raw().destroy_buffer(raw) };
+        self.device.scratch_pool.release(raw, self.size);
     }
 }
 
@@ -1882,19 +1881,31 @@ pub type BlasDescriptor<'a> = wgt::CreateBlasDescriptor<Label<'a>>;
 pub type TlasDescriptor<'a> = wgt::CreateTlasDescriptor<Label<'a>>;
 
 pub(crate) trait AccelerationStructure: Trackable {
-    fn raw(&self) -> &dyn hal::DynAccelerationStructure;
+    fn try_raw<'a>(
+        &'a self,
+        guard: &'a SnatchGuard,
+    ) -> Result<&'a dyn hal::DynAccelerationStructure, DestroyedResourceError>;
 }
 
 #[derive(Debug)]
 pub struct Blas {
-    pub(crate) raw: ManuallyDrop<Box<dyn hal::DynAccelerationStructure>>,
+    pub(crate) raw: Snatchable<Box<dyn hal::DynAccelerationStructure>>,
     pub(crate) device: Arc<Device>,
     pub(crate) size_info: hal::AccelerationStructureBuildSizes,
     pub(crate) sizes: wgt::BlasGeometrySizeDescriptors,
     pub(crate) flags: wgt::AccelerationStructureFlags,
     pub(crate) update_mode: wgt::AccelerationStructureUpdateMode,
     pub(crate) built_index: RwLock<Option<NonZeroU64>>,
+    /// Counts of the geometries of the last submitted build, which updates have to keep.
+    pub(crate) built_counts: RwLock<Vec<crate::ray_tracing::BlasGeometryCounts>>,
+    /// Size of the last build once compacted, read back after the build completed.
+    pub(crate) compacted_size: Mutex<Option<wgt::BufferAddress>>,
+    /// Whether the BLAS holds a compacted copy of another one, which leaves no room to build it.
+    pub(crate) compacted: bool,
     pub(crate) handle: u64,
+    /// The buffer holding the acceleration structure, if it was created in one
+    /// owned by the user. Kept alive for as long as the acceleration structure.
+    pub(crate) backing_buffer: Option<Arc<Buffer>>,
     /// The `label` from the descriptor used to create the resource.
     pub(crate) label: String,
     pub(crate) tracking_data: TrackingData,
@@ -1902,18 +1913,67 @@ pub struct Blas {
 
 impl Drop for Blas {
     fn drop(&mut self) {
-        resource_log!("Destroy raw {}", self.error_ident());
-        // SAFETY: We are in the Drop impl, and we don't use self.raw anymore after this point.
-        let raw = unsafe { ManuallyDrop::take(&mut self.raw) };
-        unsafe {
-            self.device.raw().destroy_acceleration_structure(raw);
+        if let Some(raw) = self.raw.take() {
+            // Compactable acceleration structures carry a query for their compacted size,
+            // which others taken from the pool wouldn't have.
+            if self.backing_buffer.is_some()
+                || self
+                    .flags
+                    .contains(wgt::AccelerationStructureFlags::ALLOW_COMPACTION)
+            {
+                resource_log!("Destroy raw {}", self.error_ident());
+                unsafe {
+                    self.device.raw().destroy_acceleration_structure(raw);
+                }
+            } else {
+                resource_log!("Release raw {} to the pool", self.error_ident());
+                self.device
+                    .blas_pool
+                    .release(raw, self.size_info.acceleration_structure_size);
+            }
         }
     }
 }
 
 impl AccelerationStructure for Blas {
-    fn raw(&self) -> &dyn hal::DynAccelerationStructure {
-        self.raw.as_ref()
+    fn try_raw<'a>(
+        &'a self,
+        guard: &'a SnatchGuard,
+    ) -> Result<&'a dyn hal::DynAccelerationStructure, DestroyedResourceError> {
+        self.raw
+            .get(guard)
+            .map(|raw| raw.as_ref())
+            .ok_or_else(|| DestroyedResourceError(self.error_ident()))
+    }
+}
+
+impl Blas {
+    pub(crate) fn destroy(self: &Arc<Self>) -> Result<(), DestroyError> {
+        let device = &self.device;
+
+        let temp = {
+            let snatch_guard = device.snatchable_lock.write();
+            let raw = match self.raw.snatch(snatch_guard) {
+                Some(raw) => raw,
+                None => {
+                    return Err(DestroyError::AlreadyDestroyed);
+                }
+            };
+
+            queue::TempResource::DestroyedAccelerationStructure(DestroyedAccelerationStructure {
+                raw: ManuallyDrop::new(raw),
+                device: Arc::clone(&self.device),
+                label: self.label().to_owned(),
+            })
+        };
+
+        let mut life_lock = device.lock_life();
+        let last_submit_index = life_lock.get_blas_latest_submission_index(self);
+        if let Some(last_submit_index) = last_submit_index {
+            life_lock.schedule_resource_destruction(temp, last_submit_index);
+        }
+
+        Ok(())
     }
 }
 
@@ -1925,15 +1985,26 @@ crate::impl_trackable!(Blas);
 
 #[derive(Debug)]
 pub struct Tlas {
-    pub(crate) raw: ManuallyDrop<Box<dyn hal::DynAccelerationStructure>>,
+    pub(crate) raw: Snatchable<Box<dyn hal::DynAccelerationStructure>>,
     pub(crate) device: Arc<Device>,
     pub(crate) size_info: hal::AccelerationStructureBuildSizes,
     pub(crate) max_instance_count: u32,
     pub(crate) flags: wgt::AccelerationStructureFlags,
     pub(crate) update_mode: wgt::AccelerationStructureUpdateMode,
     pub(crate) built_index: RwLock<Option<NonZeroU64>>,
+    /// Instance count of the last submitted build, which updates have to keep.
+    pub(crate) built_instance_count: RwLock<u32>,
     pub(crate) dependencies: RwLock<Vec<Arc<Blas>>>,
+    /// Raw handle of the acceleration structure, only used to reject raw instances that
+    /// reference a top level acceleration structure.
+    pub(crate) handle: u64,
     pub(crate) instance_buffer: ManuallyDrop<Box<dyn hal::DynBuffer>>,
+    /// Whether the instance buffer is host visible, which it is on adapters sharing their
+    /// memory with the host, see [`wgt::TlasInstanceUpload::for_device_type`].
+    pub(crate) instance_buffer_host_visible: bool,
+    /// Number of builds recorded whose submission hasn't completed yet, see
+    /// [`TlasInstanceBufferUse`].
+    pub(crate) instance_buffer_users: AtomicUsize,
     /// The `label` from the descriptor used to create the resource.
     pub(crate) label: String,
     pub(crate) tracking_data: TrackingData,
@@ -1941,19 +2012,146 @@ pub struct Tlas {
 
 impl Drop for Tlas {
     fn drop(&mut self) {
+        self.device
+            .counters
+            .tlas_blas_references
+            .sub(self.dependencies.read().len() as isize);
+        resource_log!("Destroy raw {}", self.error_ident());
         unsafe {
-            let structure = ManuallyDrop::take(&mut self.raw);
+            if let Some(structure) = self.raw.take() {
+                self.device.raw().destroy_acceleration_structure(structure);
+            }
             let buffer = ManuallyDrop::take(&mut self.instance_buffer);
-            resource_log!("Destroy raw {}", self.error_ident());
-            self.device.raw().destroy_acceleration_structure(structure);
             self.device.raw().destroy_buffer(buffer);
         }
     }
 }
 
 impl AccelerationStructure for Tlas {
-    fn raw(&self) -> &dyn hal::DynAccelerationStructure {
-        self.raw.as_ref()
+    fn try_raw<'a>(
+        &'a self,
+        guard: &'a SnatchGuard,
+    ) -> Result<&'a dyn hal::DynAccelerationStructure, DestroyedResourceError> {
+        self.raw
+            .get(guard)
+            .map(|raw| raw.as_ref())
+            .ok_or_else(|| DestroyedResourceError(self.error_ident()))
+    }
+}
+
+impl Tlas {
+    /// Marks the instance buffer as read by a build until the submission of the build
+    /// completes.
+    ///
+    /// Returns whether the build may write its instances straight into the instance buffer
+    /// from the host, which it may if `write_from_host` is requested, the instance buffer is
+    /// host visible and no other build can still read it. Otherwise the instances must be
+    /// copied in from a staging buffer, ordered after any build reading the previous ones.
+    pub(crate) fn use_instance_buffer(
+        self: &Arc<Self>,
+        write_from_host: bool,
+    ) -> (TlasInstanceBufferUse, bool) {
+        let write_from_host = write_from_host
+            && self.instance_buffer_host_visible
+            && self
+                .instance_buffer_users
+                .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok();
+        if !write_from_host {
+            self.instance_buffer_users.fetch_add(1, Ordering::AcqRel);
+        }
+        (TlasInstanceBufferUse(self.clone()), write_from_host)
+    }
+
+    /// Writes `data` to the start of the host visible instance buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The instance buffer must be host visible and at least as large as `data`.
+    /// - No build but the one writing may read the instance buffer, see
+    ///   [`Self::use_instance_buffer`].
+    pub(crate) unsafe fn write_instances(&self, data: &[u8]) -> Result<(), DeviceError> {
+        let raw_device = self.device.raw();
+        let buffer = self.instance_buffer.as_ref();
+        let size = data.len() as u64;
+        unsafe {
+            let mapping = raw_device.map_buffer(buffer, 0..size)?;
+            core::ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.as_ptr(), data.len());
+            if !mapping.is_coherent {
+                #[allow(clippy::single_range_in_vec_init)]
+                raw_device.flush_mapped_ranges(buffer, &[0..size]);
+            }
+            raw_device.unmap_buffer(buffer);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn destroy(self: &Arc<Self>) -> Result<(), DestroyError> {
+        let device = &self.device;
+
+        let temp = {
+            let snatch_guard = device.snatchable_lock.write();
+            let raw = match self.raw.snatch(snatch_guard) {
+                Some(raw) => raw,
+                None => {
+                    return Err(DestroyError::AlreadyDestroyed);
+                }
+            };
+
+            queue::TempResource::DestroyedAccelerationStructure(DestroyedAccelerationStructure {
+                raw: ManuallyDrop::new(raw),
+                device: Arc::clone(&self.device),
+                label: self.label().to_owned(),
+            })
+        };
+
+        let mut life_lock = device.lock_life();
+        let last_submit_index = life_lock.get_tlas_latest_submission_index(self);
+        if let Some(last_submit_index) = last_submit_index {
+            life_lock.schedule_resource_destruction(temp, last_submit_index);
+        }
+
+        Ok(())
+    }
+}
+
+/// Held by a recorded TLAS build until its submission completes, so that instances are only
+/// written into the instance buffer from the host while no build can read it.
+#[derive(Debug)]
+pub(crate) struct TlasInstanceBufferUse(Arc<Tlas>);
+
+impl Drop for TlasInstanceBufferUse {
+    fn drop(&mut self) {
+        self.0.instance_buffer_users.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A bottom or top level acceleration structure that has been destroyed, whose
+/// memory is released once the GPU is done with it.
+#[derive(Debug)]
+pub struct DestroyedAccelerationStructure {
+    raw: ManuallyDrop<Box<dyn hal::DynAccelerationStructure>>,
+    device: Arc<Device>,
+    label: String,
+}
+
+impl DestroyedAccelerationStructure {
+    pub fn label(&self) -> &dyn Debug {
+        &self.label
+    }
+}
+
+impl Drop for DestroyedAccelerationStructure {
+    fn drop(&mut self) {
+        resource_log!(
+            "Destroy raw AccelerationStructure (destroyed) {:?}",
+            self.label()
+        );
+        // SAFETY: We are in the Drop impl and we don't use self.raw anymore after this point.
+        let raw = unsafe { ManuallyDrop::take(&mut self.raw) };
+        unsafe {
+            self.device.raw().destroy_acceleration_structure(raw);
+        }
     }
 }
 
@@ -59,6 +59,15 @@ impl<T: AccelerationStructure> AccelerationStructureTracker<T> {
         self.allow_index(index);
 
         self.tracker_assert_in_bounds(index);
+
+        unsafe {
+            self.metadata.insert(index, resource);
+        }
+    }
+
+    /// Returns a list of all acceleration structures tracked.
+    pub fn used_resources(&self) -> impl Iterator<Item = Arc<T>> + '_ {
+        self.metadata.owned_resources()
     }
 }
 
@@ -126,11 +126,14 @@ struct EntryPoint {
     sampling_pairs: FastHashSet<(naga::Handle<Resource>, naga::Handle<Resource>)>,
     workgroup_size: [u32; 3],
     dual_source_blending: bool,
+    /// Whether the entry point traces rays, and so needs [`wgt::Features::RAY_QUERY`].
+    ray_query: bool,
 }
 
 #[derive(Debug)]
 pub struct Interface {
     limits: wgt::Limits,
+    features: wgt::Features,
     resources: naga::Arena<Resource>,
     entry_points: FastHashMap<(naga::ShaderStage, String), EntryPoint>,
 }
@@ -220,6 +223,13 @@ pub enum StageError {
     MissingEntryPoint(String),
     #[error("Shader global {0:?} is not available in the pipeline layout")]
     Binding(naga::ResourceBinding, #[source] BindingError),
+    #[error(
+        "Shader entry point uses ray queries, which require `Features::RAY_QUERY`; \
+        `Features::RAY_TRACING_ACCELERATION_STRUCTURE` only allows building acceleration structures"
+    )]
+    MissingRayQueryFeature,
+    #[error("Ray query counters binding {0:?} is already used by the shader")]
+    RayQueryCountersBindingInUse(naga::ResourceBinding),
     #[error("Unable to filter the texture ({texture:?}) by the sampler ({sampler:?})")]
     Filtering {
         texture: naga::ResourceBinding,
@@ -842,7 +852,12 @@ impl Interface {
         list.push(varying);
     }
 
-    pub fn new(module: &naga::Module, info: &naga::valid::ModuleInfo, limits: wgt::Limits) -> Self {
+    pub fn new(
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+        limits: wgt::Limits,
+        features: wgt::Features,
+    ) -> Self {
         let mut resources = naga::Arena::new();
         let mut resource_mapping = FastHashMap::default();
         for (var_handle, var) in module.global_variables.iter() {
@@ -909,7 +924,18 @@ impl Interface {
                 if !usage.is_empty() && var.binding.is_some() {
                     ep.resources.push(resource_mapping[&var_handle]);
                 }
+                if !usage.is_empty() {
+                    ep.ray_query |= matches!(
+                        module.types[var.ty].inner,
+                        naga::TypeInner::AccelerationStructure { .. }
+                    );
+                }
             }
+            // A ray query can only be initialized from an acceleration structure, but
+            // still count entry points that merely declare one.
+            ep.ray_query |= entry_point.function.local_variables.iter().any(|(_, var)| {
+                matches!(module.types[var.ty].inner, naga::TypeInner::RayQuery { .. })
+            });
 
             for key in info.sampling_set.iter() {
                 ep.sampling_pairs
@@ -923,6 +949,7 @@ impl Interface {
 
         Self {
             limits,
+            features,
             resources,
             entry_points,
         }
@@ -959,6 +986,105 @@ impl Interface {
         }
     }
 
+    /// Check that `res` is bound compatibly in `layouts`, or add it to them if
+    /// they are being derived.
+    fn check_resource(
+        res: &Resource,
+        layouts: &mut BindingLayoutSource<'_>,
+        shader_binding_sizes: &mut FastHashMap<naga::ResourceBinding, wgt::BufferSize>,
+        stage_bit: wgt::ShaderStages,
+    ) -> Result<(), BindingError> {
+        'err: {
+            match layouts {
+                BindingLayoutSource::Provided(layouts) => {
+                    // update the required binding size for this buffer
+                    if let ResourceType::Buffer { size } = res.ty {
+                        match shader_binding_sizes.entry(res.bind.clone()) {
+                            Entry::Occupied(e) => {
+                                *e.into_mut() = size.max(*e.get());
+                            }
+                            Entry::Vacant(e) => {
+                                e.insert(size);
+                            }
+                        }
+                    }
+
+                    let Some(map) = layouts.get(res.bind.group as usize) else {
+                        break 'err Err(BindingError::Missing);
+                    };
+
+                    let Some(entry) = map.get(res.bind.binding) else {
+                        break 'err Err(BindingError::Missing);
+                    };
+
+                    if !entry.visibility.contains(stage_bit) {
+                        break 'err Err(BindingError::Invisible);
+                    }
+
+                    res.check_binding_use(entry)
+                }
+                BindingLayoutSource::Derived(layouts) => {
+                    let Some(map) = layouts.get_mut(res.bind.group as usize) else {
+                        break 'err Err(BindingError::Missing);
+                    };
+
+                    let ty = match res.derive_binding_type() {
+                        Ok(ty) => ty,
+                        Err(error) => break 'err Err(error),
+                    };
+
+                    match map.entry(res.bind.binding) {
+                        indexmap::map::Entry::Occupied(e) if e.get().ty != ty => {
+                            break 'err Err(BindingError::InconsistentlyDerivedType)
+                        }
+                        indexmap::map::Entry::Occupied(e) => {
+                            e.into_mut().visibility |= stage_bit;
+                        }
+                        indexmap::map::Entry::Vacant(e) => {
+                            e.insert(BindGroupLayoutEntry {
+                                binding: res.bind.binding,
+                                ty,
+                                visibility: stage_bit,
+                                count: None,
+                            });
+                        }
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Check the binding of the ray query counters buffer used by an entry point.
+    ///
+    /// The counters are added to the shader when the pipeline is compiled, so
+    /// this treats them as one more read-write storage buffer used by the stage.
+    pub fn check_ray_query_counters(
+        &self,
+        layouts: &mut BindingLayoutSource<'_>,
+        shader_binding_sizes: &mut FastHashMap<naga::ResourceBinding, wgt::BufferSize>,
+        binding: wgt::RayQueryCountersBinding,
+        stage_bit: wgt::ShaderStages,
+    ) -> Result<(), StageError> {
+        let bind = crate::conv::map_ray_query_counters(binding);
+        if self.resources.iter().any(|(_, res)| res.bind == bind) {
+            return Err(StageError::RayQueryCountersBindingInUse(bind));
+        }
+
+        let res = Resource {
+            name: None,
+            bind,
+            ty: ResourceType::Buffer {
+                size: wgt::BufferSize::new(wgt::RAY_QUERY_COUNTERS_SIZE).unwrap(),
+            },
+            class: naga::AddressSpace::Storage {
+                access: naga::StorageAccess::LOAD | naga::StorageAccess::STORE,
+            },
+        };
+        Self::check_resource(&res, layouts, shader_binding_sizes, stage_bit)
+            .map_err(|error| StageError::Binding(res.bind, error))
+    }
+
     pub fn check_stage(
         &self,
         layouts: &mut BindingLayoutSource<'_>,
@@ -978,69 +1104,18 @@ impl Interface {
         };
         let (_stage, entry_point_name) = pair;
 
+        // Shader modules are validated with ray queries allowed whenever the device
+        // can work with acceleration structures at all, so that using them without
+        // `RAY_QUERY` is reported here, against the entry point.
+        if entry_point.ray_query && !self.features.contains(wgt::Features::RAY_QUERY) {
+            return Err(StageError::MissingRayQueryFeature);
+        }
+
         // check resources visibility
         for &handle in entry_point.resources.iter() {
             let res = &self.resources[handle];
-            let result = 'err: {
-                match layouts {
-                    BindingLayoutSource::Provided(layouts) => {
-                        // update the required binding size for this buffer
-                        if let ResourceType::Buffer { size } = res.ty {
-                            match shader_binding_sizes.entry(res.bind.clone()) {
-                                Entry::Occupied(e) => {
-                                    *e.into_mut() = size.max(*e.get());
-                                }
-                                Entry::Vacant(e) => {
-                                    e.insert(size);
-                                }
-                            }
-                        }
-
-                        let Some(map) = layouts.get(res.bind.group as usize) else {
-                            break 'err Err(BindingError::Missing);
-                        };
-
-                        let Some(entry) = map.get(res.bind.binding) else {
-                            break 'err Err(BindingError::Missing);
-                        };
-
-                        if !entry.visibility.contains(stage_bit) {
-                            break 'err Err(BindingError::Invisible);
-                        }
-
-                        res.check_binding_use(entry)
-                    }
-                    BindingLayoutSource::Derived(layouts) => {
-                        let Some(map) = layouts.get_mut(res.bind.group as usize) else {
-                            break 'err Err(BindingError::Missing);
-                        };
-
-                        let ty = match res.derive_binding_type() {
-                            Ok(ty) => ty,
-                            Err(error) => break 'err Err(error),
-                        };
-
-                        match map.entry(res.bind.binding) {
-                            indexmap::map::Entry::Occupied(e) if e.get().ty != ty => {
-                                break 'err Err(BindingError::InconsistentlyDerivedType)
-                            }
-                            indexmap::map::Entry::Occupied(e) => {
-                                e.into_mut().visibility |= stage_bit;
-                            }
-                            indexmap::map::Entry::Vacant(e) => {
-                                e.insert(BindGroupLayoutEntry {
-                                    binding: res.bind.binding,
-                                    ty,
-                                    visibility: stage_bit,
-                                    count: None,
-                                });
-                            }
-                        }
-                        Ok(())
-                    }
-                }
-            };
-            if let Err(error) = result {
+            if let Err(error) = Self::check_resource(res, layouts, shader_binding_sizes, stage_bit)
+            {
                 return Err(StageError::Binding(res.bind.clone(), error));
             }
         }
@@ -161,15 +161,15 @@ impl<A: hal::Api> Example<A> {
                 .join("shader.wgsl");
             let source = std::fs::read_to_string(shader_file).unwrap();
             let module = naga::front::wgsl::Frontend::new().parse(&source).unwrap();
-            let info = naga::valid::Validator::new(
-                naga::valid::ValidationFlags::all(),
-                naga::valid::Capabilities::empty(),
-            )
-            .validate(&module)
-            .unwrap();
+            let capabilities = naga::valid::Capabilities::empty();
+            let info =
+                naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+                    .validate(&module)
+                    .unwrap();
             hal::NagaShader {
                 module: Cow::Owned(module),
                 info,
+                capabilities,
                 debug_source: None,
             }
         };
@@ -257,6 +257,7 @@ impl<A: hal::Api> Example<A> {
                 entry_point: "vs_main",
                 constants: &constants,
                 zero_initialize_workgroup_memory: true,
+                ray_query_counters: None,
             },
             vertex_buffers: &[],
             fragment_stage: Some(hal::ProgrammableStage {
@@ -264,6 +265,7 @@ impl<A: hal::Api> Example<A> {
                 entry_point: "fs_main",
                 constants: &constants,
                 zero_initialize_workgroup_memory: true,
+                ray_query_counters: None,
             }),
             primitive: wgt::PrimitiveState {
                 topology: wgt::PrimitiveTopology::TriangleStrip,
@@ -338,15 +338,15 @@ impl<A: hal::Api> Example<A> {
                 .join("shader.wgsl");
             let source = std::fs::read_to_string(shader_file).unwrap();
             let module = naga::front::wgsl::Frontend::new().parse(&source).unwrap();
-            let info = naga::valid::Validator::new(
-                naga::valid::ValidationFlags::all(),
-                naga::valid::Capabilities::RAY_QUERY,
-            )
-            .validate(&module)
-            .unwrap();
+            let capabilities = naga::valid::Capabilities::RAY_QUERY;
+            let info =
+                naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+                    .validate(&module)
+                    .unwrap();
             hal::NagaShader {
                 module: Cow::Owned(module),
                 info,
+                capabilities,
                 debug_source: None,
             }
         };
@@ -381,6 +381,7 @@ impl<A: hal::Api> Example<A> {
                     entry_point: "main",
                     constants: &Default::default(),
                     zero_initialize_workgroup_memory: true,
+                    ray_query_counters: None,
                 },
                 cache: None,
             })
@@ -451,6 +452,8 @@ impl<A: hal::Api> Example<A> {
             vertex_format: wgt::VertexFormat::Float32x3,
             vertex_count: vertices.len() as u32,
             vertex_stride: 3 * 4,
+            vertex_offset: 0,
+            vertex_buffer_offset: 0,
             indices: Some(hal::AccelerationStructureTriangleIndices {
                 buffer: Some(&indices_buffer),
                 format: wgt::IndexFormat::Uint32,
@@ -495,6 +498,7 @@ impl<A: hal::Api> Example<A> {
                 label: Some("blas"),
                 size: blas_sizes.acceleration_structure_size,
                 format: hal::AccelerationStructureFormat::BottomLevel,
+                allow_compaction: false,
             })
         }
         .unwrap();
@@ -504,6 +508,7 @@ impl<A: hal::Api> Example<A> {
                 label: Some("tlas"),
                 size: tlas_sizes.acceleration_structure_size,
                 format: hal::AccelerationStructureFormat::TopLevel,
+                allow_compaction: false,
             })
         }
         .unwrap();
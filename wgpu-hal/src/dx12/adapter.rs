@@ -510,6 +510,7 @@ impl super::Adapter {
                     .unwrap(),
                 },
                 downlevel,
+                ray_tracing: None,
             },
         })
     }
@@ -1209,4 +1209,29 @@ impl crate::CommandEncoder for super::CommandEncoder {
     ) {
         todo!()
     }
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &super::AccelerationStructure,
+    ) {
+        todo!()
+    }
+
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &super::AccelerationStructure,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+    ) {
+        todo!()
+    }
+
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        _src: &super::AccelerationStructure,
+        _dst: &super::AccelerationStructure,
+        _copy: crate::AccelerationStructureCopy,
+    ) {
+        todo!()
+    }
 }
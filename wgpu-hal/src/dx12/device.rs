@@ -235,6 +235,16 @@ impl super::Device {
             stage.constants,
         )
         .map_err(|e| crate::PipelineError::PipelineConstants(stage_bit, format!("HLSL: {e:?}")))?;
+        let (module, info) = match stage.ray_query_counters {
+            Some(ref binding) => naga::back::ray_query_counters::instrument(
+                module,
+                info,
+                stage.module.naga.capabilities,
+                binding,
+            )
+            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("HLSL: {e:?}")))?,
+            None => (module, info),
+        };
 
         let needs_temp_options = stage.zero_initialize_workgroup_memory
             != layout.naga_options.zero_initialize_workgroup_memory;
@@ -1769,6 +1779,16 @@ impl crate::Device for super::Device {
         todo!()
     }
 
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        _desc: &crate::AccelerationStructureDescriptor,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+    ) -> Result<super::AccelerationStructure, DeviceError> {
+        // Use the buffer's D3D12 resource at the given GPU virtual address offset.
+        todo!()
+    }
+
     unsafe fn destroy_acceleration_structure(
         &self,
         _acceleration_structure: super::AccelerationStructure,
@@ -1777,6 +1797,14 @@ impl crate::Device for super::Device {
         todo!()
     }
 
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        _acceleration_structure: &super::AccelerationStructure,
+    ) -> Result<wgt::BufferAddress, crate::DeviceError> {
+        // Read back the result of `EmitRaytracingAccelerationStructurePostbuildInfo`.
+        todo!()
+    }
+
     fn get_internal_counters(&self) -> wgt::HalCounters {
         self.counters.clone()
     }
@@ -1,10 +1,11 @@
 use std::ops::Range;
 
 use crate::{
-    AccelerationStructureBarrier, Api, Attachment, BufferBarrier, BufferBinding, BufferCopy,
-    BufferTextureCopy, BuildAccelerationStructureDescriptor, ColorAttachment, CommandEncoder,
-    ComputePassDescriptor, DepthStencilAttachment, DeviceError, Label, MemoryRange,
-    PassTimestampWrites, Rect, RenderPassDescriptor, TextureBarrier, TextureCopy, TextureUses,
+    AccelerationStructureBarrier, AccelerationStructureCopy, Api, Attachment, BufferBarrier,
+    BufferBinding, BufferCopy, BufferTextureCopy, BuildAccelerationStructureDescriptor,
+    ColorAttachment, CommandEncoder, ComputePassDescriptor, DepthStencilAttachment, DeviceError,
+    Label, MemoryRange, PassTimestampWrites, Rect, RenderPassDescriptor, TextureBarrier,
+    TextureCopy, TextureUses,
 };
 
 use super::{
@@ -179,6 +180,23 @@ pub trait DynCommandEncoder: DynResource + std::fmt::Debug {
         &mut self,
         barrier: AccelerationStructureBarrier,
     );
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+    );
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+        buffer: &dyn DynBuffer,
+        offset: wgt::BufferAddress,
+    );
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        src: &dyn DynAccelerationStructure,
+        dst: &dyn DynAccelerationStructure,
+        copy: AccelerationStructureCopy,
+    );
 }
 
 impl<C: CommandEncoder + DynResource> DynCommandEncoder for C {
@@ -605,6 +623,43 @@ impl<C: CommandEncoder + DynResource> DynCommandEncoder for C {
     ) {
         unsafe { C::place_acceleration_structure_barrier(self, barrier) };
     }
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+    ) {
+        let acceleration_structure = acceleration_structure.expect_downcast_ref();
+        unsafe { C::write_acceleration_structure_compacted_size(self, acceleration_structure) };
+    }
+
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+        buffer: &dyn DynBuffer,
+        offset: wgt::BufferAddress,
+    ) {
+        let acceleration_structure = acceleration_structure.expect_downcast_ref();
+        let buffer = buffer.expect_downcast_ref();
+        unsafe {
+            C::copy_acceleration_structure_compacted_size(
+                self,
+                acceleration_structure,
+                buffer,
+                offset,
+            )
+        };
+    }
+
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        src: &dyn DynAccelerationStructure,
+        dst: &dyn DynAccelerationStructure,
+        copy: AccelerationStructureCopy,
+    ) {
+        let src = src.expect_downcast_ref();
+        let dst = dst.expect_downcast_ref();
+        unsafe { C::copy_acceleration_structure(self, src, dst, copy) };
+    }
 }
 
 impl<'a> PassTimestampWrites<'a, dyn DynQuerySet> {
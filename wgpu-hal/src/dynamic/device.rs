@@ -148,6 +148,12 @@ pub trait DynDevice: DynResource {
         &self,
         desc: &AccelerationStructureDescriptor,
     ) -> Result<Box<dyn DynAccelerationStructure>, DeviceError>;
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        desc: &AccelerationStructureDescriptor,
+        buffer: &dyn DynBuffer,
+        offset: wgt::BufferAddress,
+    ) -> Result<Box<dyn DynAccelerationStructure>, DeviceError>;
     unsafe fn get_acceleration_structure_build_sizes(
         &self,
         desc: &GetAccelerationStructureBuildSizesDescriptor<dyn DynBuffer>,
@@ -160,6 +166,10 @@ pub trait DynDevice: DynResource {
         &self,
         acceleration_structure: Box<dyn DynAccelerationStructure>,
     );
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+    ) -> Result<wgt::BufferAddress, DeviceError>;
 
     fn get_internal_counters(&self) -> wgt::HalCounters;
     fn generate_allocator_report(&self) -> Option<wgt::AllocatorReport>;
@@ -489,6 +499,17 @@ impl<D: Device + DynResource> DynDevice for D {
             .map(|b| Box::new(b) as Box<dyn DynAccelerationStructure>)
     }
 
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        desc: &AccelerationStructureDescriptor,
+        buffer: &dyn DynBuffer,
+        offset: wgt::BufferAddress,
+    ) -> Result<Box<dyn DynAccelerationStructure>, DeviceError> {
+        let buffer = buffer.expect_downcast_ref();
+        unsafe { D::create_acceleration_structure_in_buffer(self, desc, buffer, offset) }
+            .map(|b| Box::new(b) as Box<dyn DynAccelerationStructure>)
+    }
+
     unsafe fn get_acceleration_structure_build_sizes(
         &self,
         desc: &GetAccelerationStructureBuildSizesDescriptor<dyn DynBuffer>,
@@ -516,6 +537,14 @@ impl<D: Device + DynResource> DynDevice for D {
         unsafe { D::destroy_acceleration_structure(self, acceleration_structure.unbox()) }
     }
 
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        acceleration_structure: &dyn DynAccelerationStructure,
+    ) -> Result<wgt::BufferAddress, DeviceError> {
+        let acceleration_structure = acceleration_structure.expect_downcast_ref();
+        unsafe { D::get_acceleration_structure_compacted_size(self, acceleration_structure) }
+    }
+
     fn get_internal_counters(&self) -> wgt::HalCounters {
         D::get_internal_counters(self)
     }
@@ -146,6 +146,7 @@ impl<'a> ProgrammableStage<'a, dyn DynShaderModule> {
             entry_point: self.entry_point,
             constants: self.constants,
             zero_initialize_workgroup_memory: self.zero_initialize_workgroup_memory,
+            ray_query_counters: self.ray_query_counters,
         }
     }
 }
@@ -170,6 +171,8 @@ impl<'a> AccelerationStructureEntries<'a, dyn DynBuffer> {
                             first_vertex: t.first_vertex,
                             vertex_count: t.vertex_count,
                             vertex_stride: t.vertex_stride,
+                            vertex_offset: t.vertex_offset,
+                            vertex_buffer_offset: t.vertex_buffer_offset,
                             indices: t.indices.as_ref().map(|i| {
                                 AccelerationStructureTriangleIndices {
                                     buffer: i.buffer.map(|b| b.expect_downcast_ref()),
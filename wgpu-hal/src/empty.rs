@@ -289,6 +289,14 @@ impl crate::Device for Context {
     ) -> DeviceResult<Resource> {
         Ok(Resource)
     }
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        desc: &crate::AccelerationStructureDescriptor,
+        buffer: &Resource,
+        offset: wgt::BufferAddress,
+    ) -> DeviceResult<Resource> {
+        Ok(Resource)
+    }
     unsafe fn get_acceleration_structure_build_sizes<'a>(
         &self,
         _desc: &crate::GetAccelerationStructureBuildSizesDescriptor<'a, Resource>,
@@ -302,6 +310,12 @@ impl crate::Device for Context {
         Default::default()
     }
     unsafe fn destroy_acceleration_structure(&self, _acceleration_structure: Resource) {}
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        _acceleration_structure: &Resource,
+    ) -> Result<wgt::BufferAddress, crate::DeviceError> {
+        Ok(0)
+    }
 
     fn get_internal_counters(&self) -> wgt::HalCounters {
         Default::default()
@@ -503,4 +517,26 @@ impl crate::CommandEncoder for Encoder {
         _barriers: crate::AccelerationStructureBarrier,
     ) {
     }
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &Resource,
+    ) {
+    }
+
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &Resource,
+        _buffer: &Resource,
+        _offset: wgt::BufferAddress,
+    ) {
+    }
+
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        _src: &Resource,
+        _dst: &Resource,
+        _copy: crate::AccelerationStructureCopy,
+    ) {
+    }
 }
@@ -842,6 +842,7 @@ impl super::Adapter {
                     buffer_copy_offset: wgt::BufferSize::new(4).unwrap(),
                     buffer_copy_pitch: wgt::BufferSize::new(4).unwrap(),
                 },
+                ray_tracing: None,
             },
         })
     }
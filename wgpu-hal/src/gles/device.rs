@@ -1606,6 +1606,14 @@ impl crate::Device for super::Device {
     ) -> Result<super::AccelerationStructure, crate::DeviceError> {
         unimplemented!()
     }
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        _desc: &crate::AccelerationStructureDescriptor,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+    ) -> Result<super::AccelerationStructure, crate::DeviceError> {
+        unimplemented!()
+    }
     unsafe fn get_acceleration_structure_build_sizes<'a>(
         &self,
         _desc: &crate::GetAccelerationStructureBuildSizesDescriptor<'a, super::Buffer>,
@@ -1624,6 +1632,13 @@ impl crate::Device for super::Device {
     ) {
     }
 
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        _acceleration_structure: &super::AccelerationStructure,
+    ) -> Result<wgt::BufferAddress, crate::DeviceError> {
+        unimplemented!()
+    }
+
     fn get_internal_counters(&self) -> wgt::HalCounters {
         self.counters.clone()
     }
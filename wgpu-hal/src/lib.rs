@@ -909,6 +909,23 @@ pub trait Device: WasmNotSendSync {
         &self,
         desc: &AccelerationStructureDescriptor,
     ) -> Result<<Self::A as Api>::AccelerationStructure, DeviceError>;
+    /// Create an acceleration structure backed by `desc.size` bytes of `buffer`,
+    /// starting at `offset`, instead of memory of its own.
+    ///
+    /// # Safety
+    ///
+    /// - `buffer` must have been created with [`BufferUses::ACCELERATION_STRUCTURE_STORAGE`].
+    ///
+    /// - `offset` must be a multiple of [`wgt::ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT`], and
+    ///   the range must lie within `buffer`.
+    ///
+    /// - `buffer` must outlive the acceleration structure.
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        desc: &AccelerationStructureDescriptor,
+        buffer: &<Self::A as Api>::Buffer,
+        offset: wgt::BufferAddress,
+    ) -> Result<<Self::A as Api>::AccelerationStructure, DeviceError>;
     unsafe fn get_acceleration_structure_build_sizes(
         &self,
         desc: &GetAccelerationStructureBuildSizesDescriptor<<Self::A as Api>::Buffer>,
@@ -921,6 +938,20 @@ pub trait Device: WasmNotSendSync {
         &self,
         acceleration_structure: <Self::A as Api>::AccelerationStructure,
     );
+    /// Read the compacted size last written by
+    /// [`CommandEncoder::write_acceleration_structure_compacted_size`], blocking until it
+    /// is available.
+    ///
+    /// # Safety
+    ///
+    /// - `acceleration_structure` must have been created with
+    ///   [`AccelerationStructureDescriptor::allow_compaction`].
+    ///
+    /// - A command buffer writing its compacted size must have been submitted.
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        acceleration_structure: &<Self::A as Api>::AccelerationStructure,
+    ) -> Result<wgt::BufferAddress, DeviceError>;
 
     fn get_internal_counters(&self) -> wgt::HalCounters;
 
@@ -1365,6 +1396,43 @@ pub trait CommandEncoder: WasmNotSendSync + fmt::Debug {
         &mut self,
         barrier: AccelerationStructureBarrier,
     );
+
+    /// Write the size `acceleration_structure` would have once compacted, which
+    /// [`Device::get_acceleration_structure_compacted_size`] reads back.
+    ///
+    /// The acceleration structure must have been created with
+    /// [`AccelerationStructureDescriptor::allow_compaction`] and built with
+    /// [`AccelerationStructureBuildFlags::ALLOW_COMPACTION`], and its build must be ordered
+    /// before this by a barrier from `BUILD_OUTPUT` to `BUILD_INPUT`.
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &<Self::A as Api>::AccelerationStructure,
+    );
+
+    /// Copy the compacted size last written by
+    /// [`CommandEncoder::write_acceleration_structure_compacted_size`] into `buffer` at
+    /// `offset`, as a `u64`.
+    ///
+    /// The size must have been written by this or an earlier command buffer, and `buffer` must
+    /// be in [`BufferUses::COPY_DST`] state.
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &<Self::A as Api>::AccelerationStructure,
+        buffer: &<Self::A as Api>::Buffer,
+        offset: wgt::BufferAddress,
+    );
+
+    /// Copy `src` into `dst`, as described by `copy`.
+    ///
+    /// `dst` must be at least as large as `src`, or as the compacted size of `src` when
+    /// compacting, and the build of `src` must be ordered before this by a barrier from
+    /// `BUILD_OUTPUT` to `BUILD_INPUT`.
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        src: &<Self::A as Api>::AccelerationStructure,
+        dst: &<Self::A as Api>::AccelerationStructure,
+        copy: AccelerationStructureCopy,
+    );
 }
 
 bitflags!(
@@ -1540,6 +1608,8 @@ bitflags::bitflags! {
         const ACCELERATION_STRUCTURE_SCRATCH = 1 << 11;
         const BOTTOM_LEVEL_ACCELERATION_STRUCTURE_INPUT = 1 << 12;
         const TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT = 1 << 13;
+        /// Memory backing acceleration structures created in the buffer.
+        const ACCELERATION_STRUCTURE_STORAGE = 1 << 14;
         /// The combination of states that a buffer may be in _at the same time_.
         const INCLUSIVE = Self::MAP_READ.bits() | Self::COPY_SRC.bits() |
             Self::INDEX.bits() | Self::VERTEX.bits() | Self::UNIFORM.bits() |
@@ -1616,6 +1686,9 @@ pub struct Capabilities {
     pub limits: wgt::Limits,
     pub alignments: Alignments,
     pub downlevel: wgt::DownlevelCapabilities,
+    /// Build properties of acceleration structures, if the adapter supports
+    /// [`wgt::Features::RAY_TRACING_ACCELERATION_STRUCTURE`].
+    pub ray_tracing: Option<wgt::RayTracingBuildProperties>,
 }
 
 #[derive(Debug)]
@@ -1875,6 +1948,9 @@ pub struct NagaShader {
     pub module: Cow<'static, naga::Module>,
     /// Analysis information of the module.
     pub info: naga::valid::ModuleInfo,
+    /// Capabilities the module was validated with, which backends validate it with again
+    /// after instrumenting it.
+    pub capabilities: naga::valid::Capabilities,
     /// Source codes for debug
     pub debug_source: Option<DebugSource>,
 }
@@ -1920,6 +1996,12 @@ pub struct ProgrammableStage<'a, M: DynShaderModule + ?Sized> {
     /// This is required by the WebGPU spec, but may have overhead which can be avoided
     /// for cross-platform applications
     pub zero_initialize_workgroup_memory: bool,
+    /// If set, instrument every ray query in this stage with traversal counters
+    /// stored in the storage buffer at this binding.
+    ///
+    /// See `naga::back::ray_query_counters` for the layout of the buffer. Backends
+    /// without ray query support ignore this.
+    pub ray_query_counters: Option<naga::ResourceBinding>,
 }
 
 impl<M: DynShaderModule + ?Sized> Clone for ProgrammableStage<'_, M> {
@@ -1929,6 +2011,7 @@ impl<M: DynShaderModule + ?Sized> Clone for ProgrammableStage<'_, M> {
             entry_point: self.entry_point,
             constants: self.constants,
             zero_initialize_workgroup_memory: self.zero_initialize_workgroup_memory,
+            ray_query_counters: self.ray_query_counters.clone(),
         }
     }
 }
@@ -2174,6 +2257,9 @@ pub struct AccelerationStructureDescriptor<'a> {
     pub label: Label<'a>,
     pub size: wgt::BufferAddress,
     pub format: AccelerationStructureFormat,
+    /// Whether the compacted size of the acceleration structure can be
+    /// [written](CommandEncoder::write_acceleration_structure_compacted_size).
+    pub allow_compaction: bool,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -2188,6 +2274,15 @@ pub enum AccelerationStructureBuildMode {
     Update,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AccelerationStructureCopy {
+    /// Copy the acceleration structure as is.
+    Clone,
+    /// Copy the acceleration structure into its compacted size, which requires it to have
+    /// been built with [`AccelerationStructureBuildFlags::ALLOW_COMPACTION`].
+    Compact,
+}
+
 /// Information of the required size for a corresponding entries struct (+ flags)
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct AccelerationStructureBuildSizes {
@@ -2236,6 +2331,8 @@ pub enum AccelerationStructureEntries<'a, B: DynBuffer + ?Sized> {
 }
 
 /// * `first_vertex` - offset in the vertex buffer (as number of vertices)
+/// * `vertex_offset` - offset of the position within each vertex in bytes
+/// * `vertex_buffer_offset` - offset of the first vertex in the vertex buffer in bytes
 /// * `indices` - optional index buffer with attributes
 /// * `transform` - optional transform
 #[derive(Clone, Debug)]
@@ -2245,6 +2342,8 @@ pub struct AccelerationStructureTriangles<'a, B: DynBuffer + ?Sized> {
     pub first_vertex: u32,
     pub vertex_count: u32,
     pub vertex_stride: wgt::BufferAddress,
+    pub vertex_offset: wgt::BufferAddress,
+    pub vertex_buffer_offset: wgt::BufferAddress,
     pub indices: Option<AccelerationStructureTriangleIndices<'a, B>>,
     pub transform: Option<AccelerationStructureTriangleTransform<'a, B>>,
     pub flags: AccelerationStructureGeometryFlags,
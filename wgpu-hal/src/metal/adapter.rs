@@ -999,6 +999,7 @@ impl super::PrivateCapabilities {
                 buffer_copy_pitch: wgt::BufferSize::new(4).unwrap(),
             },
             downlevel,
+            ray_tracing: None,
         }
     }
 
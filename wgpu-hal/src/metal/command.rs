@@ -1277,6 +1277,31 @@ impl crate::CommandEncoder for super::CommandEncoder {
     ) {
         unimplemented!()
     }
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &super::AccelerationStructure,
+    ) {
+        unimplemented!()
+    }
+
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        _acceleration_structure: &super::AccelerationStructure,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+    ) {
+        unimplemented!()
+    }
+
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        _src: &super::AccelerationStructure,
+        _dst: &super::AccelerationStructure,
+        _copy: crate::AccelerationStructureCopy,
+    ) {
+        unimplemented!()
+    }
 }
 
 impl Drop for super::CommandEncoder {
@@ -113,6 +113,16 @@ impl super::Device {
             stage.constants,
         )
         .map_err(|e| crate::PipelineError::PipelineConstants(stage_bit, format!("MSL: {:?}", e)))?;
+        let (module, module_info) = match stage.ray_query_counters {
+            Some(ref binding) => naga::back::ray_query_counters::instrument(
+                module,
+                module_info,
+                stage.module.naga.capabilities,
+                binding,
+            )
+            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?,
+            None => (module, module_info),
+        };
 
         let ep_resources = &layout.per_stage_map[naga_stage];
 
@@ -1413,6 +1423,15 @@ impl crate::Device for super::Device {
         unimplemented!()
     }
 
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        _desc: &crate::AccelerationStructureDescriptor,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+    ) -> Result<super::AccelerationStructure, crate::DeviceError> {
+        unimplemented!()
+    }
+
     unsafe fn destroy_acceleration_structure(
         &self,
         _acceleration_structure: super::AccelerationStructure,
@@ -1420,6 +1439,13 @@ impl crate::Device for super::Device {
         unimplemented!()
     }
 
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        _acceleration_structure: &super::AccelerationStructure,
+    ) -> Result<wgt::BufferAddress, crate::DeviceError> {
+        unimplemented!()
+    }
+
     fn get_internal_counters(&self) -> wgt::HalCounters {
         self.counters.clone()
     }
@@ -1531,6 +1531,36 @@ impl super::Instance {
                 limits: wgt::DownlevelLimits {},
                 shader_model: wgt::ShaderModel::Sm5, //TODO?
             },
+            ray_tracing: if available_features
+                .contains(wgt::Features::RAY_TRACING_ACCELERATION_STRUCTURE)
+            {
+                phd_capabilities.acceleration_structure.map(|properties| {
+                    wgt::RayTracingBuildProperties {
+                        max_geometry_count: properties.max_geometry_count,
+                        max_instance_count: properties.max_instance_count,
+                        max_primitive_count: properties.max_primitive_count,
+                        min_scratch_offset_alignment: properties
+                            .min_acceleration_structure_scratch_offset_alignment,
+                        host_builds: phd_features
+                            .acceleration_structure
+                            .map_or(false, |features| {
+                                features.acceleration_structure_host_commands == vk::TRUE
+                            }),
+                        snorm16x4_vertices: supports_acceleration_structure_vertex_format(
+                            &self.shared.raw,
+                            phd,
+                            vk::Format::R16G16B16A16_SNORM,
+                        ),
+                        unorm16x4_vertices: supports_acceleration_structure_vertex_format(
+                            &self.shared.raw,
+                            phd,
+                            vk::Format::R16G16B16A16_UNORM,
+                        ),
+                    }
+                })
+            } else {
+                None
+            },
         };
 
         let adapter = super::Adapter {
@@ -2341,6 +2371,17 @@ fn supports_format(
     }
 }
 
+fn supports_acceleration_structure_vertex_format(
+    instance: &ash::Instance,
+    phd: vk::PhysicalDevice,
+    format: vk::Format,
+) -> bool {
+    let properties = unsafe { instance.get_physical_device_format_properties(phd, format) };
+    properties
+        .buffer_features
+        .contains(vk::FormatFeatureFlags::ACCELERATION_STRUCTURE_VERTEX_BUFFER_KHR)
+}
+
 fn supports_bgra8unorm_storage(
     instance: &ash::Instance,
     phd: vk::PhysicalDevice,
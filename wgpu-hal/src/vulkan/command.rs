@@ -489,7 +489,9 @@ impl crate::CommandEncoder for super::CommandEncoder {
                         let mut triangle_data =
                             vk::AccelerationStructureGeometryTrianglesDataKHR::default()
                                 .vertex_data(vk::DeviceOrHostAddressConstKHR {
-                                    device_address: get_device_address(triangles.vertex_buffer),
+                                    device_address: get_device_address(triangles.vertex_buffer)
+                                        + triangles.vertex_buffer_offset
+                                        + triangles.vertex_offset,
                                 })
                                 .vertex_format(conv::map_vertex_format(triangles.vertex_format))
                                 .max_vertex(triangles.vertex_count)
@@ -648,6 +650,84 @@ impl crate::CommandEncoder for super::CommandEncoder {
             )
         };
     }
+
+    unsafe fn write_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &super::AccelerationStructure,
+    ) {
+        let ray_tracing_functions = self
+            .device
+            .extension_fns
+            .ray_tracing
+            .as_ref()
+            .expect("Feature `RAY_TRACING` not enabled");
+        let query = acceleration_structure
+            .compacted_size_query
+            .expect("Acceleration structure doesn't allow compaction");
+
+        unsafe {
+            self.device
+                .raw
+                .cmd_reset_query_pool(self.active, query, 0, 1);
+            ray_tracing_functions
+                .acceleration_structure
+                .cmd_write_acceleration_structures_properties(
+                    self.active,
+                    &[acceleration_structure.raw],
+                    vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+                    query,
+                    0,
+                );
+        }
+    }
+
+    unsafe fn copy_acceleration_structure_compacted_size(
+        &mut self,
+        acceleration_structure: &super::AccelerationStructure,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+    ) {
+        let query = acceleration_structure
+            .compacted_size_query
+            .expect("Acceleration structure doesn't allow compaction");
+
+        unsafe {
+            self.device.raw.cmd_copy_query_pool_results(
+                self.active,
+                query,
+                0,
+                1,
+                buffer.raw,
+                offset,
+                wgt::QUERY_SIZE as u64,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )
+        };
+    }
+
+    unsafe fn copy_acceleration_structure(
+        &mut self,
+        src: &super::AccelerationStructure,
+        dst: &super::AccelerationStructure,
+        copy: crate::AccelerationStructureCopy,
+    ) {
+        let ray_tracing_functions = self
+            .device
+            .extension_fns
+            .ray_tracing
+            .as_ref()
+            .expect("Feature `RAY_TRACING` not enabled");
+
+        let info = vk::CopyAccelerationStructureInfoKHR::default()
+            .src(src.raw)
+            .dst(dst.raw)
+            .mode(conv::map_acceleration_structure_copy(copy));
+        unsafe {
+            ray_tracing_functions
+                .acceleration_structure
+                .cmd_copy_acceleration_structure(self.active, &info)
+        };
+    }
     // render
 
     unsafe fn begin_render_pass(
@@ -529,6 +529,10 @@ pub fn map_buffer_usage(usage: crate::BufferUses) -> vk::BufferUsageFlags {
         flags |= vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
             | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
     }
+    if usage.contains(crate::BufferUses::ACCELERATION_STRUCTURE_STORAGE) {
+        flags |= vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
+            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+    }
     flags
 }
 
@@ -583,8 +587,17 @@ pub fn map_buffer_usage_to_barrier(
     }
     if usage.intersects(
         crate::BufferUses::BOTTOM_LEVEL_ACCELERATION_STRUCTURE_INPUT
-            | crate::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT
-            | crate::BufferUses::ACCELERATION_STRUCTURE_SCRATCH,
+            | crate::BufferUses::TOP_LEVEL_ACCELERATION_STRUCTURE_INPUT,
+    ) {
+        // Geometry and instance data is read by the build as shader reads, so
+        // prior writes (e.g. from a compute shader) must be made visible to
+        // `SHADER_READ` in the build stage.
+        stages |= vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR;
+        access |= vk::AccessFlags::SHADER_READ;
+    }
+    if usage.intersects(
+        crate::BufferUses::ACCELERATION_STRUCTURE_SCRATCH
+            | crate::BufferUses::ACCELERATION_STRUCTURE_STORAGE,
     ) {
         stages |= vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR;
         access |= vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR
@@ -899,6 +912,15 @@ pub fn map_acceleration_structure_build_mode(
     }
 }
 
+pub fn map_acceleration_structure_copy(
+    copy: crate::AccelerationStructureCopy,
+) -> vk::CopyAccelerationStructureModeKHR {
+    match copy {
+        crate::AccelerationStructureCopy::Clone => vk::CopyAccelerationStructureModeKHR::CLONE,
+        crate::AccelerationStructureCopy::Compact => vk::CopyAccelerationStructureModeKHR::COMPACT,
+    }
+}
+
 pub fn map_acceleration_structure_flags(
     flags: crate::AccelerationStructureBuildFlags,
 ) -> vk::BuildAccelerationStructureFlagsKHR {
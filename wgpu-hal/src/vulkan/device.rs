@@ -716,9 +716,19 @@ impl super::Device {
         let stage_flags = crate::auxil::map_naga_stage(naga_stage);
         let vk_module = match *stage.module {
             super::ShaderModule::Raw(raw) => raw,
+            super::ShaderModule::RawWithRayQueries { raw, .. }
+                if stage.ray_query_counters.is_none() =>
+            {
+                raw
+            }
             super::ShaderModule::Intermediate {
                 ref naga_shader,
                 runtime_checks,
+            }
+            | super::ShaderModule::RawWithRayQueries {
+                ref naga_shader,
+                runtime_checks,
+                ..
             } => {
                 let pipeline_options = naga::back::spv::PipelineOptions {
                     entry_point: stage.entry_point.to_string(),
@@ -767,6 +777,16 @@ impl super::Device {
                 .map_err(|e| {
                     crate::PipelineError::PipelineConstants(stage_flags, format!("{e}"))
                 })?;
+                let (module, info) = match stage.ray_query_counters {
+                    Some(ref binding) => naga::back::ray_query_counters::instrument(
+                        module,
+                        info,
+                        naga_shader.capabilities,
+                        binding,
+                    )
+                    .map_err(|e| crate::PipelineError::Linkage(stage_flags, format!("{e}")))?,
+                    None => (module, info),
+                };
 
                 let spv = {
                     profiling::scope!("naga::spv::write_vec");
@@ -796,7 +816,13 @@ impl super::Device {
             _entry_point: entry_point,
             temp_raw_module: match *stage.module {
                 super::ShaderModule::Raw(_) => None,
-                super::ShaderModule::Intermediate { .. } => Some(vk_module),
+                super::ShaderModule::RawWithRayQueries { .. }
+                    if stage.ray_query_counters.is_none() =>
+                {
+                    None
+                }
+                super::ShaderModule::Intermediate { .. }
+                | super::ShaderModule::RawWithRayQueries { .. } => Some(vk_module),
             },
         })
     }
@@ -833,6 +859,55 @@ impl super::Device {
     pub fn shared_instance(&self) -> &super::InstanceShared {
         &self.shared.instance
     }
+
+    /// Create an acceleration structure in `desc.size` bytes of `buffer` at `offset`.
+    unsafe fn create_raw_acceleration_structure(
+        &self,
+        desc: &crate::AccelerationStructureDescriptor,
+        buffer: vk::Buffer,
+        offset: wgt::BufferAddress,
+    ) -> Result<vk::AccelerationStructureKHR, crate::DeviceError> {
+        let ray_tracing_functions = self
+            .shared
+            .extension_fns
+            .ray_tracing
+            .as_ref()
+            .expect("Feature `RAY_TRACING` not enabled");
+
+        let vk_info = vk::AccelerationStructureCreateInfoKHR::default()
+            .buffer(buffer)
+            .offset(offset)
+            .size(desc.size)
+            .ty(conv::map_acceleration_structure_format(desc.format));
+
+        let raw = unsafe {
+            ray_tracing_functions
+                .acceleration_structure
+                .create_acceleration_structure(&vk_info, None)?
+        };
+
+        if let Some(label) = desc.label {
+            unsafe { self.shared.set_object_name(raw, label) };
+        }
+
+        Ok(raw)
+    }
+
+    /// Create the query holding the compacted size of an acceleration structure created
+    /// with `desc`, if it allows compaction.
+    unsafe fn create_compacted_size_query(
+        &self,
+        desc: &crate::AccelerationStructureDescriptor,
+    ) -> Result<Option<vk::QueryPool>, crate::DeviceError> {
+        if !desc.allow_compaction {
+            return Ok(None);
+        }
+        let vk_info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR)
+            .query_count(1);
+        let raw = unsafe { self.shared.raw.create_query_pool(&vk_info, None)? };
+        Ok(Some(raw))
+    }
 }
 
 impl crate::Device for super::Device {
@@ -1658,6 +1733,7 @@ impl crate::Device for super::Device {
         desc: &crate::ShaderModuleDescriptor,
         shader: crate::ShaderInput,
     ) -> Result<super::ShaderModule, crate::ShaderError> {
+        let mut ray_query_shader = None;
         let spv = match shader {
             crate::ShaderInput::Naga(naga_shader) => {
                 if self
@@ -1688,15 +1764,23 @@ impl crate::Device for super::Device {
                         binding_array: naga::proc::BoundsCheckPolicy::Unchecked,
                     };
                 }
-                Cow::Owned(
-                    naga::back::spv::write_vec(
-                        &naga_shader.module,
-                        &naga_shader.info,
-                        &naga_options,
-                        None,
-                    )
-                    .map_err(|e| crate::ShaderError::Compilation(format!("{e}")))?,
+                let spv = naga::back::spv::write_vec(
+                    &naga_shader.module,
+                    &naga_shader.info,
+                    &naga_options,
+                    None,
                 )
+                .map_err(|e| crate::ShaderError::Compilation(format!("{e}")))?;
+                // Pipelines may ask for ray queries to be instrumented, which needs the IR.
+                if naga_shader
+                    .module
+                    .types
+                    .iter()
+                    .any(|(_, ty)| matches!(ty.inner, naga::TypeInner::RayQuery { .. }))
+                {
+                    ray_query_shader = Some(naga_shader);
+                }
+                Cow::Owned(spv)
             }
             crate::ShaderInput::SpirV(spv) => Cow::Borrowed(spv),
         };
@@ -1709,12 +1793,19 @@ impl crate::Device for super::Device {
 
         self.counters.shader_modules.add(1);
 
-        Ok(super::ShaderModule::Raw(raw))
+        Ok(match ray_query_shader {
+            Some(naga_shader) => super::ShaderModule::RawWithRayQueries {
+                raw,
+                naga_shader,
+                runtime_checks: desc.runtime_checks,
+            },
+            None => super::ShaderModule::Raw(raw),
+        })
     }
 
     unsafe fn destroy_shader_module(&self, module: super::ShaderModule) {
         match module {
-            super::ShaderModule::Raw(raw) => {
+            super::ShaderModule::Raw(raw) | super::ShaderModule::RawWithRayQueries { raw, .. } => {
                 unsafe { self.shared.raw.destroy_shader_module(raw, None) };
             }
             super::ShaderModule::Intermediate { .. } => {}
@@ -2316,13 +2407,6 @@ impl crate::Device for super::Device {
         &self,
         desc: &crate::AccelerationStructureDescriptor,
     ) -> Result<super::AccelerationStructure, crate::DeviceError> {
-        let ray_tracing_functions = self
-            .shared
-            .extension_fns
-            .ray_tracing
-            .as_ref()
-            .expect("Feature `RAY_TRACING` not enabled");
-
         let vk_buffer_info = vk::BufferCreateInfo::default()
             .size(desc.size)
             .usage(
@@ -2353,29 +2437,41 @@ impl crate::Device for super::Device {
                 self.shared.set_object_name(raw_buffer, label);
             }
 
-            let vk_info = vk::AccelerationStructureCreateInfoKHR::default()
-                .buffer(raw_buffer)
-                .offset(0)
-                .size(desc.size)
-                .ty(conv::map_acceleration_structure_format(desc.format));
+            let raw_acceleration_structure =
+                self.create_raw_acceleration_structure(desc, raw_buffer, 0)?;
+            let compacted_size_query = self.create_compacted_size_query(desc)?;
 
-            let raw_acceleration_structure = ray_tracing_functions
-                .acceleration_structure
-                .create_acceleration_structure(&vk_info, None)?;
-
-            if let Some(label) = desc.label {
-                self.shared
-                    .set_object_name(raw_acceleration_structure, label);
-            }
+            self.counters
+                .acceleration_structure_memory
+                .add(block.size() as isize);
+            self.counters.acceleration_structures.add(1);
 
             Ok(super::AccelerationStructure {
                 raw: raw_acceleration_structure,
-                buffer: raw_buffer,
-                block: Mutex::new(block),
+                storage: Some((raw_buffer, Mutex::new(block))),
+                compacted_size_query,
             })
         }
     }
 
+    unsafe fn create_acceleration_structure_in_buffer(
+        &self,
+        desc: &crate::AccelerationStructureDescriptor,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+    ) -> Result<super::AccelerationStructure, crate::DeviceError> {
+        let raw = unsafe { self.create_raw_acceleration_structure(desc, buffer.raw, offset)? };
+        let compacted_size_query = unsafe { self.create_compacted_size_query(desc)? };
+
+        self.counters.acceleration_structures.add(1);
+
+        Ok(super::AccelerationStructure {
+            raw,
+            storage: None,
+            compacted_size_query,
+        })
+    }
+
     unsafe fn destroy_acceleration_structure(
         &self,
         acceleration_structure: super::AccelerationStructure,
@@ -2391,13 +2487,39 @@ impl crate::Device for super::Device {
             ray_tracing_functions
                 .acceleration_structure
                 .destroy_acceleration_structure(acceleration_structure.raw, None);
-            self.shared
-                .raw
-                .destroy_buffer(acceleration_structure.buffer, None);
-            self.mem_allocator
-                .lock()
-                .dealloc(&*self.shared, acceleration_structure.block.into_inner());
+            if let Some((buffer, block)) = acceleration_structure.storage {
+                self.shared.raw.destroy_buffer(buffer, None);
+                let block = block.into_inner();
+                self.counters
+                    .acceleration_structure_memory
+                    .sub(block.size() as isize);
+                self.mem_allocator.lock().dealloc(&*self.shared, block);
+            }
+            if let Some(query) = acceleration_structure.compacted_size_query {
+                self.shared.raw.destroy_query_pool(query, None);
+            }
         }
+
+        self.counters.acceleration_structures.sub(1);
+    }
+
+    unsafe fn get_acceleration_structure_compacted_size(
+        &self,
+        acceleration_structure: &super::AccelerationStructure,
+    ) -> Result<wgt::BufferAddress, crate::DeviceError> {
+        let query = acceleration_structure
+            .compacted_size_query
+            .expect("Acceleration structure doesn't allow compaction");
+        let mut size = [0u64];
+        unsafe {
+            self.shared.raw.get_query_pool_results(
+                query,
+                0,
+                &mut size,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )?
+        };
+        Ok(size[0])
     }
 
     fn get_internal_counters(&self) -> wgt::HalCounters {
@@ -669,8 +669,11 @@ impl crate::DynBuffer for Buffer {}
 #[derive(Debug)]
 pub struct AccelerationStructure {
     raw: vk::AccelerationStructureKHR,
-    buffer: vk::Buffer,
-    block: Mutex<gpu_alloc::MemoryBlock<vk::DeviceMemory>>,
+    /// The buffer and memory backing the acceleration structure, unless it
+    /// was created in a buffer owned by the user.
+    storage: Option<(vk::Buffer, Mutex<gpu_alloc::MemoryBlock<vk::DeviceMemory>>)>,
+    /// Query holding the compacted size, if the acceleration structure allows compaction.
+    compacted_size_query: Option<vk::QueryPool>,
 }
 
 impl crate::DynAccelerationStructure for AccelerationStructure {}
@@ -837,6 +840,13 @@ impl crate::DynCommandBuffer for CommandBuffer {}
 #[allow(clippy::large_enum_variant)]
 pub enum ShaderModule {
     Raw(vk::ShaderModule),
+    /// Compiled eagerly, keeping the IR for pipelines that instrument its ray queries, see
+    /// [`crate::ProgrammableStage::ray_query_counters`].
+    RawWithRayQueries {
+        raw: vk::ShaderModule,
+        naga_shader: crate::NagaShader,
+        runtime_checks: bool,
+    },
     Intermediate {
         naga_shader: crate::NagaShader,
         runtime_checks: bool,
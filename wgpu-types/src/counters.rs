@@ -116,12 +116,15 @@ pub struct HalCounters {
     pub shader_modules: InternalCounter,
     pub query_sets: InternalCounter,
     pub fences: InternalCounter,
+    pub acceleration_structures: InternalCounter,
 
     // Resources
     /// Amount of allocated gpu memory attributed to buffers, in bytes.
     pub buffer_memory: InternalCounter,
     /// Amount of allocated gpu memory attributed to textures, in bytes.
     pub texture_memory: InternalCounter,
+    /// Amount of allocated gpu memory attributed to acceleration structures, in bytes.
+    pub acceleration_structure_memory: InternalCounter,
     /// Number of gpu memory allocations.
     pub memory_allocations: InternalCounter,
 }
@@ -130,6 +133,11 @@ pub struct HalCounters {
 #[derive(Clone, Default)]
 pub struct CoreCounters {
     // TODO    #[cfg(features=)]
+    /// Number of references to bottom level acceleration structures held by the top level
+    /// acceleration structures built from them. Each top level acceleration structure holds a
+    /// single reference per distinct bottom level acceleration structure, however many of its
+    /// instances use it.
+    pub tlas_blas_references: InternalCounter,
 }
 
 /// All internal counters, exposed for debugging purposes.
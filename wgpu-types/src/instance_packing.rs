@@ -0,0 +1,431 @@
+//! Packing of top level acceleration structure instances into the raw instance layout.
+//!
+//! This only does bit manipulation and uses nothing but `core`, so it can be copied into or
+//! reused by `no_std` tools that bake instance buffers offline.
+#![deny(clippy::std_instead_of_core, clippy::alloc_instead_of_core)]
+
+use crate::AccelerationStructureInstanceFlags;
+
+/// Size in bytes of a [`PackedInstance`].
+pub const PACKED_INSTANCE_SIZE: usize = core::mem::size_of::<PackedInstance>();
+
+/// Largest custom index of an instance, which has 24 bits.
+pub const MAX_CUSTOM_INDEX: u32 = (1 << 24) - 1;
+
+/// Largest shader binding table record offset of an instance, which has 24 bits.
+pub const MAX_SHADER_BINDING_TABLE_RECORD_OFFSET: u32 = (1 << 24) - 1;
+
+/// Fields of an instance that can't be packed into a [`PackedInstance`].
+///
+/// The mask and flags are bytes and always fit into their 8 bits, but the custom index and the
+/// shader binding table record offset share their 32 bits with them and have to be checked, as
+/// setting bits above their 24 would change the mask or flags instead. Instances also can't force
+/// their geometries to be both opaque and non-opaque.
+///
+/// All problems of an instance are collected, so they can be reported at once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InstanceFieldErrors {
+    /// The custom index, if it is larger than [`MAX_CUSTOM_INDEX`].
+    pub custom_index: Option<u32>,
+    /// The shader binding table record offset, if it is larger than
+    /// [`MAX_SHADER_BINDING_TABLE_RECORD_OFFSET`].
+    pub shader_binding_table_record_offset: Option<u32>,
+    /// Whether both [`AccelerationStructureInstanceFlags::FORCE_OPAQUE`] and
+    /// [`AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE`] are set.
+    pub conflicting_flags: bool,
+}
+
+impl InstanceFieldErrors {
+    /// Check the fields of an instance that don't fit their packed bits by type alone.
+    pub fn check(
+        custom_index: u32,
+        shader_binding_table_record_offset: u32,
+        flags: AccelerationStructureInstanceFlags,
+    ) -> Self {
+        Self {
+            custom_index: (custom_index > MAX_CUSTOM_INDEX).then_some(custom_index),
+            shader_binding_table_record_offset: (shader_binding_table_record_offset
+                > MAX_SHADER_BINDING_TABLE_RECORD_OFFSET)
+                .then_some(shader_binding_table_record_offset),
+            conflicting_flags: flags.contains(
+                AccelerationStructureInstanceFlags::FORCE_OPAQUE
+                    | AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE,
+            ),
+        }
+    }
+
+    /// Whether all fields are valid.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl core::fmt::Display for InstanceFieldErrors {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut separator = "";
+        if let Some(custom_index) = self.custom_index {
+            write!(
+                f,
+                "custom index {custom_index:#x} uses more than 24 bits (max {MAX_CUSTOM_INDEX:#x})"
+            )?;
+            separator = ", ";
+        }
+        if let Some(offset) = self.shader_binding_table_record_offset {
+            write!(
+                f,
+                "{separator}shader binding table record offset {offset:#x} uses more than 24 bits \
+                 (max {MAX_SHADER_BINDING_TABLE_RECORD_OFFSET:#x})"
+            )?;
+            separator = ", ";
+        }
+        if self.conflicting_flags {
+            write!(
+                f,
+                "{separator}both FORCE_OPAQUE and FORCE_NO_OPAQUE flags are set"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A top level acceleration structure instance in the layout of a raw instance buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PackedInstance {
+    /// Affine transform matrix 3x4 (rows x columns, row mayor order).
+    pub transform: [f32; 12],
+    /// Custom index in the lower 24 bits, mask in the upper 8 bits.
+    pub custom_index_and_mask: u32,
+    /// Shader binding table record offset in the lower 24 bits,
+    /// [`AccelerationStructureInstanceFlags`] in the upper 8 bits.
+    pub shader_binding_table_record_offset_and_flags: u32,
+    /// Device address or handle of the referenced bottom level acceleration structure.
+    pub acceleration_structure_reference: u64,
+}
+
+impl PackedInstance {
+    /// Pack an instance.
+    ///
+    /// # Panics
+    /// - If any field is invalid, see [`InstanceFieldErrors`]. The message lists all of them.
+    pub fn new(
+        transform: [f32; 12],
+        custom_index: u32,
+        mask: u8,
+        shader_binding_table_record_offset: u32,
+        flags: AccelerationStructureInstanceFlags,
+        acceleration_structure_reference: u64,
+    ) -> Self {
+        let errors =
+            InstanceFieldErrors::check(custom_index, shader_binding_table_record_offset, flags);
+        assert!(errors.is_empty(), "Instance can't be packed: {errors}");
+        Self {
+            transform,
+            custom_index_and_mask: custom_index | (u32::from(mask) << 24),
+            shader_binding_table_record_offset_and_flags: shader_binding_table_record_offset
+                | (u32::from(flags.bits()) << 24),
+            acceleration_structure_reference,
+        }
+    }
+
+    /// The custom index of the instance.
+    pub fn custom_index(&self) -> u32 {
+        self.custom_index_and_mask & MAX_CUSTOM_INDEX
+    }
+
+    /// The mask of the instance.
+    pub fn mask(&self) -> u8 {
+        (self.custom_index_and_mask >> 24) as u8
+    }
+
+    /// The shader binding table record offset of the instance.
+    pub fn shader_binding_table_record_offset(&self) -> u32 {
+        self.shader_binding_table_record_offset_and_flags & MAX_SHADER_BINDING_TABLE_RECORD_OFFSET
+    }
+
+    /// The flags of the instance, `None` if unknown flags are set.
+    pub fn flags(&self) -> Option<AccelerationStructureInstanceFlags> {
+        AccelerationStructureInstanceFlags::from_bits(
+            (self.shader_binding_table_record_offset_and_flags >> 24) as u8,
+        )
+    }
+
+    /// Set the custom index, leaving the mask alone.
+    ///
+    /// # Panics
+    /// - If `custom_index` is larger than [`MAX_CUSTOM_INDEX`].
+    pub fn set_custom_index(&mut self, custom_index: u32) {
+        let errors = InstanceFieldErrors::check(
+            custom_index,
+            0,
+            AccelerationStructureInstanceFlags::empty(),
+        );
+        assert!(errors.is_empty(), "Instance can't be packed: {errors}");
+        self.custom_index_and_mask =
+            custom_index | (self.custom_index_and_mask & !MAX_CUSTOM_INDEX);
+    }
+
+    /// Set the mask, leaving the custom index alone.
+    pub fn set_mask(&mut self, mask: u8) {
+        self.custom_index_and_mask = self.custom_index() | (u32::from(mask) << 24);
+    }
+
+    /// Set the shader binding table record offset, leaving the flags alone.
+    ///
+    /// # Panics
+    /// - If `offset` is larger than [`MAX_SHADER_BINDING_TABLE_RECORD_OFFSET`].
+    pub fn set_shader_binding_table_record_offset(&mut self, offset: u32) {
+        let errors =
+            InstanceFieldErrors::check(0, offset, AccelerationStructureInstanceFlags::empty());
+        assert!(errors.is_empty(), "Instance can't be packed: {errors}");
+        self.shader_binding_table_record_offset_and_flags = offset
+            | (self.shader_binding_table_record_offset_and_flags
+                & !MAX_SHADER_BINDING_TABLE_RECORD_OFFSET);
+    }
+
+    /// Set the flags, leaving the shader binding table record offset alone.
+    ///
+    /// # Panics
+    /// - If `flags` contain both [`AccelerationStructureInstanceFlags::FORCE_OPAQUE`] and
+    ///   [`AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE`].
+    pub fn set_flags(&mut self, flags: AccelerationStructureInstanceFlags) {
+        let errors = InstanceFieldErrors::check(0, 0, flags);
+        assert!(errors.is_empty(), "Instance can't be packed: {errors}");
+        self.shader_binding_table_record_offset_and_flags =
+            self.shader_binding_table_record_offset() | (u32::from(flags.bits()) << 24);
+    }
+
+    /// The instance as bytes in native endianness, as they are uploaded.
+    pub fn to_ne_bytes(&self) -> [u8; PACKED_INSTANCE_SIZE] {
+        let mut bytes = [0; PACKED_INSTANCE_SIZE];
+        let words = self.transform.iter().map(|f| f.to_bits()).chain([
+            self.custom_index_and_mask,
+            self.shader_binding_table_record_offset_and_flags,
+        ]);
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_ne_bytes());
+        }
+        bytes[56..].copy_from_slice(&self.acceleration_structure_reference.to_ne_bytes());
+        bytes
+    }
+
+    /// Read an instance from bytes in native endianness, the inverse of [`Self::to_ne_bytes`].
+    pub fn from_ne_bytes(bytes: &[u8; PACKED_INSTANCE_SIZE]) -> Self {
+        let word = |i: usize| u32::from_ne_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+        Self {
+            transform: core::array::from_fn(|i| f32::from_bits(word(i))),
+            custom_index_and_mask: word(12),
+            shader_binding_table_record_offset_and_flags: word(13),
+            acceleration_structure_reference: u64::from_ne_bytes(bytes[56..].try_into().unwrap()),
+        }
+    }
+}
+
+/// Convert an affine transform given as its 4 columns (the 3 axes and the translation, as
+/// stored by most math libraries) into the row major 3x4 matrix of an instance.
+pub fn transform_rows_from_columns(columns: [[f32; 3]; 4]) -> [f32; 12] {
+    core::array::from_fn(|i| columns[i % 4][i / 4])
+}
+
+/// Convert the row major 3x4 matrix of an instance back into the 4 columns of the affine
+/// transform, the inverse of [`transform_rows_from_columns`].
+pub fn transform_columns_from_rows(rows: [f32; 12]) -> [[f32; 3]; 4] {
+    core::array::from_fn(|column| core::array::from_fn(|row| rows[row * 4 + column]))
+}
+
+/// Convert a 4x4 matrix given as its 4 columns (like `glam::Mat4::to_cols_array_2d`) into the
+/// row major 3x4 matrix of an instance, dropping the last row.
+///
+/// Acceleration structures can only hold affine transforms, so the last row has to be
+/// `[0, 0, 0, 1]`. Debug builds assert this to catch projective transforms, whose last row
+/// would otherwise be silently ignored.
+pub fn transform_rows_from_matrix(columns: [[f32; 4]; 4]) -> [f32; 12] {
+    debug_assert!(
+        columns.map(|column| column[3]) == [0.0, 0.0, 0.0, 1.0],
+        "Instance transform {columns:?} is not affine, its last row must be [0, 0, 0, 1]"
+    );
+    core::array::from_fn(|i| columns[i % 4][i / 4])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        assert_eq!(PACKED_INSTANCE_SIZE, 64);
+    }
+
+    #[test]
+    fn round_trip() {
+        let columns = [
+            [1.0, 2.0, 3.0],
+            [4.0, 5.0, 6.0],
+            [7.0, 8.0, 9.0],
+            [10.0, 11.0, 12.0],
+        ];
+        let rows = transform_rows_from_columns(columns);
+        assert_eq!(
+            rows,
+            [1.0, 4.0, 7.0, 10.0, 2.0, 5.0, 8.0, 11.0, 3.0, 6.0, 9.0, 12.0]
+        );
+        assert_eq!(transform_columns_from_rows(rows), columns);
+
+        let flags = AccelerationStructureInstanceFlags::FORCE_OPAQUE
+            | AccelerationStructureInstanceFlags::TRIANGLE_FLIP_FACING;
+        let instance = PackedInstance::new(
+            rows,
+            MAX_CUSTOM_INDEX,
+            0xa5,
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET - 1,
+            flags,
+            0x0123_4567_89ab_cdef,
+        );
+        assert_eq!(instance.custom_index(), MAX_CUSTOM_INDEX);
+        assert_eq!(instance.mask(), 0xa5);
+        assert_eq!(
+            instance.shader_binding_table_record_offset(),
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET - 1
+        );
+        assert_eq!(instance.flags(), Some(flags));
+
+        let bytes = instance.to_ne_bytes();
+        assert_eq!(bytes[56..], 0x0123_4567_89ab_cdef_u64.to_ne_bytes());
+        assert_eq!(PackedInstance::from_ne_bytes(&bytes), instance);
+    }
+
+    #[test]
+    fn setters() {
+        let mut instance = PackedInstance::new(
+            [0.0; 12],
+            MAX_CUSTOM_INDEX,
+            0xff,
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET,
+            AccelerationStructureInstanceFlags::FORCE_OPAQUE,
+            0,
+        );
+
+        instance.set_custom_index(0x12345);
+        instance.set_shader_binding_table_record_offset(3);
+        assert_eq!(instance.mask(), 0xff);
+        assert_eq!(
+            instance.flags(),
+            Some(AccelerationStructureInstanceFlags::FORCE_OPAQUE)
+        );
+
+        instance.set_mask(0x0f);
+        instance.set_flags(AccelerationStructureInstanceFlags::TRIANGLE_FLIP_FACING);
+        assert_eq!(instance.custom_index(), 0x12345);
+        assert_eq!(instance.mask(), 0x0f);
+        assert_eq!(instance.shader_binding_table_record_offset(), 3);
+        assert_eq!(
+            instance.flags(),
+            Some(AccelerationStructureInstanceFlags::TRIANGLE_FLIP_FACING)
+        );
+
+        instance.set_custom_index(MAX_CUSTOM_INDEX);
+        instance.set_shader_binding_table_record_offset(MAX_SHADER_BINDING_TABLE_RECORD_OFFSET);
+        assert_eq!(instance.mask(), 0x0f);
+        assert_eq!(
+            instance.flags(),
+            Some(AccelerationStructureInstanceFlags::TRIANGLE_FLIP_FACING)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "custom index 0x1000000 uses more than 24 bits")]
+    fn set_custom_index_out_of_range() {
+        PackedInstance::default().set_custom_index(MAX_CUSTOM_INDEX + 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "shader binding table record offset 0x1000000 uses more than 24 bits"
+    )]
+    fn set_shader_binding_table_record_offset_out_of_range() {
+        PackedInstance::default()
+            .set_shader_binding_table_record_offset(MAX_SHADER_BINDING_TABLE_RECORD_OFFSET + 1);
+    }
+
+    #[test]
+    fn from_matrix() {
+        let columns = [
+            [1.0, 2.0, 3.0, 0.0],
+            [4.0, 5.0, 6.0, 0.0],
+            [7.0, 8.0, 9.0, 0.0],
+            [10.0, 11.0, 12.0, 1.0],
+        ];
+        assert_eq!(
+            transform_rows_from_matrix(columns),
+            [1.0, 4.0, 7.0, 10.0, 2.0, 5.0, 8.0, 11.0, 3.0, 6.0, 9.0, 12.0]
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not affine")]
+    fn from_projective_matrix() {
+        let mut columns = [[0.0; 4]; 4];
+        for (i, column) in columns.iter_mut().enumerate() {
+            column[i] = 1.0;
+        }
+        // A perspective divide by z.
+        columns[2][3] = 1.0;
+        transform_rows_from_matrix(columns);
+    }
+
+    #[test]
+    fn field_errors() {
+        let flags = AccelerationStructureInstanceFlags::all()
+            - AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE;
+        assert!(InstanceFieldErrors::check(
+            MAX_CUSTOM_INDEX,
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET,
+            flags
+        )
+        .is_empty());
+
+        let errors = InstanceFieldErrors::check(
+            MAX_CUSTOM_INDEX + 1,
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET + 1,
+            AccelerationStructureInstanceFlags::all(),
+        );
+        assert_eq!(
+            errors,
+            InstanceFieldErrors {
+                custom_index: Some(0x0100_0000),
+                shader_binding_table_record_offset: Some(0x0100_0000),
+                conflicting_flags: true,
+            }
+        );
+        assert_eq!(
+            errors.to_string(),
+            "custom index 0x1000000 uses more than 24 bits (max 0xffffff), \
+             shader binding table record offset 0x1000000 uses more than 24 bits (max 0xffffff), \
+             both FORCE_OPAQUE and FORCE_NO_OPAQUE flags are set"
+        );
+
+        let errors = InstanceFieldErrors::check(
+            0,
+            MAX_SHADER_BINDING_TABLE_RECORD_OFFSET + 1,
+            AccelerationStructureInstanceFlags::empty(),
+        );
+        assert_eq!(
+            errors.to_string(),
+            "shader binding table record offset 0x1000000 uses more than 24 bits (max 0xffffff)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "uses more than 24 bits")]
+    fn custom_index_out_of_range() {
+        PackedInstance::new(
+            [0.0; 12],
+            MAX_CUSTOM_INDEX + 1,
+            0xff,
+            0,
+            AccelerationStructureInstanceFlags::empty(),
+            0,
+        );
+    }
+}
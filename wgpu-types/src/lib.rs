@@ -18,6 +18,7 @@ use std::{num::NonZeroU32, ops::Range};
 
 pub mod assertions;
 mod counters;
+pub mod instance_packing;
 pub mod math;
 
 pub use counters::*;
@@ -953,8 +954,14 @@ bitflags::bitflags! {
         /// Allows for returning of hit triangles vertex position on acceleration
         /// structure marked with [`AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN`].
         ///
+        /// This is the ray tracing position fetch feature: it is required by
+        /// `acceleration_structure<vertex_return>` and `ray_query<vertex_return>` in shaders,
+        /// whose hit vertex positions are fetched with `getCommittedHitVertexPositions`, and by
+        /// bind group layout entries of
+        /// [`BindingType::AccelerationStructure`] with `vertex_return` set.
+        ///
         /// Supported platforms:
-        /// - Vulkan
+        /// - Vulkan (with VK_KHR_ray_tracing_position_fetch)
         ///
         /// This is a native only feature
         const RAY_HIT_VERTEX_RETURN = 1 << 62;
@@ -5226,6 +5233,12 @@ bitflags::bitflags! {
         const BLAS_INPUT = 1 << 10;
         /// Allows a buffer to be used as input for a top level acceleration structure build
         const TLAS_INPUT = 1 << 11;
+        /// Allows a buffer to hold the memory of bottom level acceleration structures created
+        /// in it, so it can be aliased with other transient resources.
+        const ACCELERATION_STRUCTURE_STORAGE = 1 << 12;
+        /// Allows a buffer to be used as scratch memory for acceleration structure builds,
+        /// instead of the internal scratch buffers.
+        const ACCELERATION_STRUCTURE_SCRATCH = 1 << 13;
     }
 }
 
@@ -6659,7 +6672,8 @@ pub enum BindingType {
         /// Whether this acceleration structure can be used to
         /// create a ray query that has flag vertex return in the shader
         ///
-        /// If enabled requires
+        /// If enabled requires [`Features::RAY_HIT_VERTEX_RETURN`], and the bound
+        /// acceleration structure needs [`AccelerationStructureFlags::ALLOW_RAY_HIT_VERTEX_RETURN`].
         vertex_return: bool,
     },
 }
@@ -7307,6 +7321,10 @@ impl Default for InstanceDescriptor {
 /// Descriptor for all size defining attributes of a single triangle geometry inside a bottom level acceleration structure.
 pub struct BlasTriangleGeometrySizeDescriptor {
     /// Format of a vertex position.
+    ///
+    /// [`VertexFormat::Snorm16x4`] and [`VertexFormat::Unorm16x4`] are dequantized by the driver
+    /// during the build, ignoring the fourth component. They can only be used if the adapter
+    /// supports them, see [`RayTracingBuildProperties::supports_vertex_format`].
     pub vertex_format: VertexFormat,
     /// Number of vertices.
     pub vertex_count: u32,
@@ -7320,6 +7338,14 @@ pub struct BlasTriangleGeometrySizeDescriptor {
     pub flags: AccelerationStructureGeometryFlags,
 }
 
+impl BlasTriangleGeometrySizeDescriptor {
+    /// Number of triangles in the geometry, which is the range of the `primitive_index`
+    /// reported by ray queries hitting it.
+    pub fn primitive_count(&self) -> u32 {
+        self.index_count.unwrap_or(self.vertex_count) / 3
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Descriptor for all size defining attributes of a single procedural geometry inside a bottom level acceleration structure.
@@ -7330,9 +7356,13 @@ pub struct BlasProceduralGeometrySizeDescriptor {
     pub flags: AccelerationStructureGeometryFlags,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Descriptor for all size defining attributes of all geometries inside a bottom level acceleration structure.
+///
+/// All geometries of a bottom level acceleration structure are of the same type, as neither Vulkan
+/// nor DX12 allow mixing triangles and AABBs in one. An object made of both is built as two
+/// bottom level acceleration structures, with an instance of each in the top level one.
 pub enum BlasGeometrySizeDescriptors {
     /// Triangle geometry version.
     Triangles {
@@ -7346,6 +7376,52 @@ pub enum BlasGeometrySizeDescriptors {
     },
 }
 
+impl BlasGeometrySizeDescriptors {
+    /// Index of the first primitive of each geometry when numbering the primitives of all
+    /// geometries consecutively, in geometry order.
+    ///
+    /// Ray queries report the `geometry_index` and the geometry local `primitive_index` of a
+    /// hit. Uploading these offsets alongside a per-primitive table (e.g. of materials) lets a
+    /// shader turn them into a stable id for the whole acceleration structure:
+    ///
+    /// ```wgsl
+    /// let id = geometry_offsets[intersection.geometry_index] + intersection.primitive_index;
+    /// let material = materials[id];
+    /// ```
+    pub fn primitive_offsets(&self) -> Vec<u32> {
+        self.primitive_counts()
+            .into_iter()
+            .scan(0, |offset, count| {
+                let first = *offset;
+                *offset += count;
+                Some(first)
+            })
+            .collect()
+    }
+
+    /// Range of the `primitive_index` that ray queries can report for each geometry, in
+    /// geometry order.
+    ///
+    /// A shader indexing per-primitive data with `primitive_index` (plus the geometry's
+    /// [offset](Self::primitive_offsets)) must size its buffers to cover these ranges; an index
+    /// outside of them can't come from a hit on this acceleration structure. Out of bounds
+    /// storage buffer accesses are only clamped by the runtime checks shaders get by default,
+    /// not in modules created with `create_shader_module_unchecked`.
+    pub fn primitive_index_ranges(&self) -> Vec<Range<u32>> {
+        self.primitive_counts()
+            .into_iter()
+            .map(|count| 0..count)
+            .collect()
+    }
+
+    fn primitive_counts(&self) -> Vec<u32> {
+        match self {
+            Self::Triangles { desc } => desc.iter().map(|d| d.primitive_count()).collect(),
+            Self::AABBs { desc } => desc.iter().map(|d| d.primitive_count).collect(),
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -7356,9 +7432,162 @@ pub enum AccelerationStructureUpdateMode {
     /// If possible, perform an incremental update.
     /// Not advised for major topology changes.
     /// (Useful for e.g. skinning)
+    ///
+    /// Builds of a bottom level acceleration structure created with
+    /// [`AccelerationStructureFlags::ALLOW_UPDATE`] and this mode that don't choose an
+    /// [`AccelerationStructureBuildMode`] update it if its last submitted build has the same
+    /// geometry counts, and build it from scratch otherwise.
     PreferUpdate,
 }
 
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// How a single build of an acceleration structure is performed.
+///
+/// Builds that don't choose a mode perform a full build, unless they build a bottom level
+/// acceleration structure created with [`AccelerationStructureUpdateMode::PreferUpdate`].
+pub enum AccelerationStructureBuildMode {
+    /// Build the acceleration structure from scratch, e.g. after its topology changed.
+    Build,
+    /// Update (refit) the acceleration structure in place, starting from its last build.
+    ///
+    /// Updates are faster than builds, but the acceleration structure traces slower the more
+    /// its geometry moved since the last full build. The acceleration structure must have been
+    /// created with [`AccelerationStructureFlags::ALLOW_UPDATE`] and built before, and only
+    /// the contents of its geometry may change: an update uses the same vertex, index and
+    /// primitive counts and transform buffer presence per geometry, or the same instance count,
+    /// as the last build.
+    Update,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Memory needed to build an acceleration structure, as reported by the device before it is
+/// created.
+///
+/// The scratch sizes are rounded up to [`ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT`], which is
+/// how much of a scratch buffer each build takes up, so the sizes of several builds sharing one
+/// scratch buffer add up to the size it needs.
+pub struct AccelerationStructureBuildSizes {
+    /// Number of bytes the acceleration structure occupies.
+    pub acceleration_structure_size: BufferAddress,
+    /// Number of bytes of scratch memory a full build of the acceleration structure uses.
+    pub build_scratch_size: BufferAddress,
+    /// Number of bytes of scratch memory an update of the acceleration structure uses, zero
+    /// if it wasn't created with [`AccelerationStructureFlags::ALLOW_UPDATE`] on some backends.
+    pub update_scratch_size: BufferAddress,
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// How a copy of a bottom level acceleration structure is performed.
+pub enum AccelerationStructureCopy {
+    /// Duplicate the acceleration structure, e.g. to update the copy while the original is still
+    /// in use. The destination must have been created with the same sizes and flags.
+    Clone,
+    /// Copy the acceleration structure into the smallest memory that holds it. The source must
+    /// have been created with [`AccelerationStructureFlags::ALLOW_COMPACTION`], and its compacted
+    /// size read back since its last build.
+    Compact,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// How the instances of a top level acceleration structure are uploaded when it is built.
+pub enum TlasInstanceUpload {
+    /// Write the instances to a staging buffer and copy them to device local memory before the
+    /// build.
+    Staging,
+    /// Write the instances straight into the instance buffer of the top level acceleration
+    /// structure, skipping the staging buffer and the copy.
+    ///
+    /// Falls back to [`Self::Staging`] when the instance buffer isn't host visible, which it
+    /// only is on adapters sharing their memory with the host, and while an earlier build of
+    /// the acceleration structure may still read the instances.
+    Mapped,
+}
+
+impl TlasInstanceUpload {
+    /// The upload best suited to an adapter of the given type.
+    ///
+    /// Adapters sharing their memory with the host read host visible memory about as fast as
+    /// any other, so they build from [`Self::Mapped`] instances. All others read it over the
+    /// bus, which costs more than a copy to device local memory.
+    pub fn for_device_type(device_type: DeviceType) -> Self {
+        match device_type {
+            DeviceType::IntegratedGpu | DeviceType::Cpu => Self::Mapped,
+            DeviceType::DiscreteGpu | DeviceType::VirtualGpu | DeviceType::Other => Self::Staging,
+        }
+    }
+}
+
+#[test]
+fn tlas_instance_upload_for_device_type() {
+    for (device_type, expected) in [
+        (DeviceType::IntegratedGpu, TlasInstanceUpload::Mapped),
+        (DeviceType::Cpu, TlasInstanceUpload::Mapped),
+        (DeviceType::DiscreteGpu, TlasInstanceUpload::Staging),
+        (DeviceType::VirtualGpu, TlasInstanceUpload::Staging),
+        (DeviceType::Other, TlasInstanceUpload::Staging),
+    ] {
+        assert_eq!(
+            TlasInstanceUpload::for_device_type(device_type),
+            expected,
+            "{device_type:?}"
+        );
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Acceleration structure build properties reported by an adapter, to tune how builds are
+/// batched.
+///
+/// On Vulkan these come from `VkPhysicalDeviceAccelerationStructurePropertiesKHR` and
+/// `VkPhysicalDeviceAccelerationStructureFeaturesKHR`.
+///
+/// There is no preferred build granularity nor hint whether compaction is beneficial, since
+/// Vulkan reports neither. Whether compaction pays off can only be told per acceleration
+/// structure, by comparing its compacted size to its size after the build.
+pub struct RayTracingBuildProperties {
+    /// Maximum number of geometries in a bottom level acceleration structure.
+    pub max_geometry_count: u64,
+    /// Maximum number of instances in a top level acceleration structure.
+    pub max_instance_count: u64,
+    /// Maximum number of triangles or AABBs over all geometries of a bottom level
+    /// acceleration structure.
+    ///
+    /// Splitting a mesh into several bottom level acceleration structures is only required
+    /// above this count.
+    pub max_primitive_count: u64,
+    /// Alignment the adapter requires for the scratch memory of a build.
+    pub min_scratch_offset_alignment: u32,
+    /// Whether the adapter can build acceleration structures on the host, which wgpu does
+    /// not do yet.
+    pub host_builds: bool,
+    /// Whether vertex positions can be in the [`VertexFormat::Snorm16x4`] format.
+    pub snorm16x4_vertices: bool,
+    /// Whether vertex positions can be in the [`VertexFormat::Unorm16x4`] format.
+    pub unorm16x4_vertices: bool,
+}
+
+impl RayTracingBuildProperties {
+    /// Whether bottom level acceleration structures can be built from vertex positions in
+    /// `format`.
+    ///
+    /// Only the normalized 16-bit formats depend on the adapter, other formats aren't
+    /// restricted by it.
+    pub fn supports_vertex_format(&self, format: VertexFormat) -> bool {
+        match format {
+            VertexFormat::Snorm16x4 => self.snorm16x4_vertices,
+            VertexFormat::Unorm16x4 => self.unorm16x4_vertices,
+            _ => true,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -7391,6 +7620,9 @@ pub struct CreateTlasDescriptor<L> {
     /// Label for the top level acceleration structure.
     pub label: L,
     /// Number of instances that can be stored in the acceleration structure.
+    ///
+    /// Each instance takes 64 bytes of an internal instance buffer, so creation fails if
+    /// that buffer would exceed [`Limits::max_buffer_size`].
     pub max_instances: u32,
     /// Flags for the bottom level acceleration structure.
     pub flags: AccelerationStructureFlags,
@@ -7418,9 +7650,9 @@ bitflags::bitflags!(
         const ALLOW_UPDATE = 1 << 0;
         /// Allow the acceleration structure to be compacted in a copy operation
         const ALLOW_COMPACTION = 1 << 1;
-        /// Optimize for fast ray tracing performance
+        /// Optimize for fast ray tracing performance, can't be combined with [`Self::PREFER_FAST_BUILD`]
         const PREFER_FAST_TRACE = 1 << 2;
-        /// Optimize for fast build time
+        /// Optimize for fast build time, can't be combined with [`Self::PREFER_FAST_TRACE`]
         const PREFER_FAST_BUILD = 1 << 3;
         /// Optimize for low memory footprint (scratch and output)
         const LOW_MEMORY = 1 << 4;
@@ -7442,12 +7674,65 @@ bitflags::bitflags!(
 );
 impl_bitflags!(AccelerationStructureGeometryFlags);
 
+bitflags::bitflags!(
+    /// Flags for an instance inside a top level acceleration structure
+    ///
+    /// The opacity flags take precedence over the flags of the geometries in the instance's
+    /// bottom level acceleration structure: with [`Self::FORCE_OPAQUE`] every geometry of the
+    /// instance is opaque, even if it isn't [`AccelerationStructureGeometryFlags::OPAQUE`], and
+    /// with [`Self::FORCE_NO_OPAQUE`] none are. Ray flags given when tracing take precedence
+    /// over both.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct AccelerationStructureInstanceFlags: u8 {
+        /// Don't cull back facing triangles of the instance
+        const TRIANGLE_FACING_CULL_DISABLE = 1 << 0;
+        /// Invert which triangles of the instance are front facing
+        const TRIANGLE_FLIP_FACING = 1 << 1;
+        /// Treat all geometries of the instance as opaque, can't be combined with [`Self::FORCE_NO_OPAQUE`]
+        const FORCE_OPAQUE = 1 << 2;
+        /// Treat all geometries of the instance as non-opaque, can't be combined with [`Self::FORCE_OPAQUE`]
+        const FORCE_NO_OPAQUE = 1 << 3;
+    }
+);
+impl_bitflags!(AccelerationStructureInstanceFlags);
+
 /// Alignment requirement for transform buffers used in acceleration structure builds
 pub const TRANSFORM_BUFFER_ALIGNMENT: BufferAddress = 16;
 
 /// Alignment requirement for instance buffers used in acceleration structure builds
 pub const INSTANCE_BUFFER_ALIGNMENT: BufferAddress = 16;
 
+/// Alignment requirement for the offset of an acceleration structure created in a buffer
+pub const ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT: BufferAddress = 256;
+
+/// Alignment requirement for the offset of a scratch buffer used in acceleration structure builds,
+/// as well as for the scratch memory of each acceleration structure inside of it
+pub const ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT: BufferAddress = 256;
+
+/// Binding of the storage buffer that receives ray query traversal counters.
+///
+/// When set in a stage's compilation options, every ray query in that stage counts the work
+/// it does while traversing. The buffer at this binding must be a read-write storage buffer of
+/// at least [`RAY_QUERY_COUNTERS_SIZE`] bytes that is visible to the stage, and the shader must
+/// not declare anything at this binding itself. It holds these `u32` counters, in order:
+///
+/// 0. calls to `rayQueryProceed`, including the final one that returns `false`,
+/// 1. `rayQueryProceed` calls that stopped at a candidate triangle,
+/// 2. `rayQueryProceed` calls that stopped at a candidate AABB.
+///
+/// The counters are only ever incremented, so clear the buffer to start counting again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RayQueryCountersBinding {
+    /// The bind group index.
+    pub group: u32,
+    /// The binding number within the group.
+    pub binding: u32,
+}
+
+/// Minimum size of the buffer bound at a [`RayQueryCountersBinding`]
+pub const RAY_QUERY_COUNTERS_SIZE: BufferAddress = 12;
+
 pub use send_sync::*;
 
 #[doc(hidden)]
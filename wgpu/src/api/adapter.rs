@@ -200,6 +200,31 @@ impl Adapter {
         DynContext::adapter_features(&*self.context, &self.id, self.data.as_ref())
     }
 
+    /// Returns whether devices on this adapter can build acceleration structures and
+    /// trace rays against them with ray queries.
+    ///
+    /// This is true when [`Adapter::features`] contains both
+    /// [`Features::RAY_TRACING_ACCELERATION_STRUCTURE`] and [`Features::RAY_QUERY`].
+    /// Devices still need to be requested with those features to use them.
+    pub fn supports_ray_tracing(&self) -> bool {
+        self.features()
+            .contains(Features::RAY_TRACING_ACCELERATION_STRUCTURE | Features::RAY_QUERY)
+    }
+
+    /// Acceleration structure build properties of this adapter, to tune how builds are batched.
+    ///
+    /// Returns `None` if the adapter does not support
+    /// [`Features::RAY_TRACING_ACCELERATION_STRUCTURE`].
+    pub fn ray_tracing_build_properties(
+        &self,
+    ) -> Option<crate::ray_tracing::RayTracingBuildProperties> {
+        DynContext::adapter_ray_tracing_build_properties(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+        )
+    }
+
     /// The best limits which can be used to create devices on this adapter.
     pub fn limits(&self) -> Limits {
         DynContext::adapter_limits(&*self.context, &self.id, self.data.as_ref())
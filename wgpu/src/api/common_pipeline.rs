@@ -20,6 +20,18 @@ pub struct PipelineCompilationOptions<'a> {
     /// This is required by the WebGPU spec, but may have overhead which can be avoided
     /// for cross-platform applications
     pub zero_initialize_workgroup_memory: bool,
+    /// If set, every ray query in this stage counts the work it does into the storage buffer
+    /// at this binding.
+    ///
+    /// This is a debugging aid for seeing how expensive traversal of a scene is. It requires
+    /// [`Features::RAY_QUERY`]. The binding must not be used by the shader itself, and is
+    /// added to the layout like any other binding when the layout is derived. See
+    /// [`RayQueryCountersBinding`](crate::ray_tracing::RayQueryCountersBinding) for the
+    /// layout of the buffer.
+    ///
+    /// Only supported on native backends with ray queries; on other backends the counters are
+    /// never written.
+    pub ray_query_counters: Option<crate::ray_tracing::RayQueryCountersBinding>,
 }
 
 impl<'a> Default for PipelineCompilationOptions<'a> {
@@ -33,6 +45,7 @@ impl<'a> Default for PipelineCompilationOptions<'a> {
         Self {
             constants,
             zero_initialize_workgroup_memory: true,
+            ray_query_counters: None,
         }
     }
 }
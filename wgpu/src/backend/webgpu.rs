@@ -1369,6 +1369,15 @@ impl crate::context::Context for ContextWebGpu {
         wgt::DownlevelCapabilities::default()
     }
 
+    fn adapter_ray_tracing_build_properties(
+        &self,
+        _adapter: &Self::AdapterId,
+        _adapter_data: &Self::AdapterData,
+    ) -> Option<wgt::RayTracingBuildProperties> {
+        // Raytracing not implemented for web
+        None
+    }
+
     fn adapter_get_info(
         &self,
         _adapter: &Self::AdapterId,
@@ -3751,6 +3760,58 @@ impl crate::context::Context for ContextWebGpu {
         unimplemented!("Raytracing not implemented for web");
     }
 
+    fn device_create_blas_in_buffer(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _buffer: &Self::BufferId,
+        _buffer_data: &Self::BufferData,
+        _offset: wgt::BufferAddress,
+        _desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        _sizes: wgt::BlasGeometrySizeDescriptors,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_get_blas_size(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        _sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_get_blas_build_sizes(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        _sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_get_tlas_build_sizes(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_get_build_scratch_size(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _blas: &[(Self::BlasId, Option<wgt::AccelerationStructureBuildMode>)],
+        _tlas: &[(Self::TlasId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> wgt::BufferAddress {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
     fn device_create_tlas(
         &self,
         _device: &Self::DeviceId,
@@ -3760,6 +3821,48 @@ impl crate::context::Context for ContextWebGpu {
         unimplemented!("Raytracing not implemented for web");
     }
 
+    fn device_set_scratch_pool_idle_polls(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _polls: u32,
+    ) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_trim_blas_pool(&self, _device: &Self::DeviceId, _device_data: &Self::DeviceData) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_get_blas_compacted_size(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _blas: &Self::BlasId,
+        _blas_data: &Self::BlasData,
+    ) -> wgt::BufferAddress {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn device_create_compacted_blas(
+        &self,
+        _device: &Self::DeviceId,
+        _device_data: &Self::DeviceData,
+        _source: &Self::BlasId,
+        _source_data: &Self::BlasData,
+        _label: crate::Label<'_>,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        _queue: &Self::QueueId,
+        _queue_data: &Self::QueueData,
+    ) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
     fn command_encoder_build_acceleration_structures_unsafe_tlas<'a>(
         &'a self,
         _encoder: &Self::CommandEncoderId,
@@ -3776,6 +3879,40 @@ impl crate::context::Context for ContextWebGpu {
         _encoder_data: &Self::CommandEncoderData,
         _blas: impl Iterator<Item = crate::ray_tracing::ContextBlasBuildEntry<'a, Self>>,
         _tlas: impl Iterator<Item = crate::ray_tracing::ContextTlasPackage<'a, Self>>,
+        _scratch: Option<(&Self::BufferId, wgt::BufferAddress)>,
+    ) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn command_encoder_copy_blas(
+        &self,
+        _encoder: &Self::CommandEncoderId,
+        _encoder_data: &Self::CommandEncoderData,
+        _src: &Self::BlasId,
+        _dst: &Self::BlasId,
+        _copy: wgt::AccelerationStructureCopy,
+    ) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn command_encoder_copy_tlas(
+        &self,
+        _encoder: &Self::CommandEncoderId,
+        _encoder_data: &Self::CommandEncoderData,
+        _src: &Self::TlasId,
+        _dst: &Self::TlasId,
+    ) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        _encoder: &Self::CommandEncoderId,
+        _encoder_data: &Self::CommandEncoderData,
+        _blas: &Self::BlasId,
+        _destination: &Self::BufferId,
+        _destination_data: &Self::BufferData,
+        _destination_offset: wgt::BufferAddress,
     ) {
         unimplemented!("Raytracing not implemented for web");
     }
@@ -3795,6 +3932,24 @@ impl crate::context::Context for ContextWebGpu {
     fn tlas_drop(&self, _tlas: &Self::TlasId, _tlas_data: &Self::TlasData) {
         unimplemented!("Raytracing not implemented for web");
     }
+
+    fn tlas_create_resized(
+        &self,
+        _tlas: &Self::TlasId,
+        _tlas_data: &Self::TlasData,
+        _max_instances: u32,
+    ) -> (Self::TlasId, Self::TlasData) {
+        unimplemented!("Raytracing not implemented for web");
+    }
+
+    fn tlas_resolve_blas_handles(
+        &self,
+        _tlas: &Self::TlasId,
+        _tlas_data: &Self::TlasData,
+        _handles: &[u64],
+    ) -> Vec<Result<Self::BlasId, String>> {
+        unimplemented!("Raytracing not implemented for web");
+    }
 }
 
 pub(crate) type SurfaceOutputDetail = ();
@@ -737,6 +737,17 @@ impl crate::Context for ContextWgpuCore {
         }
     }
 
+    fn adapter_ray_tracing_build_properties(
+        &self,
+        adapter: &Self::AdapterId,
+        _adapter_data: &Self::AdapterData,
+    ) -> Option<wgt::RayTracingBuildProperties> {
+        match self.0.adapter_ray_tracing_build_properties(*adapter) {
+            Ok(properties) => properties,
+            Err(err) => self.handle_error_fatal(err, "Adapter::ray_tracing_build_properties"),
+        }
+    }
+
     fn adapter_get_info(
         &self,
         adapter: &wgc::id::AdapterId,
@@ -1186,6 +1197,7 @@ impl crate::Context for ContextWgpuCore {
                         .vertex
                         .compilation_options
                         .zero_initialize_workgroup_memory,
+                    ray_query_counters: desc.vertex.compilation_options.ray_query_counters,
                 },
                 buffers: Borrowed(&vertex_buffers),
             },
@@ -1200,6 +1212,7 @@ impl crate::Context for ContextWgpuCore {
                     zero_initialize_workgroup_memory: frag
                         .compilation_options
                         .zero_initialize_workgroup_memory,
+                    ray_query_counters: frag.compilation_options.ray_query_counters,
                 },
                 targets: Borrowed(frag.targets),
             }),
@@ -1242,6 +1255,7 @@ impl crate::Context for ContextWgpuCore {
                 zero_initialize_workgroup_memory: desc
                     .compilation_options
                     .zero_initialize_workgroup_memory,
+                ray_query_counters: desc.compilation_options.ray_query_counters,
             },
             cache: desc.cache.map(|c| c.id.into()),
         };
@@ -3379,6 +3393,126 @@ impl crate::Context for ContextWgpuCore {
         )
     }
 
+    fn device_create_blas_in_buffer(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        buffer: &Self::BufferId,
+        _buffer_data: &Self::BufferData,
+        offset: wgt::BufferAddress,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData) {
+        let global = &self.0;
+        let (id, handle, error) = global.device_create_blas_in_buffer(
+            *device,
+            *buffer,
+            offset,
+            &desc.map_label(|l| l.map(Borrowed)),
+            sizes,
+            None,
+        );
+        if let Some(cause) = error {
+            self.handle_error(
+                &device_data.error_sink,
+                cause,
+                desc.label,
+                "Device::create_blas_in_buffer",
+            );
+        }
+        (id, handle, Blas {})
+    }
+
+    fn device_get_blas_size(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress {
+        let global = &self.0;
+        match global.device_get_blas_size(*device, &desc.map_label(|l| l.map(Borrowed)), sizes) {
+            Ok(size) => size,
+            Err(cause) => {
+                self.handle_error(
+                    &device_data.error_sink,
+                    cause,
+                    desc.label,
+                    "Device::get_blas_size",
+                );
+                0
+            }
+        }
+    }
+
+    fn device_get_blas_build_sizes(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        let global = &self.0;
+        match global.device_get_blas_build_sizes(
+            *device,
+            &desc.map_label(|l| l.map(Borrowed)),
+            sizes,
+        ) {
+            Ok(size_info) => size_info,
+            Err(cause) => {
+                self.handle_error(
+                    &device_data.error_sink,
+                    cause,
+                    desc.label,
+                    "Device::get_blas_build_sizes",
+                );
+                Default::default()
+            }
+        }
+    }
+
+    fn device_get_tlas_build_sizes(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        let global = &self.0;
+        match global.device_get_tlas_build_sizes(*device, &desc.map_label(|l| l.map(Borrowed))) {
+            Ok(size_info) => size_info,
+            Err(cause) => {
+                self.handle_error(
+                    &device_data.error_sink,
+                    cause,
+                    desc.label,
+                    "Device::get_tlas_build_sizes",
+                );
+                Default::default()
+            }
+        }
+    }
+
+    fn device_get_build_scratch_size(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        blas: &[(Self::BlasId, Option<wgt::AccelerationStructureBuildMode>)],
+        tlas: &[(Self::TlasId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> wgt::BufferAddress {
+        let global = &self.0;
+        match global.device_get_build_scratch_size(*device, blas, tlas) {
+            Ok(size) => size,
+            Err(cause) => {
+                self.handle_error_nolabel(
+                    &device_data.error_sink,
+                    cause,
+                    "Device::get_build_scratch_size",
+                );
+                0
+            }
+        }
+    }
+
     fn device_create_tlas(
         &self,
         device: &Self::DeviceId,
@@ -3393,7 +3527,7 @@ impl crate::Context for ContextWgpuCore {
                 &device_data.error_sink,
                 cause,
                 desc.label,
-                "Device::create_blas",
+                "Device::create_tlas",
             );
         }
         (
@@ -3404,6 +3538,89 @@ impl crate::Context for ContextWgpuCore {
         )
     }
 
+    fn device_set_scratch_pool_idle_polls(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        polls: u32,
+    ) {
+        let global = &self.0;
+        if let Err(cause) = global.device_set_scratch_pool_idle_polls(*device, polls) {
+            self.handle_error_nolabel(
+                &device_data.error_sink,
+                cause,
+                "Device::set_scratch_pool_idle_polls",
+            );
+        }
+    }
+
+    fn device_trim_blas_pool(&self, device: &Self::DeviceId, device_data: &Self::DeviceData) {
+        let global = &self.0;
+        if let Err(cause) = global.device_trim_blas_pool(*device) {
+            self.handle_error_nolabel(&device_data.error_sink, cause, "Device::trim_blas_pool");
+        }
+    }
+
+    fn device_get_blas_compacted_size(
+        &self,
+        _device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        blas: &Self::BlasId,
+        _blas_data: &Self::BlasData,
+    ) -> wgt::BufferAddress {
+        let global = &self.0;
+        match global.blas_compacted_size(*blas) {
+            Ok(size) => size,
+            Err(cause) => {
+                self.handle_error_nolabel(
+                    &device_data.error_sink,
+                    cause,
+                    "Device::get_blas_compacted_size",
+                );
+                0
+            }
+        }
+    }
+
+    fn device_create_compacted_blas(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        source: &Self::BlasId,
+        _source_data: &Self::BlasData,
+        label: crate::Label<'_>,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData) {
+        let global = &self.0;
+        let (id, handle, error) =
+            global.device_create_compacted_blas(*device, *source, label.map(Borrowed), None);
+        if let Some(cause) = error {
+            self.handle_error(
+                &device_data.error_sink,
+                cause,
+                label,
+                "Device::create_compacted_blas",
+            );
+        }
+        (
+            id,
+            handle,
+            Blas {
+                // error_sink: Arc::clone(&device_data.error_sink),
+            },
+        )
+    }
+
+    fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        queue: &Self::QueueId,
+        _queue_data: &Self::QueueData,
+    ) {
+        let global = &self.0;
+        if let Err(cause) = global.queue_wait_for_acceleration_structure_builds(*queue) {
+            self.handle_error_fatal(cause, "Queue::wait_for_acceleration_structure_builds");
+        }
+    }
+
     fn command_encoder_build_acceleration_structures_unsafe_tlas<'a>(
         &'a self,
         encoder: &Self::CommandEncoderId,
@@ -3427,6 +3644,8 @@ impl crate::Context for ContextWgpuCore {
                             transform_buffer_offset: tg.transform_buffer_offset,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                         }
                     });
@@ -3449,6 +3668,7 @@ impl crate::Context for ContextWgpuCore {
             wgc::ray_tracing::BlasBuildEntry {
                 blas_id: e.blas_id,
                 geometries,
+                mode: e.mode,
             }
         });
 
@@ -3458,6 +3678,7 @@ impl crate::Context for ContextWgpuCore {
                     tlas_id: e.tlas_id,
                     instance_buffer_id: e.instance_buffer_id,
                     instance_count: e.instance_count,
+                    mode: e.mode,
                 }
             },
         );
@@ -3479,6 +3700,7 @@ impl crate::Context for ContextWgpuCore {
         encoder_data: &Self::CommandEncoderData,
         blas: impl Iterator<Item = crate::ray_tracing::ContextBlasBuildEntry<'a, Self>>,
         tlas: impl Iterator<Item = crate::ray_tracing::ContextTlasPackage<'a, Self>>,
+        scratch: Option<(&Self::BufferId, wgt::BufferAddress)>,
     ) {
         let global = &self.0;
 
@@ -3496,6 +3718,8 @@ impl crate::Context for ContextWgpuCore {
                             transform_buffer_offset: tg.transform_buffer_offset,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                         }
                     });
@@ -3518,6 +3742,7 @@ impl crate::Context for ContextWgpuCore {
             wgc::ray_tracing::BlasBuildEntry {
                 blas_id: e.blas_id,
                 geometries,
+                mode: e.mode,
             }
         });
 
@@ -3529,6 +3754,9 @@ impl crate::Context for ContextWgpuCore {
                         transform: instance.transform,
                         custom_index: instance.custom_index,
                         mask: instance.mask,
+                        flags: instance.flags,
+                        shader_binding_table_record_offset: instance
+                            .shader_binding_table_record_offset,
                     })
                 },
             );
@@ -3536,16 +3764,74 @@ impl crate::Context for ContextWgpuCore {
                 tlas_id: e.tlas_id,
                 instances: Box::new(instances),
                 lowest_unmodified: e.lowest_unmodified,
+                instance_upload: e.instance_upload,
+                mode: e.mode,
             }
         });
 
-        if let Err(cause) =
-            global.command_encoder_build_acceleration_structures(*encoder, blas, tlas)
-        {
+        let result = match scratch {
+            None => global.command_encoder_build_acceleration_structures(*encoder, blas, tlas),
+            Some((buffer, offset)) => global
+                .command_encoder_build_acceleration_structures_with_scratch(
+                    *encoder, blas, tlas, *buffer, offset,
+                ),
+        };
+        if let Err(cause) = result {
             self.handle_error_nolabel(
                 &encoder_data.error_sink,
                 cause,
-                "CommandEncoder::build_acceleration_structures_unsafe_tlas",
+                "CommandEncoder::build_acceleration_structures",
+            );
+        }
+    }
+
+    fn command_encoder_copy_blas(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        src: &Self::BlasId,
+        dst: &Self::BlasId,
+        copy: wgt::AccelerationStructureCopy,
+    ) {
+        let global = &self.0;
+        if let Err(cause) = global.command_encoder_copy_blas(*encoder, *src, *dst, copy) {
+            self.handle_error_nolabel(&encoder_data.error_sink, cause, "CommandEncoder::copy_blas");
+        }
+    }
+
+    fn command_encoder_copy_tlas(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        src: &Self::TlasId,
+        dst: &Self::TlasId,
+    ) {
+        let global = &self.0;
+        if let Err(cause) = global.command_encoder_copy_tlas(*encoder, *src, *dst) {
+            self.handle_error_nolabel(&encoder_data.error_sink, cause, "CommandEncoder::copy_tlas");
+        }
+    }
+
+    fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        blas: &Self::BlasId,
+        destination: &Self::BufferId,
+        _destination_data: &Self::BufferData,
+        destination_offset: wgt::BufferAddress,
+    ) {
+        let global = &self.0;
+        if let Err(cause) = global.command_encoder_resolve_blas_compacted_size(
+            *encoder,
+            *blas,
+            *destination,
+            destination_offset,
+        ) {
+            self.handle_error_nolabel(
+                &encoder_data.error_sink,
+                cause,
+                "CommandEncoder::resolve_blas_compacted_size",
             );
         }
     }
@@ -3569,6 +3855,39 @@ impl crate::Context for ContextWgpuCore {
         let global = &self.0;
         global.tlas_drop(*tlas)
     }
+
+    fn tlas_create_resized(
+        &self,
+        tlas: &Self::TlasId,
+        _tlas_data: &Self::TlasData,
+        max_instances: u32,
+    ) -> (Self::TlasId, Self::TlasData) {
+        let global = &self.0;
+        let (id, error) = global.tlas_create_resized(*tlas, max_instances, None);
+        if let Some(cause) = error {
+            self.handle_error_fatal(cause, "Tlas::create_resized");
+        }
+        (
+            id,
+            Tlas {
+                // error_sink: Arc::clone(&device_data.error_sink),
+            },
+        )
+    }
+
+    fn tlas_resolve_blas_handles(
+        &self,
+        tlas: &Self::TlasId,
+        _tlas_data: &Self::TlasData,
+        handles: &[u64],
+    ) -> Vec<Result<Self::BlasId, String>> {
+        let global = &self.0;
+        global
+            .blas_ids_from_handles(tlas.backend(), handles)
+            .into_iter()
+            .map(|blas| blas.map_err(|err| err.to_string()))
+            .collect()
+    }
 }
 
 impl<T> From<ObjectId> for wgc::id::Id<T>
@@ -140,6 +140,11 @@ pub trait Context: Debug + WasmNotSendSync + Sized {
         adapter: &Self::AdapterId,
         adapter_data: &Self::AdapterData,
     ) -> DownlevelCapabilities;
+    fn adapter_ray_tracing_build_properties(
+        &self,
+        adapter: &Self::AdapterId,
+        adapter_data: &Self::AdapterData,
+    ) -> Option<wgt::RayTracingBuildProperties>;
     fn adapter_get_info(
         &self,
         adapter: &Self::AdapterId,
@@ -1054,12 +1059,77 @@ pub trait Context: Debug + WasmNotSendSync + Sized {
         desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
         sizes: wgt::BlasGeometrySizeDescriptors,
     ) -> (Self::BlasId, Option<u64>, Self::BlasData);
+    #[allow(clippy::too_many_arguments)]
+    fn device_create_blas_in_buffer(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        buffer: &Self::BufferId,
+        buffer_data: &Self::BufferData,
+        offset: wgt::BufferAddress,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData);
+    fn device_get_blas_size(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress;
+    fn device_get_blas_build_sizes(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::AccelerationStructureBuildSizes;
+    fn device_get_tlas_build_sizes(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
+    ) -> wgt::AccelerationStructureBuildSizes;
+    fn device_get_build_scratch_size(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        blas: &[(Self::BlasId, Option<wgt::AccelerationStructureBuildMode>)],
+        tlas: &[(Self::TlasId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> wgt::BufferAddress;
     fn device_create_tlas(
         &self,
         device: &Self::DeviceId,
         device_data: &Self::DeviceData,
         desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
     ) -> (Self::TlasId, Self::TlasData);
+    fn device_set_scratch_pool_idle_polls(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        polls: u32,
+    );
+    fn device_trim_blas_pool(&self, device: &Self::DeviceId, device_data: &Self::DeviceData);
+    fn device_get_blas_compacted_size(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        blas: &Self::BlasId,
+        blas_data: &Self::BlasData,
+    ) -> wgt::BufferAddress;
+    fn device_create_compacted_blas(
+        &self,
+        device: &Self::DeviceId,
+        device_data: &Self::DeviceData,
+        source: &Self::BlasId,
+        source_data: &Self::BlasData,
+        label: crate::Label<'_>,
+    ) -> (Self::BlasId, Option<u64>, Self::BlasData);
+    fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        queue: &Self::QueueId,
+        queue_data: &Self::QueueData,
+    );
     fn command_encoder_build_acceleration_structures_unsafe_tlas<'a>(
         &'a self,
         encoder: &Self::CommandEncoderId,
@@ -1073,11 +1143,48 @@ pub trait Context: Debug + WasmNotSendSync + Sized {
         encoder_data: &Self::CommandEncoderData,
         blas: impl Iterator<Item = crate::ray_tracing::ContextBlasBuildEntry<'a, Self>>,
         tlas: impl Iterator<Item = crate::ray_tracing::ContextTlasPackage<'a, Self>>,
+        scratch: Option<(&Self::BufferId, BufferAddress)>,
+    );
+    fn command_encoder_copy_blas(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        src: &Self::BlasId,
+        dst: &Self::BlasId,
+        copy: wgt::AccelerationStructureCopy,
+    );
+    fn command_encoder_copy_tlas(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        src: &Self::TlasId,
+        dst: &Self::TlasId,
+    );
+    fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        encoder_data: &Self::CommandEncoderData,
+        blas: &Self::BlasId,
+        destination: &Self::BufferId,
+        destination_data: &Self::BufferData,
+        destination_offset: BufferAddress,
     );
     fn blas_destroy(&self, blas: &Self::BlasId, blas_data: &Self::BlasData);
     fn blas_drop(&self, blas: &Self::BlasId, blas_data: &Self::BlasData);
     fn tlas_destroy(&self, tlas: &Self::TlasId, tlas_data: &Self::TlasData);
     fn tlas_drop(&self, tlas: &Self::TlasId, tlas_data: &Self::TlasData);
+    fn tlas_create_resized(
+        &self,
+        tlas: &Self::TlasId,
+        tlas_data: &Self::TlasData,
+        max_instances: u32,
+    ) -> (Self::TlasId, Self::TlasData);
+    fn tlas_resolve_blas_handles(
+        &self,
+        tlas: &Self::TlasId,
+        tlas_data: &Self::TlasData,
+        handles: &[u64],
+    ) -> Vec<Result<Self::BlasId, String>>;
 }
 
 /// Object id.
@@ -1236,6 +1343,11 @@ pub(crate) trait DynContext: Debug + WasmNotSendSync {
         adapter: &ObjectId,
         adapter_data: &crate::Data,
     ) -> DownlevelCapabilities;
+    fn adapter_ray_tracing_build_properties(
+        &self,
+        adapter: &ObjectId,
+        adapter_data: &crate::Data,
+    ) -> Option<wgt::RayTracingBuildProperties>;
     fn adapter_get_info(&self, adapter: &ObjectId, adapter_data: &crate::Data) -> AdapterInfo;
     fn adapter_get_texture_format_features(
         &self,
@@ -2073,12 +2185,77 @@ pub(crate) trait DynContext: Debug + WasmNotSendSync {
         desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
         sizes: wgt::BlasGeometrySizeDescriptors,
     ) -> (ObjectId, Option<u64>, Box<crate::Data>);
+    #[allow(clippy::too_many_arguments)]
+    fn device_create_blas_in_buffer(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        buffer: &ObjectId,
+        buffer_data: &crate::Data,
+        offset: wgt::BufferAddress,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+    ) -> (ObjectId, Option<u64>, Box<crate::Data>);
+    fn device_get_blas_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress;
+    fn device_get_blas_build_sizes(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::AccelerationStructureBuildSizes;
+    fn device_get_tlas_build_sizes(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
+    ) -> wgt::AccelerationStructureBuildSizes;
+    fn device_get_build_scratch_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        blas: &[(ObjectId, Option<wgt::AccelerationStructureBuildMode>)],
+        tlas: &[(ObjectId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> wgt::BufferAddress;
     fn device_create_tlas(
         &self,
         device: &ObjectId,
         device_data: &crate::Data,
         desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
     ) -> (ObjectId, Box<crate::Data>);
+    fn device_set_scratch_pool_idle_polls(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        polls: u32,
+    );
+    fn device_trim_blas_pool(&self, device: &ObjectId, device_data: &crate::Data);
+    fn device_get_blas_compacted_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        blas: &ObjectId,
+        blas_data: &crate::Data,
+    ) -> wgt::BufferAddress;
+    fn device_create_compacted_blas(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        source: &ObjectId,
+        source_data: &crate::Data,
+        label: crate::Label<'_>,
+    ) -> (ObjectId, Option<u64>, Box<crate::Data>);
+    fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        queue: &ObjectId,
+        queue_data: &crate::Data,
+    );
     fn command_encoder_build_acceleration_structures_unsafe_tlas(
         &self,
         encoder: &ObjectId,
@@ -2092,11 +2269,48 @@ pub(crate) trait DynContext: Debug + WasmNotSendSync {
         encoder_data: &crate::Data,
         blas: &mut dyn Iterator<Item = crate::ray_tracing::DynContextBlasBuildEntry<'_>>,
         tlas: &mut dyn Iterator<Item = crate::ray_tracing::DynContextTlasPackage<'_>>,
+        scratch: Option<(&ObjectId, BufferAddress)>,
+    );
+    fn command_encoder_copy_blas(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        src: &ObjectId,
+        dst: &ObjectId,
+        copy: wgt::AccelerationStructureCopy,
+    );
+    fn command_encoder_copy_tlas(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        src: &ObjectId,
+        dst: &ObjectId,
+    );
+    fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        blas: &ObjectId,
+        destination: &ObjectId,
+        destination_data: &crate::Data,
+        destination_offset: BufferAddress,
     );
     fn blas_destroy(&self, blas: &ObjectId, blas_data: &crate::Data);
     fn blas_drop(&self, blas: &ObjectId, blas_data: &crate::Data);
     fn tlas_destroy(&self, tlas: &ObjectId, tlas_data: &crate::Data);
     fn tlas_drop(&self, tlas: &ObjectId, tlas_data: &crate::Data);
+    fn tlas_create_resized(
+        &self,
+        tlas: &ObjectId,
+        tlas_data: &crate::Data,
+        max_instances: u32,
+    ) -> (ObjectId, Box<crate::Data>);
+    fn tlas_resolve_blas_handles(
+        &self,
+        tlas: &ObjectId,
+        tlas_data: &crate::Data,
+        handles: &[u64],
+    ) -> Vec<Result<ObjectId, String>>;
     fn render_pass_end(&self, pass: &mut ObjectId, pass_data: &mut crate::Data);
 }
 
@@ -2190,6 +2404,16 @@ where
         Context::adapter_downlevel_capabilities(self, &adapter, adapter_data)
     }
 
+    fn adapter_ray_tracing_build_properties(
+        &self,
+        adapter: &ObjectId,
+        adapter_data: &crate::Data,
+    ) -> Option<wgt::RayTracingBuildProperties> {
+        let adapter = <T::AdapterId>::from(*adapter);
+        let adapter_data = downcast_ref(adapter_data);
+        Context::adapter_ray_tracing_build_properties(self, &adapter, adapter_data)
+    }
+
     fn adapter_get_info(&self, adapter: &ObjectId, adapter_data: &crate::Data) -> AdapterInfo {
         let adapter = <T::AdapterId>::from(*adapter);
         let adapter_data = downcast_ref(adapter_data);
@@ -4151,6 +4375,88 @@ where
         (blas.into(), handle, Box::new(data) as _)
     }
 
+    fn device_create_blas_in_buffer(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        buffer: &ObjectId,
+        buffer_data: &crate::Data,
+        offset: wgt::BufferAddress,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: wgt::BlasGeometrySizeDescriptors,
+    ) -> (ObjectId, Option<u64>, Box<crate::Data>) {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        let buffer = <T::BufferId>::from(*buffer);
+        let buffer_data = downcast_ref(buffer_data);
+        let (blas, handle, data) = Context::device_create_blas_in_buffer(
+            self,
+            &device,
+            device_data,
+            &buffer,
+            buffer_data,
+            offset,
+            desc,
+            sizes,
+        );
+        (blas.into(), handle, Box::new(data) as _)
+    }
+
+    fn device_get_blas_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        Context::device_get_blas_size(self, &device, device_data, desc, sizes)
+    }
+
+    fn device_get_blas_build_sizes(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateBlasDescriptor<'_>,
+        sizes: &wgt::BlasGeometrySizeDescriptors,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        Context::device_get_blas_build_sizes(self, &device, device_data, desc, sizes)
+    }
+
+    fn device_get_tlas_build_sizes(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        desc: &crate::ray_tracing::CreateTlasDescriptor<'_>,
+    ) -> wgt::AccelerationStructureBuildSizes {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        Context::device_get_tlas_build_sizes(self, &device, device_data, desc)
+    }
+
+    fn device_get_build_scratch_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        blas: &[(ObjectId, Option<wgt::AccelerationStructureBuildMode>)],
+        tlas: &[(ObjectId, Option<wgt::AccelerationStructureBuildMode>)],
+    ) -> wgt::BufferAddress {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        let blas = blas
+            .iter()
+            .map(|&(blas, mode)| (<T::BlasId>::from(blas), mode))
+            .collect::<Vec<_>>();
+        let tlas = tlas
+            .iter()
+            .map(|&(tlas, mode)| (<T::TlasId>::from(tlas), mode))
+            .collect::<Vec<_>>();
+        Context::device_get_build_scratch_size(self, &device, device_data, &blas, &tlas)
+    }
+
     fn device_create_tlas(
         &self,
         device: &ObjectId,
@@ -4163,6 +4469,70 @@ where
         (tlas.into(), Box::new(data) as _)
     }
 
+    fn device_set_scratch_pool_idle_polls(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        polls: u32,
+    ) {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        Context::device_set_scratch_pool_idle_polls(self, &device, device_data, polls)
+    }
+
+    fn device_trim_blas_pool(&self, device: &ObjectId, device_data: &crate::Data) {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        Context::device_trim_blas_pool(self, &device, device_data)
+    }
+
+    fn device_get_blas_compacted_size(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        blas: &ObjectId,
+        blas_data: &crate::Data,
+    ) -> wgt::BufferAddress {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        let blas = <T::BlasId>::from(*blas);
+        let blas_data = downcast_ref(blas_data);
+        Context::device_get_blas_compacted_size(self, &device, device_data, &blas, blas_data)
+    }
+
+    fn device_create_compacted_blas(
+        &self,
+        device: &ObjectId,
+        device_data: &crate::Data,
+        source: &ObjectId,
+        source_data: &crate::Data,
+        label: crate::Label<'_>,
+    ) -> (ObjectId, Option<u64>, Box<crate::Data>) {
+        let device = <T::DeviceId>::from(*device);
+        let device_data = downcast_ref(device_data);
+        let source = <T::BlasId>::from(*source);
+        let source_data = downcast_ref(source_data);
+        let (blas, handle, data) = Context::device_create_compacted_blas(
+            self,
+            &device,
+            device_data,
+            &source,
+            source_data,
+            label,
+        );
+        (blas.into(), handle, Box::new(data) as _)
+    }
+
+    fn queue_wait_for_acceleration_structure_builds(
+        &self,
+        queue: &ObjectId,
+        queue_data: &crate::Data,
+    ) {
+        let queue = <T::QueueId>::from(*queue);
+        let queue_data = downcast_ref(queue_data);
+        Context::queue_wait_for_acceleration_structure_builds(self, &queue, queue_data)
+    }
+
     fn command_encoder_build_acceleration_structures_unsafe_tlas(
         &self,
         encoder: &ObjectId,
@@ -4187,6 +4557,8 @@ where
                             transform_buffer_offset: tg.transform_buffer_offset,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                         }
                     });
@@ -4210,6 +4582,7 @@ where
                 blas_id: <T::BlasId>::from(e.blas_id),
                 // blas_data: downcast_ref(e.blas_data),
                 geometries,
+                mode: e.mode,
             }
         });
 
@@ -4222,6 +4595,7 @@ where
                     instance_buffer_id: <T::BufferId>::from(e.instance_buffer_id),
                     // instance_buffer_data: downcast_ref(e.instance_buffer_data),
                     instance_count: e.instance_count,
+                    mode: e.mode,
                 }
             });
 
@@ -4240,6 +4614,7 @@ where
         encoder_data: &crate::Data,
         blas: &mut dyn Iterator<Item = crate::ray_tracing::DynContextBlasBuildEntry<'_>>,
         tlas: &mut dyn Iterator<Item = crate::ray_tracing::DynContextTlasPackage<'_>>,
+        scratch: Option<(&ObjectId, BufferAddress)>,
     ) {
         let encoder = <T::CommandEncoderId>::from(*encoder);
         let encoder_data = downcast_ref(encoder_data);
@@ -4258,6 +4633,8 @@ where
                             transform_buffer_offset: tg.transform_buffer_offset,
                             first_vertex: tg.first_vertex,
                             vertex_stride: tg.vertex_stride,
+                            vertex_offset: tg.vertex_offset,
+                            vertex_buffer_offset: tg.vertex_buffer_offset,
                             index_buffer_offset: tg.index_buffer_offset,
                         }
                     });
@@ -4281,6 +4658,7 @@ where
                 blas_id: <T::BlasId>::from(e.blas_id),
                 // blas_data: downcast_ref(e.blas_data),
                 geometries,
+                mode: e.mode,
             }
         });
 
@@ -4294,6 +4672,9 @@ where
                             transform: instance.transform,
                             custom_index: instance.custom_index,
                             mask: instance.mask,
+                            flags: instance.flags,
+                            shader_binding_table_record_offset: instance
+                                .shader_binding_table_record_offset,
                         })
                     },
                 );
@@ -4301,15 +4682,74 @@ where
                     tlas_id: <T::TlasId>::from(e.tlas_id),
                     instances: Box::new(instances),
                     lowest_unmodified: e.lowest_unmodified,
+                    instance_upload: e.instance_upload,
+                    mode: e.mode,
                 }
             });
 
+        let scratch = scratch.map(|(buffer, offset)| (<T::BufferId>::from(*buffer), offset));
+
         Context::command_encoder_build_acceleration_structures(
             self,
             &encoder,
             encoder_data,
             blas,
             tlas,
+            scratch.as_ref().map(|(buffer, offset)| (buffer, *offset)),
+        )
+    }
+
+    fn command_encoder_copy_blas(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        src: &ObjectId,
+        dst: &ObjectId,
+        copy: wgt::AccelerationStructureCopy,
+    ) {
+        let encoder = <T::CommandEncoderId>::from(*encoder);
+        let encoder_data = downcast_ref(encoder_data);
+        let src = <T::BlasId>::from(*src);
+        let dst = <T::BlasId>::from(*dst);
+        Context::command_encoder_copy_blas(self, &encoder, encoder_data, &src, &dst, copy)
+    }
+
+    fn command_encoder_copy_tlas(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        src: &ObjectId,
+        dst: &ObjectId,
+    ) {
+        let encoder = <T::CommandEncoderId>::from(*encoder);
+        let encoder_data = downcast_ref(encoder_data);
+        let src = <T::TlasId>::from(*src);
+        let dst = <T::TlasId>::from(*dst);
+        Context::command_encoder_copy_tlas(self, &encoder, encoder_data, &src, &dst)
+    }
+
+    fn command_encoder_resolve_blas_compacted_size(
+        &self,
+        encoder: &ObjectId,
+        encoder_data: &crate::Data,
+        blas: &ObjectId,
+        destination: &ObjectId,
+        destination_data: &crate::Data,
+        destination_offset: BufferAddress,
+    ) {
+        let encoder = <T::CommandEncoderId>::from(*encoder);
+        let encoder_data = downcast_ref(encoder_data);
+        let blas = <T::BlasId>::from(*blas);
+        let destination = <T::BufferId>::from(*destination);
+        let destination_data = downcast_ref(destination_data);
+        Context::command_encoder_resolve_blas_compacted_size(
+            self,
+            &encoder,
+            encoder_data,
+            &blas,
+            &destination,
+            destination_data,
+            destination_offset,
         )
     }
 
@@ -4336,6 +4776,32 @@ where
         let tlas_data = downcast_ref(tlas_data);
         Context::tlas_drop(self, &tlas, tlas_data)
     }
+
+    fn tlas_create_resized(
+        &self,
+        tlas: &ObjectId,
+        tlas_data: &crate::Data,
+        max_instances: u32,
+    ) -> (ObjectId, Box<crate::Data>) {
+        let tlas = <T::TlasId>::from(*tlas);
+        let tlas_data = downcast_ref(tlas_data);
+        let (tlas, data) = Context::tlas_create_resized(self, &tlas, tlas_data, max_instances);
+        (tlas.into(), Box::new(data) as _)
+    }
+
+    fn tlas_resolve_blas_handles(
+        &self,
+        tlas: &ObjectId,
+        tlas_data: &crate::Data,
+        handles: &[u64],
+    ) -> Vec<Result<ObjectId, String>> {
+        let tlas = <T::TlasId>::from(*tlas);
+        let tlas_data = downcast_ref(tlas_data);
+        Context::tlas_resolve_blas_handles(self, &tlas, tlas_data, handles)
+            .into_iter()
+            .map(|blas| blas.map(Into::into))
+            .collect()
+    }
 }
 
 pub trait QueueWriteBuffer: WasmNotSendSync + Debug {
@@ -1,9 +1,10 @@
-use std::{fmt::Debug, ops::Range, sync::Arc, thread};
+use parking_lot::Mutex;
+use std::{borrow::Cow, fmt::Debug, ops::Range, sync::Arc, thread};
 use wgt::WasmNotSendSync;
 
 use crate::{
     context::{Context, DynContext, ObjectId},
-    BindingResource, Buffer, CommandEncoder, Data, Device, Label, C,
+    BindingResource, Buffer, CommandEncoder, Data, Device, Label, Queue, C,
 };
 
 /// Descriptor for the size defining attributes of a triangle geometry, for a bottom level acceleration structure.
@@ -26,10 +27,40 @@ static_assertions::assert_impl_all!(AccelerationStructureFlags: Send, Sync);
 pub type AccelerationStructureGeometryFlags = wgt::AccelerationStructureGeometryFlags;
 static_assertions::assert_impl_all!(AccelerationStructureGeometryFlags: Send, Sync);
 
+/// Flags for an instance inside a top level acceleration structure.
+pub type AccelerationStructureInstanceFlags = wgt::AccelerationStructureInstanceFlags;
+static_assertions::assert_impl_all!(AccelerationStructureInstanceFlags: Send, Sync);
+
 /// Update mode for acceleration structure builds.
 pub type AccelerationStructureUpdateMode = wgt::AccelerationStructureUpdateMode;
 static_assertions::assert_impl_all!(AccelerationStructureUpdateMode: Send, Sync);
 
+/// How a single build of an acceleration structure is performed.
+pub type AccelerationStructureBuildMode = wgt::AccelerationStructureBuildMode;
+static_assertions::assert_impl_all!(AccelerationStructureBuildMode: Send, Sync);
+
+/// Memory needed to build an acceleration structure.
+pub type AccelerationStructureBuildSizes = wgt::AccelerationStructureBuildSizes;
+static_assertions::assert_impl_all!(AccelerationStructureBuildSizes: Send, Sync);
+
+/// How an acceleration structure is copied into another.
+pub type AccelerationStructureCopy = wgt::AccelerationStructureCopy;
+static_assertions::assert_impl_all!(AccelerationStructureCopy: Send, Sync);
+
+/// How the instances of a [`TlasPackage`] are uploaded when it is built.
+pub type TlasInstanceUpload = wgt::TlasInstanceUpload;
+static_assertions::assert_impl_all!(TlasInstanceUpload: Send, Sync);
+
+/// Acceleration structure build properties of an [`Adapter`](crate::Adapter).
+pub type RayTracingBuildProperties = wgt::RayTracingBuildProperties;
+static_assertions::assert_impl_all!(RayTracingBuildProperties: Send, Sync);
+
+/// Binding of the storage buffer receiving ray query traversal counters.
+///
+/// See [`PipelineCompilationOptions::ray_query_counters`](crate::PipelineCompilationOptions::ray_query_counters).
+pub type RayQueryCountersBinding = wgt::RayQueryCountersBinding;
+pub use wgt::RAY_QUERY_COUNTERS_SIZE;
+
 /// Descriptor to create bottom level acceleration structures.
 pub type CreateBlasDescriptor<'a> = wgt::CreateBlasDescriptor<Label<'a>>;
 static_assertions::assert_impl_all!(CreateBlasDescriptor<'_>: Send, Sync);
@@ -38,36 +69,60 @@ static_assertions::assert_impl_all!(CreateBlasDescriptor<'_>: Send, Sync);
 pub type CreateTlasDescriptor<'a> = wgt::CreateTlasDescriptor<Label<'a>>;
 static_assertions::assert_impl_all!(CreateTlasDescriptor<'_>: Send, Sync);
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 /// Definition for a triangle geometry.
 /// The size must match the rest of the structures fields, otherwise the build will fail.
 /// (e.g. if a index count is present in the size, the index buffer must be present as well.)
 pub struct BlasTriangleGeometry<'a> {
     /// Sub descriptor for the size defining attributes of a triangle geometry.
+    ///
+    /// The vertex and index counts may be smaller than the ones the BLAS was created with, so a
+    /// BLAS can be created for the largest count a geometry will reach and rebuilt with however
+    /// many primitives it currently has.
     pub size: &'a BlasTriangleGeometrySizeDescriptor,
     /// Vertex buffer.
     pub vertex_buffer: &'a Buffer,
     /// Offset into the vertex buffer as a factor of the vertex stride.
     pub first_vertex: u32,
-    /// Vertex stride.
-    pub vertex_stride: wgt::BufferAddress,
+    /// Vertex stride in bytes, or `None` for tightly packed vertices whose stride is the size of
+    /// [`size.vertex_format`](wgt::BlasTriangleGeometrySizeDescriptor::vertex_format).
+    ///
+    /// The stride needs to be at least the size of the vertex format and a multiple of the size
+    /// of its components, and fit into 32 bits.
+    pub vertex_stride: Option<wgt::BufferAddress>,
+    /// Offset in bytes of the position within each vertex, for interleaved vertices whose
+    /// position isn't their first attribute.
+    ///
+    /// The position needs to fit into the vertex stride after the offset, and the offset needs
+    /// to be a multiple of the size of the components of the vertex format.
+    pub vertex_offset: wgt::BufferAddress,
+    /// Offset in bytes of the first vertex in the vertex buffer, before [`Self::first_vertex`]
+    /// is applied, e.g. for geometries packed into a shared buffer at arbitrary byte offsets.
+    ///
+    /// The offset needs to be a multiple of the size of the components of the vertex format.
+    pub vertex_buffer_offset: wgt::BufferAddress,
     /// Index buffer (optional).
     pub index_buffer: Option<&'a Buffer>,
     /// Index buffer offset in bytes (optional, required if index buffer is present).
     pub index_buffer_offset: Option<wgt::BufferAddress>,
     /// Transform buffer containing 3x4 (rows x columns, row mayor) affine transform matrices `[f32; 12]` (optional).
+    ///
+    /// The transform is applied to the vertices when the BLAS is built, so it isn't part of the
+    /// `object_to_world` transform of ray query intersections, which is only that of the instance.
     pub transform_buffer: Option<&'a Buffer>,
     /// Transform buffer offset in bytes (optional, required if transform buffer is present).
     pub transform_buffer_offset: Option<wgt::BufferAddress>,
 }
 static_assertions::assert_impl_all!(BlasTriangleGeometry<'_>: WasmNotSendSync);
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 /// Definition for a procedural geometry.
 /// The size must match the rest of the structures fields, otherwise the build will fail.
 /// (e.g. if a index count is present in the size, the index buffer must be present as well.)
 pub struct BlasProceduralGeometry<'a> {
     /// Sub descriptor for the size defining attributes of a procedural geometry.
+    ///
+    /// The primitive count may be smaller than the one the BLAS was created with.
     pub size: &'a BlasProceduralGeometrySizeDescriptor,
     /// Bounding box buffer.
     pub bounding_box_buffer: &'a Buffer,
@@ -79,11 +134,18 @@ pub struct BlasProceduralGeometry<'a> {
 static_assertions::assert_impl_all!(BlasProceduralGeometry<'_>: WasmNotSendSync);
 
 /// Geometries for a bottom level acceleration structure.
+///
+/// The geometries can either be owned (`vec![...].into()`), or borrowed from a slice the
+/// caller keeps around between builds (`slice.into()`), which avoids allocating a new
+/// `Vec` for every build.
+///
+/// The geometries are all triangles or all AABBs, matching the [`BlasGeometrySizeDescriptors`]
+/// the [`Blas`] was created with, see there for objects that need both.
 pub enum BlasGeometries<'a> {
     /// Triangle geometry variant.
-    TriangleGeometries(Vec<BlasTriangleGeometry<'a>>),
+    TriangleGeometries(Cow<'a, [BlasTriangleGeometry<'a>]>),
     /// Procedural geometry variant.
-    ProceduralGeometries(Vec<BlasProceduralGeometry<'a>>),
+    ProceduralGeometries(Cow<'a, [BlasProceduralGeometry<'a>]>),
 }
 static_assertions::assert_impl_all!(BlasGeometries<'_>: WasmNotSendSync);
 
@@ -93,6 +155,9 @@ pub struct BlasBuildEntry<'a> {
     pub blas: &'a Blas,
     /// Geometries.
     pub geometry: BlasGeometries<'a>,
+    /// How to build the acceleration structure. If `None`, an update if it was created with
+    /// [`AccelerationStructureUpdateMode::PreferUpdate`] and can be updated, else a full build.
+    pub mode: Option<AccelerationStructureBuildMode>,
 }
 static_assertions::assert_impl_all!(BlasBuildEntry<'_>: WasmNotSendSync);
 
@@ -104,6 +169,7 @@ pub struct Blas {
     pub(crate) id: ObjectId,
     pub(crate) data: Box<Data>,
     pub(crate) handle: Option<u64>,
+    pub(crate) sizes: BlasGeometrySizeDescriptors,
 }
 static_assertions::assert_impl_all!(Blas: WasmNotSendSync);
 
@@ -112,7 +178,15 @@ impl Blas {
     pub fn handle(&self) -> Option<u64> {
         self.handle
     }
+    /// Size descriptors the acceleration structure was created with,
+    /// which every build of it has to stay within.
+    pub fn geometry_sizes(&self) -> &BlasGeometrySizeDescriptors {
+        &self.sizes
+    }
     /// Destroy the associated native resources as soon as possible.
+    ///
+    /// The memory is released once no pending submission uses it anymore. Building
+    /// a destroyed `Blas`, or referencing it from a [`TlasInstance`], is an error.
     pub fn destroy(&self) {
         DynContext::blas_destroy(&*self.context, &self.id, self.data.as_ref());
     }
@@ -129,6 +203,12 @@ impl Drop for Blas {
 #[derive(Debug)]
 /// Top level acceleration structure.
 /// Used to represent a collection of bottom level acceleration structure instances for ray tracing.
+///
+/// The storage of a top level acceleration structure is allocated once, for the `max_instances`
+/// it was created with, and never reallocated: builds and updates write into it in place, so bind
+/// groups referencing it stay valid across them. To hold more instances, create a new [`Tlas`]
+/// and new bind groups for it; bind groups still referencing the old one keep it alive until they
+/// are dropped.
 pub struct Tlas {
     pub(crate) context: Arc<C>,
     pub(crate) id: ObjectId,
@@ -138,6 +218,9 @@ static_assertions::assert_impl_all!(Tlas: WasmNotSendSync);
 
 impl Tlas {
     /// Destroy the associated native resources as soon as possible.
+    ///
+    /// The memory is released once no pending submission uses it anymore. Building
+    /// a destroyed `Tlas`, or using it in a bind group or submission, is an error.
     pub fn destroy(&self) {
         DynContext::tlas_destroy(&*self.context, &self.id, self.data.as_ref());
     }
@@ -160,19 +243,63 @@ pub struct TlasBuildEntry<'a> {
     pub instance_buffer: &'a Buffer,
     /// Number of instances in the instance buffer.
     pub instance_count: u32,
+    /// How to build the acceleration structure, a full build if `None`.
+    pub mode: Option<AccelerationStructureBuildMode>,
 }
 static_assertions::assert_impl_all!(TlasBuildEntry<'_>: WasmNotSendSync);
 
+/// Size in bytes of a single instance record inside a raw instance buffer.
+pub const RAW_TLAS_INSTANCE_SIZE: usize = wgt::instance_packing::PACKED_INSTANCE_SIZE;
+
+pub use wgt::instance_packing::{
+    transform_columns_from_rows, transform_rows_from_columns, transform_rows_from_matrix,
+    InstanceFieldErrors, PackedInstance, MAX_CUSTOM_INDEX, MAX_SHADER_BINDING_TABLE_RECORD_OFFSET,
+};
+pub use wgt::{ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT, ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT};
+
 /// Safe instance for a top level acceleration structure.
+///
+/// Instances can only reference a [`Blas`], nesting a [`Tlas`] is rejected at compile time:
+///
+/// ```compile_fail
+/// # fn instance(tlas: &wgpu::ray_tracing::Tlas) {
+/// wgpu::ray_tracing::TlasInstance::new(tlas, [0.0; 12], 0, 0xff);
+/// # }
+/// ```
 #[derive(Debug, Clone)]
 pub struct TlasInstance {
     pub(crate) blas: ObjectId,
     /// Affine transform matrix 3x4 (rows x columns, row mayor order).
     pub transform: [f32; 12],
-    /// Custom index for the instance used inside the shader (max 24 bits).
+    /// Custom index for the instance used inside the shader (at most [`MAX_CUSTOM_INDEX`]).
+    ///
+    /// Builds of packages containing an instance with a larger custom index fail validation.
     pub custom_index: u32,
     /// Mask for the instance used inside the shader to filter instances.
     pub mask: u8,
+    /// Flags for the instance, empty by default.
+    ///
+    /// [`AccelerationStructureInstanceFlags::FORCE_OPAQUE`] and
+    /// [`AccelerationStructureInstanceFlags::FORCE_NO_OPAQUE`] override whether the geometries of
+    /// the instance's [`Blas`] were marked [`AccelerationStructureGeometryFlags::OPAQUE`]. Builds
+    /// of packages containing an instance with both set fail validation.
+    pub flags: AccelerationStructureInstanceFlags,
+    /// Shader binding table record offset of the instance, 0 by default (at most
+    /// [`MAX_SHADER_BINDING_TABLE_RECORD_OFFSET`]).
+    ///
+    /// With ray queries the shader binding table is implicit: the offset doesn't select any
+    /// shader, it is only read back as `sbt_record_offset` of the committed `RayIntersection`,
+    /// so shaders can use it to pick the material or intersection logic of an instance. Instances
+    /// with triangle and procedural geometries can freely use different offsets.
+    ///
+    /// Ray tracing pipelines, which wgpu doesn't support yet, select the hit group record
+    /// `offset + sbt_offset + geometry_index * sbt_stride` from the offset and stride given when
+    /// tracing, so an offset needs to leave room for `sbt_stride` records per geometry there.
+    ///
+    /// Builds of packages containing an instance with a larger offset fail validation.
+    pub shader_binding_table_record_offset: u32,
+    object_id: Option<u32>,
+    visible: bool,
 }
 
 impl TlasInstance {
@@ -181,19 +308,161 @@ impl TlasInstance {
     /// - transform: Transform buffer offset in bytes (optional, required if transform buffer is present)
     /// - custom_index: Custom index for the instance used inside the shader (max 24 bits)
     /// - mask: Mask for the instance used inside the shader to filter instances
+    ///
+    /// # Panics
+    /// - If `custom_index` doesn't fit into 24 bits. Since the custom index shares its
+    ///   32 bits with the mask, it would otherwise overwrite the mask.
     pub fn new(blas: &Blas, transform: [f32; 12], custom_index: u32, mask: u8) -> Self {
+        assert!(
+            custom_index <= MAX_CUSTOM_INDEX,
+            "Custom index {custom_index:#x} of a TlasInstance uses more than 24 bits (max {MAX_CUSTOM_INDEX:#x})"
+        );
         Self {
             blas: blas.id,
             transform,
             custom_index,
             mask,
+            flags: AccelerationStructureInstanceFlags::empty(),
+            shader_binding_table_record_offset: 0,
+            object_id: None,
+            visible: true,
+        }
+    }
+
+    /// Start building an instance of `blas` with named fields.
+    ///
+    /// The builder starts out with an identity transform, custom index 0, mask `0xff`, no
+    /// flags and shader binding table record offset 0.
+    pub fn builder(blas: &Blas) -> TlasInstanceBuilder {
+        TlasInstanceBuilder {
+            instance: Self::new(blas, IDENTITY_TRANSFORM, 0, 0xff),
         }
     }
 
+    /// Construct an instance of `blas` with the fields of a [`PackedInstance`], e.g. one read
+    /// back from a raw instance buffer or baked offline.
+    ///
+    /// The acceleration structure reference of `packed` is ignored in favor of `blas`, and unknown
+    /// flags are dropped.
+    pub fn from_packed(blas: &Blas, packed: &PackedInstance) -> Self {
+        let mut instance = Self::new(blas, packed.transform, packed.custom_index(), packed.mask());
+        instance.shader_binding_table_record_offset = packed.shader_binding_table_record_offset();
+        instance.flags = AccelerationStructureInstanceFlags::from_bits_truncate(
+            (packed.shader_binding_table_record_offset_and_flags >> 24) as u8,
+        );
+        instance
+    }
+
     /// Set the bottom level acceleration structure.
     pub fn set_blas(&mut self, blas: &Blas) {
         self.blas = blas.id;
     }
+
+    /// Set the transform from a 4x4 matrix given as its 4 columns (like
+    /// `glam::Mat4::to_cols_array_2d`), see [`transform_rows_from_matrix`].
+    ///
+    /// The last row is dropped. It has to be `[0, 0, 0, 1]`, which debug builds assert.
+    pub fn set_transform(&mut self, matrix: [[f32; 4]; 4]) {
+        self.transform = transform_rows_from_matrix(matrix);
+    }
+
+    /// Hide or show the instance without clearing its slot in the package.
+    ///
+    /// A hidden instance is built with a mask of 0, so no ray hits it, while [`Self::mask`] keeps
+    /// the mask it is built with once it is visible again. Like any other change to an instance,
+    /// this only takes effect once the package is built again.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Whether the instance can be hit by rays, see [`Self::set_visible`].
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Give the instance a full 32 bit object id, which shaders recover from a hit through
+    /// [`TlasPackage::object_id_table`] since only the 24 bit custom index is stored in the
+    /// acceleration structure. With `None` (the default) the object id is the custom index.
+    pub fn set_object_id(&mut self, object_id: Option<u32>) {
+        self.object_id = object_id;
+    }
+
+    /// The object id of the instance, see [`Self::set_object_id`].
+    pub fn object_id(&self) -> u32 {
+        self.object_id.unwrap_or(self.custom_index)
+    }
+}
+
+const IDENTITY_TRANSFORM: [f32; 12] = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+];
+
+/// Builder for a [`TlasInstance`] with named fields, created by [`TlasInstance::builder`].
+///
+/// ```no_run
+/// # fn instance(blas: &wgpu::ray_tracing::Blas, transform: [f32; 12]) {
+/// let instance = wgpu::ray_tracing::TlasInstance::builder(blas)
+///     .transform(transform)
+///     .custom_index(42)
+///     .mask(0x01)
+///     .build();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct TlasInstanceBuilder {
+    instance: TlasInstance,
+}
+
+impl TlasInstanceBuilder {
+    /// Set the affine transform matrix 3x4 (rows x columns, row mayor order).
+    pub fn transform(mut self, transform: [f32; 12]) -> Self {
+        self.instance.transform = transform;
+        self
+    }
+
+    /// Set the custom index (at most [`MAX_CUSTOM_INDEX`]).
+    pub fn custom_index(mut self, custom_index: u32) -> Self {
+        self.instance.custom_index = custom_index;
+        self
+    }
+
+    /// Set the mask used inside the shader to filter instances.
+    pub fn mask(mut self, mask: u8) -> Self {
+        self.instance.mask = mask;
+        self
+    }
+
+    /// Set the [shader binding table record offset](TlasInstance::shader_binding_table_record_offset).
+    pub fn sbt_offset(mut self, offset: u32) -> Self {
+        self.instance.shader_binding_table_record_offset = offset;
+        self
+    }
+
+    /// Set the [instance flags](TlasInstance::flags).
+    pub fn flags(mut self, flags: AccelerationStructureInstanceFlags) -> Self {
+        self.instance.flags = flags;
+        self
+    }
+
+    /// Finish building the instance.
+    ///
+    /// # Panics
+    /// - If any field is invalid: a custom index or shader binding table record offset that
+    ///   doesn't fit into 24 bits, or flags forcing the instance to be both opaque and
+    ///   non-opaque. The message lists all of them, see [`InstanceFieldErrors`].
+    pub fn build(self) -> TlasInstance {
+        let instance = self.instance;
+        let errors = InstanceFieldErrors::check(
+            instance.custom_index,
+            instance.shader_binding_table_record_offset,
+            instance.flags,
+        );
+        assert!(errors.is_empty(), "Invalid TlasInstance: {errors}");
+        instance
+    }
 }
 
 pub(crate) struct DynContextTlasInstance<'a> {
@@ -201,6 +470,8 @@ pub(crate) struct DynContextTlasInstance<'a> {
     pub(crate) transform: &'a [f32; 12],
     pub(crate) custom_index: u32,
     pub(crate) mask: u8,
+    pub(crate) flags: AccelerationStructureInstanceFlags,
+    pub(crate) shader_binding_table_record_offset: u32,
 }
 
 /// [Context version] see `TlasInstance`.
@@ -210,16 +481,50 @@ pub struct ContextTlasInstance<'a, T: Context> {
     pub(crate) transform: &'a [f32; 12],
     pub(crate) custom_index: u32,
     pub(crate) mask: u8,
+    pub(crate) flags: AccelerationStructureInstanceFlags,
+    pub(crate) shader_binding_table_record_offset: u32,
 }
 
 /// The safe version of TlasEntry, containing TlasInstances instead of a raw buffer.
 pub struct TlasPackage {
     pub(crate) tlas: Tlas,
     pub(crate) instances: Vec<Option<TlasInstance>>,
+    /// Number of `Some` slots, not counting the ones in `uncounted`.
+    pub(crate) active_instances: usize,
+    /// Slots lent out by the last mutable access, which may have been set or cleared since.
+    pub(crate) uncounted: Range<usize>,
     pub(crate) lowest_unmodified: u32,
+    pub(crate) instance_upload: Option<TlasInstanceUpload>,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
+    pub(crate) last_build_info: Mutex<TlasBuildInfo>,
+    pub(crate) auto_grow: bool,
+    pub(crate) reallocated: bool,
 }
 static_assertions::assert_impl_all!(TlasPackage: WasmNotSendSync);
 
+/// What the most recent build of a [`TlasPackage`] processed, see [`TlasPackage::last_build_info`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TlasBuildInfo {
+    /// Number of instances built into the tlas.
+    ///
+    /// Empty (`None`) slots of the package aren't built, so this is the number of `Some` slots.
+    pub instance_count: u32,
+    /// Number of slots, from the start of the package, that were marked as modified and
+    /// uploaded with the build.
+    pub dirty_count: u32,
+}
+
+/// A copy of the instances of a [`TlasPackage`], taken with [`TlasPackage::snapshot`] and
+/// put back with [`TlasPackage::restore`], e.g. to undo edits.
+///
+/// Instances reference their [`Blas`] without keeping it alive, so the referenced
+/// bottom level acceleration structures must still exist when the restored package is built.
+#[derive(Debug, Clone)]
+pub struct TlasSnapshot {
+    instances: Vec<Option<TlasInstance>>,
+}
+static_assertions::assert_impl_all!(TlasSnapshot: WasmNotSendSync);
+
 impl TlasPackage {
     /// Construct TlasPackage consuming the Tlas (prevents modification of the Tlas without using this package).
     /// (max_instances needs to fit into tlas)
@@ -233,8 +538,113 @@ impl TlasPackage {
         Self {
             tlas,
             lowest_unmodified: instances.len() as u32,
+            active_instances: count_active(&instances),
+            uncounted: 0..0,
             instances,
+            instance_upload: None,
+            mode: None,
+            last_build_info: Mutex::default(),
+            auto_grow: false,
+            reallocated: false,
+        }
+    }
+
+    /// Let writes past the end of the package grow it instead of failing.
+    ///
+    /// When [`Self::get_mut_slice`], [`Self::get_mut_single`], [`Self::set_instances`],
+    /// [`Self::fill`] or [`Self::write_instances_raw`] access an instance beyond the capacity of
+    /// the package, the capacity is raised to the next power of two that fits it. This
+    /// reallocates the tlas, so bind groups using the old one have to be recreated with
+    /// [`Self::as_binding`], see [`Self::take_reallocated`]. The new tlas keeps the label, flags
+    /// and update mode of the old one.
+    ///
+    /// Disabled by default, since every growth allocates a new tlas.
+    ///
+    /// # Panics
+    /// - On growth, if the new tlas can't be created, e.g. because its instance buffer would
+    ///   exceed [`Limits::max_buffer_size`](wgt::Limits::max_buffer_size).
+    pub fn with_auto_grow(mut self, auto_grow: bool) -> Self {
+        self.auto_grow = auto_grow;
+        self
+    }
+
+    /// Whether the package grows on writes past its end, see [`Self::with_auto_grow`].
+    pub fn auto_grow(&self) -> bool {
+        self.auto_grow
+    }
+
+    /// Whether the tlas was reallocated by [auto growth](Self::with_auto_grow) since the last
+    /// call, in which case bind groups have to be recreated to use the new tlas.
+    pub fn take_reallocated(&mut self) -> bool {
+        std::mem::take(&mut self.reallocated)
+    }
+
+    /// Make room for `len` instances if the package is set to [auto grow](Self::with_auto_grow),
+    /// returning whether they fit.
+    fn grow_to(&mut self, len: usize) -> bool {
+        if len <= self.instances.len() {
+            return true;
+        }
+        if !self.auto_grow {
+            return false;
+        }
+        let capacity = len.next_power_of_two();
+        let (id, data) = DynContext::tlas_create_resized(
+            &*self.tlas.context,
+            &self.tlas.id,
+            self.tlas.data.as_ref(),
+            capacity as u32,
+        );
+        self.tlas = Tlas {
+            context: Arc::clone(&self.tlas.context),
+            id,
+            data,
+        };
+        self.instances.resize(capacity, None);
+        // The new tlas is unbuilt, so everything has to be uploaded and fully built.
+        self.lowest_unmodified = capacity as u32;
+        if self.mode == Some(AccelerationStructureBuildMode::Update) {
+            self.mode = None;
         }
+        self.reallocated = true;
+        true
+    }
+
+    /// What the most recent build of the package processed, to debug instances missing from
+    /// the tlas.
+    ///
+    /// This is recorded when the build is encoded. Before the package was built for the first
+    /// time, all counts are 0.
+    pub fn last_build_info(&self) -> TlasBuildInfo {
+        *self.last_build_info.lock()
+    }
+
+    /// Choose how the instances are uploaded when the package is built.
+    ///
+    /// By default (`None`) this is picked from the type of the adapter, see
+    /// [`TlasInstanceUpload::for_device_type`].
+    pub fn set_instance_upload(&mut self, instance_upload: Option<TlasInstanceUpload>) {
+        self.instance_upload = instance_upload;
+    }
+
+    /// How the instances are uploaded when the package is built, see
+    /// [`Self::set_instance_upload`].
+    pub fn instance_upload(&self) -> Option<TlasInstanceUpload> {
+        self.instance_upload
+    }
+
+    /// Choose how the following builds of the package are performed, e.g. to refit it with
+    /// [`AccelerationStructureBuildMode::Update`] while only instance transforms change, and
+    /// to force a full build again after instances were added or removed.
+    ///
+    /// By default (`None`) the package is fully built.
+    pub fn set_build_mode(&mut self, mode: Option<AccelerationStructureBuildMode>) {
+        self.mode = mode;
+    }
+
+    /// How the package is built, see [`Self::set_build_mode`].
+    pub fn build_mode(&self) -> Option<AccelerationStructureBuildMode> {
+        self.mode
     }
 
     /// Get a reference to all instances.
@@ -242,32 +652,260 @@ impl TlasPackage {
         &self.instances
     }
 
+    /// Get a reference to a single instance.
+    /// Returns None if the index is out of bounds or the slot is empty.
+    /// Unlike [`Self::get_mut_single`] this doesn't mark anything as modified.
+    pub fn get_single(&self, index: usize) -> Option<&TlasInstance> {
+        self.instances.get(index)?.as_ref()
+    }
+
+    /// Number of instance slots in the package, including empty ones.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether the package has no instance slots at all.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Number of instances in the package, i.e. slots that aren't `None`, which is the number
+    /// of instances the next build puts into the tlas.
+    ///
+    /// The count is kept up to date as instances are written, so this doesn't scan the
+    /// package. Only the slots handed out by the last call to [`Self::get_mut_slice`],
+    /// [`Self::get_mut_single`] or [`Self::iter_mut`] are counted again, since they may have
+    /// been changed through the returned references.
+    pub fn active_instance_count(&self) -> usize {
+        self.active_instances + count_active(&self.instances[self.uncounted.clone()])
+    }
+
+    /// Count the slots handed out by the last mutable access again.
+    fn settle_active_instances(&mut self) {
+        let uncounted = std::mem::replace(&mut self.uncounted, 0..0);
+        self.active_instances += count_active(&self.instances[uncounted]);
+    }
+
+    /// Hand out the slots in `range` mutably, leaving them uncounted until the next access.
+    fn lend_mut(&mut self, range: Range<usize>) -> &mut [Option<TlasInstance>] {
+        self.settle_active_instances();
+        self.active_instances -= count_active(&self.instances[range.clone()]);
+        self.uncounted = range.clone();
+        &mut self.instances[range]
+    }
+
+    /// Iterate over all instance slots with their index, including empty ones.
+    /// Unlike [`Self::iter_mut`] this doesn't mark anything as modified.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Option<TlasInstance>)> + '_ {
+        self.instances.iter().enumerate()
+    }
+
+    /// Iterate mutably over all instance slots with their index, including empty ones, e.g. to
+    /// animate every instance.
+    /// All elements are marked as modified, so the next build uploads all of them.
+    ///
+    /// ```
+    /// # fn animate(tlas_package: &mut wgpu::ray_tracing::TlasPackage, angle: f32) {
+    /// let (sin, cos) = angle.sin_cos();
+    /// for (_, instance) in tlas_package.iter_mut() {
+    ///     let Some(instance) = instance else { continue };
+    ///     // Turn every instance around its own y axis, keeping its position.
+    ///     for row in instance.transform.chunks_exact_mut(4) {
+    ///         let (x, z) = (row[0], row[2]);
+    ///         row[0] = cos * x - sin * z;
+    ///         row[2] = sin * x + cos * z;
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut Option<TlasInstance>)> + '_ {
+        self.lowest_unmodified = self.instances.len() as u32;
+        self.lend_mut(0..self.instances.len())
+            .iter_mut()
+            .enumerate()
+    }
+
     /// Get a mutable slice to a range of instances.
-    /// Returns None if the range is out of bounds.
+    /// Returns None if the range is out of bounds, unless the package [auto grows](Self::with_auto_grow).
     /// All elements from the lowest accessed index up are marked as modified.
     /// For better performance it is recommended to reduce access to low elements.
     pub fn get_mut_slice(&mut self, range: Range<usize>) -> Option<&mut [Option<TlasInstance>]> {
-        if range.end > self.instances.len() {
+        if !self.grow_to(range.end) {
             return None;
         }
         if range.end as u32 > self.lowest_unmodified {
             self.lowest_unmodified = range.end as u32;
         }
-        Some(&mut self.instances[range])
+        Some(self.lend_mut(range))
     }
 
     /// Get a single mutable reference to an instance.
-    /// Returns None if the range is out of bounds.
+    /// Returns None if the index is out of bounds, unless the package [auto grows](Self::with_auto_grow).
     /// All elements from the lowest accessed index up are marked as modified.
     /// For better performance it is recommended to reduce access to low elements.
     pub fn get_mut_single(&mut self, index: usize) -> Option<&mut Option<TlasInstance>> {
-        if index >= self.instances.len() {
+        if !self.grow_to(index + 1) {
             return None;
         }
         if index as u32 + 1 > self.lowest_unmodified {
             self.lowest_unmodified = index as u32 + 1;
         }
-        Some(&mut self.instances[index])
+        Some(&mut self.lend_mut(index..index + 1)[0])
+    }
+
+    /// Replace the instances starting at `offset` with `instances`, e.g. to populate a whole
+    /// grid of instances at once.
+    /// All elements from the lowest written index up are marked as modified.
+    ///
+    /// # Panics
+    /// - If `instances` don't fit into the package starting at `offset`, unless the package
+    ///   [auto grows](Self::with_auto_grow).
+    pub fn set_instances(&mut self, offset: usize, instances: &[Option<TlasInstance>]) {
+        let capacity = self.instances.len();
+        offset
+            .checked_add(instances.len())
+            .and_then(|end| self.get_mut_slice(offset..end))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Setting {} instances at offset {offset} overruns the package capacity of {capacity} instances",
+                    instances.len()
+                )
+            })
+            .clone_from_slice(instances);
+        self.settle_active_instances();
+    }
+
+    /// Set all instances in `range` to `instance`, e.g. `None` to remove them from the tlas.
+    /// All elements from the lowest written index up are marked as modified.
+    ///
+    /// # Panics
+    /// - If `range` is out of bounds, unless the package [auto grows](Self::with_auto_grow).
+    pub fn fill(&mut self, range: Range<usize>, instance: Option<TlasInstance>) {
+        let capacity = self.instances.len();
+        self.get_mut_slice(range.clone())
+            .unwrap_or_else(|| {
+                panic!("Filling instances {range:?} overruns the package capacity of {capacity} instances")
+            })
+            .fill(instance);
+        self.settle_active_instances();
+    }
+
+    /// Write pre-packed instance records into the instances starting at `offset`,
+    /// without going through [`TlasInstance`].
+    ///
+    /// Each record is [`RAW_TLAS_INSTANCE_SIZE`] bytes in the layout of a raw instance buffer:
+    /// - 12 `f32`s: affine transform matrix 3x4 (rows x columns, row mayor order)
+    /// - `u32`: custom index in the lower 24 bits, mask in the upper 8 bits
+    /// - `u32`: shader binding table record offset in the lower 24 bits and
+    ///   [`AccelerationStructureInstanceFlags`] in the upper 8 bits
+    /// - `u64`: [`Blas::handle`] of the referenced bottom level acceleration structure
+    ///
+    /// All elements from the lowest written index up are marked as modified.
+    ///
+    /// # Panics
+    /// - If the length of `data` isn't a multiple of [`RAW_TLAS_INSTANCE_SIZE`].
+    /// - If the records don't fit into the package starting at `offset`, unless the package
+    ///   [auto grows](Self::with_auto_grow).
+    /// - If a record sets unknown instance flags.
+    /// - If a record references a handle that doesn't belong to a live [`Blas`], including the
+    ///   handle of a [`Tlas`], since instances can only reference bottom level acceleration
+    ///   structures.
+    pub fn write_instances_raw(&mut self, offset: usize, data: &[u8]) {
+        assert!(
+            data.len() % RAW_TLAS_INSTANCE_SIZE == 0,
+            "Raw instance data of {} bytes is not a multiple of the instance size ({RAW_TLAS_INSTANCE_SIZE} bytes)",
+            data.len()
+        );
+        let count = data.len() / RAW_TLAS_INSTANCE_SIZE;
+        let fits = match offset.checked_add(count) {
+            Some(end) => self.grow_to(end),
+            None => false,
+        };
+        assert!(
+            fits,
+            "Writing {count} raw instances at offset {offset} overruns the package capacity of {} instances",
+            self.instances.len()
+        );
+
+        // Only resolve each referenced bottom level acceleration structure once, records
+        // typically share a few of them.
+        let records = data.chunks_exact(RAW_TLAS_INSTANCE_SIZE);
+        let record_handle = |record: &[u8]| u64::from_ne_bytes(record[56..64].try_into().unwrap());
+        let mut handles = records.clone().map(record_handle).collect::<Vec<_>>();
+        handles.sort_unstable();
+        handles.dedup();
+        let blas_ids = DynContext::tlas_resolve_blas_handles(
+            &*self.tlas.context,
+            &self.tlas.id,
+            self.tlas.data.as_ref(),
+            &handles,
+        );
+
+        self.settle_active_instances();
+        for (index, record) in records.enumerate() {
+            let blas = match &blas_ids[handles.binary_search(&record_handle(record)).unwrap()] {
+                Ok(blas) => *blas,
+                Err(err) => panic!("Raw instance {}: {err}", offset + index),
+            };
+            let record = PackedInstance::from_ne_bytes(record.try_into().unwrap());
+            let flags = record
+                .flags()
+                .unwrap_or_else(|| panic!("Raw instance {} sets unknown flags", offset + index));
+            let slot = &mut self.instances[offset + index];
+            if slot.is_none() {
+                self.active_instances += 1;
+            }
+            *slot = Some(TlasInstance {
+                blas,
+                transform: record.transform,
+                custom_index: record.custom_index(),
+                mask: record.mask(),
+                flags,
+                shader_binding_table_record_offset: record.shader_binding_table_record_offset(),
+                object_id: None,
+                visible: true,
+            });
+        }
+
+        if (offset + count) as u32 > self.lowest_unmodified {
+            self.lowest_unmodified = (offset + count) as u32;
+        }
+    }
+
+    /// Table recovering the [object ids](TlasInstance::set_object_id) of the instances in
+    /// shaders, to index scene data with more than 2^24 objects.
+    ///
+    /// Entry `i` belongs to the instance hit with `instance_id == i`, which is the `i`-th
+    /// instance of the package that isn't `None`: empty slots aren't built, so they don't take
+    /// up an instance id. Uploaded into a storage buffer, the object id of a hit is
+    /// `table[intersection.instance_id] + intersection.instance_custom_index` (wrapping), so the
+    /// custom index keeps its meaning for shaders that don't need the object id.
+    ///
+    /// Like the instances, the table has to be uploaded again when the package changes.
+    pub fn object_id_table(&self) -> Vec<u32> {
+        self.instances
+            .iter()
+            .flatten()
+            .map(|instance| instance.object_id().wrapping_sub(instance.custom_index))
+            .collect()
+    }
+
+    /// Copy the current instances, to [restore](Self::restore) them later.
+    pub fn snapshot(&self) -> TlasSnapshot {
+        TlasSnapshot {
+            instances: self.instances.clone(),
+        }
+    }
+
+    /// Replace the instances with the ones of `snapshot`.
+    /// All instances are marked as modified, so the next build uploads all of them.
+    ///
+    /// The number of instances becomes the one of the snapshot, which needs to fit into the tlas.
+    pub fn restore(&mut self, snapshot: &TlasSnapshot) {
+        self.instances.clone_from(&snapshot.instances);
+        self.active_instances = count_active(&self.instances);
+        self.uncounted = 0..0;
+        self.lowest_unmodified = self.instances.len() as u32;
     }
 
     /// Get the binding resource for the underling acceleration structure, to be used in a
@@ -281,13 +919,23 @@ impl TlasPackage {
     }
 }
 
+/// Number of slots in `instances` that aren't `None`.
+fn count_active(instances: &[Option<TlasInstance>]) -> usize {
+    instances
+        .iter()
+        .filter(|instance| instance.is_some())
+        .count()
+}
+
 pub(crate) struct DynContextBlasTriangleGeometry<'a> {
     pub(crate) size: &'a BlasTriangleGeometrySizeDescriptor,
     pub(crate) vertex_buffer: ObjectId,
     pub(crate) index_buffer: Option<ObjectId>,
     pub(crate) transform_buffer: Option<ObjectId>,
     pub(crate) first_vertex: u32,
-    pub(crate) vertex_stride: wgt::BufferAddress,
+    pub(crate) vertex_stride: Option<wgt::BufferAddress>,
+    pub(crate) vertex_offset: wgt::BufferAddress,
+    pub(crate) vertex_buffer_offset: wgt::BufferAddress,
     pub(crate) index_buffer_offset: Option<wgt::BufferAddress>,
     pub(crate) transform_buffer_offset: Option<wgt::BufferAddress>,
 }
@@ -307,18 +955,22 @@ pub(crate) enum DynContextBlasGeometries<'a> {
 pub(crate) struct DynContextBlasBuildEntry<'a> {
     pub(crate) blas_id: ObjectId,
     pub(crate) geometries: DynContextBlasGeometries<'a>,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 pub(crate) struct DynContextTlasBuildEntry {
     pub(crate) tlas_id: ObjectId,
     pub(crate) instance_buffer_id: ObjectId,
     pub(crate) instance_count: u32,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 pub(crate) struct DynContextTlasPackage<'a> {
     pub(crate) tlas_id: ObjectId,
     pub(crate) instances: Box<dyn Iterator<Item = Option<DynContextTlasInstance<'a>>> + 'a>,
     pub(crate) lowest_unmodified: u32,
+    pub(crate) instance_upload: Option<TlasInstanceUpload>,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 /// [Context version] see `BlasTriangleGeometry`.
@@ -329,7 +981,9 @@ pub struct ContextBlasTriangleGeometry<'a, T: Context> {
     pub(crate) index_buffer: Option<T::BufferId>,
     pub(crate) transform_buffer: Option<T::BufferId>,
     pub(crate) first_vertex: u32,
-    pub(crate) vertex_stride: wgt::BufferAddress,
+    pub(crate) vertex_stride: Option<wgt::BufferAddress>,
+    pub(crate) vertex_offset: wgt::BufferAddress,
+    pub(crate) vertex_buffer_offset: wgt::BufferAddress,
     pub(crate) index_buffer_offset: Option<wgt::BufferAddress>,
     pub(crate) transform_buffer_offset: Option<wgt::BufferAddress>,
 }
@@ -356,6 +1010,7 @@ pub enum ContextBlasGeometries<'a, T: Context> {
 pub struct ContextBlasBuildEntry<'a, T: Context> {
     pub(crate) blas_id: T::BlasId,
     pub(crate) geometries: ContextBlasGeometries<'a, T>,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 /// [Context version] see `TlasBuildEntry`.
@@ -364,6 +1019,7 @@ pub struct ContextTlasBuildEntry<T: Context> {
     pub(crate) tlas_id: T::TlasId,
     pub(crate) instance_buffer_id: T::BufferId,
     pub(crate) instance_count: u32,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 /// [Context version] see `TlasPackage`.
@@ -372,11 +1028,13 @@ pub struct ContextTlasPackage<'a, T: Context> {
     pub(crate) tlas_id: T::TlasId,
     pub(crate) instances: Box<dyn Iterator<Item = Option<ContextTlasInstance<'a, T>>> + 'a>,
     pub(crate) lowest_unmodified: u32,
+    pub(crate) instance_upload: Option<TlasInstanceUpload>,
+    pub(crate) mode: Option<AccelerationStructureBuildMode>,
 }
 
 /// Utility module to add traits for the device and command encoder.
 pub mod traits {
-    pub use super::{CommandEncoderRayTracing as _, DeviceRayTracing as _};
+    pub use super::{CommandEncoderRayTracing as _, DeviceRayTracing as _, QueueRayTracing as _};
 }
 
 /// Trait to add ray tracing functions to a [`Device`].
@@ -390,9 +1048,98 @@ pub trait DeviceRayTracing {
         sizes: BlasGeometrySizeDescriptors,
     ) -> Blas;
 
+    /// Create a bottom level acceleration structure whose memory is part of `buffer`,
+    /// for example to alias it with other transient resources.
+    /// - buffer: The buffer holding the acceleration structure, which needs the
+    ///     [`BufferUsages::ACCELERATION_STRUCTURE_STORAGE`](crate::BufferUsages::ACCELERATION_STRUCTURE_STORAGE) usage.
+    /// - offset: The offset of the acceleration structure in `buffer`, a multiple of
+    ///     [`ACCELERATION_STRUCTURE_OFFSET_ALIGNMENT`].
+    /// - desc: The descriptor of the acceleration structure.
+    /// - sizes: Size descriptor limiting what can be built into the acceleration structure.
+    ///
+    /// `buffer` must hold [`DeviceRayTracing::blas_size`] bytes from `offset` on. It is kept
+    /// alive as long as the acceleration structure, but destroying it makes the acceleration
+    /// structure unusable.
+    fn create_blas_in_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: wgt::BufferAddress,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: BlasGeometrySizeDescriptors,
+    ) -> Blas;
+
+    /// Number of bytes a bottom level acceleration structure created with `desc` and `sizes`
+    /// occupies in the buffer passed to [`DeviceRayTracing::create_blas_in_buffer`].
+    fn blas_size(
+        &self,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: &BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress;
+
+    /// Sizes of a bottom level acceleration structure created with `desc` and `sizes`, and of
+    /// the scratch memory building or updating it uses, without creating it.
+    ///
+    /// This lets memory for acceleration structures and scratch buffers be budgeted up front.
+    fn blas_build_sizes(
+        &self,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: &BlasGeometrySizeDescriptors,
+    ) -> AccelerationStructureBuildSizes;
+
+    /// Sizes of a top level acceleration structure created with `desc`, which holds up to
+    /// `desc.max_instances` instances, and of the scratch memory building or updating it uses,
+    /// without creating it.
+    fn tlas_build_sizes(&self, desc: &CreateTlasDescriptor<'_>) -> AccelerationStructureBuildSizes;
+
     /// Create a top level acceleration structure, used for ray tracing.
     /// - desc: The descriptor of the acceleration structure.
     fn create_tlas(&self, desc: &CreateTlasDescriptor<'_>) -> Tlas;
+
+    /// Number of bytes of scratch memory needed to build `blas` and `tlas` together with
+    /// [`CommandEncoderRayTracing::build_acceleration_structures_with_scratch`].
+    ///
+    /// The size depends on the mode of each entry: updates only need the update scratch size of
+    /// the acceleration structure, which is usually smaller than what a full build needs.
+    fn build_scratch_size(
+        &self,
+        blas: &[&BlasBuildEntry<'_>],
+        tlas: &[&TlasPackage],
+    ) -> wgt::BufferAddress;
+
+    /// Set for how many [`Device::poll`]s (including the implicit ones done by
+    /// [`Queue::submit`](crate::Queue::submit)) an unused internal scratch buffer
+    /// is kept for reuse by later acceleration structure builds before it is released.
+    /// - polls: The idle threshold, defaults to 64.
+    fn set_scratch_pool_idle_polls(&self, polls: u32);
+
+    /// Release the memory of dropped [`Blas`]es.
+    ///
+    /// When a [`Blas`] that wasn't created in a user provided buffer is dropped and no longer in
+    /// use, its memory is kept and reused by the next [`Blas`] created with the same size, so
+    /// dynamic geometry that creates and drops identically sized [`Blas`]es every frame doesn't
+    /// reallocate them. Memory that isn't reused within 64 polls of the device is released, this
+    /// releases all of it right away.
+    fn trim_blas_pool(&self);
+
+    /// Number of bytes `blas` occupies once compacted, to reclaim memory of an acceleration
+    /// structure built with [`AccelerationStructureFlags::PREFER_FAST_TRACE`].
+    ///
+    /// `blas` must have been created with [`AccelerationStructureFlags::ALLOW_COMPACTION`] and
+    /// built, and the submission building it must have completed, e.g. after a
+    /// [`Device::poll`] with [`Maintain::Wait`](crate::Maintain::Wait). The size stays valid
+    /// until `blas` is built again.
+    fn blas_compacted_size(&self, blas: &Blas) -> wgt::BufferAddress;
+
+    /// Create a bottom level acceleration structure just large enough for `source` once
+    /// compacted, which [`CommandEncoderRayTracing::copy_blas`] with
+    /// [`AccelerationStructureCopy::Compact`] copies it into.
+    ///
+    /// If [`DeviceRayTracing::blas_compacted_size`] wasn't called for `source` since it was last
+    /// built, the size is read now, so the submission building `source` must have completed,
+    /// e.g. as signaled by [`read_blas_compacted_size`]. The new acceleration structure has the
+    /// sizes, flags and update mode of `source`, and can only be filled by a compacting copy,
+    /// not built.
+    fn create_compacted_blas(&self, source: &Blas, label: Label<'_>) -> Blas;
 }
 
 impl DeviceRayTracing for Device {
@@ -406,7 +1153,34 @@ impl DeviceRayTracing for Device {
             &self.id,
             self.data.as_ref(),
             desc,
+            sizes.clone(),
+        );
+
+        Blas {
+            context: Arc::clone(&self.context),
+            id,
+            data,
+            handle,
             sizes,
+        }
+    }
+
+    fn create_blas_in_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: wgt::BufferAddress,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: BlasGeometrySizeDescriptors,
+    ) -> Blas {
+        let (id, handle, data) = DynContext::device_create_blas_in_buffer(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            &buffer.id,
+            buffer.data.as_ref(),
+            offset,
+            desc,
+            sizes.clone(),
         );
 
         Blas {
@@ -414,9 +1188,36 @@ impl DeviceRayTracing for Device {
             id,
             data,
             handle,
+            sizes,
         }
     }
 
+    fn blas_size(
+        &self,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: &BlasGeometrySizeDescriptors,
+    ) -> wgt::BufferAddress {
+        DynContext::device_get_blas_size(&*self.context, &self.id, self.data.as_ref(), desc, sizes)
+    }
+
+    fn blas_build_sizes(
+        &self,
+        desc: &CreateBlasDescriptor<'_>,
+        sizes: &BlasGeometrySizeDescriptors,
+    ) -> AccelerationStructureBuildSizes {
+        DynContext::device_get_blas_build_sizes(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            desc,
+            sizes,
+        )
+    }
+
+    fn tlas_build_sizes(&self, desc: &CreateTlasDescriptor<'_>) -> AccelerationStructureBuildSizes {
+        DynContext::device_get_tlas_build_sizes(&*self.context, &self.id, self.data.as_ref(), desc)
+    }
+
     fn create_tlas(&self, desc: &CreateTlasDescriptor<'_>) -> Tlas {
         let (id, data) =
             DynContext::device_create_tlas(&*self.context, &self.id, self.data.as_ref(), desc);
@@ -427,6 +1228,70 @@ impl DeviceRayTracing for Device {
             data,
         }
     }
+
+    fn build_scratch_size(
+        &self,
+        blas: &[&BlasBuildEntry<'_>],
+        tlas: &[&TlasPackage],
+    ) -> wgt::BufferAddress {
+        let blas = blas
+            .iter()
+            .map(|entry| (entry.blas.id, entry.mode))
+            .collect::<Vec<_>>();
+        let tlas = tlas
+            .iter()
+            .map(|package| (package.tlas.id, package.mode))
+            .collect::<Vec<_>>();
+        DynContext::device_get_build_scratch_size(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            &blas,
+            &tlas,
+        )
+    }
+
+    fn set_scratch_pool_idle_polls(&self, polls: u32) {
+        DynContext::device_set_scratch_pool_idle_polls(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            polls,
+        );
+    }
+
+    fn trim_blas_pool(&self) {
+        DynContext::device_trim_blas_pool(&*self.context, &self.id, self.data.as_ref());
+    }
+
+    fn blas_compacted_size(&self, blas: &Blas) -> wgt::BufferAddress {
+        DynContext::device_get_blas_compacted_size(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            &blas.id,
+            blas.data.as_ref(),
+        )
+    }
+
+    fn create_compacted_blas(&self, source: &Blas, label: Label<'_>) -> Blas {
+        let (id, handle, data) = DynContext::device_create_compacted_blas(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+            &source.id,
+            source.data.as_ref(),
+            label,
+        );
+
+        Blas {
+            context: Arc::clone(&self.context),
+            id,
+            data,
+            handle,
+            sizes: source.sizes.clone(),
+        }
+    }
 }
 
 /// Trait to add ray tracing functions to a [`CommandEncoder`].
@@ -443,16 +1308,72 @@ pub trait CommandEncoderRayTracing {
     ///
     /// A bottom level acceleration structure may be build and used as a reference in a top level acceleration structure in the same invocation of this function.
     ///
+    /// Builds are recorded outside of passes. Like any other command, a build recorded while a render or compute pass
+    /// of the encoder is still open (see [`ComputePass::forget_lifetime`](crate::ComputePass::forget_lifetime))
+    /// fails validation and invalidates the encoder.
+    ///
+    /// Every build writes into the memory allocated when the acceleration structure was created,
+    /// so geometry that changes every frame can be fully rebuilt into the same [`Blas`] (and instances into the same [`Tlas`])
+    /// without creating a new acceleration structure.
+    ///
+    /// # Input buffer lifetime
+    ///
+    /// The vertex, index, transform and bounding box buffers are kept alive by the command buffer, so they may be dropped
+    /// as soon as this returns and [destroyed](crate::Buffer::destroy) once the command buffer has been submitted:
+    /// their memory is only released after the submission reading them has completed.
+    ///
     /// # Bind group usage
     ///
     /// When a top level acceleration structure is used in a bind group, some validation takes place:
     ///    - The top level acceleration structure is valid and has been built.
     ///    - All the bottom level acceleration structures referenced by the top level acceleration structure are valid and have been built prior,
     ///      or at same time as the containing top level acceleration structure.
-    fn build_acceleration_structures<'a>(
+    ///
+    /// # Threading
+    ///
+    /// Builds may be recorded concurrently into separate [`CommandEncoder`]s on different threads,
+    /// e.g. to split the bottom level builds of a large scene across workers, and the resulting command buffers
+    /// submitted together in a single [`Queue::submit`](crate::Queue::submit).
+    /// Scratch memory is shared between encoders through the device and every build is ordered against the builds
+    /// of command buffers submitted before it (including earlier ones of the same submission).
+    /// In particular, a bottom level acceleration structure may be rebuilt while a top level acceleration structure build referencing it
+    /// is still in flight: the rebuild waits for that build to finish reading it. The top level acceleration structure then has to be
+    /// rebuilt before it is used again, see [Bind group usage](#bind-group-usage).
+    /// The builds of one level within a single call may run concurrently, so an acceleration structure may only be built once per call.
+    ///
+    /// Builds are ordered by when they were recorded, not by when they were submitted:
+    /// a top level acceleration structure build must be recorded after the builds of all the bottom level acceleration structures it references,
+    /// and its command buffer submitted no earlier than theirs.
+    fn build_acceleration_structures<'a, 'b: 'a>(
+        &mut self,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
+        tlas: impl IntoIterator<Item = &'a TlasPackage>,
+    );
+
+    /// Build bottom and top level acceleration structures using scratch memory provided by the caller,
+    /// instead of the internal scratch buffers shared through the device.
+    /// See [`CommandEncoderRayTracing::build_acceleration_structures`] for more details.
+    /// - scratch_buffer: The buffer used as scratch memory, which needs the
+    ///     [`BufferUsages::ACCELERATION_STRUCTURE_SCRATCH`](crate::BufferUsages::ACCELERATION_STRUCTURE_SCRATCH) usage.
+    /// - scratch_offset: The offset of the scratch memory in `scratch_buffer`, a multiple of
+    ///     [`ACCELERATION_STRUCTURE_SCRATCH_ALIGNMENT`].
+    ///
+    /// `scratch_buffer` must hold [`DeviceRayTracing::build_scratch_size`] bytes for the built
+    /// acceleration structures from `scratch_offset` on. The scratch memory may be reused by later
+    /// builds, which are ordered against this one.
+    ///
+    /// Building many acceleration structures in a single call needs scratch memory for all of
+    /// them at once. To build a large batch, e.g. the bottom level acceleration structures of a
+    /// scene at startup, with a single small buffer instead, build them in several calls reusing
+    /// the same scratch memory, sized for the largest call, e.g. from the
+    /// [`build_scratch_size`](AccelerationStructureBuildSizes::build_scratch_size) reported by
+    /// [`DeviceRayTracing::blas_build_sizes`].
+    fn build_acceleration_structures_with_scratch<'a, 'b: 'a>(
         &mut self,
-        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'a>>,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
         tlas: impl IntoIterator<Item = &'a TlasPackage>,
+        scratch_buffer: &Buffer,
+        scratch_offset: wgt::BufferAddress,
     );
 
     /// Build bottom and top level acceleration structures.
@@ -465,18 +1386,59 @@ pub trait CommandEncoderRayTracing {
     ///       when the corresponding top level acceleration structure is built. (builds may happen in the same invocation of this function).
     ///    - At the time when the top level acceleration structure is used in a bind group, all associated bottom level acceleration structures must be valid,
     ///      and built (no later than the time when the top level acceleration structure was built).
-    unsafe fn build_acceleration_structures_unsafe_tlas<'a>(
+    unsafe fn build_acceleration_structures_unsafe_tlas<'a, 'b: 'a>(
         &mut self,
-        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'a>>,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
         tlas: impl IntoIterator<Item = &'a TlasBuildEntry<'a>>,
     );
+
+    /// Copy the last build of `src` into `dst`, which may be used in place of `src` in top level
+    /// acceleration structures built after this.
+    ///
+    /// With [`AccelerationStructureCopy::Clone`], `dst` must have been created with the same
+    /// geometry sizes and flags as `src`, and can then be updated independently of it, e.g. to
+    /// refit a copy while `src` is still being traced against.
+    ///
+    /// With [`AccelerationStructureCopy::Compact`], `dst` is usually created with
+    /// [`DeviceRayTracing::create_compacted_blas`]. The build of `src` must not change between
+    /// [`DeviceRayTracing::blas_compacted_size`] and the submission of this copy, so the flow is:
+    /// build `src`, submit and wait for the submission, read its compacted size, create `dst`,
+    /// and record the copy.
+    fn copy_blas(&mut self, src: &Blas, dst: &Blas, copy: AccelerationStructureCopy);
+
+    /// Clone the last build of `src` into `dst`, which must have been created with the same
+    /// maximum instance count and flags.
+    ///
+    /// `dst` references the same bottom level acceleration structures as `src`, and can be
+    /// updated or rebuilt independently of it.
+    fn copy_tlas(&mut self, src: &Tlas, dst: &Tlas);
+
+    /// Copy the compacted size of `blas` as of its last build into `destination` at
+    /// `destination_offset`, as a `u64`.
+    ///
+    /// Mapping `destination`, or a buffer it is copied to, reads the size without blocking on
+    /// the build like [`DeviceRayTracing::blas_compacted_size`] does, see
+    /// [`read_blas_compacted_size`] for a helper doing so.
+    ///
+    /// `blas` must have been created with [`AccelerationStructureFlags::ALLOW_COMPACTION`] and
+    /// built by this or an earlier submission. `destination` needs
+    /// [`BufferUsages::QUERY_RESOLVE`](crate::BufferUsages::QUERY_RESOLVE) and
+    /// `destination_offset` must be a multiple of
+    /// [`QUERY_RESOLVE_BUFFER_ALIGNMENT`](crate::QUERY_RESOLVE_BUFFER_ALIGNMENT).
+    fn resolve_blas_compacted_size(
+        &mut self,
+        blas: &Blas,
+        destination: &Buffer,
+        destination_offset: wgt::BufferAddress,
+    );
 }
 
-impl CommandEncoderRayTracing for CommandEncoder {
-    fn build_acceleration_structures<'a>(
+impl CommandEncoder {
+    fn build_acceleration_structures_impl<'a, 'b: 'a>(
         &mut self,
-        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'a>>,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
         tlas: impl IntoIterator<Item = &'a TlasPackage>,
+        scratch: Option<(&Buffer, wgt::BufferAddress)>,
     ) {
         let id = self.id.as_ref().unwrap();
 
@@ -498,6 +1460,8 @@ impl CommandEncoderRayTracing for CommandEncoder {
 
                                 first_vertex: tg.first_vertex,
                                 vertex_stride: tg.vertex_stride,
+                                vertex_offset: tg.vertex_offset,
+                                vertex_buffer_offset: tg.vertex_buffer_offset,
                                 index_buffer_offset: tg.index_buffer_offset,
                                 transform_buffer_offset: tg.transform_buffer_offset,
                             },
@@ -522,22 +1486,31 @@ impl CommandEncoderRayTracing for CommandEncoder {
             DynContextBlasBuildEntry {
                 blas_id: e.blas.id,
                 geometries,
+                mode: e.mode,
             }
         });
 
         let mut tlas = tlas.into_iter().map(|e: &TlasPackage| {
+            *e.last_build_info.lock() = TlasBuildInfo {
+                instance_count: e.active_instance_count() as u32,
+                dirty_count: e.lowest_unmodified,
+            };
             let instances = e.instances.iter().map(|instance: &Option<TlasInstance>| {
                 instance.as_ref().map(|instance| DynContextTlasInstance {
                     blas: instance.blas,
                     transform: &instance.transform,
                     custom_index: instance.custom_index,
-                    mask: instance.mask,
+                    mask: if instance.visible { instance.mask } else { 0 },
+                    flags: instance.flags,
+                    shader_binding_table_record_offset: instance.shader_binding_table_record_offset,
                 })
             });
             DynContextTlasPackage {
                 tlas_id: e.tlas.id,
                 instances: Box::new(instances),
                 lowest_unmodified: e.lowest_unmodified,
+                instance_upload: e.instance_upload,
+                mode: e.mode,
             }
         });
 
@@ -547,12 +1520,33 @@ impl CommandEncoderRayTracing for CommandEncoder {
             self.data.as_ref(),
             &mut blas,
             &mut tlas,
+            scratch.map(|(buffer, offset)| (&buffer.id, offset)),
         );
     }
+}
+
+impl CommandEncoderRayTracing for CommandEncoder {
+    fn build_acceleration_structures<'a, 'b: 'a>(
+        &mut self,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
+        tlas: impl IntoIterator<Item = &'a TlasPackage>,
+    ) {
+        self.build_acceleration_structures_impl(blas, tlas, None);
+    }
+
+    fn build_acceleration_structures_with_scratch<'a, 'b: 'a>(
+        &mut self,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
+        tlas: impl IntoIterator<Item = &'a TlasPackage>,
+        scratch_buffer: &Buffer,
+        scratch_offset: wgt::BufferAddress,
+    ) {
+        self.build_acceleration_structures_impl(blas, tlas, Some((scratch_buffer, scratch_offset)));
+    }
 
-    unsafe fn build_acceleration_structures_unsafe_tlas<'a>(
+    unsafe fn build_acceleration_structures_unsafe_tlas<'a, 'b: 'a>(
         &mut self,
-        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'a>>,
+        blas: impl IntoIterator<Item = &'a BlasBuildEntry<'b>>,
         tlas: impl IntoIterator<Item = &'a TlasBuildEntry<'a>>,
     ) {
         let id = self.id.as_ref().unwrap();
@@ -575,6 +1569,8 @@ impl CommandEncoderRayTracing for CommandEncoder {
 
                                 first_vertex: tg.first_vertex,
                                 vertex_stride: tg.vertex_stride,
+                                vertex_offset: tg.vertex_offset,
+                                vertex_buffer_offset: tg.vertex_buffer_offset,
                                 index_buffer_offset: tg.index_buffer_offset,
                                 transform_buffer_offset: tg.transform_buffer_offset,
                             },
@@ -599,6 +1595,7 @@ impl CommandEncoderRayTracing for CommandEncoder {
             DynContextBlasBuildEntry {
                 blas_id: e.blas.id,
                 geometries,
+                mode: e.mode,
             }
         });
 
@@ -608,6 +1605,7 @@ impl CommandEncoderRayTracing for CommandEncoder {
                 tlas_id: e.tlas.id,
                 instance_buffer_id: e.instance_buffer.id,
                 instance_count: e.instance_count,
+                mode: e.mode,
             });
 
         DynContext::command_encoder_build_acceleration_structures_unsafe_tlas(
@@ -618,4 +1616,118 @@ impl CommandEncoderRayTracing for CommandEncoder {
             &mut tlas,
         );
     }
+
+    fn copy_blas(&mut self, src: &Blas, dst: &Blas, copy: AccelerationStructureCopy) {
+        let id = self.id.as_ref().unwrap();
+
+        DynContext::command_encoder_copy_blas(
+            &*self.context,
+            id,
+            self.data.as_ref(),
+            &src.id,
+            &dst.id,
+            copy,
+        );
+    }
+
+    fn copy_tlas(&mut self, src: &Tlas, dst: &Tlas) {
+        let id = self.id.as_ref().unwrap();
+
+        DynContext::command_encoder_copy_tlas(
+            &*self.context,
+            id,
+            self.data.as_ref(),
+            &src.id,
+            &dst.id,
+        );
+    }
+
+    fn resolve_blas_compacted_size(
+        &mut self,
+        blas: &Blas,
+        destination: &Buffer,
+        destination_offset: wgt::BufferAddress,
+    ) {
+        let id = self.id.as_ref().unwrap();
+
+        DynContext::command_encoder_resolve_blas_compacted_size(
+            &*self.context,
+            id,
+            self.data.as_ref(),
+            &blas.id,
+            &destination.id,
+            destination.data.as_ref(),
+            destination_offset,
+        );
+    }
+}
+
+/// Read the compacted size of `blas` back without blocking on its build, calling `callback`
+/// with it once the build has completed and [`Device::poll`] noticed.
+///
+/// This submits a copy of the size into a mappable buffer with
+/// [`CommandEncoderRayTracing::resolve_blas_compacted_size`] to `queue`, so it must be called
+/// after the submission building `blas`. The size is the one
+/// [`DeviceRayTracing::blas_compacted_size`] returns, and once the callback was called,
+/// [`DeviceRayTracing::create_compacted_blas`] can create a BLAS of that size for `blas` without
+/// reading it again.
+pub fn read_blas_compacted_size(
+    device: &Device,
+    queue: &Queue,
+    blas: &Blas,
+    callback: impl FnOnce(Result<wgt::BufferAddress, crate::BufferAsyncError>)
+        + wgt::WasmNotSend
+        + 'static,
+) {
+    let size = wgt::QUERY_SIZE as wgt::BufferAddress;
+    let resolve = device.create_buffer(&crate::BufferDescriptor {
+        label: Some("Compacted size"),
+        size,
+        usage: crate::BufferUsages::QUERY_RESOLVE | crate::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    #[allow(clippy::arc_with_non_send_sync)] // False positive on emscripten
+    let readback = Arc::new(device.create_buffer(&crate::BufferDescriptor {
+        label: Some("Compacted size readback"),
+        size,
+        usage: crate::BufferUsages::MAP_READ | crate::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }));
+
+    let mut encoder =
+        device.create_command_encoder(&crate::CommandEncoderDescriptor { label: None });
+    encoder.resolve_blas_compacted_size(blas, &resolve, 0);
+    encoder.copy_buffer_to_buffer(&resolve, 0, &readback, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    readback
+        .clone()
+        .slice(..)
+        .map_async(crate::MapMode::Read, move |result| {
+            callback(result.map(|()| {
+                let data = readback.slice(..).get_mapped_range();
+                wgt::BufferAddress::from_ne_bytes(data[..].try_into().unwrap())
+            }));
+        });
+}
+
+/// Trait to add ray tracing functions to a [`Queue`].
+pub trait QueueRayTracing {
+    /// Block until every acceleration structure build submitted to this queue so far has
+    /// completed on the GPU.
+    ///
+    /// Unlike [`Device::poll`] with [`Maintain::Wait`](crate::Maintain::Wait), this only waits
+    /// for the last submission that contained builds, not for work submitted after it. It
+    /// returns immediately if no builds were submitted.
+    fn wait_for_acceleration_structure_builds(&self);
+}
+
+impl QueueRayTracing for Queue {
+    fn wait_for_acceleration_structure_builds(&self) {
+        DynContext::queue_wait_for_acceleration_structure_builds(
+            &*self.context,
+            &self.id,
+            self.data.as_ref(),
+        );
+    }
 }
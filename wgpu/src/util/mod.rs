@@ -7,6 +7,7 @@ mod belt;
 mod device;
 mod encoder;
 mod init;
+mod ray_tracing;
 
 use std::sync::Arc;
 use std::{
@@ -19,6 +20,7 @@ pub use belt::StagingBelt;
 pub use device::{BufferInitDescriptor, DeviceExt, TextureDataOrder};
 pub use encoder::RenderEncoder;
 pub use init::*;
+pub use ray_tracing::{find_degenerate_triangles, DegenerateTriangles};
 pub use wgt::{math::*, DispatchIndirectArgs, DrawIndexedIndirectArgs, DrawIndirectArgs};
 
 /// Treat the given byte slice as a SPIR-V module.
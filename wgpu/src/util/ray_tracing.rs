@@ -0,0 +1,127 @@
+/// Degenerate triangles found by [`find_degenerate_triangles`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DegenerateTriangles {
+    /// Number of triangles that were checked.
+    pub triangle_count: u32,
+    /// Indices of the degenerate triangles, in ascending order.
+    pub triangles: Vec<u32>,
+}
+
+impl DegenerateTriangles {
+    /// Returns true if no degenerate triangle was found.
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+
+/// Checks triangle geometry for degenerate (zero area) triangles before it is uploaded
+/// for a bottom level acceleration structure build.
+///
+/// Degenerate triangles can never be hit, but they still take up space in the acceleration
+/// structure and can cause traversal artifacts on some drivers, so they are best removed from
+/// the mesh. A triangle is degenerate if its edges are collinear up to `f32` precision,
+/// which includes triangles with repeated vertices.
+///
+/// `positions` are the vertex positions and `indices`, if any, the index buffer contents
+/// of the geometry, as for a [`BlasTriangleGeometry`](crate::ray_tracing::BlasTriangleGeometry).
+/// Trailing indices or vertices that don't make up a full triangle are ignored.
+///
+/// This runs on the host and visits every triangle, so it is meant for checking meshes
+/// while developing or when they are imported, not before every build. A warning with the
+/// number of degenerate triangles is logged if any are found.
+///
+/// # Panics
+///
+/// - An index is out of bounds of `positions`.
+pub fn find_degenerate_triangles(
+    positions: &[[f32; 3]],
+    indices: Option<&[u32]>,
+) -> DegenerateTriangles {
+    let triangle = |i: usize| -> [[f32; 3]; 3] {
+        match indices {
+            Some(indices) => [0, 1, 2].map(|v| positions[indices[i * 3 + v] as usize]),
+            None => [0, 1, 2].map(|v| positions[i * 3 + v]),
+        }
+    };
+    let triangle_count = match indices {
+        Some(indices) => indices.len() / 3,
+        None => positions.len() / 3,
+    };
+
+    let triangles: Vec<u32> = (0..triangle_count)
+        .filter(|&i| is_degenerate(triangle(i)))
+        .map(|i| i as u32)
+        .collect();
+
+    if !triangles.is_empty() {
+        log::warn!(
+            "{} of {} triangles are degenerate and should be removed before building a BLAS",
+            triangles.len(),
+            triangle_count
+        );
+    }
+
+    DegenerateTriangles {
+        triangle_count: triangle_count as u32,
+        triangles,
+    }
+}
+
+fn is_degenerate([a, b, c]: [[f32; 3]; 3]) -> bool {
+    let sub = |x: [f32; 3], y: [f32; 3]| [x[0] - y[0], x[1] - y[1], x[2] - y[2]];
+    let dot = |x: [f32; 3], y: [f32; 3]| x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+    let (e1, e2) = (sub(b, a), sub(c, a));
+    let cross = [
+        e1[1] * e2[2] - e1[2] * e2[1],
+        e1[2] * e2[0] - e1[0] * e2[2],
+        e1[0] * e2[1] - e1[1] * e2[0],
+    ];
+    // |e1 x e2| = |e1| |e2| sin(angle), comparing the squares keeps this independent of scale.
+    dot(cross, cross) <= f32::EPSILON * f32::EPSILON * dot(e1, e1) * dot(e2, e2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_degenerate_triangles;
+
+    const QUAD: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+
+    #[test]
+    fn clean_mesh_passes() {
+        let report = find_degenerate_triangles(&QUAD, Some(&[0, 1, 2, 0, 2, 3]));
+        assert_eq!(report.triangle_count, 2);
+        assert!(report.is_empty());
+
+        // Tiny but well shaped triangles aren't degenerate.
+        let tiny = QUAD.map(|p| p.map(|x| x * 1e-6));
+        assert!(find_degenerate_triangles(&tiny, Some(&[0, 1, 2, 0, 2, 3])).is_empty());
+    }
+
+    #[test]
+    fn degenerate_triangles_are_flagged() {
+        // Repeated index, collinear vertices, and a valid triangle in between.
+        let report = find_degenerate_triangles(&QUAD, Some(&[0, 1, 1, 0, 2, 3, 0, 2, 0]));
+        assert_eq!(report.triangle_count, 3);
+        assert_eq!(report.triangles, [0, 2]);
+
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [2.0, 2.0, 2.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [5.0, 5.0, 5.0],
+            [5.0, 5.0, 5.0],
+            [5.0, 5.0, 5.0],
+        ];
+        let report = find_degenerate_triangles(&positions, None);
+        assert_eq!(report.triangle_count, 3);
+        assert_eq!(report.triangles, [0, 2]);
+    }
+}